@@ -0,0 +1,599 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use cdb32::Encoding;
+
+xflags::xflags! {
+    src "./src/flags.rs"
+
+    /// Read and write CDB files.
+    cmd cdb {
+        /// Print every record of a database. This is the default command, so
+        /// `cdb dump` may be shortened to `cdb`.
+        default cmd dump {
+            /// CDB file path
+            required file: PathBuf
+            /// Keep running, printing the records again whenever the file is
+            /// replaced
+            optional --follow
+            /// With --follow, print only the records added (+), removed (-) or
+            /// changed (~) by each replacement
+            optional --diff
+            /// With --follow, how often to check the file, in milliseconds;
+            /// 1000 by default
+            optional --interval ms: u64
+            /// Output format: table (the default), cdbdump or json
+            optional --format format: OutputFormat
+            /// Encoding of keys and values in table and json output: utf8-lossy
+            /// (the default), hex, base64 or escape
+            optional --encoding encoding: DataEncoding
+            /// Encoding of keys, overriding --encoding
+            optional --key-encoding encoding: DataEncoding
+            /// Encoding of values, overriding --encoding
+            optional --value-encoding encoding: DataEncoding
+        }
+
+        /// Print every value of a key, one per line, exiting with status 1
+        /// if there are none.
+        ///
+        /// With --stdin, look up each line of standard input as a key instead,
+        /// printing a line for every value found, as the found marker, the key
+        /// and the value separated by tabs, or the missing marker and the key
+        /// for a key with no values.
+        cmd get {
+            /// CDB file path
+            required file: PathBuf
+            /// The key to look up
+            optional key: OsString
+            /// Encoding of keys: utf8-lossy (the default, taking them as they are), hex,
+            /// base64 or escape
+            optional --key-encoding encoding: DataEncoding
+            /// Look up the keys read from standard input, one per line
+            optional --stdin
+            /// With --stdin, encoding of values: utf8-lossy (the default), hex, base64
+            /// or escape
+            optional --value-encoding encoding: DataEncoding
+            /// With --stdin, the marker for a value found, `+` by default
+            optional --found marker: String
+            /// With --stdin, the marker for a missing key, `-` by default
+            optional --missing marker: String
+        }
+
+        /// Build a database from cdbmake records read from standard input,
+        /// replacing the output file atomically once complete.
+        cmd make {
+            /// CDB file path
+            required output: PathBuf
+        }
+
+        /// Merge several databases into one.
+        cmd merge {
+            /// CDB file paths to merge, in order
+            repeated inputs: PathBuf
+            /// CDB file path to write
+            required -o, --output output: PathBuf
+            /// What to do with a key found more than once: keep all its records
+            /// (the default), only the first or last, or fail with an error
+            optional --on-duplicate policy: OnDuplicate
+        }
+
+        /// Print the keys matching a regular expression, exiting with
+        /// status 1 if there are none.
+        cmd grep {
+            /// CDB file path
+            required file: PathBuf
+            /// The regular expression to match keys against
+            required pattern: OsString
+            /// Match keys starting with the pattern taken literally
+            optional --prefix
+            /// Print the value of each matching record after its key and a tab
+            optional --values
+            /// Encoding of printed keys: utf8-lossy (the default), hex, base64 or escape
+            optional --key-encoding encoding: DataEncoding
+            /// Encoding of printed values: utf8-lossy (the default), hex, base64 or escape
+            optional --value-encoding encoding: DataEncoding
+        }
+
+        /// Copy a database into the other on-disk format.
+        cmd convert {
+            /// Format to write: cdb32 (reading a cdb64 file) or cdb64 (reading a
+            /// cdb32 file)
+            required --to format: DbFormat
+            /// CDB file path to read
+            required input: PathBuf
+            /// CDB file path to write
+            required output: PathBuf
+        }
+
+        /// Recover the intact records of a truncated or corrupt database into
+        /// a new one, reporting what was lost.
+        cmd salvage {
+            /// Damaged CDB file path
+            required input: PathBuf
+            /// CDB file path to write
+            required output: PathBuf
+            /// Format of both files: cdb32 (the default) or cdb64
+            optional --format format: DbFormat
+        }
+
+        /// Export a database to a (key BLOB, value BLOB) table of an SQLite
+        /// database. Needs cdb built with the sqlite feature.
+        cmd export-sqlite {
+            /// CDB file path
+            required file: PathBuf
+            /// SQLite database path, created if needed
+            required database: PathBuf
+            /// Name of the table, `cdb` by default
+            optional --table name: String
+        }
+
+        /// List the largest records of a database.
+        cmd top {
+            /// CDB file path
+            required file: PathBuf
+            /// What to measure: value-size (the default), key-size or record-size
+            optional --by measure: SizeMeasure
+            /// Number of records to list, 10 by default
+            optional -n, --count count: usize
+            /// Encoding of printed keys: utf8-lossy (the default), hex, base64 or escape
+            optional --key-encoding encoding: DataEncoding
+        }
+
+        /// Print a random sample of the records of a database, chosen in a
+        /// single pass.
+        cmd sample {
+            /// CDB file path
+            required file: PathBuf
+            /// Number of records to print, 10 by default
+            optional -n, --count count: usize
+            /// Seed for the random choice, to repeat a sample
+            optional --seed seed: u64
+            /// Output format: table (the default), cdbdump or json
+            optional --format format: OutputFormat
+            /// Encoding of keys and values in table and json output: utf8-lossy
+            /// (the default), hex, base64 or escape
+            optional --encoding encoding: DataEncoding
+            /// Encoding of keys, overriding --encoding
+            optional --key-encoding encoding: DataEncoding
+            /// Encoding of values, overriding --encoding
+            optional --value-encoding encoding: DataEncoding
+        }
+
+        /// Print a histogram of how many values each key has.
+        cmd dups {
+            /// CDB file path
+            required file: PathBuf
+            /// Also list the keys with at least this many values
+            optional --threshold count: usize
+            /// Encoding of listed keys: utf8-lossy (the default), hex, base64 or escape
+            optional --key-encoding encoding: DataEncoding
+        }
+
+        /// Measure full scan throughput and the throughput and latency of
+        /// random lookups of present and missing keys.
+        cmd bench {
+            /// CDB file path
+            required file: PathBuf
+            /// Number of lookups of each kind, 100000 by default
+            optional -n, --lookups count: usize
+            /// Seed for choosing the keys looked up
+            optional --seed seed: u64
+        }
+
+        /// Print the key of every record, one per line.
+        cmd keys {
+            /// CDB file path
+            required file: PathBuf
+            /// Print each key once, however many records it has
+            optional --unique
+            /// Print only the number of keys
+            optional --count
+            /// Encoding of keys: utf8-lossy (the default), hex, base64 or escape
+            optional --key-encoding encoding: DataEncoding
+        }
+
+        /// Serve lookups over HTTP. GET /v1/get/KEY returns the first value of
+        /// the percent-encoded KEY, and GET /v1/keys lists each key once,
+        /// percent-encoded, one per line. Files replaced while serving are
+        /// reloaded. Needs cdb built with the serve feature.
+        cmd serve {
+            /// CDB file paths; keys are looked up in each in turn
+            repeated files: PathBuf
+            /// Address to listen on; 127.0.0.1:8080 by default
+            optional --listen addr: String
+            /// How often to check the files for replacement, in milliseconds;
+            /// 1000 by default
+            optional --interval ms: u64
+            /// Most requests handled at once; 64 by default
+            optional --concurrency n: usize
+        }
+    }
+}
+// generated start
+// The following code is generated by `xflags` macro.
+// Run `env UPDATE_XFLAGS=1 cargo build` to regenerate.
+#[derive(Debug)]
+pub struct Cdb {
+    pub subcommand: CdbCmd,
+}
+
+#[derive(Debug)]
+pub enum CdbCmd {
+    Dump(Dump),
+    Get(Get),
+    Make(Make),
+    Merge(Merge),
+    Grep(Grep),
+    Convert(Convert),
+    Salvage(Salvage),
+    ExportSqlite(ExportSqlite),
+    Top(Top),
+    Sample(Sample),
+    Dups(Dups),
+    Bench(Bench),
+    Keys(Keys),
+    Serve(Serve),
+}
+
+#[derive(Debug)]
+pub struct Dump {
+    pub file: PathBuf,
+
+    pub follow: bool,
+    pub diff: bool,
+    pub interval: Option<u64>,
+    pub format: Option<OutputFormat>,
+    pub encoding: Option<DataEncoding>,
+    pub key_encoding: Option<DataEncoding>,
+    pub value_encoding: Option<DataEncoding>,
+}
+
+#[derive(Debug)]
+pub struct Get {
+    pub file: PathBuf,
+    pub key: Option<OsString>,
+
+    pub key_encoding: Option<DataEncoding>,
+    pub stdin: bool,
+    pub value_encoding: Option<DataEncoding>,
+    pub found: Option<String>,
+    pub missing: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Make {
+    pub output: PathBuf,
+}
+
+#[derive(Debug)]
+pub struct Merge {
+    pub inputs: Vec<PathBuf>,
+
+    pub output: PathBuf,
+    pub on_duplicate: Option<OnDuplicate>,
+}
+
+#[derive(Debug)]
+pub struct Grep {
+    pub file: PathBuf,
+    pub pattern: OsString,
+
+    pub prefix: bool,
+    pub values: bool,
+    pub key_encoding: Option<DataEncoding>,
+    pub value_encoding: Option<DataEncoding>,
+}
+
+#[derive(Debug)]
+pub struct Convert {
+    pub input: PathBuf,
+    pub output: PathBuf,
+
+    pub to: DbFormat,
+}
+
+#[derive(Debug)]
+pub struct Salvage {
+    pub input: PathBuf,
+    pub output: PathBuf,
+
+    pub format: Option<DbFormat>,
+}
+
+#[derive(Debug)]
+pub struct ExportSqlite {
+    pub file: PathBuf,
+    pub database: PathBuf,
+
+    pub table: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Top {
+    pub file: PathBuf,
+
+    pub by: Option<SizeMeasure>,
+    pub count: Option<usize>,
+    pub key_encoding: Option<DataEncoding>,
+}
+
+#[derive(Debug)]
+pub struct Sample {
+    pub file: PathBuf,
+
+    pub count: Option<usize>,
+    pub seed: Option<u64>,
+    pub format: Option<OutputFormat>,
+    pub encoding: Option<DataEncoding>,
+    pub key_encoding: Option<DataEncoding>,
+    pub value_encoding: Option<DataEncoding>,
+}
+
+#[derive(Debug)]
+pub struct Dups {
+    pub file: PathBuf,
+
+    pub threshold: Option<usize>,
+    pub key_encoding: Option<DataEncoding>,
+}
+
+#[derive(Debug)]
+pub struct Bench {
+    pub file: PathBuf,
+
+    pub lookups: Option<usize>,
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug)]
+pub struct Keys {
+    pub file: PathBuf,
+
+    pub unique: bool,
+    pub count: bool,
+    pub key_encoding: Option<DataEncoding>,
+}
+
+#[derive(Debug)]
+pub struct Serve {
+    pub files: Vec<PathBuf>,
+
+    pub listen: Option<String>,
+    pub interval: Option<u64>,
+    pub concurrency: Option<usize>,
+}
+
+impl Cdb {
+    #[allow(dead_code)]
+    pub fn from_env_or_exit() -> Self {
+        Self::from_env_or_exit_()
+    }
+
+    #[allow(dead_code)]
+    pub fn from_env() -> xflags::Result<Self> {
+        Self::from_env_()
+    }
+
+    #[allow(dead_code)]
+    pub fn from_vec(args: Vec<std::ffi::OsString>) -> xflags::Result<Self> {
+        Self::from_vec_(args)
+    }
+}
+// generated end
+
+/// How the records are printed.
+#[derive(Clone, Copy, Debug)]
+pub enum OutputFormat {
+    /// A table of keys and values, for reading.
+    Table,
+    /// The `+klen,dlen:key->data` records of djb's `cdbdump`, which
+    /// `cdbmake` reads back.
+    Cdbdump,
+    /// One JSON object per line, with `key` and `value` members.
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "table" => Ok(OutputFormat::Table),
+            "cdbdump" => Ok(OutputFormat::Cdbdump),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!(
+                "unknown format `{}`, expected table, cdbdump or json",
+                s
+            )),
+        }
+    }
+}
+
+/// The on-disk format of a database.
+#[derive(Clone, Copy, Debug)]
+pub enum DbFormat {
+    /// The classic format with 32-bit offsets.
+    Cdb32,
+    /// The 64-bit variant.
+    Cdb64,
+}
+
+impl FromStr for DbFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "cdb32" => Ok(DbFormat::Cdb32),
+            "cdb64" => Ok(DbFormat::Cdb64),
+            _ => Err(format!("unknown format `{}`, expected cdb32 or cdb64", s)),
+        }
+    }
+}
+
+/// How `top` measures the size of a record.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum SizeMeasure {
+    Key,
+    #[default]
+    Value,
+    Record,
+}
+
+impl FromStr for SizeMeasure {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "key-size" => Ok(SizeMeasure::Key),
+            "value-size" => Ok(SizeMeasure::Value),
+            "record-size" => Ok(SizeMeasure::Record),
+            _ => Err(format!(
+                "unknown measure `{}`, expected key-size, value-size or record-size",
+                s
+            )),
+        }
+    }
+}
+
+impl SizeMeasure {
+    pub fn measure(self, key: &[u8], value: &[u8]) -> usize {
+        match self {
+            SizeMeasure::Key => key.len(),
+            SizeMeasure::Value => value.len(),
+            SizeMeasure::Record => key.len() + value.len(),
+        }
+    }
+}
+
+/// What `merge` does with a key found more than once.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum OnDuplicate {
+    #[default]
+    All,
+    First,
+    Last,
+    Error,
+}
+
+impl FromStr for OnDuplicate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "all" => Ok(OnDuplicate::All),
+            "first" => Ok(OnDuplicate::First),
+            "last" => Ok(OnDuplicate::Last),
+            "error" => Ok(OnDuplicate::Error),
+            _ => Err(format!(
+                "unknown policy `{}`, expected all, first, last or error",
+                s
+            )),
+        }
+    }
+}
+
+/// How keys and values are rendered as text.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum DataEncoding {
+    /// As UTF-8, replacing invalid sequences.
+    #[default]
+    Utf8Lossy,
+    Hex,
+    Base64,
+    /// Printable ASCII as it is, and other bytes escaped as `\n` or
+    /// `\xff` as in Rust and C.
+    Escape,
+}
+
+impl FromStr for DataEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "utf8-lossy" => Ok(DataEncoding::Utf8Lossy),
+            "hex" => Ok(DataEncoding::Hex),
+            "base64" => Ok(DataEncoding::Base64),
+            "escape" => Ok(DataEncoding::Escape),
+            _ => Err(format!(
+                "unknown encoding `{}`, expected utf8-lossy, hex, base64 or escape",
+                s
+            )),
+        }
+    }
+}
+
+impl DataEncoding {
+    pub fn encode(self, data: &[u8]) -> String {
+        let encoded = match self {
+            DataEncoding::Utf8Lossy => return String::from_utf8_lossy(data).into_owned(),
+            DataEncoding::Hex => Encoding::Hex.encode(data),
+            DataEncoding::Base64 => Encoding::Base64.encode(data),
+            DataEncoding::Escape => return data.escape_ascii().to_string(),
+        };
+        String::from_utf8(encoded).unwrap()
+    }
+
+    /// Decode a command line argument. As UTF-8 the argument is taken as
+    /// it is, even if it is not valid UTF-8.
+    pub fn decode(self, arg: &OsString) -> std::io::Result<Vec<u8>> {
+        self.decode_bytes(&os_bytes(arg))
+    }
+
+    /// Decode text, which as UTF-8 is taken as it is.
+    pub fn decode_bytes(self, text: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            DataEncoding::Utf8Lossy => Ok(text.to_vec()),
+            DataEncoding::Hex => Encoding::Hex.decode(text),
+            DataEncoding::Base64 => Encoding::Base64.decode(text),
+            DataEncoding::Escape => unescape(text),
+        }
+    }
+}
+
+/// Undo the escapes of [`DataEncoding::Escape`].
+fn unescape(text: &[u8]) -> std::io::Result<Vec<u8>> {
+    let invalid = || {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("invalid escape in `{}`", text.escape_ascii()),
+        )
+    };
+    let mut out = Vec::with_capacity(text.len());
+    let mut bytes = text.iter();
+    while let Some(&byte) = bytes.next() {
+        if byte != b'\\' {
+            out.push(byte);
+            continue;
+        }
+        let byte = match bytes.next().ok_or_else(invalid)? {
+            b'n' => b'\n',
+            b'r' => b'\r',
+            b't' => b'\t',
+            b'0' => b'\0',
+            b'x' => {
+                let digits = [
+                    *bytes.next().ok_or_else(invalid)?,
+                    *bytes.next().ok_or_else(invalid)?,
+                ];
+                let digits = std::str::from_utf8(&digits).map_err(|_| invalid())?;
+                u8::from_str_radix(digits, 16).map_err(|_| invalid())?
+            }
+            &byte @ (b'\\' | b'\'' | b'"') => byte,
+            _ => return Err(invalid()),
+        };
+        out.push(byte);
+    }
+    Ok(out)
+}
+
+#[cfg(unix)]
+fn os_bytes(arg: &OsString) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    arg.as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+fn os_bytes(arg: &OsString) -> Vec<u8> {
+    arg.to_string_lossy().into_owned().into_bytes()
+}
@@ -1,29 +1,701 @@
-use std::{io::Result, path::PathBuf};
-use std::ffi::CStr;
+use std::borrow::Borrow;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::io::{self, BufRead, Result, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use cdb32::CDB;
+use cdb32::diff::{diff, Change};
+use cdb32::{
+    CDBWriter, DuplicatePolicy, Format, Format32, Format64, GenericCDB, GenericCDBWriter,
+    ReloadableCDB, CDB,
+};
+use regex::bytes::Regex;
 
-pub fn main() -> Result<()> {
-    let flags = xflags::parse_or_exit! {
-        /// CDB file path
-        required cdb: PathBuf
-    };
+mod flags;
+#[cfg(feature = "serve")]
+mod serve;
+
+use flags::{DataEncoding, DbFormat, OnDuplicate, OutputFormat};
+#[cfg(feature = "serve")]
+use serve::serve;
+
+type Record = (Vec<u8>, Vec<u8>);
+
+fn dump_table<I: IntoIterator<Item = Result<Record>>>(
+    records: I,
+    keys: DataEncoding,
+    values: DataEncoding,
+) -> Result<()> {
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    writeln!(out, "{:>40} = value", "key")?;
+    writeln!(out, "{:->40} - {:->40}", "", "")?;
+    for entry in records {
+        let (key, value) = entry?;
+        writeln!(out, "{:>40} = {}", keys.encode(&key), values.encode(&value))?;
+    }
+    out.flush()
+}
 
-    let db = CDB::open(flags.cdb)?;
+fn dump_cdbdump<I: IntoIterator<Item = Result<Record>>>(records: I) -> Result<()> {
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    for entry in records {
+        let (key, value) = entry?;
+        cdb32::text::write_record(&mut out, &key, &value)?;
+    }
+    out.write_all(b"\n")?;
+    out.flush()
+}
 
-    println!("  {:>40} = value", "key");
-    println!("{:->42} - {:->40}", "", "");
-    for entry in db.iter() {
+fn dump_json<I: IntoIterator<Item = Result<Record>>>(
+    records: I,
+    keys: DataEncoding,
+    values: DataEncoding,
+) -> Result<()> {
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    for entry in records {
         let (key, value) = entry?;
-        let keyarr = format!("{:#?}", &key);
+        let record = serde_json::json!({
+            "key": keys.encode(&key),
+            "value": values.encode(&value),
+        });
+        serde_json::to_writer(&mut out, &record)?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()
+}
+
+/// Print `records` in the chosen format. Keys and values are encoded
+/// with `keys` or `values` if given, and otherwise with `both`.
+fn print_records<I: IntoIterator<Item = Result<Record>>>(
+    records: I,
+    format: Option<OutputFormat>,
+    both: Option<DataEncoding>,
+    keys: Option<DataEncoding>,
+    values: Option<DataEncoding>,
+) -> Result<()> {
+    let both = both.unwrap_or_default();
+    let keys = keys.unwrap_or(both);
+    let values = values.unwrap_or(both);
+    match format.unwrap_or(OutputFormat::Table) {
+        OutputFormat::Table => dump_table(records, keys, values),
+        OutputFormat::Cdbdump => dump_cdbdump(records),
+        OutputFormat::Json => dump_json(records, keys, values),
+    }
+}
+
+fn dump(flags: flags::Dump) -> Result<ExitCode> {
+    if flags.follow {
+        return follow(flags);
+    }
+    let db = CDB::open(flags.file)?;
+    print_records(
+        db.iter(),
+        flags.format,
+        flags.encoding,
+        flags.key_encoding,
+        flags.value_encoding,
+    )?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Print the changes between two versions of a database.
+fn print_diff(old: &CDB, new: &CDB, keys: DataEncoding, values: DataEncoding) -> Result<()> {
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    for change in diff(old, new) {
+        match change? {
+            Change::Added { key, value } => {
+                writeln!(out, "+ {} = {}", keys.encode(&key), values.encode(&value))?
+            }
+            Change::Removed { key, value } => {
+                writeln!(out, "- {} = {}", keys.encode(&key), values.encode(&value))?
+            }
+            Change::Changed { key, old, new } => writeln!(
+                out,
+                "~ {} = {} -> {}",
+                keys.encode(&key),
+                values.encode(&old),
+                values.encode(&new)
+            )?,
+        }
+    }
+    out.flush()
+}
+
+/// Dump the database, then again, or the changes, whenever it is
+/// replaced. This runs until interrupted.
+fn follow(flags: flags::Dump) -> Result<ExitCode> {
+    let both = flags.encoding.unwrap_or_default();
+    let keys = flags.key_encoding.unwrap_or(both);
+    let values = flags.value_encoding.unwrap_or(both);
+    let interval = Duration::from_millis(flags.interval.unwrap_or(1000));
+    let db = ReloadableCDB::open(&flags.file)?;
+    let mut current = db.current();
+    print_records(
+        current.iter(),
+        flags.format,
+        flags.encoding,
+        Some(keys),
+        Some(values),
+    )?;
+    loop {
+        thread::sleep(interval);
+        match db.reload_if_changed() {
+            Ok(true) => {}
+            Ok(false) => continue,
+            // The file may be briefly missing, or only partly written by
+            // a producer which does not replace it atomically.
+            Err(err) => {
+                eprintln!("cdb: {}: {}", flags.file.display(), err);
+                continue;
+            }
+        }
+        let new = db.current();
+        eprintln!("==> {} replaced <==", flags.file.display());
+        if flags.diff {
+            print_diff(&current, &new, keys, values)?;
+        } else {
+            print_records(
+                new.iter(),
+                flags.format,
+                flags.encoding,
+                Some(keys),
+                Some(values),
+            )?;
+        }
+        current = new;
+    }
+}
+
+fn get(flags: flags::Get) -> Result<ExitCode> {
+    let key = match (&flags.key, flags.stdin) {
+        (None, true) => return get_stdin(flags),
+        (Some(key), false) => flags.key_encoding.unwrap_or_default().decode(key)?,
+        _ => {
+            eprintln!("cdb get: give either a key or --stdin");
+            return Ok(ExitCode::from(2));
+        }
+    };
+    let db = CDB::open(flags.file)?;
+
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let mut found = false;
+    for value in db.find(&key) {
+        out.write_all(&value?)?;
+        out.write_all(b"\n")?;
+        found = true;
+    }
+    out.flush()?;
+    Ok(if found {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// Look up each line of standard input as a key.
+fn get_stdin(flags: flags::Get) -> Result<ExitCode> {
+    let db = CDB::open(&flags.file)?;
+    let keys = flags.key_encoding.unwrap_or_default();
+    let values = flags.value_encoding.unwrap_or_default();
+    let found = flags.found.as_deref().unwrap_or("+");
+    let missing = flags.missing.as_deref().unwrap_or("-");
+
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    for line in io::stdin().lock().split(b'\n') {
+        let mut line = line?;
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        let key = keys.decode_bytes(&line)?;
+        let shown = String::from_utf8_lossy(&line);
+        let mut any = false;
+        for value in db.find(&key) {
+            writeln!(out, "{}\t{}\t{}", found, shown, values.encode(&value?))?;
+            any = true;
+        }
+        if !any {
+            writeln!(out, "{}\t{}", missing, shown)?;
+        }
+    }
+    out.flush()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn make(flags: flags::Make) -> Result<ExitCode> {
+    let mut cdb = CDBWriter::create(flags.output)?;
+    cdb.add_from_text(io::stdin().lock())?;
+    cdb.finish()?;
+    Ok(ExitCode::SUCCESS)
+}
 
-        let sk = format!("{:>40}", String::from_utf8(key).unwrap_or(keyarr));
+/// Return `true` if any of `dbs` has a record with the key.
+fn exists_in<D: Borrow<CDB>>(dbs: &[D], key: &[u8]) -> Result<bool> {
+    for db in dbs {
+        if db.borrow().exists(key)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
 
-        let strval  = CStr::from_bytes_until_nul(value.as_slice());
-        let sv = strval.unwrap().to_string_lossy();
+fn merge(flags: flags::Merge) -> Result<ExitCode> {
+    if flags.inputs.is_empty() {
+        eprintln!("cdb merge: no input files");
+        return Ok(ExitCode::from(2));
+    }
+    let inputs = flags
+        .inputs
+        .iter()
+        .map(CDB::open)
+        .collect::<Result<Vec<_>>>()?;
+    let policy = flags.on_duplicate.unwrap_or_default();
+    let mut out = CDBWriter::create(flags.output)?;
+    out.set_duplicate_policy(match policy {
+        OnDuplicate::All | OnDuplicate::Last => DuplicatePolicy::Allow,
+        OnDuplicate::First => DuplicatePolicy::KeepFirst,
+        OnDuplicate::Error => DuplicatePolicy::Reject,
+    });
 
-	println!("{:?} = {:?}", sk, sv);
+    for (i, db) in inputs.iter().enumerate() {
+        if let OnDuplicate::Last = policy {
+            // Keep each key's last record, which is in the last input
+            // holding the key.
+            let later = &inputs[i + 1..];
+            for key in db.unique_keys() {
+                let key = key?;
+                if exists_in(later, &key)? {
+                    continue;
+                }
+                if let Some(value) = db.find(&key).last() {
+                    out.add(&key, value?)?;
+                }
+            }
+        } else {
+            for record in db.iter() {
+                let (key, value) = record?;
+                out.add(key, value)?;
+            }
+        }
     }
+    out.finish()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn grep(flags: flags::Grep) -> Result<ExitCode> {
+    let pattern = DataEncoding::Utf8Lossy.decode(&flags.pattern)?;
+    let regex = if flags.prefix {
+        None
+    } else {
+        let pattern = String::from_utf8_lossy(&pattern);
+        match Regex::new(&pattern) {
+            Ok(regex) => Some(regex),
+            Err(err) => {
+                eprintln!("cdb grep: {}", err);
+                return Ok(ExitCode::from(2));
+            }
+        }
+    };
+    let db = CDB::open(flags.file)?;
+    let keys = flags.key_encoding.unwrap_or_default();
+    let values = flags.value_encoding.unwrap_or_default();
 
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let mut found = false;
+    for record in db.iter() {
+        let (key, value) = record?;
+        let matched = match &regex {
+            Some(regex) => regex.is_match(&key),
+            None => key.starts_with(&pattern),
+        };
+        if !matched {
+            continue;
+        }
+        found = true;
+        if flags.values {
+            writeln!(out, "{}\t{}", keys.encode(&key), values.encode(&value))?;
+        } else {
+            writeln!(out, "{}", keys.encode(&key))?;
+        }
+    }
+    out.flush()?;
+    Ok(if found {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// Copy every record of a database in format `F1` into a new one in
+/// format `F2`.
+fn copy<F1: Format, F2: Format>(input: &Path, output: PathBuf) -> Result<()> {
+    let db = GenericCDB::<F1>::open(input)?;
+    let mut out = GenericCDBWriter::<F2>::create(output)?;
+    for record in db.iter() {
+        let (key, value) = record?;
+        out.add(key, value)?;
+    }
+    out.finish()?;
     Ok(())
 }
+
+fn convert(flags: flags::Convert) -> Result<ExitCode> {
+    // A failed conversion, such as one too big for the 32-bit format,
+    // leaves no partial output behind.
+    match flags.to {
+        DbFormat::Cdb32 => copy::<Format64, Format32>(&flags.input, flags.output)?,
+        DbFormat::Cdb64 => copy::<Format32, Format64>(&flags.input, flags.output)?,
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Copy the intact records of a damaged database in format `F` into a
+/// new one, reporting what was lost on standard error.
+fn salvage_to<F: Format>(input: &Path, output: PathBuf) -> Result<()> {
+    let db = GenericCDB::<F>::open_lenient(input)?;
+    let mut out = GenericCDBWriter::<F>::create(output)?;
+    let mut records = db.salvage_iter();
+    let mut count = 0_u64;
+    for record in &mut records {
+        match record {
+            Ok((key, value)) => {
+                out.add(key, value)?;
+                count += 1;
+            }
+            // A read error ends the walk, and is reported as damage below.
+            Err(err) => eprintln!("cdb salvage: {}", err),
+        }
+    }
+    match records.damage() {
+        Some(pos) => eprintln!(
+            "cdb salvage: recovered {} records; lost {} bytes from byte {}",
+            count,
+            records.bytes_lost(),
+            pos
+        ),
+        None => eprintln!("cdb salvage: recovered {} records; no damage found", count),
+    }
+    out.finish()?;
+    Ok(())
+}
+
+fn salvage(flags: flags::Salvage) -> Result<ExitCode> {
+    match flags.format.unwrap_or(DbFormat::Cdb32) {
+        DbFormat::Cdb32 => salvage_to::<Format32>(&flags.input, flags.output)?,
+        DbFormat::Cdb64 => salvage_to::<Format64>(&flags.input, flags.output)?,
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Copy every record into `table` of the SQLite database, creating the
+/// table if needed, in one transaction.
+#[cfg(feature = "sqlite")]
+fn export_sqlite(flags: flags::ExportSqlite) -> Result<ExitCode> {
+    let to_io = |err: rusqlite::Error| io::Error::other(err);
+    let db = CDB::open(flags.file)?;
+    let table = flags.table.as_deref().unwrap_or("cdb");
+    let table = format!("\"{}\"", table.replace('"', "\"\""));
+    let mut sqlite = rusqlite::Connection::open(&flags.database).map_err(to_io)?;
+    let tx = sqlite.transaction().map_err(to_io)?;
+    tx.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} (key BLOB NOT NULL, value BLOB NOT NULL)",
+            table
+        ),
+        [],
+    )
+    .map_err(to_io)?;
+    {
+        let mut insert = tx
+            .prepare(&format!(
+                "INSERT INTO {} (key, value) VALUES (?1, ?2)",
+                table
+            ))
+            .map_err(to_io)?;
+        for record in db.iter() {
+            let (key, value) = record?;
+            insert.execute((key, value)).map_err(to_io)?;
+        }
+    }
+    tx.commit().map_err(to_io)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn export_sqlite(flags: flags::ExportSqlite) -> Result<ExitCode> {
+    eprintln!(
+        "cdb export-sqlite: cannot export {} to table {} of {}: cdb was built without the sqlite feature",
+        flags.file.display(),
+        flags.table.as_deref().unwrap_or("cdb"),
+        flags.database.display()
+    );
+    Ok(ExitCode::from(2))
+}
+
+#[cfg(not(feature = "serve"))]
+fn serve(flags: flags::Serve) -> Result<ExitCode> {
+    // The flags are only used by the server.
+    let _ = (flags.files, flags.listen, flags.interval, flags.concurrency);
+    eprintln!("cdb serve: cdb was built without the serve feature");
+    Ok(ExitCode::from(2))
+}
+
+fn top(flags: flags::Top) -> Result<ExitCode> {
+    let db = CDB::open(flags.file)?;
+    let by = flags.by.unwrap_or_default();
+    let count = flags.count.unwrap_or(10);
+    let keys = flags.key_encoding.unwrap_or_default();
+
+    // A min-heap of the largest records so far, ordered by size and then
+    // position so that ties keep the earlier record.
+    let mut largest = BinaryHeap::with_capacity(count + 1);
+    for (i, record) in db.iter().enumerate() {
+        let (key, value) = record?;
+        let size = by.measure(&key, &value);
+        largest.push(Reverse((size, Reverse(i), key, value.len())));
+        if largest.len() > count {
+            largest.pop();
+        }
+    }
+
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    writeln!(out, "{:>12} {:>12}  key", "key bytes", "value bytes")?;
+    for Reverse((_, _, key, value_len)) in largest.into_sorted_vec() {
+        writeln!(
+            out,
+            "{:>12} {:>12}  {}",
+            key.len(),
+            value_len,
+            keys.encode(&key)
+        )?;
+    }
+    out.flush()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn sample(flags: flags::Sample) -> Result<ExitCode> {
+    let db = CDB::open(flags.file)?;
+    let count = flags.count.unwrap_or(10);
+    let mut rng = match flags.seed {
+        Some(seed) => fastrand::Rng::with_seed(seed),
+        None => fastrand::Rng::new(),
+    };
+
+    // Reservoir sampling: the i-th record replaces a random one of the
+    // `count` kept with probability count / (i + 1).
+    let mut kept = Vec::with_capacity(count);
+    for (i, record) in db.iter().enumerate() {
+        let record = record?;
+        if kept.len() < count {
+            kept.push((i, record));
+        } else {
+            let j = rng.usize(..=i);
+            if j < count {
+                kept[j] = (i, record);
+            }
+        }
+    }
+    kept.sort_by_key(|(i, _)| *i);
+
+    print_records(
+        kept.into_iter().map(|(_, record)| Ok(record)),
+        flags.format,
+        flags.encoding,
+        flags.key_encoding,
+        flags.value_encoding,
+    )?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn dups(flags: flags::Dups) -> Result<ExitCode> {
+    let db = CDB::open(flags.file)?;
+    let keys = flags.key_encoding.unwrap_or_default();
+
+    let mut histogram = BTreeMap::<usize, u64>::new();
+    let mut listed = Vec::new();
+    for key in db.unique_keys() {
+        let key = key?;
+        let count = db.count(&key)?;
+        *histogram.entry(count).or_default() += 1;
+        if flags.threshold.is_some_and(|threshold| count >= threshold) {
+            listed.push((count, key));
+        }
+    }
+
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    writeln!(out, "{:>8} {:>12}", "values", "keys")?;
+    for (count, keys) in histogram {
+        writeln!(out, "{:>8} {:>12}", count, keys)?;
+    }
+    if let Some(threshold) = flags.threshold {
+        listed.sort_by_key(|(count, _)| Reverse(*count));
+        writeln!(out, "\nkeys with at least {} values:", threshold)?;
+        for (count, key) in listed {
+            writeln!(out, "{:>8}  {}", count, keys.encode(&key))?;
+        }
+    }
+    out.flush()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The most keys `bench` holds in memory to look up.
+const BENCH_KEYS: usize = 100_000;
+
+/// Look up `lookups` keys chosen by `next_key`, printing the throughput
+/// and latency percentiles.
+fn bench_lookups(
+    out: &mut impl Write,
+    name: &str,
+    db: &CDB,
+    lookups: usize,
+    mut next_key: impl FnMut() -> Vec<u8>,
+) -> Result<()> {
+    let mut latencies = Vec::with_capacity(lookups);
+    let start = Instant::now();
+    for _ in 0..lookups {
+        let key = next_key();
+        let lookup = Instant::now();
+        if let Some(value) = db.get(&key) {
+            value?;
+        }
+        latencies.push(lookup.elapsed());
+    }
+    let elapsed = start.elapsed();
+    latencies.sort_unstable();
+    let percentile = |p: usize| {
+        latencies
+            .get((latencies.len() * p / 100).min(latencies.len().saturating_sub(1)))
+            .copied()
+            .unwrap_or_default()
+    };
+    writeln!(
+        out,
+        "{:<8}{} lookups in {:.3?} ({:.0} lookups/s); p50 {:?}, p90 {:?}, p99 {:?}, max {:?}",
+        name,
+        lookups,
+        elapsed,
+        lookups as f64 / elapsed.as_secs_f64(),
+        percentile(50),
+        percentile(90),
+        percentile(99),
+        latencies.last().copied().unwrap_or(Duration::ZERO),
+    )
+}
+
+fn bench(flags: flags::Bench) -> Result<ExitCode> {
+    let db = CDB::open(flags.file)?;
+    let lookups = flags.lookups.unwrap_or(100_000);
+    let mut rng = match flags.seed {
+        Some(seed) => fastrand::Rng::with_seed(seed),
+        None => fastrand::Rng::new(),
+    };
+
+    // Scan the whole file, keeping a sample of the keys to look up.
+    let mut keys = Vec::new();
+    let (mut records, mut bytes) = (0_usize, 0_u64);
+    let start = Instant::now();
+    for record in db.iter() {
+        let (key, value) = record?;
+        bytes += (key.len() + value.len()) as u64;
+        if keys.len() < BENCH_KEYS {
+            keys.push(key);
+        } else {
+            let j = rng.usize(..=records);
+            if j < BENCH_KEYS {
+                keys[j] = key;
+            }
+        }
+        records += 1;
+    }
+    let elapsed = start.elapsed();
+
+    let mut out = io::stdout().lock();
+    let secs = elapsed.as_secs_f64();
+    writeln!(
+        out,
+        "{:<8}{} records, {} bytes in {:.3?} ({:.0} records/s, {:.1} MiB/s)",
+        "scan",
+        records,
+        bytes,
+        elapsed,
+        records as f64 / secs,
+        bytes as f64 / secs / (1024.0 * 1024.0),
+    )?;
+    if !keys.is_empty() {
+        bench_lookups(&mut out, "hits", &db, lookups, || {
+            keys[rng.usize(..keys.len())].clone()
+        })?;
+    }
+    // Random 16 byte keys, which are almost certainly missing.
+    bench_lookups(&mut out, "misses", &db, lookups, || {
+        (0..16).map(|_| rng.u8(..)).collect()
+    })?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn keys(flags: flags::Keys) -> Result<ExitCode> {
+    let db = CDB::open(flags.file)?;
+    let encoding = flags.key_encoding.unwrap_or_default();
+    let keys: Box<dyn Iterator<Item = Result<Vec<u8>>>> = if flags.unique {
+        Box::new(db.unique_keys())
+    } else {
+        Box::new(db.keys())
+    };
+
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    if flags.count {
+        let mut count = 0_u64;
+        for key in keys {
+            key?;
+            count += 1;
+        }
+        writeln!(out, "{}", count)?;
+    } else {
+        for key in keys {
+            writeln!(out, "{}", encoding.encode(&key?))?;
+        }
+    }
+    out.flush()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run() -> Result<ExitCode> {
+    // The default command cannot be named on the command line, so accept
+    // `cdb dump` by dropping the name.
+    let mut args = std::env::args_os().skip(1).collect::<Vec<_>>();
+    if args.first().is_some_and(|arg| arg == "dump") {
+        args.remove(0);
+    }
+    let flags = match flags::Cdb::from_vec(args) {
+        Ok(flags) => flags,
+        Err(err) => err.exit(),
+    };
+    match flags.subcommand {
+        flags::CdbCmd::Dump(flags) => dump(flags),
+        flags::CdbCmd::Get(flags) => get(flags),
+        flags::CdbCmd::Make(flags) => make(flags),
+        flags::CdbCmd::Merge(flags) => merge(flags),
+        flags::CdbCmd::Grep(flags) => grep(flags),
+        flags::CdbCmd::Convert(flags) => convert(flags),
+        flags::CdbCmd::Salvage(flags) => salvage(flags),
+        flags::CdbCmd::ExportSqlite(flags) => export_sqlite(flags),
+        flags::CdbCmd::Top(flags) => top(flags),
+        flags::CdbCmd::Sample(flags) => sample(flags),
+        flags::CdbCmd::Dups(flags) => dups(flags),
+        flags::CdbCmd::Bench(flags) => bench(flags),
+        flags::CdbCmd::Keys(flags) => keys(flags),
+        flags::CdbCmd::Serve(flags) => serve(flags),
+    }
+}
+
+pub fn main() -> ExitCode {
+    match run() {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("cdb: {}", err);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use std::io::{self, Result};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::{Body, Bytes};
+use axum::extract::State;
+use axum::http::{header, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use cdb32::{ReloadableCDB, CDB};
+use tokio::sync::{mpsc, Semaphore};
+use tokio_stream::wrappers::ReceiverStream;
+use tower::limit::GlobalConcurrencyLimitLayer;
+
+use crate::flags;
+
+/// The files served, shared by every request.
+type Dbs = Arc<Vec<ReloadableCDB>>;
+
+/// Decode `%XX` escapes, returning `None` for a malformed escape.
+fn percent_decode(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = text.bytes();
+    let mut out = Vec::with_capacity(text.len());
+    while let Some(b) = bytes.next() {
+        if b != b'%' {
+            out.push(b);
+            continue;
+        }
+        let hex = [bytes.next()?, bytes.next()?];
+        out.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+    }
+    Some(out)
+}
+
+/// Escape every byte but the unreserved characters of RFC 3986.
+fn percent_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len());
+    for &b in data {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+fn text(status: StatusCode, body: impl Into<String>) -> Response {
+    (status, [(header::CONTENT_TYPE, "text/plain")], body.into()).into_response()
+}
+
+/// Take a consistent set of files, even if one is replaced while the
+/// request is handled.
+fn current(dbs: &Dbs) -> Vec<Arc<CDB>> {
+    dbs.iter().map(ReloadableCDB::current).collect()
+}
+
+/// Return the first value of `key` in the first database which has it.
+fn lookup(dbs: &[Arc<CDB>], key: &[u8]) -> Result<Option<Vec<u8>>> {
+    for db in dbs {
+        if let Some(value) = db.get(key) {
+            return value.map(Some);
+        }
+    }
+    Ok(None)
+}
+
+async fn get_value(State(dbs): State<Dbs>, uri: Uri) -> Response {
+    // The key is decoded here rather than by a path extractor, as keys
+    // need not be UTF-8.
+    let key = match uri.path().strip_prefix("/v1/get/").map(percent_decode) {
+        Some(Some(key)) => key,
+        _ => return text(StatusCode::BAD_REQUEST, "Malformed key\n"),
+    };
+    let dbs = current(&dbs);
+    match tokio::task::spawn_blocking(move || lookup(&dbs, &key)).await {
+        Ok(Ok(Some(value))) => {
+            ([(header::CONTENT_TYPE, "application/octet-stream")], value).into_response()
+        }
+        Ok(Ok(None)) => text(StatusCode::NOT_FOUND, "Key not found\n"),
+        Ok(Err(err)) => text(StatusCode::INTERNAL_SERVER_ERROR, format!("{}\n", err)),
+        Err(err) => text(StatusCode::INTERNAL_SERVER_ERROR, format!("{}\n", err)),
+    }
+}
+
+/// Send each key once, skipping keys already in an earlier database,
+/// stopping if the client goes away.
+fn send_keys(dbs: &[Arc<CDB>], tx: &mpsc::Sender<Result<Bytes>>) -> Result<()> {
+    for (i, db) in dbs.iter().enumerate() {
+        for key in db.unique_keys() {
+            let key = key?;
+            if !crate::exists_in(&dbs[..i], &key)? {
+                let line = Bytes::from(percent_encode(&key) + "\n");
+                if tx.blocking_send(Ok(line)).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Stream the keys, as there may be too many to hold at once.
+async fn get_keys(State(dbs): State<Dbs>) -> Response {
+    let dbs = current(&dbs);
+    let (tx, rx) = mpsc::channel(64);
+    tokio::task::spawn_blocking(move || {
+        if let Err(err) = send_keys(&dbs, &tx) {
+            eprintln!("cdb: {}", err);
+            // Cut the body short, so the client sees the failure.
+            let _ = tx.blocking_send(Err(err));
+        }
+    });
+    (
+        [(header::CONTENT_TYPE, "text/plain")],
+        Body::from_stream(ReceiverStream::new(rx)),
+    )
+        .into_response()
+}
+
+/// Route the requests, handling no more at once than `limit` has permits.
+fn router(dbs: Dbs, limit: Arc<Semaphore>) -> Router {
+    Router::new()
+        .route("/v1/keys", get(get_keys))
+        .route("/v1/get/", get(get_value))
+        .route("/v1/get/{*key}", get(get_value))
+        .fallback(|| async { text(StatusCode::NOT_FOUND, "Not found\n") })
+        .layer(GlobalConcurrencyLimitLayer::with_semaphore(limit))
+        .with_state(dbs)
+}
+
+/// Reload any replaced files every `interval`.
+async fn reload(dbs: Dbs, interval: Duration) {
+    let mut ticks = tokio::time::interval(interval);
+    ticks.tick().await;
+    loop {
+        ticks.tick().await;
+        let dbs = Arc::clone(&dbs);
+        let _ = tokio::task::spawn_blocking(move || {
+            for db in dbs.iter() {
+                match db.reload_if_changed() {
+                    Ok(true) => eprintln!("cdb: reloaded {}", db.path().display()),
+                    Ok(false) => {}
+                    // Keep serving the old file; the new one may only be
+                    // partly written.
+                    Err(err) => eprintln!("cdb: {}: {}", db.path().display(), err),
+                }
+            }
+        })
+        .await;
+    }
+}
+
+#[tokio::main]
+pub(crate) async fn serve(flags: flags::Serve) -> Result<ExitCode> {
+    if flags.files.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "No CDB files to serve",
+        ));
+    }
+    let dbs = flags
+        .files
+        .iter()
+        .map(ReloadableCDB::open)
+        .collect::<Result<Vec<_>>>()?;
+    let dbs = Arc::new(dbs);
+    let listener =
+        tokio::net::TcpListener::bind(flags.listen.as_deref().unwrap_or("127.0.0.1:8080")).await?;
+    eprintln!("cdb: listening on http://{}", listener.local_addr()?);
+
+    let interval = Duration::from_millis(flags.interval.unwrap_or(1000));
+    tokio::spawn(reload(Arc::clone(&dbs), interval));
+
+    let limit = Semaphore::new(flags.concurrency.unwrap_or(64).max(1));
+    axum::serve(listener, router(dbs, Arc::new(limit))).await?;
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+    use cdb32::CDBWriter;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn send(app: Router, uri: &str) -> (StatusCode, Bytes) {
+        let request = Request::get(uri).body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, body)
+    }
+
+    #[tokio::test]
+    async fn test_router() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.cdb");
+        CDBWriter::from_iter(&path, [("one", "1"), ("a b", "2")]).unwrap();
+        let dbs = Arc::new(vec![ReloadableCDB::open(&path).unwrap()]);
+        let limit = Arc::new(Semaphore::new(1));
+        let app = router(dbs, Arc::clone(&limit));
+
+        assert_eq!(
+            send(app.clone(), "/v1/get/one").await,
+            (StatusCode::OK, Bytes::from("1"))
+        );
+        assert_eq!(
+            send(app.clone(), "/v1/get/a%20b").await,
+            (StatusCode::OK, Bytes::from("2"))
+        );
+        assert_eq!(
+            send(app.clone(), "/v1/get/two").await.0,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            send(app.clone(), "/v1/get/%zz").await.0,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(send(app.clone(), "/other").await.0, StatusCode::NOT_FOUND);
+
+        // With the only permit taken a request waits until it is released.
+        let permit = limit.acquire().await.unwrap();
+        let waiting = send(app, "/v1/get/one");
+        tokio::pin!(waiting);
+        let timeout = tokio::time::timeout(Duration::from_millis(50), &mut waiting);
+        assert!(timeout.await.is_err());
+        drop(permit);
+        assert_eq!(waiting.await, (StatusCode::OK, Bytes::from("1")));
+    }
+}
@@ -0,0 +1,126 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use cdb32::{CDBWriter, CDB};
+
+fn cdb(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cdb"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn cdb_with_stdin(args: &[&str], input: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cdb"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn write_db(dir: &Path, name: &str, records: &[(&str, &str)]) -> PathBuf {
+    let path = dir.join(name);
+    CDBWriter::from_iter(&path, records.iter().copied()).unwrap();
+    path
+}
+
+fn values(path: &Path, key: &str) -> Vec<String> {
+    let db = CDB::open(path).unwrap();
+    db.find(key.as_bytes())
+        .map(|value| String::from_utf8(value?).map_err(io::Error::other))
+        .collect::<io::Result<Vec<_>>>()
+        .unwrap()
+}
+
+#[test]
+fn test_merge_on_duplicate() {
+    let dir = tempfile::tempdir().unwrap();
+    let a = write_db(dir.path(), "a.cdb", &[("shared", "a"), ("only-a", "1")]);
+    let b = write_db(dir.path(), "b.cdb", &[("shared", "b1"), ("shared", "b2")]);
+    let c = write_db(dir.path(), "c.cdb", &[("shared", "c"), ("only-c", "3")]);
+    let inputs = [
+        a.to_str().unwrap(),
+        b.to_str().unwrap(),
+        c.to_str().unwrap(),
+    ];
+    let output = dir.path().join("merged.cdb");
+
+    for (policy, expected) in [
+        ("first", &["a"][..]),
+        ("last", &["c"]),
+        ("all", &["a", "b1", "b2", "c"]),
+    ] {
+        let mut args = vec!["merge", "-o", output.to_str().unwrap()];
+        args.extend(["--on-duplicate", policy]);
+        args.extend(inputs);
+        let out = cdb(&args);
+        assert!(out.status.success(), "{}: {:?}", policy, out);
+        assert_eq!(values(&output, "shared"), expected, "{}", policy);
+        assert_eq!(values(&output, "only-a"), ["1"], "{}", policy);
+        assert_eq!(values(&output, "only-c"), ["3"], "{}", policy);
+    }
+
+    // Without the flag every record is kept.
+    let mut args = vec!["merge", "-o", output.to_str().unwrap()];
+    args.extend(inputs);
+    assert!(cdb(&args).status.success());
+    assert_eq!(values(&output, "shared").len(), 4);
+
+    let mut args = vec!["merge", "-o", output.to_str().unwrap()];
+    args.extend(["--on-duplicate", "error"]);
+    args.extend(inputs);
+    assert!(!cdb(&args).status.success());
+}
+
+#[test]
+fn test_get() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = write_db(
+        dir.path(),
+        "db.cdb",
+        &[("one", "1"), ("two", "2"), ("one", "uno")],
+    );
+    let db = db.to_str().unwrap();
+
+    let out = cdb(&["get", db, "one"]);
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(out.stdout, b"1\nuno\n");
+
+    let out = cdb(&["get", db, "three"]);
+    assert_eq!(out.status.code(), Some(1));
+    assert_eq!(out.stdout, b"");
+
+    let out = cdb(&["get", db]);
+    assert_eq!(out.status.code(), Some(2));
+}
+
+#[test]
+fn test_get_stdin() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = write_db(
+        dir.path(),
+        "db.cdb",
+        &[("one", "1"), ("two", "2"), ("one", "uno")],
+    );
+    let db = db.to_str().unwrap();
+
+    let out = cdb_with_stdin(&["get", db, "--stdin"], b"one\nthree\r\ntwo\n");
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8(out.stdout).unwrap(),
+        "+\tone\t1\n+\tone\tuno\n-\tthree\n+\ttwo\t2\n"
+    );
+
+    let args = ["get", db, "--stdin", "--found", "HIT", "--missing", "MISS"];
+    let out = cdb_with_stdin(&args, b"three\ntwo\n");
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8(out.stdout).unwrap(),
+        "MISS\tthree\nHIT\ttwo\t2\n"
+    );
+}
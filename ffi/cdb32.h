@@ -0,0 +1,44 @@
+/* C interface to the cdb32 library, modelled on tinycdb.
+ *
+ * Handles are opaque. Functions returning a pointer return NULL on
+ * failure, and functions returning int return a negative number;
+ * cdb_strerror() then describes the failure.
+ */
+
+#ifndef CDB32_H
+#define CDB32_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+struct cdb;
+struct cdb_make;
+
+const char *cdb_strerror(void);
+
+struct cdb *cdb_open(const char *name);
+struct cdb *cdb_open_bytes(const void *data, size_t len);
+void cdb_free(struct cdb *cdbp);
+int cdb_find(struct cdb *cdbp, const void *key, unsigned klen);
+int cdb_findnext(struct cdb *cdbp);
+unsigned cdb_datalen(const struct cdb *cdbp);
+const void *cdb_getdata(const struct cdb *cdbp);
+int cdb_seqinit(struct cdb *cdbp);
+int cdb_seqnext(struct cdb *cdbp);
+unsigned cdb_keylen(const struct cdb *cdbp);
+const void *cdb_getkey(const struct cdb *cdbp);
+
+struct cdb_make *cdb_make_start(const char *name);
+int cdb_make_add(struct cdb_make *cdbmp, const void *key, unsigned klen,
+                 const void *val, unsigned vlen);
+int cdb_make_finish(struct cdb_make *cdbmp);
+void cdb_make_free(struct cdb_make *cdbmp);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* CDB32_H */
@@ -0,0 +1,4 @@
+//! Builds the C interface of `cdb32`, declared in `cdb32.h`, as a shared
+//! and a static library. See the `cdb32::ffi` module.
+
+pub use cdb32::ffi::*;
@@ -0,0 +1,4 @@
+//! Builds the JavaScript bindings of `cdb32` for `wasm32-unknown-unknown`.
+//! See the `cdb32::wasm` module.
+
+pub use cdb32::wasm::*;
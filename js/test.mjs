@@ -0,0 +1,28 @@
+// Run with: node js/test.mjs, after generating js/pkg as described in
+// the cdb32::wasm documentation.
+
+import assert from "node:assert/strict";
+import { readFile } from "node:fs/promises";
+
+import { initSync, CdbReader } from "./pkg/cdb32.js";
+
+const root = new URL("../", import.meta.url);
+initSync({ module: await readFile(new URL("js/pkg/cdb32_bg.wasm", root)) });
+
+const text = (bytes) => new TextDecoder().decode(bytes);
+
+const cdb = CdbReader.fromBytes(await readFile(new URL("tests/test1.cdb", root)));
+assert.equal(text(cdb.get("one")), "Hello");
+assert.deepEqual(cdb.find(new TextEncoder().encode("one")).map(text), ["Hello", ", World!"]);
+assert.equal(cdb.get("three"), undefined);
+assert.ok(cdb.has("two"));
+assert.throws(() => cdb.get(1), /string or a Uint8Array/);
+const records = cdb.entries().map(([key, value]) => [text(key), text(value)]);
+assert.equal(records.length, 4);
+assert.deepEqual(records[1], ["two", "Goodbye"]);
+cdb.free();
+assert.throws(() => cdb.get("one"));
+
+assert.throws(() => CdbReader.fromBytes(new Uint8Array(100)), Error);
+
+console.log("ok");
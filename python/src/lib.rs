@@ -0,0 +1,242 @@
+//! Python bindings for `cdb32`, built into the `cdb32` module by
+//! maturin:
+//!
+//! ```text
+//! cd python && maturin develop
+//! ```
+//!
+//! ```python
+//! from cdb32 import Cdb, CdbWriter
+//!
+//! with CdbWriter("data.cdb") as writer:
+//!     writer.add(b"one", b"Hello")
+//!     writer.add(b"one", b", world")
+//!
+//! with Cdb.open("data.cdb") as cdb:
+//!     assert cdb.get(b"one") == b"Hello"
+//!     assert cdb.find(b"one") == [b"Hello", b", world"]
+//!     for key, value in cdb:
+//!         print(key, value)
+//! ```
+//!
+//! Keys and values are `bytes`; they may also be given as `str`, which
+//! is encoded as UTF-8. Failures raise `CdbError`, a subclass of
+//! `OSError`.
+
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use ::cdb32::{CDBOwnedKeyValueIter, CDBWriter as Writer, CDB};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyKeyError, PyOSError, PyStopIteration, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyString};
+
+create_exception!(
+    cdb32,
+    CdbError,
+    PyOSError,
+    "A failure reported by the cdb32 library."
+);
+
+fn cdb_error(err: io::Error) -> PyErr {
+    CdbError::new_err(err.to_string())
+}
+
+/// A key or value given as `bytes` or as `str`.
+struct Bytes(Vec<u8>);
+
+impl<'py> FromPyObject<'py> for Bytes {
+    fn extract_bound(obj: &Bound<'py, PyAny>) -> PyResult<Self> {
+        if let Ok(s) = obj.downcast::<PyString>() {
+            return Ok(Bytes(s.to_str()?.as_bytes().to_vec()));
+        }
+        Ok(Bytes(obj.extract::<&[u8]>()?.to_vec()))
+    }
+}
+
+/// A database opened for reading.
+#[pyclass(module = "cdb32")]
+struct Cdb {
+    cdb: Option<Arc<CDB>>,
+}
+
+impl Cdb {
+    fn live(&self) -> PyResult<&Arc<CDB>> {
+        self.cdb
+            .as_ref()
+            .ok_or_else(|| PyValueError::new_err("operation on a closed Cdb"))
+    }
+}
+
+#[pymethods]
+impl Cdb {
+    /// Open the database in the file `path`.
+    #[staticmethod]
+    fn open(path: PathBuf) -> PyResult<Self> {
+        let cdb = CDB::open(path).map_err(cdb_error)?;
+        Ok(Cdb {
+            cdb: Some(Arc::new(cdb)),
+        })
+    }
+
+    /// Close the database. Closing it again does nothing.
+    fn close(&mut self) {
+        self.cdb = None;
+    }
+
+    fn __enter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        _exc_type: PyObject,
+        _exc_value: PyObject,
+        _traceback: PyObject,
+    ) -> bool {
+        self.close();
+        false
+    }
+
+    /// Return the first value of `key`, or `default` if it has none.
+    #[pyo3(signature = (key, default = None))]
+    fn get(&self, py: Python<'_>, key: Bytes, default: Option<PyObject>) -> PyResult<PyObject> {
+        match self.live()?.get(&key.0) {
+            Some(value) => Ok(PyBytes::new(py, &value.map_err(cdb_error)?)
+                .into_any()
+                .unbind()),
+            None => Ok(default.unwrap_or_else(|| py.None())),
+        }
+    }
+
+    /// Return a list of every value of `key`, in the order added.
+    fn find<'py>(&self, py: Python<'py>, key: Bytes) -> PyResult<Vec<Bound<'py, PyBytes>>> {
+        self.live()?
+            .find(&key.0)
+            .map(|value| Ok(PyBytes::new(py, &value.map_err(cdb_error)?)))
+            .collect()
+    }
+
+    fn __getitem__<'py>(&self, py: Python<'py>, key: Bytes) -> PyResult<Bound<'py, PyBytes>> {
+        match self.live()?.get(&key.0) {
+            Some(value) => Ok(PyBytes::new(py, &value.map_err(cdb_error)?)),
+            None => Err(PyKeyError::new_err(PyBytes::new(py, &key.0).unbind())),
+        }
+    }
+
+    fn __contains__(&self, key: Bytes) -> PyResult<bool> {
+        self.live()?.exists(&key.0).map_err(cdb_error)
+    }
+
+    fn __len__(&self) -> PyResult<usize> {
+        Ok(self.live()?.len())
+    }
+
+    /// Iterate over the `(key, value)` pairs of every record.
+    fn __iter__(&self) -> PyResult<CdbIter> {
+        let records = Arc::clone(self.live()?).into_records();
+        Ok(CdbIter(Mutex::new(records)))
+    }
+}
+
+/// An iterator over the records of a [`Cdb`], which keeps the database
+/// open while it is used.
+#[pyclass(module = "cdb32")]
+struct CdbIter(Mutex<CDBOwnedKeyValueIter>);
+
+#[pymethods]
+impl CdbIter {
+    fn __iter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    fn __next__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<(Bound<'py, PyBytes>, Bound<'py, PyBytes>)> {
+        let next = self.0.lock().unwrap().next();
+        match next {
+            Some(record) => {
+                let (key, value) = record.map_err(cdb_error)?;
+                Ok((PyBytes::new(py, &key), PyBytes::new(py, &value)))
+            }
+            None => Err(PyStopIteration::new_err(())),
+        }
+    }
+}
+
+/// Writes a new database to `path`.
+///
+/// Records are written to a temporary file which `finish` renames to
+/// `path`. Used as a context manager, the writer is finished when the
+/// block ends, or aborted if it raises.
+#[pyclass(module = "cdb32")]
+struct CdbWriter {
+    // The writer is not Sync, as it may hold a progress callback.
+    writer: Mutex<Option<Writer>>,
+}
+
+fn finished() -> PyErr {
+    PyValueError::new_err("operation on a finished CdbWriter")
+}
+
+#[pymethods]
+impl CdbWriter {
+    #[new]
+    fn new(path: PathBuf) -> PyResult<Self> {
+        let writer = Writer::create(path).map_err(cdb_error)?;
+        Ok(CdbWriter {
+            writer: Mutex::new(Some(writer)),
+        })
+    }
+
+    /// Add a record.
+    fn add(&self, key: Bytes, value: Bytes) -> PyResult<()> {
+        let mut writer = self.writer.lock().unwrap();
+        let writer = writer.as_mut().ok_or_else(finished)?;
+        writer.add(key.0, value.0).map_err(cdb_error)
+    }
+
+    /// Write out the database and rename it into place.
+    fn finish(&self) -> PyResult<()> {
+        let writer = self.writer.lock().unwrap().take().ok_or_else(finished)?;
+        writer.finish().map(drop).map_err(cdb_error)
+    }
+
+    /// Abandon the database, removing the temporary file.
+    fn abort(&self) -> PyResult<()> {
+        match self.writer.lock().unwrap().take() {
+            Some(writer) => writer.abort().map_err(cdb_error),
+            None => Ok(()),
+        }
+    }
+
+    fn __enter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &self,
+        exc_type: PyObject,
+        _exc_value: PyObject,
+        _traceback: PyObject,
+        py: Python<'_>,
+    ) -> PyResult<bool> {
+        if exc_type.is_none(py) {
+            self.finish()?;
+        } else {
+            self.abort()?;
+        }
+        Ok(false)
+    }
+}
+
+#[pymodule]
+fn cdb32(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Cdb>()?;
+    m.add_class::<CdbWriter>()?;
+    m.add("CdbError", m.py().get_type::<CdbError>())?;
+    Ok(())
+}
@@ -0,0 +1,50 @@
+import os
+import tempfile
+import unittest
+
+from cdb32 import Cdb, CdbError, CdbWriter
+
+TESTS = os.path.join(os.path.dirname(__file__), "..", "tests")
+
+
+class CdbTest(unittest.TestCase):
+    def test_read(self):
+        with Cdb.open(os.path.join(TESTS, "test1.cdb")) as cdb:
+            self.assertEqual(cdb.get(b"one"), b"Hello")
+            self.assertEqual(cdb.find("one"), [b"Hello", b", World!"])
+            self.assertEqual(cdb[b"two"], b"Goodbye")
+            self.assertNotIn(b"three", cdb)
+            self.assertEqual(cdb.get(b"three", b"none"), b"none")
+            with self.assertRaises(KeyError):
+                cdb[b"three"]
+            self.assertEqual(len(cdb), 4)
+            records = list(cdb)
+            self.assertEqual(len(records), 4)
+            self.assertEqual(records[:2], [(b"one", b"Hello"), (b"two", b"Goodbye")])
+
+    def test_write(self):
+        with tempfile.TemporaryDirectory() as tmp:
+            path = os.path.join(tmp, "test.cdb")
+            with CdbWriter(path) as writer:
+                writer.add(b"\x00key", b"\xffvalue")
+                writer.add("", "")
+            with Cdb.open(path) as cdb:
+                self.assertEqual(list(cdb), [(b"\x00key", b"\xffvalue"), (b"", b"")])
+
+            with self.assertRaises(RuntimeError):
+                with CdbWriter(os.path.join(tmp, "aborted.cdb")) as writer:
+                    writer.add(b"one", b"1")
+                    raise RuntimeError
+            self.assertEqual(os.listdir(tmp), ["test.cdb"])
+
+    def test_errors(self):
+        with self.assertRaises(CdbError):
+            Cdb.open(os.path.join(TESTS, "missing.cdb"))
+        cdb = Cdb.open(os.path.join(TESTS, "test1.cdb"))
+        cdb.close()
+        with self.assertRaises(ValueError):
+            cdb.get(b"one")
+
+
+if __name__ == "__main__":
+    unittest.main()
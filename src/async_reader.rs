@@ -0,0 +1,191 @@
+use std::future::Future;
+use std::io;
+use std::mem;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use tokio::task::{self, JoinHandle};
+
+use crate::format::{Format, Format32, Format64};
+use crate::reader::{CDBOwnedValueIter, GenericCDB, OpenOptions, Result};
+
+/// Asynchronous CDB file reader, generic over the on-disk [`Format`].
+///
+/// Lookups run on tokio's blocking thread pool, so page faults on a cold
+/// mapping or positioned reads never block the executor. Most code
+/// should use the [`AsyncCDB`] or [`AsyncCDB64`] aliases.
+///
+/// # Example
+///
+/// ```
+/// # #[tokio::main]
+/// # async fn main() -> std::io::Result<()> {
+/// use cdb32::AsyncCDB;
+///
+/// let cdb = AsyncCDB::open("tests/test1.cdb").await?;
+/// if let Some(record) = cdb.get(b"one").await {
+///     println!("{:?}", record?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct GenericAsyncCDB<F> {
+    cdb: Arc<GenericCDB<F>>,
+}
+
+/// Asynchronous CDB file reader for the classic 32-bit format.
+pub type AsyncCDB = GenericAsyncCDB<Format32>;
+
+/// Asynchronous CDB file reader for the 64-bit format.
+pub type AsyncCDB64 = GenericAsyncCDB<Format64>;
+
+impl<F> Clone for GenericAsyncCDB<F> {
+    fn clone(&self) -> Self {
+        GenericAsyncCDB {
+            cdb: Arc::clone(&self.cdb),
+        }
+    }
+}
+
+/// Run a blocking closure on tokio's blocking thread pool.
+async fn unblock<T, G>(f: G) -> Result<T>
+where
+    T: Send + 'static,
+    G: FnOnce() -> T + Send + 'static,
+{
+    join(task::spawn_blocking(f).await)
+}
+
+fn join<T>(result: std::result::Result<T, task::JoinError>) -> Result<T> {
+    match result {
+        Ok(value) => Ok(value),
+        Err(err) => match err.try_into_panic() {
+            Ok(payload) => std::panic::resume_unwind(payload),
+            Err(err) => Err(io::Error::new(io::ErrorKind::Other, err)),
+        },
+    }
+}
+
+impl<F: Format> GenericAsyncCDB<F> {
+    /// Opens the named file and returns the asynchronous CDB reader.
+    pub async fn open<P: AsRef<Path>>(filename: P) -> Result<Self> {
+        Self::open_with_options(filename, &OpenOptions::new()).await
+    }
+
+    /// Opens the named file with the given options and returns the
+    /// asynchronous CDB reader.
+    pub async fn open_with_options<P: AsRef<Path>>(
+        filename: P,
+        options: &OpenOptions,
+    ) -> Result<Self> {
+        let file = tokio::fs::File::open(filename).await?.into_std().await;
+        let options = options.clone();
+        let cdb = unblock(move || GenericCDB::from_file(file, &options)).await??;
+        Ok(GenericAsyncCDB { cdb: Arc::new(cdb) })
+    }
+
+    /// Return the underlying synchronous reader.
+    pub fn reader(&self) -> &Arc<GenericCDB<F>> {
+        &self.cdb
+    }
+
+    /// Find the first record with the named key.
+    pub async fn get(&self, key: &[u8]) -> Option<Result<Vec<u8>>> {
+        let cdb = Arc::clone(&self.cdb);
+        let key = key.to_vec();
+        match unblock(move || cdb.get(&key)).await {
+            Ok(result) => result,
+            Err(err) => Some(Err(err)),
+        }
+    }
+
+    /// Find all records with the named key. The returned stream produces
+    /// each value associated with the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// use std::future::poll_fn;
+    /// use std::pin::Pin;
+    ///
+    /// use cdb32::AsyncCDB;
+    /// use futures_core::Stream;
+    ///
+    /// let cdb = AsyncCDB::open("tests/test1.cdb").await?;
+    /// let mut values = cdb.find(b"one");
+    /// while let Some(result) = poll_fn(|cx| Pin::new(&mut values).poll_next(cx)).await {
+    ///     println!("{:?}", result?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn find(&self, key: &[u8]) -> AsyncCDBValueStream<F> {
+        AsyncCDBValueStream {
+            state: State::Idle(Arc::clone(&self.cdb).into_find(key)),
+        }
+    }
+}
+
+impl<F> From<GenericCDB<F>> for GenericAsyncCDB<F> {
+    fn from(cdb: GenericCDB<F>) -> Self {
+        GenericAsyncCDB { cdb: Arc::new(cdb) }
+    }
+}
+
+type Step<F> = (CDBOwnedValueIter<F>, Option<Result<Vec<u8>>>);
+
+#[derive(Debug)]
+enum State<F> {
+    Idle(CDBOwnedValueIter<F>),
+    Busy(JoinHandle<Step<F>>),
+    Done,
+}
+
+/// Stream over a set of records in the CDB with the same key.
+///
+/// See [`GenericAsyncCDB::find`]
+#[derive(Debug)]
+pub struct AsyncCDBValueStream<F = Format32> {
+    state: State<F>,
+}
+
+impl<F: Format> Stream for AsyncCDBValueStream<F> {
+    type Item = Result<Vec<u8>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match mem::replace(&mut self.state, State::Done) {
+                State::Idle(mut iter) => {
+                    self.state = State::Busy(task::spawn_blocking(move || {
+                        let item = iter.next();
+                        (iter, item)
+                    }));
+                }
+                State::Busy(mut handle) => {
+                    return match Pin::new(&mut handle).poll(cx) {
+                        Poll::Pending => {
+                            self.state = State::Busy(handle);
+                            Poll::Pending
+                        }
+                        Poll::Ready(result) => match join(result) {
+                            Ok((iter, item)) => {
+                                if item.is_some() {
+                                    self.state = State::Idle(iter);
+                                }
+                                Poll::Ready(item)
+                            }
+                            Err(err) => Poll::Ready(Some(Err(err))),
+                        },
+                    };
+                }
+                State::Done => return Poll::Ready(None),
+            }
+        }
+    }
+}
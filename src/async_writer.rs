@@ -0,0 +1,211 @@
+use std::fs;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+
+use crate::format::{Format, Format32, Format64};
+use crate::hash::DjbHash;
+use crate::reader::Result;
+use crate::writer::{BuildStats, DuplicatePolicy, Durability, GenericCDBMake};
+
+/// Write records to the file once this many bytes are pending.
+const FLUSH_SIZE: usize = 64 * 1024;
+
+/// The bytes written by a maker which are yet to be written to the file.
+///
+/// The maker only seeks to where it already is, except when finishing,
+/// which is handled separately.
+#[derive(Debug, Default)]
+struct Pending {
+    data: Vec<u8>,
+    written: u64,
+}
+
+impl Pending {
+    fn pos(&self) -> u64 {
+        self.written + self.data.len() as u64
+    }
+}
+
+impl Write for Pending {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for Pending {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        match pos {
+            SeekFrom::Start(pos) if pos == self.pos() => Ok(pos),
+            SeekFrom::Current(0) => Ok(self.pos()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Seeking is not supported by the asynchronous writer",
+            )),
+        }
+    }
+}
+
+/// Asynchronous CDB file writer, generic over the on-disk [`Format`].
+///
+/// Like [`GenericCDBWriter`](crate::GenericCDBWriter), this writes to a
+/// temporary file which [`finish`](Self::finish) renames over the
+/// destination, but the file is written with tokio's asynchronous file
+/// I/O, so a long build does not tie up an executor thread. Most code
+/// should use the [`AsyncCDBWriter`] or [`AsyncCDB64Writer`] aliases.
+///
+/// # Example
+///
+/// ```
+/// # #[tokio::main]
+/// # async fn main() -> std::io::Result<()> {
+/// # let tmp_dir = tempfile::tempdir()?;
+/// # let path = tmp_dir.path().join("async.cdb");
+/// use cdb32::{AsyncCDBWriter, CDB};
+///
+/// let mut cdb = AsyncCDBWriter::create(&path).await?;
+/// cdb.add(b"one", b"Hello").await?;
+/// cdb.add(b"two", b"Goodbye").await?;
+/// cdb.finish().await?;
+///
+/// let cdb = CDB::open(&path)?;
+/// assert_eq!(cdb.get(b"two").unwrap()?, b"Goodbye");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct GenericAsyncCDBWriter<F> {
+    dstname: PathBuf,
+    tmpname: PathBuf,
+    durability: Durability,
+    temp_exists: bool,
+    file: Option<tokio::fs::File>,
+    cdb: GenericCDBMake<F, DjbHash, Pending>,
+}
+
+/// Asynchronous CDB file writer for the classic 32-bit format.
+pub type AsyncCDBWriter = GenericAsyncCDBWriter<Format32>;
+
+/// Asynchronous CDB file writer for the 64-bit format.
+pub type AsyncCDB64Writer = GenericAsyncCDBWriter<Format64>;
+
+impl<F: Format> GenericAsyncCDBWriter<F> {
+    /// Safely create a new CDB file, writing to a temporary file named
+    /// after it with a `".tmp"` suffix.
+    pub async fn create<P: Into<PathBuf>>(filename: P) -> Result<Self> {
+        let dstname = filename.into();
+        let mut tmpname = dstname.clone().into_os_string();
+        tmpname.push(".tmp");
+        let tmpname = PathBuf::from(tmpname);
+        let file = tokio::fs::File::create(&tmpname).await?;
+        let mut writer = GenericAsyncCDBWriter {
+            dstname,
+            tmpname,
+            durability: Durability::None,
+            temp_exists: true,
+            file: Some(file),
+            cdb: GenericCDBMake::with_capacity(0, Pending::default())?,
+        };
+        writer.write_pending(0).await?;
+        Ok(writer)
+    }
+
+    /// Write out the pending bytes, if there are at least `min` of them.
+    async fn write_pending(&mut self, min: usize) -> Result<()> {
+        let pending = self.cdb.get_mut();
+        if pending.data.len() < min || pending.data.is_empty() {
+            return Ok(());
+        }
+        let data = mem::take(&mut pending.data);
+        pending.written += data.len() as u64;
+        self.file.as_mut().unwrap().write_all(&data).await
+    }
+
+    /// Add a record to the CDB file.
+    pub async fn add<K: AsRef<[u8]>, V: AsRef<[u8]>>(&mut self, key: K, data: V) -> Result<()> {
+        self.cdb.add(key, data)?;
+        self.write_pending(FLUSH_SIZE).await
+    }
+
+    /// Append a bloom filter over the keys when finishing. See
+    /// [`GenericCDBMake::set_bloom_filter`].
+    pub fn set_bloom_filter(&mut self, bits_per_key: u32) {
+        self.cdb.set_bloom_filter(bits_per_key)
+    }
+
+    /// Choose what to do with records whose key was already added. See
+    /// [`GenericCDBMake::set_duplicate_policy`].
+    pub fn set_duplicate_policy(&mut self, duplicates: DuplicatePolicy) {
+        self.cdb.set_duplicate_policy(duplicates)
+    }
+
+    /// Follow every value with a checksum of its record. See
+    /// [`GenericCDBMake::set_checksums`].
+    pub fn set_checksums(&mut self, checksums: bool) {
+        self.cdb.set_checksums(checksums)
+    }
+
+    /// Choose how much to sync to disk when finishing. The default is
+    /// [`Durability::None`].
+    pub fn set_durability(&mut self, durability: Durability) {
+        self.durability = durability;
+    }
+
+    /// Abandon the database, removing the temporary file.
+    pub async fn abort(mut self) -> Result<()> {
+        drop(self.file.take());
+        self.temp_exists = false;
+        tokio::fs::remove_file(&self.tmpname).await
+    }
+
+    /// Finish writing the temporary file and rename it over the
+    /// destination, returning statistics about the database built.
+    pub async fn finish(mut self) -> Result<BuildStats> {
+        let header = self.cdb.write_index()?;
+        self.write_pending(0).await?;
+        let mut file = self.file.take().unwrap();
+        file.flush().await?;
+        file.seek(SeekFrom::Start(0)).await?;
+        file.write_all(&header).await?;
+        file.flush().await?;
+        if self.durability != Durability::None {
+            file.sync_all().await?;
+        }
+        drop(file);
+
+        tokio::fs::rename(&self.tmpname, &self.dstname).await?;
+        self.temp_exists = false;
+        if self.durability == Durability::FileAndDir {
+            sync_parent(&self.dstname).await?;
+        }
+        Ok(self.cdb.stats())
+    }
+}
+
+#[cfg(unix)]
+async fn sync_parent(path: &Path) -> Result<()> {
+    tokio::fs::File::open(crate::writer::parent_dir(path))
+        .await?
+        .sync_all()
+        .await
+}
+
+#[cfg(not(unix))]
+async fn sync_parent(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+impl<F> Drop for GenericAsyncCDBWriter<F> {
+    fn drop(&mut self) {
+        if self.temp_exists {
+            let _ = fs::remove_file(&self.tmpname);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::f64::consts::LN_2;
+
+use crate::error::err_corrupt;
+use crate::format::Format;
+use crate::hash::CdbHash;
+use crate::reader::{GenericCDB, Result};
+use crate::trailer::{Section, BLOOM_MAGIC};
+
+/// Spread the 32-bit key hash over 64 bits (the splitmix64 finalizer),
+/// from which the filter's bit positions are derived.
+fn mix(khash: u32) -> u64 {
+    let mut z = (khash as u64).wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+fn bit_positions(khash: u32, k: u32, nbits: u64) -> impl Iterator<Item = u64> {
+    let h1 = mix(khash);
+    let h2 = h1.rotate_left(32) | 1;
+    (0..k as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % nbits)
+}
+
+/// Build the body of a bloom filter section over the hashes of `count`
+/// keys: the number of hash functions as 32-bit little endian, then the
+/// filter bits.
+pub(crate) fn build<I: Iterator<Item = u32>>(
+    hashes: I,
+    count: usize,
+    bits_per_key: u32,
+) -> Vec<u8> {
+    let mut body = empty(count, bits_per_key);
+    for khash in hashes {
+        insert(&mut body, khash);
+    }
+    body
+}
+
+/// Return the body of a bloom filter section sized for `count` keys,
+/// with no keys inserted yet.
+pub(crate) fn empty(count: usize, bits_per_key: u32) -> Vec<u8> {
+    let k = ((bits_per_key as f64 * LN_2).round() as u32).clamp(1, 30);
+    let nbytes = ((count as u64 * bits_per_key as u64 + 7) / 8).max(8);
+    let mut body = vec![0_u8; 4 + nbytes as usize];
+    body[..4].copy_from_slice(&k.to_le_bytes());
+    body
+}
+
+/// Add a key hash to the body of a bloom filter section.
+pub(crate) fn insert(body: &mut [u8], khash: u32) {
+    let k = u32::from_le_bytes(body[..4].try_into().unwrap());
+    let bits = &mut body[4..];
+    for bit in bit_positions(khash, k, bits.len() as u64 * 8) {
+        bits[(bit / 8) as usize] |= 1 << (bit % 8);
+    }
+}
+
+#[derive(Debug)]
+enum Bits {
+    /// Position of the filter in a mapped or in-memory database.
+    Mapped(u64),
+    /// Copy of the filter for readers without a mapping.
+    Owned(Box<[u8]>),
+}
+
+/// A bloom filter over the key hashes of a database, appended by the
+/// writer to let lookups of absent keys skip the hash tables.
+#[derive(Debug)]
+pub(crate) struct Bloom {
+    k: u32,
+    nbits: u64,
+    bits: Bits,
+}
+
+impl Bloom {
+    /// Load the bloom filter section, if there is one.
+    pub(crate) fn load<F: Format, H: CdbHash>(
+        cdb: &GenericCDB<F, H>,
+        sections: &[Section],
+    ) -> Result<Option<Bloom>> {
+        let section = match sections.iter().find(|s| s.magic == BLOOM_MAGIC) {
+            Some(section) => section,
+            None => return Ok(None),
+        };
+        if section.len <= 4 {
+            return err_corrupt(section.pos, "bloom filter section is too short");
+        }
+        let mut buf = [0_u8; 4];
+        cdb.read(&mut buf, section.pos)?;
+        let k = u32::from_le_bytes(buf);
+        if !(1..=30).contains(&k) {
+            return err_corrupt(section.pos, "bloom filter has an invalid hash count");
+        }
+        let (pos, len) = (section.pos + 4, section.len - 4);
+        let bits = if cdb.is_mapped() {
+            Bits::Mapped(pos)
+        } else {
+            Bits::Owned(cdb.read_vec(pos, len)?.into_boxed_slice())
+        };
+        Ok(Some(Bloom {
+            k,
+            nbits: len * 8,
+            bits,
+        }))
+    }
+
+    /// Return `false` if no key with this hash is in the database.
+    pub(crate) fn may_contain<F: Format, H: CdbHash>(
+        &self,
+        cdb: &GenericCDB<F, H>,
+        khash: u32,
+    ) -> bool {
+        let bits = match &self.bits {
+            Bits::Mapped(pos) => match cdb.slice(*pos, self.nbits / 8) {
+                Ok(bits) => bits,
+                Err(_) => return true,
+            },
+            Bits::Owned(bits) => bits,
+        };
+        contains(bits, self.k, khash)
+    }
+}
+
+fn contains(bits: &[u8], k: u32, khash: u32) -> bool {
+    bit_positions(khash, k, bits.len() as u64 * 8)
+        .all(|bit| bits[(bit / 8) as usize] & (1 << (bit % 8)) != 0)
+}
+
+#[test]
+fn false_positives() {
+    use crate::hash::hash;
+
+    let keys = (0..10000).map(|i| format!("key{}", i)).collect::<Vec<_>>();
+    let body = build(keys.iter().map(|key| hash(key.as_bytes())), keys.len(), 10);
+    let k = u32::from_le_bytes(body[..4].try_into().unwrap());
+    let bits = &body[4..];
+    assert!(keys
+        .iter()
+        .all(|key| contains(bits, k, hash(key.as_bytes()))));
+    let false_positives = (10000..20000)
+        .filter(|i| contains(bits, k, hash(format!("key{}", i).as_bytes())))
+        .count();
+    assert!(false_positives < 300, "{} false positives", false_positives);
+}
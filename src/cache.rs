@@ -0,0 +1,286 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::format::{Format, Format32, Format64};
+use crate::hash::{CdbHash, DjbHash};
+use crate::reader::{GenericCDB, Result};
+
+/// Limits on the size of a [`GenericCachedCDB`].
+///
+/// Entries are counted, and their size is the length of the key plus the
+/// length of the value as stored, before decoding. The least recently
+/// used entries are dropped to stay within both limits.
+///
+/// # Example
+///
+/// ```
+/// use cdb32::{CacheOptions, CachedCDB, CDB};
+///
+/// let cdb = CDB::open("tests/test1.cdb").unwrap();
+/// let cdb = CachedCDB::new(cdb, CacheOptions::new().max_entries(10_000).max_bytes(1 << 20));
+/// ```
+#[derive(Clone, Debug)]
+pub struct CacheOptions {
+    max_entries: usize,
+    max_bytes: usize,
+    negative: bool,
+}
+
+impl Default for CacheOptions {
+    fn default() -> Self {
+        CacheOptions::new()
+    }
+}
+
+impl CacheOptions {
+    /// Create a new set of options, defaulting to at most 1024 entries of
+    /// any size, with misses cached.
+    pub fn new() -> CacheOptions {
+        CacheOptions {
+            max_entries: 1024,
+            max_bytes: usize::MAX,
+            negative: true,
+        }
+    }
+
+    /// Set the largest number of entries to keep.
+    pub fn max_entries(&mut self, max_entries: usize) -> &mut CacheOptions {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Set the largest total size of the entries to keep. A value larger
+    /// than this on its own is never cached.
+    pub fn max_bytes(&mut self, max_bytes: usize) -> &mut CacheOptions {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Choose whether to remember keys which are not in the database, so
+    /// repeated lookups of them are answered from the cache too. This is
+    /// the default.
+    pub fn cache_misses(&mut self, negative: bool) -> &mut CacheOptions {
+        self.negative = negative;
+        self
+    }
+}
+
+/// Counters describing the use of a [`GenericCachedCDB`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Lookups answered from the cache, including cached misses.
+    pub hits: u64,
+    /// Lookups which went to the database.
+    pub misses: u64,
+    /// Number of entries in the cache.
+    pub entries: usize,
+    /// Total size of the entries in the cache.
+    pub bytes: usize,
+}
+
+type DecodeFn<T> = dyn Fn(&[u8]) -> Result<T> + Send + Sync;
+
+struct Decoder<T>(Box<DecodeFn<T>>);
+
+impl<T> fmt::Debug for Decoder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Decoder")
+    }
+}
+
+/// A cached value, or `None` for a key not in the database.
+#[derive(Debug)]
+struct Entry<T> {
+    value: Option<Arc<T>>,
+    size: usize,
+    used: u64,
+}
+
+/// The cached entries, with their order of use.
+#[derive(Debug)]
+struct Lru<T> {
+    entries: HashMap<Vec<u8>, Entry<T>>,
+    order: BTreeMap<u64, Vec<u8>>,
+    clock: u64,
+    bytes: usize,
+}
+
+impl<T> Lru<T> {
+    fn new() -> Self {
+        Lru {
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            clock: 0,
+            bytes: 0,
+        }
+    }
+
+    /// Look up `key`, marking it as the most recently used.
+    fn get(&mut self, key: &[u8]) -> Option<Option<Arc<T>>> {
+        let entry = self.entries.get_mut(key)?;
+        self.clock += 1;
+        let key = self.order.remove(&entry.used).unwrap();
+        entry.used = self.clock;
+        self.order.insert(self.clock, key);
+        Some(entry.value.clone())
+    }
+
+    fn insert(&mut self, key: &[u8], value: Option<Arc<T>>, size: usize, options: &CacheOptions) {
+        if size > options.max_bytes || options.max_entries == 0 {
+            return;
+        }
+        self.clock += 1;
+        let entry = Entry {
+            value,
+            size,
+            used: self.clock,
+        };
+        if let Some(old) = self.entries.insert(key.to_vec(), entry) {
+            self.order.remove(&old.used);
+            self.bytes -= old.size;
+        }
+        self.order.insert(self.clock, key.to_vec());
+        self.bytes += size;
+        while self.entries.len() > options.max_entries || self.bytes > options.max_bytes {
+            let (_, key) = self.order.pop_first().unwrap();
+            let old = self.entries.remove(&key).unwrap();
+            self.bytes -= old.size;
+        }
+    }
+}
+
+/// A CDB reader which keeps recently read values in memory, generic
+/// over the on-disk [`Format`] and the type `T` of the decoded values.
+///
+/// Each value is decoded once, when it is first read, and shared as an
+/// `Arc<T>` until it is dropped from the cache, so lookups of popular
+/// keys skip both the file access and the decoding: decompression,
+/// deserialization or any other work done by the decoder given to
+/// [`with_decoder`](Self::with_decoder). This helps most when the file
+/// is not memory-mapped. Keys which are not in the database can be
+/// cached too. Only the first value of each key is cached, as returned
+/// by [`get`](GenericCDB::get), and errors are never cached.
+///
+/// The cache is shared between threads. Most code should use the
+/// [`CachedCDB`] or [`CachedCDB64`] aliases.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use cdb32::{CacheOptions, CachedCDB, CDB};
+///
+/// let cdb = CDB::open("tests/test1.cdb")?;
+/// let cdb = CachedCDB::with_decoder(cdb, &CacheOptions::new(), |value| {
+///     Ok(String::from_utf8_lossy(value).into_owned())
+/// });
+/// assert_eq!(*cdb.get(b"two").unwrap()?, "Goodbye");
+/// assert_eq!(*cdb.get(b"two").unwrap()?, "Goodbye");
+/// assert!(cdb.get(b"four").is_none());
+/// assert_eq!(cdb.stats().hits, 1);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct GenericCachedCDB<F, T = Vec<u8>, H = DjbHash> {
+    cdb: GenericCDB<F, H>,
+    decoder: Decoder<T>,
+    options: CacheOptions,
+    lru: Mutex<Lru<T>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// Cached CDB reader for the classic 32-bit format.
+pub type CachedCDB<T = Vec<u8>> = GenericCachedCDB<Format32, T>;
+
+/// Cached CDB reader for the 64-bit format.
+pub type CachedCDB64<T = Vec<u8>> = GenericCachedCDB<Format64, T>;
+
+impl<F: Format, H: CdbHash> GenericCachedCDB<F, Vec<u8>, H> {
+    /// Cache the values of `cdb` as they are read.
+    pub fn new(cdb: GenericCDB<F, H>, options: &CacheOptions) -> Self {
+        Self::with_decoder(cdb, options, |value| Ok(value.to_vec()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<F: Format, H: CdbHash, T: serde::de::DeserializeOwned + 'static> GenericCachedCDB<F, T, H> {
+    /// Cache the values of `cdb` deserialized with the default
+    /// [`Postcard`](crate::Postcard) codec, as read by
+    /// [`get_de`](GenericCDB::get_de).
+    pub fn deserialized(cdb: GenericCDB<F, H>, options: &CacheOptions) -> Self {
+        Self::deserialized_with::<crate::Postcard>(cdb, options)
+    }
+
+    /// Cache the values of `cdb` deserialized with the codec `C`.
+    pub fn deserialized_with<C: crate::Codec + 'static>(
+        cdb: GenericCDB<F, H>,
+        options: &CacheOptions,
+    ) -> Self {
+        Self::with_decoder(cdb, options, C::decode::<T>)
+    }
+}
+
+impl<F: Format, T, H: CdbHash> GenericCachedCDB<F, T, H> {
+    /// Cache the values of `cdb` as decoded by `decoder`. An error from
+    /// the decoder is returned by the lookup.
+    pub fn with_decoder<D>(cdb: GenericCDB<F, H>, options: &CacheOptions, decoder: D) -> Self
+    where
+        D: Fn(&[u8]) -> Result<T> + Send + Sync + 'static,
+    {
+        GenericCachedCDB {
+            cdb,
+            decoder: Decoder(Box::new(decoder)),
+            options: options.clone(),
+            lru: Mutex::new(Lru::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Return the underlying database.
+    pub fn cdb(&self) -> &GenericCDB<F, H> {
+        &self.cdb
+    }
+
+    /// Find the first record with the named key, and return its decoded
+    /// value.
+    pub fn get(&self, key: &[u8]) -> Option<Result<Arc<T>>> {
+        if let Some(value) = self.lru.lock().unwrap().get(key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return value.map(Ok);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let (value, size) = match self.cdb.get(key) {
+            Some(Ok(data)) => match (self.decoder.0)(&data) {
+                Ok(value) => (Some(Arc::new(value)), key.len() + data.len()),
+                Err(err) => return Some(Err(err)),
+            },
+            Some(Err(err)) => return Some(Err(err)),
+            None if self.options.negative => (None, key.len()),
+            None => return None,
+        };
+        let mut lru = self.lru.lock().unwrap();
+        lru.insert(key, value.clone(), size, &self.options);
+        value.map(Ok)
+    }
+
+    /// Drop every cached entry.
+    pub fn clear(&self) {
+        *self.lru.lock().unwrap() = Lru::new();
+    }
+
+    /// Return counters describing the use of the cache.
+    pub fn stats(&self) -> CacheStats {
+        let lru = self.lru.lock().unwrap();
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: lru.entries.len(),
+            bytes: lru.bytes,
+        }
+    }
+}
@@ -0,0 +1,65 @@
+// Values in a checksummed database are followed by the CRC-32 (IEEE,
+// as used by zlib) of the record's key and stored value, 32-bit little
+// endian.
+
+/// Size of the checksum after each value.
+pub(crate) const CHECKSUM_SIZE: u64 = 4;
+
+/// Identifies the checksum of a database in its checksum section.
+pub(crate) const CRC32_ID: u8 = 1;
+
+const TABLE: [u32; 256] = table();
+
+const fn table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// An incremental CRC-32 computation.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Crc32(u32);
+
+impl Crc32 {
+    pub(crate) fn new() -> Crc32 {
+        Crc32(!0)
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.0 = TABLE[((self.0 ^ byte as u32) & 0xff) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    pub(crate) fn finish(self) -> u32 {
+        !self.0
+    }
+}
+
+/// Return the checksum of a record.
+pub(crate) fn checksum(key: &[u8], value: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(key);
+    crc.update(value);
+    crc.finish()
+}
+
+#[test]
+fn check_value() {
+    assert_eq!(checksum(b"", b""), 0);
+    assert_eq!(checksum(b"1234", b"56789"), 0xcbf4_3926);
+}
@@ -0,0 +1,241 @@
+use std::io::{self, Seek, Write};
+
+use serde::de::DeserializeOwned;
+use serde::ser::{Error as _, SerializeMap, Serializer};
+use serde::Serialize;
+
+use crate::format::Format;
+use crate::hash::CdbHash;
+use crate::reader::{GenericCDB, Result};
+use crate::writer::{GenericCDBMake, GenericCDBWriter};
+
+/// A serialization format for typed values, used by
+/// [`GenericCDB::get_de_with`] and [`GenericCDBMake::add_ser_with`], and
+/// for typed keys by [`GenericCDB::get_typed_with`] and
+/// [`GenericCDBMake::add_typed_with`].
+///
+/// Implement this to store values with a format other than the default
+/// [`Postcard`], such as `bincode` or JSON.
+pub trait Codec {
+    /// Serialize `value` into bytes.
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>>;
+
+    /// Deserialize a value from `data`.
+    fn decode<T: DeserializeOwned>(data: &[u8]) -> Result<T>;
+}
+
+/// The default [`Codec`], using the compact
+/// [postcard](https://docs.rs/postcard) wire format.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Postcard;
+
+impl Codec for Postcard {
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+        postcard::to_allocvec(value).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    fn decode<T: DeserializeOwned>(data: &[u8]) -> Result<T> {
+        postcard::from_bytes(data).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+impl<F: Format, H: CdbHash> GenericCDB<F, H> {
+    /// Find the first record with the named key and deserialize its
+    /// value with the [`Postcard`] codec.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// # let tmp_dir = tempfile::tempdir()?;
+    /// # let path = tmp_dir.path().join("typed.cdb");
+    /// use cdb32::{CDBWriter, CDB};
+    ///
+    /// let mut writer = CDBWriter::create(&path)?;
+    /// writer.add_ser(b"point", &(3_i32, -4_i32))?;
+    /// writer.finish()?;
+    ///
+    /// let cdb = CDB::open(&path)?;
+    /// let point = cdb.get_de::<(i32, i32)>(b"point").unwrap()?;
+    /// assert_eq!(point, (3, -4));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_de<T: DeserializeOwned>(&self, key: &[u8]) -> Option<Result<T>> {
+        self.get_de_with::<Postcard, T>(key)
+    }
+
+    /// Find the first record with the named key and deserialize its
+    /// value with the codec `C`.
+    pub fn get_de_with<C: Codec, T: DeserializeOwned>(&self, key: &[u8]) -> Option<Result<T>> {
+        Some(self.get(key)?.and_then(|value| C::decode(&value)))
+    }
+
+    /// Find the first record whose key is `key` serialized with the
+    /// [`Postcard`] codec, and deserialize its value with it, reading
+    /// records written by [`GenericCDBMake::add_typed`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut cdb = cdb32::CDBMake::in_memory()?;
+    /// cdb.add_typed(&("users", 1000_u32), "alice")?;
+    /// let cdb = cdb32::CDB::from_bytes(cdb.into_vec()?)?;
+    ///
+    /// let name = cdb.get_typed::<_, String>(&("users", 1000_u32)).unwrap()?;
+    /// assert_eq!(name, "alice");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_typed<K: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        key: &K,
+    ) -> Option<Result<T>> {
+        self.get_typed_with::<Postcard, K, T>(key)
+    }
+
+    /// Find the first record whose key is `key` serialized with the
+    /// codec `C`, and deserialize its value with it.
+    pub fn get_typed_with<C: Codec, K: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        key: &K,
+    ) -> Option<Result<T>> {
+        match C::encode(key) {
+            Ok(key) => self.get_de_with::<C, T>(&key),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// A key or value, serialized as a string if it is valid UTF-8 and the
+/// format is human readable, and as bytes otherwise.
+struct Field<'a>(&'a [u8]);
+
+impl Serialize for Field<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match std::str::from_utf8(self.0) {
+            Ok(text) if serializer.is_human_readable() => serializer.serialize_str(text),
+            _ => serializer.serialize_bytes(self.0),
+        }
+    }
+}
+
+/// Serializes the whole database as a map from each distinct key to the
+/// list of its values, in the order they were added, for debugging and
+/// export.
+///
+/// Keys and values which are valid UTF-8 are serialized as strings by
+/// human readable formats such as JSON, and everything else as bytes.
+/// JSON object keys must be strings, so serializing a database with
+/// other keys to JSON fails.
+///
+/// Finding the distinct keys costs a lookup for every record, and the
+/// database is scanned twice, as some formats need the number of keys
+/// first.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// let cdb = cdb32::CDB::open("tests/test1.cdb")?;
+/// let json = serde_json::to_string(&cdb)?;
+/// assert!(json.starts_with(r#"{"one":["Hello",", World!"],"two":["Goodbye"],"#));
+/// # Ok(())
+/// # }
+/// ```
+impl<F: Format, H: CdbHash> Serialize for GenericCDB<F, H> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let len = self
+            .unique_keys()
+            .try_fold(0, |len, key| key.map(|_| len + 1))
+            .map_err(S::Error::custom)?;
+        let mut map = serializer.serialize_map(Some(len))?;
+        for key in self.unique_keys() {
+            let key = key.map_err(S::Error::custom)?;
+            let values = self
+                .find(&key)
+                .collect::<Result<Vec<_>>>()
+                .map_err(S::Error::custom)?;
+            let values = values.iter().map(|value| Field(value)).collect::<Vec<_>>();
+            map.serialize_entry(&Field(&key), &values)?;
+        }
+        map.end()
+    }
+}
+
+impl<F: Format, H: CdbHash, W: Write + Seek> GenericCDBMake<F, H, W> {
+    /// Add a record to the CDB file, serializing its value with the
+    /// [`Postcard`] codec.
+    pub fn add_ser<T: Serialize + ?Sized>(&mut self, key: &[u8], value: &T) -> Result<()> {
+        self.add_ser_with::<Postcard, T>(key, value)
+    }
+
+    /// Add a record to the CDB file, serializing its value with the
+    /// codec `C`.
+    pub fn add_ser_with<C: Codec, T: Serialize + ?Sized>(
+        &mut self,
+        key: &[u8],
+        value: &T,
+    ) -> Result<()> {
+        self.add(key, &C::encode(value)?)
+    }
+
+    /// Add a record to the CDB file, serializing both its key and its
+    /// value with the [`Postcard`] codec. Read it back with
+    /// [`GenericCDB::get_typed`].
+    pub fn add_typed<K: Serialize + ?Sized, T: Serialize + ?Sized>(
+        &mut self,
+        key: &K,
+        value: &T,
+    ) -> Result<()> {
+        self.add_typed_with::<Postcard, K, T>(key, value)
+    }
+
+    /// Add a record to the CDB file, serializing both its key and its
+    /// value with the codec `C`.
+    pub fn add_typed_with<C: Codec, K: Serialize + ?Sized, T: Serialize + ?Sized>(
+        &mut self,
+        key: &K,
+        value: &T,
+    ) -> Result<()> {
+        self.add(&C::encode(key)?, &C::encode(value)?)
+    }
+}
+
+impl<F: Format, H: CdbHash> GenericCDBWriter<F, H> {
+    /// Add a record to the CDB file, serializing its value with the
+    /// [`Postcard`] codec.
+    pub fn add_ser<T: Serialize + ?Sized>(&mut self, key: &[u8], value: &T) -> Result<()> {
+        self.add_ser_with::<Postcard, T>(key, value)
+    }
+
+    /// Add a record to the CDB file, serializing its value with the
+    /// codec `C`.
+    pub fn add_ser_with<C: Codec, T: Serialize + ?Sized>(
+        &mut self,
+        key: &[u8],
+        value: &T,
+    ) -> Result<()> {
+        self.add(key, &C::encode(value)?)
+    }
+    /// Add a record to the CDB file, serializing both its key and its
+    /// value with the [`Postcard`] codec.
+    pub fn add_typed<K: Serialize + ?Sized, T: Serialize + ?Sized>(
+        &mut self,
+        key: &K,
+        value: &T,
+    ) -> Result<()> {
+        self.add_typed_with::<Postcard, K, T>(key, value)
+    }
+
+    /// Add a record to the CDB file, serializing both its key and its
+    /// value with the codec `C`.
+    pub fn add_typed_with<C: Codec, K: Serialize + ?Sized, T: Serialize + ?Sized>(
+        &mut self,
+        key: &K,
+        value: &T,
+    ) -> Result<()> {
+        self.add(&C::encode(key)?, &C::encode(value)?)
+    }
+}
@@ -0,0 +1,164 @@
+//! Streaming comparison of the records of two databases.
+//!
+//! [`is_subset`] checks that every record of one database is in
+//! another, such as to validate that an incremental rebuild did not drop
+//! any entries, and [`first_missing`] finds a record which is not. Each
+//! key of the first database is looked up in the second through its hash
+//! table, so neither database is loaded into memory, and the two may
+//! differ in format and hash function.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> std::io::Result<()> {
+//! use cdb32::compare;
+//!
+//! let old = cdb32::CDB::open("tests/test1.cdb")?;
+//! let mut new = cdb32::CDBMake::in_memory()?;
+//! for record in old.iter() {
+//!     let (key, value) = record?;
+//!     if key != b"two" {
+//!         new.add(&key, &value)?;
+//!     }
+//! }
+//! let new = cdb32::CDB::from_bytes(new.into_vec()?)?;
+//!
+//! assert!(compare::is_subset(&new, &old)?);
+//! assert!(!compare::is_subset(&old, &new)?);
+//! assert_eq!(
+//!     compare::first_missing(&old, &new)?,
+//!     Some((b"two".to_vec(), b"Goodbye".to_vec()))
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::format::Format;
+use crate::hash::CdbHash;
+use crate::image::Scan;
+use crate::reader::{GenericCDB, Result};
+
+/// Return `true` if every record of `a` is also in `b`. See
+/// [`GenericCDB::is_subset_of`].
+pub fn is_subset<F: Format, H: CdbHash, F2: Format, H2: CdbHash>(
+    a: &GenericCDB<F, H>,
+    b: &GenericCDB<F2, H2>,
+) -> Result<bool> {
+    a.is_subset_of(b)
+}
+
+/// Return `true` if every record of `b` is also in `a`.
+pub fn is_superset<F: Format, H: CdbHash, F2: Format, H2: CdbHash>(
+    a: &GenericCDB<F, H>,
+    b: &GenericCDB<F2, H2>,
+) -> Result<bool> {
+    b.is_subset_of(a)
+}
+
+/// Return the first record of `a`, in stored order, which is not in `b`,
+/// or `None` if `a` is a subset of `b`.
+///
+/// A key with several values must have each of them in `b` at least as
+/// many times, so the record returned may be a repeated value.
+pub fn first_missing<F: Format, H: CdbHash, F2: Format, H2: CdbHash>(
+    a: &GenericCDB<F, H>,
+    b: &GenericCDB<F2, H2>,
+) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+    let mut scan = Scan::start(a);
+    while let Some((kpos, klen, dpos, _)) = scan.next(a).transpose()? {
+        let key = a.read_vec(kpos, klen)?;
+        // Handle each key only at the record a lookup finds first.
+        let first = a.probe(H::hash(&key)).next(a, &key).transpose()?;
+        if first.map(|(pos, _)| pos) != Some(dpos) {
+            continue;
+        }
+        let mut theirs = b.find(&key).collect::<Result<Vec<_>>>()?;
+        for value in a.find(&key) {
+            let value = value?;
+            match theirs.iter().position(|v| *v == value) {
+                Some(i) => {
+                    theirs.swap_remove(i);
+                }
+                None => return Ok(Some((key, value))),
+            }
+        }
+    }
+    Ok(None)
+}
+
+impl<F: Format, H: CdbHash> GenericCDB<F, H> {
+    /// Return `true` if every record of this database is also in
+    /// `other`, which may have a different format or hash function.
+    ///
+    /// Records are compared as in [`diff`](crate::diff::diff): the order of records does not
+    /// matter, but a key with several values must have each of them in
+    /// `other` at least as many times. Memory use is bounded by the
+    /// records of a single key, and the comparison stops at the first
+    /// record missing from `other`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut small = cdb32::CDBMake::in_memory()?;
+    /// small.add(b"one", b"Hello")?;
+    /// let small = cdb32::CDB::from_bytes(small.into_vec()?)?;
+    ///
+    /// let big = cdb32::CDB::open("tests/test1.cdb")?;
+    /// assert!(small.is_subset_of(&big)?);
+    /// assert!(!big.is_subset_of(&small)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn is_subset_of<F2: Format, H2: CdbHash>(
+        &self,
+        other: &GenericCDB<F2, H2>,
+    ) -> Result<bool> {
+        let mut scan = Scan::start(self);
+        while let Some((kpos, klen, dpos, _)) = scan.next(self).transpose()? {
+            let key = self.read_vec(kpos, klen)?;
+            // Handle each key only at the record a lookup finds first.
+            let first = self.probe(H::hash(&key)).next(self, &key).transpose()?;
+            if first.map(|(pos, _)| pos) != Some(dpos) {
+                continue;
+            }
+            let mut theirs = other.find(&key).collect::<Result<Vec<_>>>()?;
+            for value in self.find(&key) {
+                let value = value?;
+                match theirs.iter().position(|v| *v == value) {
+                    Some(i) => {
+                        theirs.swap_remove(i);
+                    }
+                    None => return Ok(false),
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Return `true` if this database and `other` hold the same records,
+    /// answering "did anything actually change?" without dumping either.
+    ///
+    /// This is [`is_subset_of`](Self::is_subset_of) in both directions,
+    /// so the order of records, the format, the hash function and any
+    /// options such as checksums or compression are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut cdb = cdb32::CDB64Make::in_memory()?;
+    /// cdb.add(b"two", b"Goodbye")?;
+    /// cdb.add(b"one", b"Hello")?;
+    /// cdb.add(b"one", b", World!")?;
+    /// cdb.add(b"this key will be split across two reads", b"Got it.")?;
+    /// let cdb = cdb32::CDB64::from_bytes(cdb.into_vec()?)?;
+    ///
+    /// assert!(cdb.content_eq(&cdb32::CDB::open("tests/test1.cdb")?)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn content_eq<F2: Format, H2: CdbHash>(&self, other: &GenericCDB<F2, H2>) -> Result<bool> {
+        Ok(self.is_subset_of(other)? && other.is_subset_of(self)?)
+    }
+}
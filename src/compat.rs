@@ -0,0 +1,104 @@
+//! Reference databases for checking compatibility with other CDB
+//! implementations.
+//!
+//! Each [`TestVector`] holds the input to D. J. Bernstein's `cdbmake`, in
+//! the format of the [`text`](crate::text) module, and the database
+//! `cdbmake` built from it. The databases are in the classic format read
+//! by other implementations such as tinycdb, so the vectors can also be
+//! fed to those implementations in downstream test suites.
+//!
+//! [`check`] verifies that this crate builds exactly the same bytes from
+//! each input, and reads back the records of each database.
+//!
+//! # Examples
+//!
+//! ```
+//! cdb32::compat::check().unwrap();
+//!
+//! for vector in cdb32::compat::VECTORS {
+//!     let cdb = cdb32::CDB::from_bytes(vector.database.to_vec()).unwrap();
+//!     assert!(cdb.verify().unwrap().is_ok(), "{}", vector.name);
+//! }
+//! ```
+
+use std::io;
+
+use crate::reader::{Result, CDB};
+use crate::text;
+use crate::writer::CDBMake;
+
+/// A database built by `cdbmake`, with the input it was built from.
+#[derive(Clone, Copy, Debug)]
+pub struct TestVector {
+    /// A short name for the vector.
+    pub name: &'static str,
+    /// The `cdbmake` input, ending with an empty line.
+    pub input: &'static [u8],
+    /// The database `cdbmake` built from `input`.
+    pub database: &'static [u8],
+}
+
+/// The reference databases.
+pub const VECTORS: &[TestVector] = &[
+    TestVector {
+        name: "test1",
+        input: include_bytes!("../tests/test1.txt"),
+        database: include_bytes!("../tests/test1.cdb"),
+    },
+    TestVector {
+        name: "test2",
+        input: include_bytes!("../tests/test2.txt"),
+        database: include_bytes!("../tests/test2.cdb"),
+    },
+];
+
+fn err_mismatch<T>(name: &str, what: &str) -> Result<T> {
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Compatibility vector {}: {}", name, what),
+    ))
+}
+
+impl TestVector {
+    /// Check that this crate builds exactly [`database`](Self::database)
+    /// from [`input`](Self::input), and that reading the database finds
+    /// every input record, both in order and by key.
+    pub fn check(&self) -> Result<()> {
+        let mut cdb = CDBMake::in_memory()?;
+        cdb.add_from_text(self.input)?;
+        let built = cdb.into_vec()?;
+        if let Some(pos) = built.iter().zip(self.database).position(|(a, b)| a != b) {
+            return err_mismatch(
+                self.name,
+                &format!("built database differs at byte {}", pos),
+            );
+        }
+        if built.len() != self.database.len() {
+            return err_mismatch(self.name, "built database differs in length");
+        }
+
+        let cdb = CDB::from_bytes(self.database.to_vec())?;
+        let mut records = cdb.iter();
+        for record in text::records(self.input) {
+            let (key, value) = record?;
+            if records.next().transpose()? != Some((key.clone(), value.clone())) {
+                return err_mismatch(self.name, "records differ from the input");
+            }
+            if !cdb
+                .find(&key)
+                .any(|found| found.is_ok_and(|found| found == value))
+            {
+                return err_mismatch(self.name, "lookup does not find an input record");
+            }
+        }
+        if records.next().is_some() {
+            return err_mismatch(self.name, "database has records not in the input");
+        }
+        Ok(())
+    }
+}
+
+/// Check every vector in [`VECTORS`], returning the first failure.
+pub fn check() -> Result<()> {
+    VECTORS.iter().try_for_each(TestVector::check)
+}
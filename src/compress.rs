@@ -0,0 +1,83 @@
+use crate::error::{err_corrupt, Error};
+use crate::reader::Result;
+
+// Values in a compressed database start with a tag byte: RAW values
+// follow as is, LZ4 values are followed by their decompressed length
+// (32-bit little endian) and an LZ4 block.
+pub(crate) const RAW: u8 = 0;
+const LZ4: u8 = 1;
+
+/// Identifies the compression of a database in its compression section.
+pub(crate) const LZ4_ID: u8 = 1;
+
+/// A compression algorithm for record values, set with
+/// [`GenericCDBMake::set_compression`](crate::GenericCDBMake::set_compression).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    /// The LZ4 block format, which is fast to decompress.
+    Lz4,
+}
+
+/// Encode a value for a compressed database, compressing it if it is at
+/// least `threshold` bytes long and compression makes it smaller.
+pub(crate) fn encode(data: &[u8], threshold: usize) -> Vec<u8> {
+    if data.len() >= threshold && data.len() <= u32::MAX as usize {
+        let block = lz4_flex::block::compress(data);
+        if block.len() + 4 < data.len() {
+            let mut value = Vec::with_capacity(5 + block.len());
+            value.push(LZ4);
+            value.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            value.extend_from_slice(&block);
+            return value;
+        }
+    }
+    let mut value = Vec::with_capacity(1 + data.len());
+    value.push(RAW);
+    value.extend_from_slice(data);
+    value
+}
+
+/// Decode a value stored at `pos` in a compressed database, failing
+/// before decompressing if it claims to be longer than `max_len`.
+pub(crate) fn decode(mut value: Vec<u8>, pos: u64, max_len: u64) -> Result<Vec<u8>> {
+    match value.first() {
+        Some(&RAW) => {
+            value.remove(0);
+            Ok(value)
+        }
+        Some(&LZ4) if value.len() >= 5 => {
+            let len = u32::from_le_bytes(value[1..5].try_into().unwrap());
+            if u64::from(len) > max_len {
+                return Err(Error::LimitExceeded {
+                    offset: pos,
+                    limit: "max_value_len",
+                }
+                .into());
+            }
+            match lz4_flex::block::decompress(&value[5..], len as usize) {
+                Ok(out) if out.len() == len as usize => Ok(out),
+                _ => err_corrupt(pos, "corrupt compressed value"),
+            }
+        }
+        _ => err_corrupt(pos, "unknown value compression tag"),
+    }
+}
+
+#[test]
+fn round_trip() {
+    let mut inputs: Vec<Vec<u8>> = vec![
+        vec![],
+        b"a".to_vec(),
+        b"abcdefghijklmnop".to_vec(),
+        vec![b'x'; 100_000],
+        (0..50_000_u32).map(|i| (i * 7919 % 251) as u8).collect(),
+    ];
+    let text = "the quick brown fox jumps over the lazy dog; ".repeat(500);
+    inputs.push(text.into_bytes());
+    for input in inputs {
+        assert_eq!(decode(encode(&input, 0), 0, u64::MAX).unwrap(), input);
+    }
+    assert!(encode(&[b'x'; 100_000], 0).len() < 1000);
+    assert!(decode(vec![LZ4, 20, 0, 0, 0, 0x1f, 1, 0, 0], 0, u64::MAX).is_err());
+}
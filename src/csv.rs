@@ -0,0 +1,211 @@
+//! Import and export of CSV files.
+//!
+//! [`GenericCDBMake::add_from_csv`] adds a record for each row of a CSV
+//! file, taking the key and value from the columns chosen in
+//! [`CsvOptions`], and [`GenericCDB::dump_csv`] writes a database out as
+//! a two column CSV file. Files are read and written with the `csv`
+//! crate: fields may be quoted with `"`, in which case they can hold
+//! delimiters, newlines and doubled quotes. Binary keys and values can be hex or base64 encoded,
+//! as set by [`CsvOptions::key_encoding`] and
+//! [`CsvOptions::value_encoding`].
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> std::io::Result<()> {
+//! use cdb32::csv::CsvOptions;
+//! use cdb32::{CDBMake, Encoding, CDB};
+//!
+//! let input = b"id,name,colour\n1,apple,\"red, or green\"\n2,banana,yellow\n";
+//! let mut options = CsvOptions::new();
+//! options.headers(true).key_column(1).value_column(2);
+//! let mut cdb = CDBMake::in_memory()?;
+//! assert_eq!(cdb.add_from_csv(&input[..], &options)?, 2);
+//! let cdb = CDB::from_bytes(cdb.into_vec()?)?;
+//! assert_eq!(cdb.get(b"apple").unwrap()?, b"red, or green");
+//!
+//! let mut out = Vec::new();
+//! cdb.dump_csv(&mut out, CsvOptions::new().value_encoding(Encoding::Hex))?;
+//! assert_eq!(out, b"apple,7265642c206f7220677265656e\nbanana,79656c6c6f77\n");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::{self, BufRead, Seek, Write};
+
+use csv::{ByteRecord, ReaderBuilder, WriterBuilder};
+
+use crate::encoding::Encoding;
+use crate::format::Format;
+use crate::hash::CdbHash;
+use crate::reader::{GenericCDB, Result};
+use crate::writer::{GenericCDBMake, GenericCDBWriter};
+
+/// Options for reading and writing CSV files.
+///
+/// By default fields are separated by commas, there is no header row,
+/// keys are read from the first column and values from the second, and
+/// both are stored as they are.
+#[derive(Clone, Debug)]
+pub struct CsvOptions {
+    delimiter: u8,
+    headers: bool,
+    key_column: usize,
+    value_column: usize,
+    key_encoding: Encoding,
+    value_encoding: Encoding,
+}
+
+impl CsvOptions {
+    /// Create a new set of options with the defaults.
+    pub fn new() -> CsvOptions {
+        CsvOptions {
+            delimiter: b',',
+            headers: false,
+            key_column: 0,
+            value_column: 1,
+            key_encoding: Encoding::Text,
+            value_encoding: Encoding::Text,
+        }
+    }
+
+    /// Separate fields with `delimiter` rather than a comma, such as
+    /// `b'\t'` for tab separated files.
+    pub fn delimiter(&mut self, delimiter: u8) -> &mut CsvOptions {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Choose whether the first row is a header. A header is skipped when
+    /// reading, and `key,value` is written when exporting.
+    pub fn headers(&mut self, headers: bool) -> &mut CsvOptions {
+        self.headers = headers;
+        self
+    }
+
+    /// Read keys from the column numbered `column`, counting from zero.
+    pub fn key_column(&mut self, column: usize) -> &mut CsvOptions {
+        self.key_column = column;
+        self
+    }
+
+    /// Read values from the column numbered `column`, counting from zero.
+    pub fn value_column(&mut self, column: usize) -> &mut CsvOptions {
+        self.value_column = column;
+        self
+    }
+
+    /// Choose how keys are encoded in the file.
+    pub fn key_encoding(&mut self, encoding: Encoding) -> &mut CsvOptions {
+        self.key_encoding = encoding;
+        self
+    }
+
+    /// Choose how values are encoded in the file.
+    pub fn value_encoding(&mut self, encoding: Encoding) -> &mut CsvOptions {
+        self.value_encoding = encoding;
+        self
+    }
+}
+
+impl Default for CsvOptions {
+    fn default() -> CsvOptions {
+        CsvOptions::new()
+    }
+}
+
+/// Convert an error from the `csv` crate, reporting malformed input as
+/// [`io::ErrorKind::InvalidData`].
+fn csv_error(err: csv::Error) -> io::Error {
+    if err.is_io_error() {
+        io::Error::from(err)
+    } else {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+impl<F: Format, H: CdbHash> GenericCDB<F, H> {
+    /// Write every record in the database to `writer` as a CSV file with
+    /// a key and a value column, returning how many records were written.
+    ///
+    /// The delimiter, header and encodings are taken from `options`; the
+    /// column numbers are ignored.
+    pub fn dump_csv<W: Write>(&self, writer: W, options: &CsvOptions) -> Result<u64> {
+        let mut writer = WriterBuilder::new()
+            .delimiter(options.delimiter)
+            .from_writer(writer);
+        if options.headers {
+            writer.write_record(["key", "value"]).map_err(csv_error)?;
+        }
+        let mut count = 0;
+        for record in self.iter() {
+            let (key, value) = record?;
+            writer
+                .write_record([
+                    options.key_encoding.encode(&key),
+                    options.value_encoding.encode(&value),
+                ])
+                .map_err(csv_error)?;
+            count += 1;
+        }
+        writer.flush()?;
+        Ok(count)
+    }
+}
+
+/// Call `add` with the key and value of every row of a CSV file.
+fn read_csv<R: BufRead>(
+    reader: R,
+    options: &CsvOptions,
+    mut add: impl FnMut(Vec<u8>, Vec<u8>) -> Result<()>,
+) -> Result<u64> {
+    let mut csv = ReaderBuilder::new()
+        .delimiter(options.delimiter)
+        .has_headers(options.headers)
+        .flexible(true)
+        .from_reader(reader);
+    let mut record = ByteRecord::new();
+    let mut count = 0;
+    while csv.read_byte_record(&mut record).map_err(csv_error)? {
+        let (key, value) = match (
+            record.get(options.key_column),
+            record.get(options.value_column),
+        ) {
+            (Some(key), Some(value)) => (key, value),
+            _ => {
+                let line = record.position().map_or(0, |pos| pos.line());
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Malformed CSV in line {}: missing the key or value column",
+                        line
+                    ),
+                ));
+            }
+        };
+        let key = options.key_encoding.decode(key)?;
+        let value = options.value_encoding.decode(value)?;
+        add(key, value)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+impl<F: Format, H: CdbHash, W: Write + Seek> GenericCDBMake<F, H, W> {
+    /// Add a record for every row of a CSV file read from `reader`,
+    /// returning how many were added. See the [`csv`](crate::csv) module.
+    ///
+    /// Rows before any malformed input are still added.
+    pub fn add_from_csv<R: BufRead>(&mut self, reader: R, options: &CsvOptions) -> Result<u64> {
+        read_csv(reader, options, |key, value| self.add(key, value))
+    }
+}
+
+impl<F: Format, H: CdbHash> GenericCDBWriter<F, H> {
+    /// Add a record for every row of a CSV file read from `reader`,
+    /// returning how many were added. See
+    /// [`GenericCDBMake::add_from_csv`].
+    pub fn add_from_csv<R: BufRead>(&mut self, reader: R, options: &CsvOptions) -> Result<u64> {
+        read_csv(reader, options, |key, value| self.add(key, value))
+    }
+}
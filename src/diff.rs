@@ -0,0 +1,203 @@
+//! Differences between two CDB databases.
+//!
+//! [`diff`] compares an old and a new database record by record, for
+//! example to summarize what changed between two generations of a lookup
+//! table before deploying the new one. Both files are scanned in order
+//! and each key is looked up in the other file, so memory use is bounded
+//! by the records of a single key rather than the size of the databases.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> std::io::Result<()> {
+//! use cdb32::diff::{diff, Change};
+//!
+//! let mut old = cdb32::CDBMake::in_memory()?;
+//! old.add(b"one", b"1")?;
+//! old.add(b"two", b"2")?;
+//! let old = cdb32::CDB::from_bytes(old.into_vec()?)?;
+//!
+//! let mut new = cdb32::CDBMake::in_memory()?;
+//! new.add(b"two", b"two")?;
+//! new.add(b"three", b"3")?;
+//! let new = cdb32::CDB::from_bytes(new.into_vec()?)?;
+//!
+//! let changes = diff(&old, &new).collect::<std::io::Result<Vec<_>>>()?;
+//! assert_eq!(
+//!     changes,
+//!     [
+//!         Change::Removed { key: b"one".to_vec(), value: b"1".to_vec() },
+//!         Change::Changed { key: b"two".to_vec(), old: b"2".to_vec(), new: b"two".to_vec() },
+//!         Change::Added { key: b"three".to_vec(), value: b"3".to_vec() },
+//!     ]
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+
+use crate::format::{Format, Format32};
+use crate::hash::{CdbHash, DjbHash};
+use crate::image::Scan;
+use crate::reader::{GenericCDB, Result};
+
+/// One difference between two databases.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// A record present only in the new database.
+    Added { key: Vec<u8>, value: Vec<u8> },
+    /// A record present only in the old database.
+    Removed { key: Vec<u8>, value: Vec<u8> },
+    /// A key with a single record in both databases, whose value differs.
+    Changed {
+        key: Vec<u8>,
+        old: Vec<u8>,
+        new: Vec<u8>,
+    },
+}
+
+/// Counts of the differences between two databases, from
+/// [`Diff::summary`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DiffSummary {
+    /// The number of records only in the new database.
+    pub added: u64,
+    /// The number of records only in the old database.
+    pub removed: u64,
+    /// The number of keys whose single value changed.
+    pub changed: u64,
+}
+
+/// Iterator over the differences between two databases, created by
+/// [`diff`].
+///
+/// Keys of the old database are reported in the order they first appear
+/// in it, followed by the keys only in the new database. Keys with
+/// several records are compared as multisets of values: only the values
+/// not present in both are reported, as added or removed.
+pub struct Diff<'a, F = Format32, H = DjbHash> {
+    old: &'a GenericCDB<F, H>,
+    new: &'a GenericCDB<F, H>,
+    scan: Scan,
+    scanning_new: bool,
+    done: bool,
+    pending: VecDeque<Change>,
+}
+
+/// Compare the `old` and `new` databases.
+pub fn diff<'a, F: Format, H: CdbHash>(
+    old: &'a GenericCDB<F, H>,
+    new: &'a GenericCDB<F, H>,
+) -> Diff<'a, F, H> {
+    Diff {
+        old,
+        new,
+        scan: Scan::start(old),
+        scanning_new: false,
+        done: false,
+        pending: VecDeque::new(),
+    }
+}
+
+impl<'a, F: Format, H: CdbHash> Diff<'a, F, H> {
+    /// Count the differences, consuming the iterator.
+    pub fn summary(self) -> Result<DiffSummary> {
+        let mut summary = DiffSummary::default();
+        for change in self {
+            match change? {
+                Change::Added { .. } => summary.added += 1,
+                Change::Removed { .. } => summary.removed += 1,
+                Change::Changed { .. } => summary.changed += 1,
+            }
+        }
+        Ok(summary)
+    }
+
+    /// Scan for the next key with differences, queueing them.
+    fn advance(&mut self) -> Result<bool> {
+        loop {
+            let side = if self.scanning_new {
+                self.new
+            } else {
+                self.old
+            };
+            let (kpos, klen, dpos, _) = match self.scan.next(side).transpose()? {
+                Some(record) => record,
+                None if self.scanning_new => return Ok(false),
+                None => {
+                    self.scanning_new = true;
+                    self.scan = Scan::start(self.new);
+                    continue;
+                }
+            };
+            let key = side.read_vec(kpos, klen)?;
+            // Handle each key only at the record a lookup finds first.
+            let first = side.probe(H::hash(&key)).next(side, &key).transpose()?;
+            if first.map(|(pos, _)| pos) != Some(dpos) {
+                continue;
+            }
+            if self.scanning_new {
+                if !self.old.exists(&key)? {
+                    for value in self.new.find(&key) {
+                        let key = key.clone();
+                        self.pending.push_back(Change::Added { key, value: value? });
+                    }
+                }
+            } else {
+                let old = self.old.find(&key).collect::<Result<Vec<_>>>()?;
+                let new = self.new.find(&key).collect::<Result<Vec<_>>>()?;
+                self.compare(key, old, new);
+            }
+            if !self.pending.is_empty() {
+                return Ok(true);
+            }
+        }
+    }
+
+    fn compare(&mut self, key: Vec<u8>, old: Vec<Vec<u8>>, mut new: Vec<Vec<u8>>) {
+        if let ([old], [new]) = (&old[..], &new[..]) {
+            if old != new {
+                self.pending.push_back(Change::Changed {
+                    key,
+                    old: old.clone(),
+                    new: new.clone(),
+                });
+            }
+            return;
+        }
+        for value in old {
+            match new.iter().position(|v| *v == value) {
+                Some(i) => {
+                    new.remove(i);
+                }
+                None => {
+                    let key = key.clone();
+                    self.pending.push_back(Change::Removed { key, value });
+                }
+            }
+        }
+        for value in new {
+            let key = key.clone();
+            self.pending.push_back(Change::Added { key, value });
+        }
+    }
+}
+
+impl<'a, F: Format, H: CdbHash> Iterator for Diff<'a, F, H> {
+    type Item = Result<Change>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pending.is_empty() && !self.done {
+            match self.advance() {
+                Ok(true) => {}
+                Ok(false) => self.done = true,
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
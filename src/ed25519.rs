@@ -0,0 +1,105 @@
+// Ed25519 signatures, as specified in RFC 8032, made and checked with
+// the ed25519-dalek crate. Verification is strict, rejecting
+// non-canonical encodings and small-order keys.
+
+use std::fmt;
+
+/// An Ed25519 key for signing databases, set with
+/// [`GenericCDBMake::set_signing_key`](crate::GenericCDBMake::set_signing_key).
+///
+/// The key is not shown by its `Debug` output.
+///
+/// # Examples
+///
+/// ```
+/// use cdb32::SigningKey;
+///
+/// let key = SigningKey::from_seed([7; 32]);
+/// let public = key.verifying_key().to_bytes();
+/// assert_eq!(format!("{:?}", key), "SigningKey(..)");
+/// ```
+#[derive(Clone)]
+pub struct SigningKey(ed25519_dalek::SigningKey);
+
+impl SigningKey {
+    /// Derive the key from a secret 32-byte seed, as RFC 8032 does.
+    pub fn from_seed(seed: [u8; 32]) -> SigningKey {
+        SigningKey(ed25519_dalek::SigningKey::from_bytes(&seed))
+    }
+
+    /// Return the public key, which checks signatures made with this key.
+    pub fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey(self.0.verifying_key().to_bytes())
+    }
+
+    pub(crate) fn sign(&self, message: &[u8]) -> [u8; 64] {
+        use ed25519_dalek::Signer;
+        self.0.sign(message).to_bytes()
+    }
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SigningKey(..)")
+    }
+}
+
+/// An Ed25519 public key, for checking the signature of a database with
+/// [`GenericCDB::open_verified`](crate::GenericCDB::open_verified).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifyingKey([u8; 32]);
+
+impl VerifyingKey {
+    /// Use the given encoded public key.
+    pub fn from_bytes(bytes: [u8; 32]) -> VerifyingKey {
+        VerifyingKey(bytes)
+    }
+
+    /// Return the encoded public key.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0
+    }
+
+    pub(crate) fn verify(&self, message: &[u8], signature: &[u8; 64]) -> bool {
+        let signature = ed25519_dalek::Signature::from_bytes(signature);
+        ed25519_dalek::VerifyingKey::from_bytes(&self.0)
+            .and_then(|key| key.verify_strict(message, &signature))
+            .is_ok()
+    }
+}
+
+#[test]
+fn rfc8032_vectors() {
+    fn unhex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    let vectors = [
+        (
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+            "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+            "",
+            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155\
+             5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+        ),
+        (
+            "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+            "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+            "72",
+            "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da\
+             085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+        ),
+    ];
+    for (seed, public, message, signature) in vectors {
+        let key = SigningKey::from_seed(unhex(seed).try_into().unwrap());
+        assert_eq!(key.verifying_key().to_bytes()[..], unhex(public));
+        let message = unhex(message);
+        let signed = key.sign(&message);
+        assert_eq!(signed[..], unhex(signature));
+        assert!(key.verifying_key().verify(&message, &signed));
+        assert!(!key.verifying_key().verify(b"other", &signed));
+    }
+}
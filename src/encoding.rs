@@ -0,0 +1,87 @@
+use std::io;
+
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+use base64::Engine;
+
+use crate::reader::Result;
+
+/// How keys and values are written in text formats such as CSV and JSON
+/// Lines, which cannot always hold arbitrary bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Encoding {
+    /// The bytes as they are, which in JSON must be valid UTF-8.
+    #[default]
+    Text,
+    /// Lower case hexadecimal, two digits per byte. Either case is
+    /// accepted when reading.
+    Hex,
+    /// Standard base64 with padding, as in RFC 4648. Padding is optional
+    /// when reading.
+    Base64,
+}
+
+fn err_encoding<T>(encoding: Encoding) -> Result<T> {
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Invalid {:?} encoded data", encoding),
+    ))
+}
+
+impl Encoding {
+    /// Encode `data`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cdb32::Encoding;
+    ///
+    /// assert_eq!(Encoding::Hex.encode(b"\x00\xff"), b"00ff");
+    /// assert_eq!(Encoding::Base64.encode(b"hi"), b"aGk=");
+    /// ```
+    pub fn encode(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Encoding::Text => data.to_vec(),
+            Encoding::Hex => hex::encode(data).into_bytes(),
+            Encoding::Base64 => STANDARD.encode(data).into_bytes(),
+        }
+    }
+
+    /// Decode `text`, returning an error of kind
+    /// [`io::ErrorKind::InvalidData`] if it is not validly encoded.
+    pub fn decode(self, text: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Encoding::Text => Ok(text.to_vec()),
+            Encoding::Hex => hex::decode(text).or_else(|_| err_encoding(self)),
+            // Padding is optional, but must be complete if present.
+            Encoding::Base64 if text.contains(&b'=') => {
+                STANDARD.decode(text).or_else(|_| err_encoding(self))
+            }
+            Encoding::Base64 => STANDARD_NO_PAD.decode(text).or_else(|_| err_encoding(self)),
+        }
+    }
+}
+
+#[test]
+fn round_trip() {
+    let cases: [(&[u8], &[u8], &[u8]); 5] = [
+        (b"", b"", b""),
+        (b"f", b"66", b"Zg=="),
+        (b"fo", b"666f", b"Zm8="),
+        (b"foo", b"666f6f", b"Zm9v"),
+        (b"\xff\x00foob", b"ff00666f6f62", b"/wBmb29i"),
+    ];
+    for (data, hex, base64) in cases {
+        assert_eq!(Encoding::Hex.encode(data), hex);
+        assert_eq!(Encoding::Base64.encode(data), base64);
+        assert_eq!(Encoding::Hex.decode(hex).unwrap(), data);
+        assert_eq!(Encoding::Base64.decode(base64).unwrap(), data);
+    }
+    assert_eq!(Encoding::Hex.decode(b"FF").unwrap(), b"\xff");
+    assert_eq!(Encoding::Base64.decode(b"Zg").unwrap(), b"f");
+    for bad in [&b"Z"[..], b"Zg=", b"Z===", b"Zg==Zg==", b"Z!=="] {
+        assert!(Encoding::Base64.decode(bad).is_err(), "{:?}", bad);
+    }
+    assert!(Encoding::Hex.decode(b"f").is_err());
+    assert!(Encoding::Hex.decode(b"fg").is_err());
+}
@@ -0,0 +1,163 @@
+use std::fmt;
+
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce, Tag};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::error::err_corrupt;
+use crate::reader::Result;
+
+// Values in an encrypted database are sealed with ChaCha20-Poly1305, as
+// specified in RFC 8439, with the record key as associated data, so a
+// value cannot be moved to another key unnoticed. Each stored value is
+// the nonce, the ciphertext and the tag. The nonce is derived from the
+// record with a keyed hash, so equal records give equal ciphertexts and
+// rebuilding a database gives the same file, while different records
+// never share a nonce.
+
+/// Identifies the encryption of a database in its encryption section.
+pub(crate) const CHACHA20_POLY1305_ID: u8 = 1;
+
+const NONCE_SIZE: usize = 12;
+const TAG_SIZE: usize = 16;
+
+/// Size of the key check stored in the encryption section.
+pub(crate) const KEY_CHECK_SIZE: usize = 16;
+
+/// How many bytes longer a value is when encrypted.
+pub(crate) const OVERHEAD: usize = NONCE_SIZE + TAG_SIZE;
+
+/// A 256-bit key for encrypting record values, set with
+/// [`GenericCDBMake::set_encryption`](crate::GenericCDBMake::set_encryption)
+/// and [`OpenOptions::encryption_key`](crate::OpenOptions::encryption_key).
+///
+/// The key is not shown by its `Debug` output.
+///
+/// # Examples
+///
+/// ```
+/// use cdb32::EncryptionKey;
+///
+/// let key = EncryptionKey::new([7; 32]);
+/// assert_eq!(format!("{:?}", key), "EncryptionKey(..)");
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey([u8; 32]);
+
+impl EncryptionKey {
+    /// Use the given bytes as the key.
+    pub fn new(key: [u8; 32]) -> EncryptionKey {
+        EncryptionKey(key)
+    }
+}
+
+impl From<[u8; 32]> for EncryptionKey {
+    fn from(key: [u8; 32]) -> EncryptionKey {
+        EncryptionKey(key)
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+/// The keys derived from an [`EncryptionKey`] for one purpose each.
+#[derive(Clone)]
+pub(crate) struct Cipher {
+    aead: ChaCha20Poly1305,
+    nonce_key: [u8; 32],
+    check: [u8; KEY_CHECK_SIZE],
+}
+
+impl fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Cipher")
+    }
+}
+
+impl Cipher {
+    pub(crate) fn new(key: &EncryptionKey) -> Cipher {
+        let mut check = [0; KEY_CHECK_SIZE];
+        check.copy_from_slice(&hmac(&key.0, &[b"cdb32 key check"])[..KEY_CHECK_SIZE]);
+        Cipher {
+            aead: ChaCha20Poly1305::new(&hmac(&key.0, &[b"cdb32 value encryption"]).into()),
+            nonce_key: hmac(&key.0, &[b"cdb32 value nonce"]),
+            check,
+        }
+    }
+
+    /// Return a value identifying the key without revealing it, stored
+    /// so that opening with the wrong key fails at once.
+    pub(crate) fn key_check(&self) -> [u8; KEY_CHECK_SIZE] {
+        self.check
+    }
+
+    /// Encrypt the value of the record with the given key.
+    pub(crate) fn seal(&self, key: &[u8], value: &[u8]) -> Vec<u8> {
+        let digest = hmac(
+            &self.nonce_key,
+            &[&(key.len() as u64).to_le_bytes(), key, value],
+        );
+        let nonce = Nonce::from_slice(&digest[..NONCE_SIZE]);
+        let mut sealed = Vec::with_capacity(OVERHEAD + value.len());
+        sealed.extend_from_slice(nonce);
+        sealed.extend_from_slice(value);
+        let tag = self
+            .aead
+            .encrypt_in_place_detached(nonce, key, &mut sealed[NONCE_SIZE..])
+            .expect("value too long to encrypt");
+        sealed.extend_from_slice(&tag);
+        sealed
+    }
+
+    /// Decrypt the value stored at `pos` for the record with the given
+    /// key, failing if it was tampered with.
+    pub(crate) fn open(&self, key: &[u8], pos: u64, mut sealed: Vec<u8>) -> Result<Vec<u8>> {
+        if sealed.len() < OVERHEAD {
+            return err_corrupt(pos, "encrypted value is too short");
+        }
+        let len = sealed.len() - TAG_SIZE;
+        let tag = *Tag::from_slice(&sealed[len..]);
+        sealed.truncate(len);
+        let (nonce, ciphertext) = sealed.split_at_mut(NONCE_SIZE);
+        let nonce = *Nonce::from_slice(nonce);
+        if self
+            .aead
+            .decrypt_in_place_detached(&nonce, key, ciphertext, &tag)
+            .is_err()
+        {
+            return err_corrupt(pos, "encrypted value failed authentication");
+        }
+        sealed.drain(..NONCE_SIZE);
+        Ok(sealed)
+    }
+}
+
+/// HMAC-SHA256 of the concatenation of `parts`.
+fn hmac(key: &[u8; 32], parts: &[&[u8]]) -> [u8; 32] {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC takes any key length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+#[test]
+fn seal_and_open() {
+    let cipher = Cipher::new(&EncryptionKey::new([1; 32]));
+    let sealed = cipher.seal(b"key", b"value");
+    assert_eq!(sealed.len(), 5 + OVERHEAD);
+    assert_eq!(cipher.seal(b"key", b"value"), sealed);
+    assert_ne!(
+        cipher.seal(b"key", b"other")[..NONCE_SIZE],
+        sealed[..NONCE_SIZE]
+    );
+    assert_eq!(cipher.open(b"key", 0, sealed.clone()).unwrap(), b"value");
+    assert!(cipher.open(b"other", 0, sealed.clone()).is_err());
+    let mut tampered = sealed;
+    tampered[NONCE_SIZE] ^= 1;
+    assert!(cipher.open(b"key", 0, tampered).is_err());
+}
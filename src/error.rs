@@ -0,0 +1,131 @@
+use std::error;
+use std::fmt;
+use std::io;
+
+/// The kinds of failure reported by this crate.
+///
+/// Functions return [`io::Result`](std::io::Result), so that errors from
+/// the underlying file pass straight through, but failures found by the
+/// crate itself are carried inside the [`io::Error`] as an `Error`.
+/// Converting an [`io::Error`] with [`Error::from`] recovers it, so that
+/// callers can tell a corrupt file from a transient I/O failure; any
+/// other [`io::Error`] becomes [`Error::Io`].
+///
+/// # Examples
+///
+/// ```
+/// use cdb32::{Error, CDB};
+///
+/// let err = CDB::from_bytes(vec![0; 100]).unwrap_err();
+/// assert!(matches!(Error::from(err), Error::Corrupt { offset: 0, .. }));
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An I/O error, such as one from reading or writing the file.
+    Io(io::Error),
+    /// The file is not a valid database. `offset` is the position in the
+    /// file of the malformed structure.
+    Corrupt { offset: u64, reason: &'static str },
+    /// The database would be larger than its [`Format`](crate::Format)
+    /// allows.
+    TooBig,
+    /// A key is too long for the database's format.
+    KeyTooLong,
+    /// A value is too long for the database's format.
+    ValueTooLong,
+    /// A key was added twice to a writer which rejects duplicates.
+    DuplicateKey,
+    /// The checksum stored with the record at `offset` does not match it.
+    ChecksumMismatch { offset: u64 },
+    /// The record at `offset` goes over a limit set in
+    /// [`OpenOptions`](crate::OpenOptions), named by `limit`, such as
+    /// `"max_value_len"`.
+    LimitExceeded { offset: u64, limit: &'static str },
+    /// The signature of a signed database does not match its contents,
+    /// or was made with another key.
+    BadSignature,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => err.fmt(f),
+            Error::Corrupt { offset, reason } => {
+                write!(f, "Invalid file format at offset {}: {}", offset, reason)
+            }
+            Error::TooBig => f.write_str("File too big"),
+            Error::KeyTooLong => f.write_str("Key too big"),
+            Error::ValueTooLong => f.write_str("Data too big"),
+            Error::DuplicateKey => f.write_str("Duplicate key"),
+            Error::ChecksumMismatch { offset } => {
+                write!(f, "Record checksum mismatch at offset {}", offset)
+            }
+            Error::LimitExceeded { offset, limit } => {
+                write!(f, "Record at offset {} exceeds the {} limit", offset, limit)
+            }
+            Error::BadSignature => f.write_str("Database signature does not match"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        if !err.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+            return Error::Io(err);
+        }
+        *err.into_inner().unwrap().downcast::<Error>().unwrap()
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        if let Error::Io(err) = err {
+            return err;
+        }
+        let kind = match err {
+            Error::Io(_) => unreachable!(),
+            Error::Corrupt { .. } | Error::ChecksumMismatch { .. } => io::ErrorKind::InvalidData,
+            Error::LimitExceeded { .. } | Error::BadSignature => io::ErrorKind::InvalidData,
+            Error::KeyTooLong | Error::ValueTooLong => io::ErrorKind::InvalidInput,
+            Error::DuplicateKey => io::ErrorKind::AlreadyExists,
+            Error::TooBig => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+/// Return an error for a malformed structure at `offset`.
+pub(crate) fn err_corrupt<T>(offset: u64, reason: &'static str) -> io::Result<T> {
+    Err(Error::Corrupt { offset, reason }.into())
+}
+
+#[test]
+fn round_trip() {
+    let err = io::Error::from(Error::Corrupt {
+        offset: 8,
+        reason: "bad",
+    });
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "Invalid file format at offset 8: bad");
+    assert!(matches!(
+        Error::from(err),
+        Error::Corrupt {
+            offset: 8,
+            reason: "bad"
+        }
+    ));
+
+    let err = Error::from(io::Error::from(io::ErrorKind::NotFound));
+    assert!(matches!(&err, Error::Io(err) if err.kind() == io::ErrorKind::NotFound));
+    assert_eq!(io::Error::from(err).kind(), io::ErrorKind::NotFound);
+}
@@ -0,0 +1,365 @@
+//! A C interface modelled on [tinycdb](https://www.corpit.ru/mjt/tinycdb.html).
+//!
+//! The functions here use the names and return conventions of tinycdb,
+//! so that C and C++ callers can switch to this crate with few changes.
+//! Build the `cdb32-ffi` workspace member to get a shared and a static
+//! library exporting them, declared in its `cdb32.h` header.
+//!
+//! Unlike tinycdb, the `struct cdb` and `struct cdb_make` handles are
+//! opaque and allocated here: [`cdb_open`] and [`cdb_make_start`] take a
+//! file name rather than a descriptor, and return `NULL` on failure.
+//! [`cdb_open_bytes`] opens a database held in memory instead.
+//! Functions returning `int` return a negative number on failure. In
+//! either case [`cdb_strerror`] describes the most recent failure in the
+//! calling thread.
+//!
+//! Lookups follow tinycdb: [`cdb_find`] returns `1` and makes the first
+//! value of a key current, or `0` if there is none, and [`cdb_findnext`]
+//! moves on to the key's next value. [`cdb_datalen`] and [`cdb_getdata`]
+//! return the current value. [`cdb_seqinit`] and [`cdb_seqnext`] step
+//! through every record instead, making its key current as well, as
+//! returned by [`cdb_keylen`] and [`cdb_getkey`].
+//!
+//! # Examples
+//!
+//! ```c
+//! struct cdb *cdb = cdb_open("data.cdb");
+//! if (cdb == NULL) {
+//!     fprintf(stderr, "%s\n", cdb_strerror());
+//!     return 1;
+//! }
+//! for (int r = cdb_find(cdb, "one", 3); r > 0; r = cdb_findnext(cdb))
+//!     fwrite(cdb_getdata(cdb), 1, cdb_datalen(cdb), stdout);
+//! cdb_free(cdb);
+//! ```
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, c_uint, c_void, CStr, CString};
+use std::io;
+use std::path::PathBuf;
+use std::ptr;
+use std::slice;
+use std::sync::Arc;
+
+use crate::reader::{CDBOwnedKeyValueIter, CDBOwnedValueIter, Result, CDB};
+use crate::writer::CDBWriter;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Record `err` as the calling thread's most recent failure.
+fn set_error(err: &io::Error) {
+    let msg = CString::new(err.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(msg));
+}
+
+/// Return the result, or record the error and return `fail`.
+fn or_fail<T>(result: Result<T>, fail: T) -> T {
+    result.unwrap_or_else(|err| {
+        set_error(&err);
+        fail
+    })
+}
+
+/// Convert a C file name to a path.
+unsafe fn path(name: *const c_char) -> Result<PathBuf> {
+    if name.is_null() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "NULL file name",
+        ));
+    }
+    let name = CStr::from_ptr(name);
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Ok(std::ffi::OsStr::from_bytes(name.to_bytes()).into())
+    }
+    #[cfg(not(unix))]
+    match name.to_str() {
+        Ok(name) => Ok(name.into()),
+        Err(_) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "File name is not valid UTF-8",
+        )),
+    }
+}
+
+/// View `len` bytes at `data`, which may be `NULL` if `len` is zero.
+unsafe fn bytes<'a>(data: *const c_void, len: c_uint) -> &'a [u8] {
+    if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data.cast(), len as usize)
+    }
+}
+
+/// An open database, returned by [`cdb_open`] or [`cdb_open_bytes`].
+#[allow(non_camel_case_types)]
+pub struct cdb {
+    cdb: Arc<CDB>,
+    find: Option<CDBOwnedValueIter>,
+    seq: Option<CDBOwnedKeyValueIter>,
+    key: Vec<u8>,
+    data: Vec<u8>,
+}
+
+/// A database being written, returned by [`cdb_make_start`].
+#[allow(non_camel_case_types)]
+pub struct cdb_make {
+    writer: CDBWriter,
+}
+
+/// Describe the most recent failure in the calling thread, or return
+/// `NULL` if there has been none. The string is valid until the next
+/// failing call in the same thread.
+#[no_mangle]
+pub extern "C" fn cdb_strerror() -> *const c_char {
+    LAST_ERROR.with(|last| match &*last.borrow() {
+        Some(msg) => msg.as_ptr(),
+        None => ptr::null(),
+    })
+}
+
+/// Wrap an opened database in a handle, or return `NULL` on failure.
+fn into_handle(opened: Result<CDB>) -> *mut cdb {
+    let opened = opened.map(|cdb| {
+        Box::into_raw(Box::new(cdb {
+            cdb: Arc::new(cdb),
+            find: None,
+            seq: None,
+            key: Vec::new(),
+            data: Vec::new(),
+        }))
+    });
+    or_fail(opened, ptr::null_mut())
+}
+
+/// Open the database in the file `name`, returning `NULL` on failure.
+///
+/// # Safety
+///
+/// `name` must be `NULL` or a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn cdb_open(name: *const c_char) -> *mut cdb {
+    into_handle(path(name).and_then(CDB::open))
+}
+
+/// Open a database from a copy of the `len` bytes at `data`, returning
+/// `NULL` on failure.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn cdb_open_bytes(data: *const c_void, len: usize) -> *mut cdb {
+    let data = if len == 0 {
+        Vec::new()
+    } else {
+        slice::from_raw_parts(data.cast::<u8>(), len).to_vec()
+    };
+    into_handle(CDB::from_bytes(data))
+}
+
+/// Close a database opened by [`cdb_open`] or [`cdb_open_bytes`]. `NULL` is ignored.
+///
+/// # Safety
+///
+/// `cdbp` must be `NULL` or a handle from [`cdb_open`] which has not been
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn cdb_free(cdbp: *mut cdb) {
+    if !cdbp.is_null() {
+        drop(Box::from_raw(cdbp));
+    }
+}
+
+/// Move to the next value of the current lookup.
+fn next_value(cdbp: &mut cdb) -> c_int {
+    match cdbp.find.as_mut().and_then(Iterator::next) {
+        Some(Ok(data)) => {
+            cdbp.data = data;
+            1
+        }
+        Some(Err(err)) => {
+            cdbp.find = None;
+            set_error(&err);
+            -1
+        }
+        None => {
+            cdbp.find = None;
+            0
+        }
+    }
+}
+
+/// Look up the `klen` byte key at `key`, returning `1` and making it and
+/// its first value current if it is found, `0` if it is not, or `-1` on
+/// failure.
+///
+/// # Safety
+///
+/// `cdbp` must be a live handle from [`cdb_open`], and `key` must point
+/// to `klen` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn cdb_find(cdbp: *mut cdb, key: *const c_void, klen: c_uint) -> c_int {
+    let cdbp = &mut *cdbp;
+    let key = bytes(key, klen);
+    cdbp.find = Some(Arc::clone(&cdbp.cdb).into_find(key));
+    cdbp.key = key.to_vec();
+    next_value(cdbp)
+}
+
+/// Make the next value of the key last passed to [`cdb_find`] current,
+/// returning `1`, or return `0` if there are no more, or `-1` on failure.
+///
+/// # Safety
+///
+/// `cdbp` must be a live handle from [`cdb_open`].
+#[no_mangle]
+pub unsafe extern "C" fn cdb_findnext(cdbp: *mut cdb) -> c_int {
+    next_value(&mut *cdbp)
+}
+
+/// Start stepping through every record with [`cdb_seqnext`], returning
+/// `0`.
+///
+/// # Safety
+///
+/// `cdbp` must be a live handle from [`cdb_open`].
+#[no_mangle]
+pub unsafe extern "C" fn cdb_seqinit(cdbp: *mut cdb) -> c_int {
+    let cdbp = &mut *cdbp;
+    cdbp.seq = Some(Arc::clone(&cdbp.cdb).into_records());
+    0
+}
+
+/// Make the next record current, returning `1`, or return `0` after the
+/// last record, or `-1` on failure.
+///
+/// # Safety
+///
+/// `cdbp` must be a live handle from [`cdb_open`].
+#[no_mangle]
+pub unsafe extern "C" fn cdb_seqnext(cdbp: *mut cdb) -> c_int {
+    let cdbp = &mut *cdbp;
+    match cdbp.seq.as_mut().and_then(Iterator::next) {
+        Some(Ok((key, data))) => {
+            cdbp.key = key;
+            cdbp.data = data;
+            1
+        }
+        Some(Err(err)) => {
+            cdbp.seq = None;
+            set_error(&err);
+            -1
+        }
+        None => {
+            cdbp.seq = None;
+            0
+        }
+    }
+}
+
+/// Return the length of the current key.
+///
+/// # Safety
+///
+/// `cdbp` must be a live handle from [`cdb_open`].
+#[no_mangle]
+pub unsafe extern "C" fn cdb_keylen(cdbp: *const cdb) -> c_uint {
+    (*cdbp).key.len() as c_uint
+}
+
+/// Return the current key, which is valid until the next call to
+/// [`cdb_find`], [`cdb_seqnext`] or [`cdb_free`] with `cdbp`.
+///
+/// # Safety
+///
+/// `cdbp` must be a live handle from [`cdb_open`].
+#[no_mangle]
+pub unsafe extern "C" fn cdb_getkey(cdbp: *const cdb) -> *const c_void {
+    (*cdbp).key.as_ptr().cast()
+}
+
+/// Return the length of the current value.
+///
+/// # Safety
+///
+/// `cdbp` must be a live handle from [`cdb_open`].
+#[no_mangle]
+pub unsafe extern "C" fn cdb_datalen(cdbp: *const cdb) -> c_uint {
+    (*cdbp).data.len() as c_uint
+}
+
+/// Return the current value, which is valid until the next call to
+/// [`cdb_find`], [`cdb_findnext`], [`cdb_seqnext`] or [`cdb_free`] with
+/// `cdbp`.
+///
+/// # Safety
+///
+/// `cdbp` must be a live handle from [`cdb_open`].
+#[no_mangle]
+pub unsafe extern "C" fn cdb_getdata(cdbp: *const cdb) -> *const c_void {
+    (*cdbp).data.as_ptr().cast()
+}
+
+/// Start writing a database to the file `name`, returning `NULL` on
+/// failure. The records are written to a temporary file which
+/// [`cdb_make_finish`] renames to `name`.
+///
+/// # Safety
+///
+/// `name` must be `NULL` or a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn cdb_make_start(name: *const c_char) -> *mut cdb_make {
+    let started = path(name)
+        .and_then(CDBWriter::create)
+        .map(|writer| Box::into_raw(Box::new(cdb_make { writer })));
+    or_fail(started, ptr::null_mut())
+}
+
+/// Add a record, returning `0`, or `-1` on failure.
+///
+/// # Safety
+///
+/// `cdbmp` must be a live handle from [`cdb_make_start`], and `key` and
+/// `val` must point to `klen` and `vlen` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn cdb_make_add(
+    cdbmp: *mut cdb_make,
+    key: *const c_void,
+    klen: c_uint,
+    val: *const c_void,
+    vlen: c_uint,
+) -> c_int {
+    let added = (*cdbmp).writer.add(bytes(key, klen), bytes(val, vlen));
+    or_fail(added.map(|_| 0), -1)
+}
+
+/// Finish the database and free `cdbmp`, returning `0`, or `-1` on
+/// failure, in which case the file is left as it was.
+///
+/// # Safety
+///
+/// `cdbmp` must be a live handle from [`cdb_make_start`], and is freed.
+#[no_mangle]
+pub unsafe extern "C" fn cdb_make_finish(cdbmp: *mut cdb_make) -> c_int {
+    let finished = Box::from_raw(cdbmp).writer.finish();
+    or_fail(finished.map(|_| 0), -1)
+}
+
+/// Abandon the database, removing the temporary file, and free `cdbmp`.
+/// `NULL` is ignored.
+///
+/// # Safety
+///
+/// `cdbmp` must be `NULL` or a live handle from [`cdb_make_start`], and
+/// is freed.
+#[no_mangle]
+pub unsafe extern "C" fn cdb_make_free(cdbmp: *mut cdb_make) {
+    if !cdbmp.is_null() {
+        let _ = Box::from_raw(cdbmp).writer.abort();
+    }
+}
@@ -0,0 +1,104 @@
+use core::fmt::Debug;
+
+use crate::{uint32, uint64};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// The on-disk integer width of a CDB file.
+///
+/// Classic CDB files store every position, length and hash as a 32-bit
+/// integer, limiting them to 4 GiB. The widely used 64-bit variant has
+/// the same layout with every integer widened to 64 bits, so its header
+/// is 4096 bytes instead of 2048. Hash values are computed the same way
+/// in both.
+///
+/// This trait is sealed and implemented only by [`Format32`] and
+/// [`Format64`].
+pub trait Format: sealed::Sealed + Debug + Send + Sync + 'static {
+    /// Size in bytes of each integer stored in the file.
+    const WIDTH: usize;
+    /// Largest file size the format can address.
+    const MAX_SIZE: u64;
+    /// Size in bytes of a `(position, length)` pair, hash table slot or
+    /// record header.
+    const PAIR_SIZE: usize = 2 * Self::WIDTH;
+    /// Size in bytes of the header holding the 256 hash table pointers.
+    const HEADER_SIZE: usize = 256 * Self::PAIR_SIZE;
+
+    #[doc(hidden)]
+    fn unpack(buf: &[u8]) -> u64;
+
+    #[doc(hidden)]
+    fn pack(buf: &mut [u8], value: u64);
+
+    #[doc(hidden)]
+    fn unpack2(buf: &[u8]) -> (u64, u64) {
+        (
+            Self::unpack(&buf[..Self::WIDTH]),
+            Self::unpack(&buf[Self::WIDTH..Self::PAIR_SIZE]),
+        )
+    }
+
+    #[doc(hidden)]
+    fn pack2(buf: &mut [u8], value0: u64, value1: u64) {
+        Self::pack(&mut buf[..Self::WIDTH], value0);
+        Self::pack(&mut buf[Self::WIDTH..Self::PAIR_SIZE], value1);
+    }
+}
+
+/// The classic CDB format with 32-bit integers.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Format32;
+
+impl sealed::Sealed for Format32 {}
+
+impl Format for Format32 {
+    const WIDTH: usize = 4;
+    const MAX_SIZE: u64 = 0xffffffff;
+
+    fn unpack(buf: &[u8]) -> u64 {
+        uint32::unpack(buf) as u64
+    }
+
+    fn pack(buf: &mut [u8], value: u64) {
+        uint32::pack(buf, value as u32)
+    }
+
+    fn unpack2(buf: &[u8]) -> (u64, u64) {
+        let (a, b) = uint32::unpack2(buf);
+        (a as u64, b as u64)
+    }
+
+    fn pack2(buf: &mut [u8], value0: u64, value1: u64) {
+        uint32::pack2(buf, value0 as u32, value1 as u32)
+    }
+}
+
+/// The 64-bit CDB variant, for databases larger than 4 GiB.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Format64;
+
+impl sealed::Sealed for Format64 {}
+
+impl Format for Format64 {
+    const WIDTH: usize = 8;
+    const MAX_SIZE: u64 = u64::MAX;
+
+    fn unpack(buf: &[u8]) -> u64 {
+        uint64::unpack(buf)
+    }
+
+    fn pack(buf: &mut [u8], value: u64) {
+        uint64::pack(buf, value)
+    }
+
+    fn unpack2(buf: &[u8]) -> (u64, u64) {
+        uint64::unpack2(buf)
+    }
+
+    fn pack2(buf: &mut [u8], value0: u64, value1: u64) {
+        uint64::pack2(buf, value0, value1)
+    }
+}
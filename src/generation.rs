@@ -0,0 +1,265 @@
+use std::fs;
+use std::io;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+use crate::format::{Format, Format32, Format64};
+use crate::reader::{GenericCDB, OpenOptions, Result};
+use crate::writer::{replace_file, BuildStats, Durability, GenericCDBWriter};
+
+/// A directory of numbered versions of one database, with a pointer to
+/// the current version, generic over the on-disk [`Format`].
+///
+/// Each rebuild is written to a new generation, `name.<generation>.cdb`,
+/// and the pointer file `name.current`, which holds the number of the
+/// current generation, is then atomically replaced. Readers open the
+/// current generation through the pointer, so they never see a partly
+/// written file, and a bad build can be undone with
+/// [`rollback`](Self::rollback). Only the newest generations are kept,
+/// three unless [`set_keep`](Self::set_keep) says otherwise.
+///
+/// The store expects one writer at a time. Most code should use the
+/// [`GenerationStore`] or [`GenerationStore64`] aliases.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// # let tmp_dir = tempfile::tempdir()?;
+/// use cdb32::GenerationStore;
+///
+/// let store = GenerationStore::new(tmp_dir.path(), "users");
+/// for value in ["old", "new"] {
+///     let mut writer = store.create()?;
+///     writer.add(b"alice", value.as_bytes())?;
+///     writer.finish()?;
+/// }
+/// assert_eq!(store.current_generation()?, Some(2));
+/// assert_eq!(store.open_current()?.get(b"alice").unwrap()?, b"new");
+///
+/// assert_eq!(store.rollback()?, 1);
+/// assert_eq!(store.open_current()?.get(b"alice").unwrap()?, b"old");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct GenericGenerationStore<F> {
+    dir: PathBuf,
+    name: String,
+    keep: usize,
+    durability: Durability,
+    format: PhantomData<F>,
+}
+
+/// Generation store for the classic 32-bit format.
+pub type GenerationStore = GenericGenerationStore<Format32>;
+
+/// Generation store for the 64-bit format.
+pub type GenerationStore64 = GenericGenerationStore<Format64>;
+
+impl<F: Format> GenericGenerationStore<F> {
+    /// Use the generations of the database `name` in the directory `dir`.
+    pub fn new<P: Into<PathBuf>>(dir: P, name: &str) -> Self {
+        GenericGenerationStore {
+            dir: dir.into(),
+            name: name.to_string(),
+            keep: 3,
+            durability: Durability::default(),
+            format: PhantomData,
+        }
+    }
+
+    /// Set how many of the newest generations to keep, at least one. The
+    /// current generation is always kept, even after a rollback.
+    pub fn set_keep(&mut self, keep: usize) {
+        self.keep = keep.max(1);
+    }
+
+    /// Set how new generations and the pointer file are synced to disk.
+    pub fn set_durability(&mut self, durability: Durability) {
+        self.durability = durability;
+    }
+
+    /// Return the path of the file of `generation`.
+    pub fn path(&self, generation: u64) -> PathBuf {
+        self.dir.join(format!("{}.{}.cdb", self.name, generation))
+    }
+
+    /// Return the path of the pointer file.
+    pub fn pointer_path(&self) -> PathBuf {
+        self.dir.join(format!("{}.current", self.name))
+    }
+
+    /// Return the generations on disk, oldest first.
+    pub fn generations(&self) -> Result<Vec<u64>> {
+        let prefix = format!("{}.", self.name);
+        let mut generations = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let name = entry?.file_name();
+            let generation = name
+                .to_str()
+                .and_then(|name| name.strip_prefix(&prefix))
+                .and_then(|name| name.strip_suffix(".cdb"))
+                .filter(|number| number.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|number| number.parse::<u64>().ok());
+            generations.extend(generation);
+        }
+        generations.sort_unstable();
+        Ok(generations)
+    }
+
+    /// Return the current generation, or `None` if none has been
+    /// published yet.
+    pub fn current_generation(&self) -> Result<Option<u64>> {
+        let text = match fs::read_to_string(self.pointer_path()) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        match text.trim_end().parse() {
+            Ok(generation) => Ok(Some(generation)),
+            Err(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Malformed generation pointer {:?}", text),
+            )),
+        }
+    }
+
+    /// Open the current generation.
+    ///
+    /// Returns an error of kind [`io::ErrorKind::NotFound`] if no
+    /// generation has been published yet.
+    pub fn open_current(&self) -> Result<GenericCDB<F>> {
+        self.open_current_with_options(&OpenOptions::new())
+    }
+
+    /// Open the current generation with the given options.
+    pub fn open_current_with_options(&self, options: &OpenOptions) -> Result<GenericCDB<F>> {
+        match self.current_generation()? {
+            Some(generation) => self.open_with_options(generation, options),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No generation of {} has been published", self.name),
+            )),
+        }
+    }
+
+    /// Open the given generation, current or not.
+    pub fn open(&self, generation: u64) -> Result<GenericCDB<F>> {
+        self.open_with_options(generation, &OpenOptions::new())
+    }
+
+    /// Open the given generation with the given options.
+    pub fn open_with_options(
+        &self,
+        generation: u64,
+        options: &OpenOptions,
+    ) -> Result<GenericCDB<F>> {
+        GenericCDB::from_file(fs::File::open(self.path(generation))?, options)
+    }
+
+    /// Start writing the next generation, numbered one above the newest
+    /// on disk.
+    pub fn create(&self) -> Result<GenericGenerationWriter<'_, F>> {
+        let generation = self.generations()?.last().map_or(1, |newest| newest + 1);
+        let mut writer = GenericCDBWriter::create(self.path(generation))?;
+        writer.set_durability(self.durability);
+        Ok(GenericGenerationWriter {
+            store: self,
+            generation,
+            writer,
+        })
+    }
+
+    /// Make `generation` the current one, which must exist on disk.
+    pub fn set_current(&self, generation: u64) -> Result<()> {
+        fs::metadata(self.path(generation))?;
+        let text = format!("{}\n", generation);
+        replace_file(&self.pointer_path(), text.as_bytes(), self.durability)
+    }
+
+    /// Make the newest generation before the current one current again,
+    /// returning its number.
+    ///
+    /// Returns an error of kind [`io::ErrorKind::NotFound`] if there is no
+    /// earlier generation on disk.
+    pub fn rollback(&self) -> Result<u64> {
+        let current = self.current_generation()?;
+        let previous = self
+            .generations()?
+            .into_iter()
+            .rev()
+            .find(|&generation| current.is_some_and(|current| generation < current));
+        match previous {
+            Some(previous) => {
+                self.set_current(previous)?;
+                Ok(previous)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No earlier generation of {} to roll back to", self.name),
+            )),
+        }
+    }
+
+    /// Remove all but the newest generations and the current one,
+    /// returning the numbers of those removed.
+    ///
+    /// On Unix, readers which already opened a removed generation keep
+    /// reading it.
+    pub fn prune(&self) -> Result<Vec<u64>> {
+        let current = self.current_generation()?;
+        let mut generations = self.generations()?;
+        let old = generations.len().saturating_sub(self.keep);
+        generations.truncate(old);
+        generations.retain(|&generation| Some(generation) != current);
+        for &generation in &generations {
+            fs::remove_file(self.path(generation))?;
+        }
+        Ok(generations)
+    }
+}
+
+/// A writer for a new generation of a [`GenericGenerationStore`],
+/// created by [`GenericGenerationStore::create`].
+///
+/// Finishing the writer publishes the generation. Dropping it without
+/// finishing leaves the current generation as it is.
+#[derive(Debug)]
+pub struct GenericGenerationWriter<'a, F> {
+    store: &'a GenericGenerationStore<F>,
+    generation: u64,
+    writer: GenericCDBWriter<F>,
+}
+
+/// Generation writer for the classic 32-bit format.
+pub type GenerationWriter<'a> = GenericGenerationWriter<'a, Format32>;
+
+/// Generation writer for the 64-bit format.
+pub type GenerationWriter64<'a> = GenericGenerationWriter<'a, Format64>;
+
+impl<'a, F: Format> GenericGenerationWriter<'a, F> {
+    /// Return the number of the generation being written.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Return the underlying writer, to add records in other ways.
+    pub fn writer_mut(&mut self) -> &mut GenericCDBWriter<F> {
+        &mut self.writer
+    }
+
+    /// Add a record to the new generation.
+    pub fn add(&mut self, key: &[u8], data: &[u8]) -> Result<()> {
+        self.writer.add(key, data)
+    }
+
+    /// Finish the new generation, make it the current one and prune old
+    /// generations, returning statistics about the database built.
+    pub fn finish(self) -> Result<BuildStats> {
+        let stats = self.writer.finish()?;
+        self.store.set_current(self.generation)?;
+        self.store.prune()?;
+        Ok(stats)
+    }
+}
@@ -1,10 +1,22 @@
+//! Hashing of CDB keys.
+//!
+//! [`hash`] computes the standard CDB hash of a whole key in one call,
+//! while [`DjbHasher`] computes it incrementally, for keys assembled from
+//! several parts. Either can be passed to
+//! [`GenericCDB::find_with_hash`](crate::GenericCDB::find_with_hash).
+
+use alloc::vec::Vec;
+use core::hash::Hasher;
+
 const HASHSTART: u32 = 0x1505;
 
+/// Mix one more byte into the hash `h`.
 pub fn add(h: u32, c: u8) -> u32 {
     //(h + (h << 5)) ^ (c as u32)
     h.wrapping_shl(5).wrapping_add(h) ^ (c as u32)
 }
 
+/// Compute the standard CDB hash of `buf`.
 pub fn hash(buf: &[u8]) -> u32 {
     let mut h = HASHSTART;
     for c in buf {
@@ -13,6 +25,166 @@ pub fn hash(buf: &[u8]) -> u32 {
     h
 }
 
+/// Incremental version of [`hash`].
+///
+/// This also implements [`Hasher`], whose `finish` returns the 32-bit
+/// hash widened to 64 bits. Note that the `Hash` implementations of most
+/// types write more than their bytes, so feed it with
+/// [`update`](Self::update) or [`Hasher::write`] rather than through
+/// `Hash`.
+///
+/// # Example
+///
+/// ```
+/// use cdb32::hash::{hash, DjbHasher};
+///
+/// let mut hasher = DjbHasher::new();
+/// hasher.update(b"user:");
+/// hasher.update(b"1000");
+/// assert_eq!(hasher.value(), hash(b"user:1000"));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct DjbHasher {
+    h: u32,
+}
+
+impl DjbHasher {
+    /// Create a hasher for an empty key.
+    pub fn new() -> Self {
+        DjbHasher { h: HASHSTART }
+    }
+
+    /// Add `bytes` to the end of the key being hashed.
+    pub fn update(&mut self, bytes: &[u8]) {
+        for c in bytes {
+            self.h = add(self.h, *c);
+        }
+    }
+
+    /// Return the hash of the bytes added so far.
+    pub fn value(&self) -> u32 {
+        self.h
+    }
+}
+
+impl Default for DjbHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher for DjbHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        self.h as u64
+    }
+}
+
+/// A hash function for CDB keys.
+///
+/// The format only requires a 32-bit hash, whose low 8 bits select one
+/// of the 256 hash tables. Almost every CDB uses [`DjbHash`], which is
+/// the default everywhere; implement this trait to read or write files
+/// from other tools which use a different function. The reader and the
+/// writer must of course agree.
+///
+/// # Example
+///
+/// ```
+/// use cdb32::CdbHash;
+///
+/// /// 32-bit FNV-1a.
+/// struct Fnv;
+///
+/// impl CdbHash for Fnv {
+///     fn hash(key: &[u8]) -> u32 {
+///         key.iter()
+///             .fold(0x811c9dc5, |h, &c| (h ^ c as u32).wrapping_mul(0x01000193))
+///     }
+/// }
+/// ```
+pub trait CdbHash: Send + Sync + 'static {
+    /// Hash `key`.
+    fn hash(key: &[u8]) -> u32;
+}
+
+/// The original CDB hash function by D. J. Bernstein.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DjbHash;
+
+impl CdbHash for DjbHash {
+    fn hash(key: &[u8]) -> u32 {
+        hash(key)
+    }
+}
+
+/// A key together with its precomputed CDB hash.
+///
+/// Looking a key up or adding a record hashes the key each time. When
+/// the same keys are used repeatedly, building a `CDBKey` once avoids
+/// the rehashing.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use cdb32::{CDBKey, CDB};
+///
+/// let key = CDBKey::new(b"one");
+/// let cdb = CDB::open("tests/test1.cdb")?;
+/// for result in cdb.find_with_key(&key) {
+///     println!("{:?}", result?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CDBKey {
+    key: Vec<u8>,
+    hash: u32,
+}
+
+impl CDBKey {
+    /// Create a new key handle, computing the hash of `key`.
+    pub fn new(key: &[u8]) -> CDBKey {
+        CDBKey {
+            key: key.to_vec(),
+            hash: hash(key),
+        }
+    }
+
+    /// Create a new key handle, computing the hash of `key` with the
+    /// hash function `H` instead of the default [`DjbHash`].
+    pub fn with_hasher<H: CdbHash>(key: &[u8]) -> CDBKey {
+        CDBKey {
+            key: key.to_vec(),
+            hash: H::hash(key),
+        }
+    }
+
+    /// Return the bytes of the key.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.key
+    }
+
+    /// Return the CDB hash of the key.
+    pub fn hash_value(&self) -> u32 {
+        self.hash
+    }
+}
+
+#[test]
+fn incremental() {
+    let mut hasher = DjbHasher::new();
+    assert_eq!(hasher.value(), hash(b""));
+    hasher.update(b"Hello, ");
+    hasher.write(b"world!");
+    assert_eq!(hasher.finish(), 0x564369e8);
+}
+
 #[test]
 fn samples() {
     assert_eq!(hash(b""), 0x0001505);
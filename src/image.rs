@@ -0,0 +1,481 @@
+use alloc::vec::Vec;
+use core::cmp::min;
+use core::fmt;
+use core::marker::PhantomData;
+
+use crate::format::{Format, Format32, Format64};
+use crate::hash::hash;
+
+const KEYSIZE: usize = 32;
+
+/// The error returned when a database image is malformed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidFormat;
+
+impl fmt::Display for InvalidFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Invalid file format")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidFormat {}
+
+#[cfg(feature = "std")]
+impl From<InvalidFormat> for std::io::Error {
+    fn from(err: InvalidFormat) -> Self {
+        std::io::Error::new(std::io::ErrorKind::Other, err.to_string())
+    }
+}
+
+/// Limits on what lookups accept from a database, guarding against
+/// pathological files from untrusted sources.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Limits {
+    /// Whether every record found must lie within the data section.
+    pub(crate) checked: bool,
+    /// The most hash table slots a lookup examines between matches.
+    pub(crate) max_probes: u64,
+    /// The longest value a lookup or scan returns.
+    pub(crate) max_value_len: u64,
+    /// The most records a lookup returns for one key.
+    pub(crate) max_records: u64,
+}
+
+impl Limits {
+    pub(crate) const NONE: Limits = Limits {
+        checked: false,
+        max_probes: u64::MAX,
+        max_value_len: u64::MAX,
+        max_records: u64::MAX,
+    };
+}
+
+/// Random access to the bytes of a database, shared by the file-backed
+/// reader and [`GenericCDBImage`] so that both use the same lookup
+/// logic.
+pub(crate) trait Storage<F: Format> {
+    type Error;
+
+    /// Return the raw header holding the 256 hash table pointers.
+    fn header(&self) -> &[u8];
+
+    /// Return the size of the database.
+    fn size(&self) -> u64;
+
+    /// Fill `buf` with the bytes starting at `pos`.
+    fn read(&self, buf: &mut [u8], pos: u64) -> Result<(), Self::Error>;
+
+    /// Return the error for a malformed structure at `offset`.
+    fn corrupt(offset: u64, reason: &'static str) -> Self::Error;
+
+    /// Return the error for the record at `offset` going over the limit
+    /// named `limit`.
+    fn over_limit(offset: u64, limit: &'static str) -> Self::Error;
+
+    /// Return the limits lookups must respect.
+    fn limits(&self) -> Limits {
+        Limits::NONE
+    }
+
+    /// Return the position and number of slots of hash table `i`.
+    fn table(&self, i: usize) -> (u64, u64) {
+        let x = i * F::PAIR_SIZE;
+        F::unpack2(&self.header()[x..x + F::PAIR_SIZE])
+    }
+
+    /// Return the end of the data section, where the first hash table
+    /// starts.
+    fn data_end(&self) -> u64 {
+        self.table(0).0.min(self.size())
+    }
+
+    /// Return the position and number of slots of the hash table for
+    /// `khash`, and the position of the slot the probe starts at.
+    fn hash_table(&self, khash: u32) -> (u64, u64, u64) {
+        let (hpos, hslots) = self.table((khash & 0xff) as usize);
+        let kpos = if hslots > 0 {
+            hpos.wrapping_add(((khash >> 8) as u64 % hslots) * F::PAIR_SIZE as u64)
+        } else {
+            0
+        };
+        (hpos, hslots, kpos)
+    }
+
+    /// Return `true` if the bytes at `pos` are equal to `key`.
+    fn match_key(&self, key: &[u8], pos: u64) -> Result<bool, Self::Error> {
+        let mut buf = [0_u8; KEYSIZE];
+        let mut len = key.len();
+        let mut pos = pos;
+        let mut keypos = 0;
+
+        while len > 0 {
+            let n = min(len, buf.len());
+            self.read(&mut buf[..n], pos)?;
+            if buf[..n] != key[keypos..keypos + n] {
+                return Ok(false);
+            }
+            pos += n as u64;
+            keypos += n;
+            len -= n;
+        }
+        Ok(true)
+    }
+}
+
+/// Position within the hash table chain for one key.
+#[derive(Debug)]
+pub(crate) struct Probe {
+    khash: u32,
+    kloop: u64,
+    misses: u64,
+    found: u64,
+    pub(crate) kpos: u64,
+    hpos: u64,
+    hslots: u64,
+}
+
+impl Probe {
+    pub(crate) fn start<F: Format, S: Storage<F>>(cdb: &S, khash: u32) -> Self {
+        let (hpos, hslots, kpos) = cdb.hash_table(khash);
+        Probe {
+            khash,
+            kloop: 0,
+            misses: 0,
+            found: 0,
+            kpos,
+            hpos,
+            hslots,
+        }
+    }
+
+    /// Return a probe which finds nothing.
+    #[cfg(feature = "std")]
+    pub(crate) fn empty() -> Self {
+        Probe {
+            khash: 0,
+            kloop: 0,
+            misses: 0,
+            found: 0,
+            kpos: 0,
+            hpos: 0,
+            hslots: 0,
+        }
+    }
+
+    /// Advance to the next record matching `key`, returning the position
+    /// and length of its data.
+    pub(crate) fn next<F: Format, S: Storage<F>>(
+        &mut self,
+        cdb: &S,
+        key: &[u8],
+    ) -> Option<Result<(u64, u64), S::Error>> {
+        self.advance(cdb, key).transpose()
+    }
+
+    fn advance<F: Format, S: Storage<F>>(
+        &mut self,
+        cdb: &S,
+        key: &[u8],
+    ) -> Result<Option<(u64, u64)>, S::Error> {
+        let pair = F::PAIR_SIZE as u64;
+        let limits = cdb.limits();
+        while self.kloop < self.hslots {
+            if self.misses >= limits.max_probes {
+                return Err(S::corrupt(self.hpos, "too many hash table probes"));
+            }
+            let mut buf = [0_u8; 16];
+            let buf = &mut buf[..F::PAIR_SIZE];
+            let slot = self.kpos;
+            cdb.read(buf, slot)?;
+            let (khash, pos) = F::unpack2(buf);
+            if pos == 0 {
+                return Ok(None);
+            }
+            self.kloop += 1;
+            self.misses += 1;
+            self.kpos += pair;
+            let hend = self
+                .hslots
+                .checked_mul(pair)
+                .and_then(|len| self.hpos.checked_add(len))
+                .ok_or_else(|| S::corrupt(self.hpos, "hash table overflows"))?;
+            if self.kpos == hend {
+                self.kpos = self.hpos;
+            }
+            if khash != self.khash as u64 {
+                continue;
+            }
+            if limits.checked && (pos < F::HEADER_SIZE as u64 || pos >= cdb.data_end()) {
+                return Err(S::corrupt(slot, "slot points outside the data section"));
+            }
+            cdb.read(buf, pos)?;
+            let (klen, dlen) = F::unpack2(buf);
+            let dpos = pos
+                .checked_add(pair)
+                .and_then(|kpos| kpos.checked_add(klen))
+                .ok_or_else(|| S::corrupt(pos, "record length overflows"))?;
+            if klen != key.len() as u64 || !cdb.match_key(key, dpos - klen)? {
+                continue;
+            }
+            if limits.checked
+                && dpos
+                    .checked_add(dlen)
+                    .map_or(true, |end| end > cdb.data_end())
+            {
+                return Err(S::corrupt(pos, "record extends past the data section"));
+            }
+            if dlen > limits.max_value_len {
+                return Err(S::over_limit(pos, "max_value_len"));
+            }
+            if self.found >= limits.max_records {
+                return Err(S::over_limit(pos, "max_find_records"));
+            }
+            self.found += 1;
+            self.misses = 0;
+            return Ok(Some((dpos, dlen)));
+        }
+        Ok(None)
+    }
+}
+
+/// Position and length of a record's key, then of its data.
+pub(crate) type RecordPos = (u64, u64, u64, u64);
+
+/// Position of a sequential walk through the data section.
+#[derive(Debug)]
+pub(crate) struct Scan {
+    pos: u64,
+    data_end: u64,
+}
+
+impl Scan {
+    pub(crate) fn start<F: Format, S: Storage<F>>(cdb: &S) -> Self {
+        Scan {
+            pos: F::HEADER_SIZE as u64,
+            data_end: cdb.data_end(),
+        }
+    }
+
+    /// Start at `pos`, which must be the start of a record, and stop at
+    /// `end`.
+    #[cfg(feature = "std")]
+    pub(crate) fn range(pos: u64, end: u64) -> Self {
+        Scan { pos, data_end: end }
+    }
+
+    /// Return the position of the next record.
+    #[cfg(feature = "std")]
+    pub(crate) fn pos(&self) -> u64 {
+        self.pos
+    }
+
+    /// Advance past the next record, returning the positions and
+    /// lengths of its key and value.
+    pub(crate) fn next<F: Format, S: Storage<F>>(
+        &mut self,
+        cdb: &S,
+    ) -> Option<Result<RecordPos, S::Error>> {
+        self.advance(cdb).transpose()
+    }
+
+    fn advance<F: Format, S: Storage<F>>(
+        &mut self,
+        cdb: &S,
+    ) -> Result<Option<RecordPos>, S::Error> {
+        let pair = F::PAIR_SIZE as u64;
+        if self.pos + pair > self.data_end {
+            return Ok(None);
+        }
+        let mut buf = [0_u8; 16];
+        let buf = &mut buf[..F::PAIR_SIZE];
+        cdb.read(buf, self.pos)?;
+        let (klen, dlen) = F::unpack2(buf);
+        let total_len = self
+            .pos
+            .saturating_add(pair)
+            .saturating_add(klen)
+            .saturating_add(dlen);
+        if total_len > self.data_end {
+            return Err(S::corrupt(self.pos, "record extends past the data section"));
+        }
+        let pos = self.pos;
+        // Move past the record first, so a scan can carry on after one
+        // which is over the limit.
+        self.pos = total_len;
+        if dlen > cdb.limits().max_value_len {
+            return Err(S::over_limit(pos, "max_value_len"));
+        }
+        let kpos = pos + pair;
+        let dpos = kpos + klen;
+        Ok(Some((kpos, klen, dpos, dlen)))
+    }
+}
+
+/// A CDB reader over a database image already in memory.
+///
+/// Unlike [`GenericCDB`](crate::GenericCDB), this type does not need an
+/// operating system and is available without the default `std` feature,
+/// so it can be used on embedded targets where the database is stored
+/// as a blob in flash. All values are returned as slices of the image.
+///
+/// # Examples
+///
+/// ```
+/// use cdb32::CDBImage;
+///
+/// static DATA: &[u8] = include_bytes!("../tests/test1.cdb");
+///
+/// let cdb = CDBImage::new(DATA).unwrap();
+/// assert_eq!(cdb.get(b"two"), Some(Ok(&b"Goodbye"[..])));
+/// ```
+#[derive(Debug)]
+pub struct GenericCDBImage<'a, F = Format32> {
+    data: &'a [u8],
+    format: PhantomData<F>,
+}
+
+/// A reader over a classic 32-bit CDB image.
+pub type CDBImage<'a> = GenericCDBImage<'a, Format32>;
+
+/// A reader over a 64-bit CDB image.
+pub type CDB64Image<'a> = GenericCDBImage<'a, Format64>;
+
+impl<'a, F> Clone for GenericCDBImage<'a, F> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, F> Copy for GenericCDBImage<'a, F> {}
+
+impl<'a, F: Format> Storage<F> for GenericCDBImage<'a, F> {
+    type Error = InvalidFormat;
+
+    fn header(&self) -> &[u8] {
+        &self.data[..F::HEADER_SIZE]
+    }
+
+    fn size(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    fn read(&self, buf: &mut [u8], pos: u64) -> Result<(), InvalidFormat> {
+        buf.copy_from_slice(self.slice(pos, buf.len() as u64)?);
+        Ok(())
+    }
+
+    fn match_key(&self, key: &[u8], pos: u64) -> Result<bool, InvalidFormat> {
+        Ok(self.slice(pos, key.len() as u64)? == key)
+    }
+
+    fn corrupt(_offset: u64, _reason: &'static str) -> InvalidFormat {
+        InvalidFormat
+    }
+
+    fn over_limit(_offset: u64, _limit: &'static str) -> InvalidFormat {
+        InvalidFormat
+    }
+}
+
+impl<'a, F: Format> GenericCDBImage<'a, F> {
+    /// Create a reader over the database image in `data`.
+    pub fn new(data: &'a [u8]) -> Result<Self, InvalidFormat> {
+        if !(F::HEADER_SIZE as u64..=F::MAX_SIZE).contains(&(data.len() as u64)) {
+            return Err(InvalidFormat);
+        }
+        Ok(GenericCDBImage {
+            data,
+            format: PhantomData,
+        })
+    }
+
+    fn slice(&self, pos: u64, len: u64) -> Result<&'a [u8], InvalidFormat> {
+        match pos.checked_add(len) {
+            Some(end) if end <= self.data.len() as u64 => {
+                Ok(&self.data[pos as usize..end as usize])
+            }
+            _ => Err(InvalidFormat),
+        }
+    }
+
+    /// Return the total number of records in the database.
+    pub fn len(&self) -> usize {
+        let slots = (0..256).fold(0_u64, |acc, i| acc.saturating_add(self.table(i).1));
+        (slots / 2) as usize
+    }
+
+    /// Return `true` if the database contains no records.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Find the first record with the named key.
+    pub fn get(&self, key: &[u8]) -> Option<Result<&'a [u8], InvalidFormat>> {
+        self.find(key).next()
+    }
+
+    /// Return `true` if at least one record has the named key.
+    pub fn exists(&self, key: &[u8]) -> Result<bool, InvalidFormat> {
+        self.get(key).transpose().map(|value| value.is_some())
+    }
+
+    /// Find all records with the named key.
+    pub fn find(&self, key: &[u8]) -> CDBImageValueIter<'a, F> {
+        CDBImageValueIter {
+            cdb: *self,
+            key: key.to_vec(),
+            probe: Probe::start(self, hash(key)),
+        }
+    }
+
+    /// Iterate over all the `(key, value)` pairs in the database.
+    pub fn iter(&self) -> CDBImageIter<'a, F> {
+        CDBImageIter {
+            cdb: *self,
+            scan: Scan::start(self),
+        }
+    }
+}
+
+/// Iterator over a set of records in a [`GenericCDBImage`] with the
+/// same key.
+#[derive(Debug)]
+pub struct CDBImageValueIter<'a, F = Format32> {
+    cdb: GenericCDBImage<'a, F>,
+    key: Vec<u8>,
+    probe: Probe,
+}
+
+impl<'a, F: Format> Iterator for CDBImageValueIter<'a, F> {
+    type Item = Result<&'a [u8], InvalidFormat>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let (dpos, dlen) = match self.probe.next(&self.cdb, &self.key)? {
+            Ok(found) => found,
+            Err(err) => return Some(Err(err)),
+        };
+        Some(self.cdb.slice(dpos, dlen))
+    }
+}
+
+/// Iterator over all the records in a [`GenericCDBImage`].
+#[derive(Debug)]
+pub struct CDBImageIter<'a, F = Format32> {
+    cdb: GenericCDBImage<'a, F>,
+    scan: Scan,
+}
+
+impl<'a, F: Format> Iterator for CDBImageIter<'a, F> {
+    type Item = Result<(&'a [u8], &'a [u8]), InvalidFormat>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let (kpos, klen, dpos, dlen) = match self.scan.next(&self.cdb)? {
+            Ok(found) => found,
+            Err(err) => return Some(Err(err)),
+        };
+        Some(
+            self.cdb
+                .slice(kpos, klen)
+                .and_then(|key| Ok((key, self.cdb.slice(dpos, dlen)?))),
+        )
+    }
+}
@@ -0,0 +1,201 @@
+//! Reverse lookup indexes, mapping values back to the keys holding them.
+//!
+//! A CDB answers "what is the value of this key" in one or two reads, but
+//! "which keys have this value" needs a scan of the whole file. An
+//! inverted index is a second database answering the reverse question:
+//! [`build`] scans a database once and writes a record for each of its
+//! records, keyed by the value and holding the key. [`build_by`] keys the
+//! index by fields extracted from each value instead, such as a user's
+//! email address out of a serialized profile, or each of a list of tags.
+//! [`lookup`] then finds the records of the first database through the
+//! index.
+//!
+//! The index holds one record per field of each record, in the order the
+//! records are stored, so [`find`](GenericCDB::find) on the index returns
+//! every key with that field. It must be rebuilt whenever the database
+//! it indexes is.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> std::io::Result<()> {
+//! # let tmp_dir = tempfile::tempdir()?;
+//! # let path = tmp_dir.path().join("users.cdb");
+//! # let index_path = tmp_dir.path().join("users.by-group.cdb");
+//! use cdb32::{invert, CDBWriter, CDB};
+//!
+//! CDBWriter::from_iter(&path, [("alice", "staff"), ("bob", "admin"), ("carol", "staff")])?;
+//! let users = CDB::open(&path)?;
+//! invert::build(&users, &index_path)?;
+//!
+//! let by_group = CDB::open(&index_path)?;
+//! let staff = by_group.find(b"staff").collect::<std::io::Result<Vec<_>>>()?;
+//! assert_eq!(staff, [b"alice", b"carol"]);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::{Seek, Write};
+use std::path::PathBuf;
+
+use crate::format::Format;
+use crate::hash::CdbHash;
+use crate::reader::{GenericCDB, Result};
+use crate::writer::{GenericCDBMake, GenericCDBWriter};
+
+/// Write an index of `cdb` keyed by value to a new file at `path`,
+/// returning the number of records written.
+pub fn build<F: Format, H: CdbHash, P: Into<PathBuf>>(
+    cdb: &GenericCDB<F, H>,
+    path: P,
+) -> Result<u64> {
+    let mut index = GenericCDBWriter::<F>::create(path)?;
+    let count = index.add_inverted(cdb)?;
+    index.finish()?;
+    Ok(count)
+}
+
+/// Write an index of `cdb` keyed by the fields `extract` returns for
+/// each value to a new file at `path`, returning the number of records
+/// written. See [`GenericCDBMake::add_inverted_by`].
+pub fn build_by<F, H, P, E>(cdb: &GenericCDB<F, H>, path: P, extract: E) -> Result<u64>
+where
+    F: Format,
+    H: CdbHash,
+    P: Into<PathBuf>,
+    E: FnMut(&[u8]) -> Result<Vec<Vec<u8>>>,
+{
+    let mut index = GenericCDBWriter::<F>::create(path)?;
+    let count = index.add_inverted_by(cdb, extract)?;
+    index.finish()?;
+    Ok(count)
+}
+
+/// Find the records of `cdb` whose keys `index` lists under `field`, as
+/// `(key, value)` pairs.
+///
+/// Each key is looked up with [`get`](GenericCDB::get), so only its first
+/// value is returned. Keys the index lists but `cdb` lacks, as happens
+/// when the index is out of date, are skipped.
+pub fn lookup<'a, F, H, F2, H2>(
+    index: &'a GenericCDB<F, H>,
+    cdb: &'a GenericCDB<F2, H2>,
+    field: &[u8],
+) -> impl Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + 'a
+where
+    F: Format,
+    H: CdbHash,
+    F2: Format,
+    H2: CdbHash,
+{
+    index.find(field).filter_map(move |key| {
+        let key = match key {
+            Ok(key) => key,
+            Err(err) => return Some(Err(err)),
+        };
+        let value = cdb.get(&key)?;
+        Some(value.map(|value| (key, value)))
+    })
+}
+
+/// Call `add` with each `(field, key)` record of the index of `cdb`,
+/// returning how many there were.
+fn inverted<F, H, E>(
+    cdb: &GenericCDB<F, H>,
+    mut extract: E,
+    mut add: impl FnMut(&[u8], &[u8]) -> Result<()>,
+) -> Result<u64>
+where
+    F: Format,
+    H: CdbHash,
+    E: FnMut(&[u8]) -> Result<Vec<Vec<u8>>>,
+{
+    let mut count = 0;
+    for record in cdb.iter() {
+        let (key, value) = record?;
+        for field in extract(&value)? {
+            add(&field, &key)?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+impl<F: Format, H: CdbHash, W: Write + Seek> GenericCDBMake<F, H, W> {
+    /// Add a record for each record of `cdb`, keyed by its value and
+    /// holding its key, returning how many were added. See the
+    /// [`invert`](crate::invert) module.
+    pub fn add_inverted<F2: Format, H2: CdbHash>(
+        &mut self,
+        cdb: &GenericCDB<F2, H2>,
+    ) -> Result<u64> {
+        self.add_inverted_by(cdb, |value| Ok(vec![value.to_vec()]))
+    }
+
+    /// Add a record for each field `extract` returns for the values of
+    /// `cdb`, keyed by the field and holding the key of the value,
+    /// returning how many were added.
+    ///
+    /// A value may have any number of fields, including none. An error
+    /// from `extract` stops the scan and is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::{CDBMake, CDB};
+    ///
+    /// let mut posts = CDBMake::in_memory()?;
+    /// posts.add(b"post1", b"rust,cdb")?;
+    /// posts.add(b"post2", b"cdb")?;
+    /// let posts = CDB::from_bytes(posts.into_vec()?)?;
+    ///
+    /// let mut by_tag = CDBMake::in_memory()?;
+    /// let count = by_tag.add_inverted_by(&posts, |tags| {
+    ///     Ok(tags.split(|&b| b == b',').map(<[u8]>::to_vec).collect())
+    /// })?;
+    /// assert_eq!(count, 3);
+    /// let by_tag = CDB::from_bytes(by_tag.into_vec()?)?;
+    /// assert_eq!(by_tag.find(b"cdb").count(), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_inverted_by<F2, H2, E>(
+        &mut self,
+        cdb: &GenericCDB<F2, H2>,
+        extract: E,
+    ) -> Result<u64>
+    where
+        F2: Format,
+        H2: CdbHash,
+        E: FnMut(&[u8]) -> Result<Vec<Vec<u8>>>,
+    {
+        inverted(cdb, extract, |field, key| self.add(field, key))
+    }
+}
+
+impl<F: Format, H: CdbHash> GenericCDBWriter<F, H> {
+    /// Add a record for each record of `cdb`, keyed by its value and
+    /// holding its key. See [`GenericCDBMake::add_inverted`].
+    pub fn add_inverted<F2: Format, H2: CdbHash>(
+        &mut self,
+        cdb: &GenericCDB<F2, H2>,
+    ) -> Result<u64> {
+        self.add_inverted_by(cdb, |value| Ok(vec![value.to_vec()]))
+    }
+
+    /// Add a record for each field `extract` returns for the values of
+    /// `cdb`. See [`GenericCDBMake::add_inverted_by`].
+    pub fn add_inverted_by<F2, H2, E>(
+        &mut self,
+        cdb: &GenericCDB<F2, H2>,
+        extract: E,
+    ) -> Result<u64>
+    where
+        F2: Format,
+        H2: CdbHash,
+        E: FnMut(&[u8]) -> Result<Vec<Vec<u8>>>,
+    {
+        inverted(cdb, extract, |field, key| self.add(field, key))
+    }
+}
@@ -0,0 +1,167 @@
+//! Import and export of JSON Lines files.
+//!
+//! Each line holds one record as an object with `key` and `value`
+//! members, such as `{"key": "one", "value": "Hello"}`.
+//! [`GenericCDBMake::add_from_jsonl`] adds the records of such a file,
+//! and [`GenericCDB::dump_jsonl`] writes a database out in the same form.
+//!
+//! JSON strings hold text, so binary keys and values are best hex or
+//! base64 encoded, as set by [`JsonlOptions::key_encoding`] and
+//! [`JsonlOptions::value_encoding`]. With the default
+//! [`Encoding::Text`], strings are stored as UTF-8, other JSON values are
+//! stored as their compact JSON text, and exporting fails on keys or
+//! values which are not valid UTF-8.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> std::io::Result<()> {
+//! use cdb32::jsonl::JsonlOptions;
+//! use cdb32::{CDBMake, Encoding, CDB};
+//!
+//! let input = br#"{"key": "one", "value": "Hello"}
+//! {"key": "two", "value": {"n": 2}}
+//! "#;
+//! let mut cdb = CDBMake::in_memory()?;
+//! assert_eq!(cdb.add_from_jsonl(&input[..], &JsonlOptions::new())?, 2);
+//! let cdb = CDB::from_bytes(cdb.into_vec()?)?;
+//! assert_eq!(cdb.get(b"two").unwrap()?, br#"{"n":2}"#);
+//!
+//! let mut out = Vec::new();
+//! cdb.dump_jsonl(&mut out, JsonlOptions::new().value_encoding(Encoding::Base64))?;
+//! assert!(out.starts_with(br#"{"key":"one","value":"SGVsbG8="}"#));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::{self, BufRead, Seek, Write};
+
+use serde_json::Value;
+
+use crate::encoding::Encoding;
+use crate::format::Format;
+use crate::hash::CdbHash;
+use crate::reader::{GenericCDB, Result};
+use crate::writer::{GenericCDBMake, GenericCDBWriter};
+
+/// Options for reading and writing JSON Lines files.
+///
+/// By default keys and values are stored as they are, with
+/// [`Encoding::Text`].
+#[derive(Clone, Debug, Default)]
+pub struct JsonlOptions {
+    key_encoding: Encoding,
+    value_encoding: Encoding,
+}
+
+impl JsonlOptions {
+    /// Create a new set of options with the defaults.
+    pub fn new() -> JsonlOptions {
+        JsonlOptions::default()
+    }
+
+    /// Choose how keys are encoded in the file.
+    pub fn key_encoding(&mut self, encoding: Encoding) -> &mut JsonlOptions {
+        self.key_encoding = encoding;
+        self
+    }
+
+    /// Choose how values are encoded in the file.
+    pub fn value_encoding(&mut self, encoding: Encoding) -> &mut JsonlOptions {
+        self.value_encoding = encoding;
+        self
+    }
+}
+
+fn err_line<T>(line: u64, msg: &str) -> Result<T> {
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Malformed JSON Lines input in line {}: {}", line, msg),
+    ))
+}
+
+/// Decode the `name` member of a record object.
+fn decode_member(record: &Value, name: &str, encoding: Encoding, line: u64) -> Result<Vec<u8>> {
+    match (record.get(name), encoding) {
+        (Some(Value::String(text)), _) => encoding.decode(text.as_bytes()),
+        (Some(value), Encoding::Text) => Ok(value.to_string().into_bytes()),
+        (Some(_), _) => err_line(line, &format!("{} is not a string", name)),
+        (None, _) => err_line(line, &format!("missing {}", name)),
+    }
+}
+
+/// Encode `data` as a JSON string.
+fn encode_member(data: &[u8], encoding: Encoding) -> Result<String> {
+    match String::from_utf8(encoding.encode(data)) {
+        Ok(text) => Ok(text),
+        Err(_) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Data is not valid UTF-8; use a hex or base64 encoding",
+        )),
+    }
+}
+
+impl<F: Format, H: CdbHash> GenericCDB<F, H> {
+    /// Write every record in the database to `writer` as JSON Lines,
+    /// returning how many records were written. See the
+    /// [`jsonl`](crate::jsonl) module.
+    pub fn dump_jsonl<W: Write>(&self, mut writer: W, options: &JsonlOptions) -> Result<u64> {
+        let mut count = 0;
+        for record in self.iter() {
+            let (key, value) = record?;
+            let record = serde_json::json!({
+                "key": encode_member(&key, options.key_encoding)?,
+                "value": encode_member(&value, options.value_encoding)?,
+            });
+            serde_json::to_writer(&mut writer, &record)?;
+            writer.write_all(b"\n")?;
+            count += 1;
+        }
+        writer.flush()?;
+        Ok(count)
+    }
+}
+
+/// Call `add` with the key and value of every line of a JSON Lines file.
+fn read_jsonl<R: BufRead>(
+    reader: R,
+    options: &JsonlOptions,
+    mut add: impl FnMut(Vec<u8>, Vec<u8>) -> Result<()>,
+) -> Result<u64> {
+    let mut count = 0;
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let lineno = i as u64 + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: Value = match serde_json::from_str(&line) {
+            Ok(record @ Value::Object(_)) => record,
+            Ok(_) => return err_line(lineno, "not an object"),
+            Err(err) => return err_line(lineno, &err.to_string()),
+        };
+        let key = decode_member(&record, "key", options.key_encoding, lineno)?;
+        let value = decode_member(&record, "value", options.value_encoding, lineno)?;
+        add(key, value)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+impl<F: Format, H: CdbHash, W: Write + Seek> GenericCDBMake<F, H, W> {
+    /// Add the records of a JSON Lines file read from `reader`, returning
+    /// how many were added. See the [`jsonl`](crate::jsonl) module.
+    ///
+    /// Records before any malformed line are still added.
+    pub fn add_from_jsonl<R: BufRead>(&mut self, reader: R, options: &JsonlOptions) -> Result<u64> {
+        read_jsonl(reader, options, |key, value| self.add(key, value))
+    }
+}
+
+impl<F: Format, H: CdbHash> GenericCDBWriter<F, H> {
+    /// Add the records of a JSON Lines file read from `reader`, returning
+    /// how many were added. See [`GenericCDBMake::add_from_jsonl`].
+    pub fn add_from_jsonl<R: BufRead>(&mut self, reader: R, options: &JsonlOptions) -> Result<u64> {
+        read_jsonl(reader, options, |key, value| self.add(key, value))
+    }
+}
@@ -39,16 +39,189 @@
 //! # }
 //! ```
 //!
+//! # Errors
+//!
+//! Functions return [`std::io::Result`], and failures found by the crate
+//! itself, such as a corrupt file, are carried inside the
+//! [`std::io::Error`] as an [`Error`] which [`Error::from`] recovers.
+//!
+//! # 64-bit databases
+//!
+//! Classic CDB files are limited to 4 GiB. The [`CDB64`], [`CDB64Make`]
+//! and [`CDB64Writer`] types read and write the common 64-bit variant of
+//! the format, which widens every stored integer to 64 bits and is
+//! otherwise identical.
+//!
+//! # `no_std` support
+//!
+//! The default `std` feature provides the file-backed reader and the
+//! writers. Without it the crate is `no_std` and needs only `alloc`,
+//! leaving [`CDBImage`] and [`CDB64Image`] to look up records in a
+//! database image held in memory, such as one embedded in firmware.
+//!
+//! # Typed values
+//!
+//! With the `serde` feature, [`GenericCDB::get_de`] and
+//! [`GenericCDBMake::add_ser`] store values of any type implementing
+//! `serde`'s traits, encoded with a pluggable [`Codec`].
+//! [`GenericCDB::get_typed`] and [`GenericCDBMake::add_typed`] encode
+//! the keys too. A whole [`GenericCDB`] can also be serialized, as a map
+//! from each key to the list of its values.
+//!
 //! # References
 //!
 //!  * [D. J. Bernstein's original software](https://cr.yp.to/cdb.html)
 //!  * [Constant Database (cdb) Internals](https://www.unixuser.org/~euske/doc/cdbinternals/index.html)
 //!  * [Wikipedia](https://en.wikipedia.org/wiki/Cdb_(software))
 
-mod hash;
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "tokio")]
+mod async_reader;
+#[cfg(feature = "tokio")]
+mod async_writer;
+#[cfg(feature = "std")]
+mod bloom;
+#[cfg(feature = "std")]
+mod cache;
+#[cfg(feature = "std")]
+mod checksum;
+#[cfg(feature = "serde")]
+mod codec;
+#[cfg(feature = "std")]
+pub mod compare;
+#[cfg(feature = "compat")]
+pub mod compat;
+#[cfg(feature = "lz4")]
+mod compress;
+#[cfg(feature = "csv")]
+pub mod csv;
+#[cfg(feature = "std")]
+pub mod diff;
+#[cfg(feature = "signing")]
+mod ed25519;
+#[cfg(any(feature = "csv", feature = "jsonl"))]
+mod encoding;
+#[cfg(feature = "encryption")]
+mod encrypt;
+#[cfg(feature = "std")]
+mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod format;
+#[cfg(feature = "std")]
+mod generation;
+pub mod hash;
+mod image;
+#[cfg(feature = "std")]
+pub mod invert;
+#[cfg(feature = "jsonl")]
+pub mod jsonl;
+#[cfg(feature = "std")]
+mod metadata;
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "std")]
+pub mod qmail;
+#[cfg(feature = "std")]
 mod reader;
+#[cfg(feature = "std")]
+mod reload;
+#[cfg(feature = "std")]
+mod salvage;
+#[cfg(feature = "std")]
+mod set;
+#[cfg(feature = "std")]
+mod sharded;
+#[cfg(feature = "bytes")]
+mod shared;
+#[cfg(feature = "signing")]
+mod signature;
+#[cfg(feature = "std")]
+mod sorted;
+#[cfg(feature = "std")]
+mod spill;
+#[cfg(feature = "std")]
+mod stats;
+#[cfg(feature = "std")]
+pub mod text;
+#[cfg(feature = "std")]
+pub mod tinydns;
+#[cfg(feature = "std")]
+mod trailer;
 mod uint32;
+mod uint64;
+#[cfg(feature = "std")]
+mod updater;
+#[cfg(feature = "std")]
+mod verify;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "std")]
 mod writer;
 
-pub use crate::reader::{CDBIter, CDBKeyValueIter, CDBValueIter, Result, CDB};
-pub use crate::writer::{CDBMake, CDBWriter};
+#[cfg(feature = "tokio")]
+pub use crate::async_reader::{AsyncCDB, AsyncCDB64, AsyncCDBValueStream, GenericAsyncCDB};
+#[cfg(feature = "tokio")]
+pub use crate::async_writer::{AsyncCDB64Writer, AsyncCDBWriter, GenericAsyncCDBWriter};
+#[cfg(feature = "std")]
+pub use crate::cache::{CacheOptions, CacheStats, CachedCDB, CachedCDB64, GenericCachedCDB};
+#[cfg(feature = "serde")]
+pub use crate::codec::{Codec, Postcard};
+#[cfg(feature = "lz4")]
+pub use crate::compress::Compression;
+#[cfg(feature = "signing")]
+pub use crate::ed25519::{SigningKey, VerifyingKey};
+#[cfg(any(feature = "csv", feature = "jsonl"))]
+pub use crate::encoding::Encoding;
+#[cfg(feature = "encryption")]
+pub use crate::encrypt::EncryptionKey;
+#[cfg(feature = "std")]
+pub use crate::error::Error;
+pub use crate::format::{Format, Format32, Format64};
+#[cfg(feature = "std")]
+pub use crate::generation::{
+    GenerationStore, GenerationStore64, GenerationWriter, GenerationWriter64,
+    GenericGenerationStore, GenericGenerationWriter,
+};
+pub use crate::hash::{CDBKey, CdbHash, DjbHash};
+pub use crate::image::{
+    CDB64Image, CDBImage, CDBImageIter, CDBImageValueIter, GenericCDBImage, InvalidFormat,
+};
+#[cfg(feature = "std")]
+pub use crate::metadata::Metadata;
+#[cfg(feature = "std")]
+pub use crate::reader::{
+    AccessPattern, CDBCursor, CDBDataReader, CDBIter, CDBKeyIter, CDBKeyValueIter,
+    CDBKeyValueRefIter, CDBOwnedKeyValueIter, CDBOwnedValueIter, CDBUniqueKeyIter, CDBValueIter,
+    CDBValueReader, CDBValueRefIter, GenericCDB, OpenOptions, Result, CDB, CDB64,
+};
+#[cfg(feature = "std")]
+pub use crate::reload::{GenericReloadableCDB, ReloadableCDB, ReloadableCDB64};
+#[cfg(feature = "std")]
+pub use crate::salvage::CDBSalvageIter;
+#[cfg(feature = "std")]
+pub use crate::set::{CDB64Set, CDBSet, CDBSetManifest, CDBSetValueIter, GenericCDBSet, SetLayout};
+#[cfg(feature = "std")]
+pub use crate::sharded::{
+    GenericShardedCDB, GenericShardedCDBWriter, ShardedCDB, ShardedCDB64, ShardedCDB64Writer,
+    ShardedCDBWriter,
+};
+#[cfg(feature = "bytes")]
+pub use crate::shared::CDBBytesIter;
+#[cfg(feature = "std")]
+pub use crate::sorted::{sorted_index_path, CDBPrefixIter, CDBSortedIter, SortedIndex};
+#[cfg(feature = "std")]
+pub use crate::stats::CDBStats;
+#[cfg(feature = "std")]
+pub use crate::updater::{CDB64Updater, CDBUpdater, GenericCDBUpdater};
+#[cfg(feature = "std")]
+pub use crate::verify::{VerifyProblem, VerifyReport};
+#[cfg(feature = "std")]
+pub use crate::writer::{
+    BuildProgress, BuildStats, CDB64Make, CDB64Writer, CDB64WriterBuilder, CDBMake, CDBWriter,
+    CDBWriterBuilder, DuplicatePolicy, Durability, GenericCDBMake, GenericCDBWriter,
+    GenericCDBWriterBuilder, REPRODUCIBLE_FORMAT_VERSION,
+};
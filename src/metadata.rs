@@ -0,0 +1,195 @@
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+
+use crate::error::err_corrupt;
+use crate::format::Format;
+use crate::hash::CdbHash;
+use crate::reader::{GenericCDB, Result};
+use crate::trailer::{self, METADATA_MAGIC};
+
+// The metadata section body is a version byte, the record count and the
+// build time in seconds since the Unix epoch (both 64-bit little
+// endian), the content digest, and then the application version.
+const METADATA_VERSION: u8 = 1;
+const FIXED_SIZE: usize = 1 + 8 + 8 + 32;
+
+/// Information about how a database was built, written when asked with
+/// [`GenericCDBMake::set_metadata`](crate::GenericCDBMake::set_metadata)
+/// and read with [`GenericCDB::metadata`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Metadata {
+    /// The number of records.
+    pub records: u64,
+    /// When the database was built, to the second.
+    pub created: SystemTime,
+    /// The version of the application which built the database.
+    pub version: String,
+    /// A SHA-256 digest of the records, in the order they were added.
+    ///
+    /// Each record contributes the length of its key (64-bit little
+    /// endian), the key, and the SHA-256 digest of its value, so the
+    /// digest depends only on the records and not on how they are
+    /// stored. [`GenericCDB::digest`] computes the same digest from the
+    /// records themselves.
+    pub digest: [u8; 32],
+}
+
+/// A running digest of the records of a database, as described for
+/// [`Metadata::digest`].
+#[derive(Clone, Debug)]
+pub(crate) struct ContentDigest(Sha256);
+
+impl ContentDigest {
+    pub(crate) fn new() -> Self {
+        ContentDigest(Sha256::new())
+    }
+
+    /// Add a record to the digest, given the digest of its value.
+    pub(crate) fn add(&mut self, key: &[u8], value_digest: [u8; 32]) {
+        self.0.update((key.len() as u64).to_le_bytes());
+        self.0.update(key);
+        self.0.update(value_digest);
+    }
+
+    pub(crate) fn finish(self) -> [u8; 32] {
+        self.0.finalize().into()
+    }
+}
+
+/// The metadata of a database being built.
+#[derive(Clone, Debug)]
+pub(crate) struct MetadataBuilder {
+    version: String,
+    created: Option<SystemTime>,
+}
+
+/// Return the time set by the `SOURCE_DATE_EPOCH` environment variable,
+/// used by reproducible builds.
+fn source_date_epoch() -> Option<SystemTime> {
+    let secs = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+impl MetadataBuilder {
+    pub(crate) fn new(version: &str, created: Option<SystemTime>) -> Self {
+        MetadataBuilder {
+            version: version.to_owned(),
+            created,
+        }
+    }
+
+    /// Return the body of the metadata section, given the digest of the
+    /// records.
+    pub(crate) fn finish(self, records: u64, digest: [u8; 32]) -> Vec<u8> {
+        let created = self
+            .created
+            .or_else(source_date_epoch)
+            .unwrap_or_else(SystemTime::now);
+        let secs = created
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let mut body = Vec::with_capacity(FIXED_SIZE + self.version.len());
+        body.push(METADATA_VERSION);
+        body.extend_from_slice(&records.to_le_bytes());
+        body.extend_from_slice(&secs.to_le_bytes());
+        body.extend_from_slice(&digest);
+        body.extend_from_slice(self.version.as_bytes());
+        body
+    }
+}
+
+impl<F: Format, H: CdbHash> GenericCDB<F, H> {
+    /// Return the metadata recorded by the writer, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut cdb = cdb32::CDBMake::in_memory()?;
+    /// cdb.set_metadata("myapp 1.2.0", None);
+    /// cdb.add(b"one", b"Hello")?;
+    /// let cdb = cdb32::CDB::from_bytes(cdb.into_vec()?)?;
+    ///
+    /// let metadata = cdb.metadata()?.unwrap();
+    /// assert_eq!(metadata.records, 1);
+    /// assert_eq!(metadata.version, "myapp 1.2.0");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn metadata(&self) -> Result<Option<Metadata>> {
+        let sections = trailer::sections(self)?;
+        let section = match sections.iter().find(|s| s.magic == METADATA_MAGIC) {
+            Some(section) => section,
+            None => return Ok(None),
+        };
+        if section.len < FIXED_SIZE as u64 {
+            return err_corrupt(section.pos, "metadata section is too short");
+        }
+        let body = self.read_vec(section.pos, section.len)?;
+        if body[0] != METADATA_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("Unsupported metadata version {}", body[0]),
+            ));
+        }
+        let version = match String::from_utf8(body[FIXED_SIZE..].to_vec()) {
+            Ok(version) => version,
+            Err(_) => return err_corrupt(section.pos, "metadata version is not UTF-8"),
+        };
+        let secs = u64::from_le_bytes(body[9..17].try_into().unwrap());
+        Ok(Some(Metadata {
+            records: u64::from_le_bytes(body[1..9].try_into().unwrap()),
+            created: UNIX_EPOCH + Duration::from_secs(secs),
+            version,
+            digest: body[17..FIXED_SIZE].try_into().unwrap(),
+        }))
+    }
+
+    /// Compute a SHA-256 digest of the records, in the order they are
+    /// stored, as described for [`Metadata::digest`].
+    ///
+    /// The digest depends only on the keys and values, so two databases
+    /// holding the same records in the same order have the same digest
+    /// whatever their format, hash function or options. It equals the
+    /// digest returned in
+    /// [`BuildStats::digest`](crate::BuildStats::digest) by the writer
+    /// which built the database, unless records were dropped as
+    /// duplicates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// # let tmp_dir = tempfile::tempdir()?;
+    /// # std::env::set_current_dir(tmp_dir.path())?;
+    /// let mut cdb = cdb32::CDBWriter::create("one.cdb")?;
+    /// cdb.set_digest(true);
+    /// cdb.add(b"one", b"Hello")?;
+    /// let stats = cdb.finish()?;
+    ///
+    /// let mut other = cdb32::CDB64Make::in_memory()?;
+    /// other.set_checksums(true);
+    /// other.add(b"one", b"Hello")?;
+    /// let other = cdb32::CDB64::from_bytes(other.into_vec()?)?;
+    ///
+    /// assert_eq!(cdb32::CDB::open("one.cdb")?.digest()?, other.digest()?);
+    /// assert_eq!(stats.digest, Some(other.digest()?));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn digest(&self) -> Result<[u8; 32]> {
+        let mut digest = ContentDigest::new();
+        for record in self.iter() {
+            let (key, value) = record?;
+            digest.add(&key, Sha256::digest(&value).into());
+        }
+        Ok(digest.finish())
+    }
+}
@@ -0,0 +1,74 @@
+use rayon::prelude::*;
+
+use crate::format::Format;
+use crate::hash::CdbHash;
+use crate::image::{Scan, Storage};
+use crate::reader::{GenericCDB, Result};
+
+/// Smallest amount of data handed to one parallel task.
+const MIN_CHUNK_SIZE: u64 = 64 * 1024;
+
+impl<F: Format, H: CdbHash> GenericCDB<F, H> {
+    /// Iterate over all the `(key, value)` pairs in the database in
+    /// parallel, using rayon's thread pool.
+    ///
+    /// The data section is split into chunks at record boundaries, found
+    /// by walking the record headers up front, and each chunk is read by
+    /// one task. Records come out in no particular order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use rayon::prelude::*;
+    /// use cdb32::CDB;
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// let total = cdb
+    ///     .par_iter()?
+    ///     .map(|result| result.map(|(_, value)| value.len()))
+    ///     .sum::<std::io::Result<usize>>()?;
+    /// println!("{} bytes of values", total);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn par_iter(
+        &self,
+    ) -> Result<impl ParallelIterator<Item = Result<(Vec<u8>, Vec<u8>)>> + '_> {
+        let chunks = self.chunks()?;
+        Ok(chunks.into_par_iter().flat_map_iter(move |(pos, end)| {
+            let mut scan = Scan::range(pos, end);
+            std::iter::from_fn(move || {
+                Some(scan.next(self)?.and_then(|(kpos, klen, dpos, dlen)| {
+                    let key = self.read_vec(kpos, klen)?;
+                    let value = self.read_value(&key, dpos, dlen)?;
+                    Ok((key, value))
+                }))
+            })
+        }))
+    }
+
+    /// Split the data section into `(start, end)` ranges of whole
+    /// records.
+    fn chunks(&self) -> Result<Vec<(u64, u64)>> {
+        let data_end = self.data_end();
+        let data_size = data_end.saturating_sub(F::HEADER_SIZE as u64);
+        let tasks = rayon::current_num_threads() as u64 * 8;
+        let chunk_size = (data_size / tasks).max(MIN_CHUNK_SIZE);
+
+        let mut chunks = Vec::new();
+        let mut scan = Scan::start(self);
+        let mut start = scan.pos();
+        while let Some(record) = scan.next(self) {
+            record?;
+            if scan.pos() - start >= chunk_size {
+                chunks.push((start, scan.pos()));
+                start = scan.pos();
+            }
+        }
+        if scan.pos() > start {
+            chunks.push((start, scan.pos()));
+        }
+        Ok(chunks)
+    }
+}
@@ -0,0 +1,391 @@
+//! Databases of the qmail mail server and its netqmail successor.
+//!
+//! Two of qmail's files are CDBs with their own key conventions:
+//!
+//! * `users/cdb`, built by `qmail-newu` from `users/assign`, maps local
+//!   parts of addresses to the user who takes their mail. Exact entries
+//!   are stored under `!`, the lower-cased local part and a NUL byte, and
+//!   wildcard entries under `!` and the lower-cased prefix with no NUL. A
+//!   record with an empty key lists the last characters of the wildcard
+//!   prefixes. [`UsersWriter`] builds the file and [`Users`] looks up
+//!   local parts the way `qmail-lspawn` does.
+//! * `control/morercpthosts.cdb`, built by `qmail-newmrh` from
+//!   `control/morercpthosts`, lists the lower-cased hosts `qmail-smtpd`
+//!   accepts mail for, each with an empty value. [`RcptHostsWriter`]
+//!   builds the file and [`RcptHosts`] matches hosts the way
+//!   `qmail-smtpd` does.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> std::io::Result<()> {
+//! # let tmp_dir = tempfile::tempdir()?;
+//! # let path = tmp_dir.path().join("cdb");
+//! use cdb32::qmail::{User, Users, UsersWriter};
+//!
+//! let alias = User::new(b"alias", 7790, 2108, b"/var/qmail/alias");
+//! let mut users = UsersWriter::create(&path)?;
+//! users.add_wildcard(b"list-", &alias)?;
+//! users.finish()?;
+//!
+//! let users = Users::open(&path)?;
+//! let user = users.get(b"List-Announce")?.unwrap();
+//! assert_eq!(user.home, b"/var/qmail/alias");
+//! assert_eq!(user.ext, b"Announce");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+
+use crate::reader::{Result, CDB};
+use crate::writer::{BuildStats, CDBWriter};
+
+fn err_data<T>(msg: &str) -> Result<T> {
+    Err(io::Error::new(io::ErrorKind::InvalidData, msg))
+}
+
+/// Parse a decimal number, returning `None` if `text` is not one.
+fn parse_u32(text: &[u8]) -> Option<u32> {
+    std::str::from_utf8(text).ok()?.parse().ok()
+}
+
+/// The key of the exact `users/cdb` entry for `local`.
+fn exact_key(local: &[u8]) -> Vec<u8> {
+    let mut key = Vec::with_capacity(local.len() + 2);
+    key.push(b'!');
+    key.extend(local.iter().map(u8::to_ascii_lowercase));
+    key.push(0);
+    key
+}
+
+/// The key of the wildcard `users/cdb` entry for `prefix`.
+fn wildcard_key(prefix: &[u8]) -> Vec<u8> {
+    let mut key = Vec::with_capacity(prefix.len() + 1);
+    key.push(b'!');
+    key.extend(prefix.iter().map(u8::to_ascii_lowercase));
+    key
+}
+
+/// The user who takes the mail for a local part, as listed in
+/// `users/assign` and stored in `users/cdb`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct User {
+    /// The login name of the user.
+    pub name: Vec<u8>,
+    /// The user ID the delivery runs as.
+    pub uid: u32,
+    /// The group ID the delivery runs as.
+    pub gid: u32,
+    /// The home directory holding the `.qmail` files.
+    pub home: Vec<u8>,
+    /// The separator between `.qmail` and the extension, `-` or empty.
+    pub dash: Vec<u8>,
+    /// The extension selecting the `.qmail` file. For a wildcard entry,
+    /// a lookup appends the rest of the local part.
+    pub ext: Vec<u8>,
+}
+
+impl User {
+    /// Create an entry for the user `name` with the given IDs and home
+    /// directory, with no extension.
+    pub fn new(name: &[u8], uid: u32, gid: u32, home: &[u8]) -> Self {
+        User {
+            name: name.to_vec(),
+            uid,
+            gid,
+            home: home.to_vec(),
+            dash: Vec::new(),
+            ext: Vec::new(),
+        }
+    }
+
+    /// Set the `.qmail` extension, with `-` as the separator.
+    pub fn with_ext(mut self, ext: &[u8]) -> Self {
+        self.dash = b"-".to_vec();
+        self.ext = ext.to_vec();
+        self
+    }
+
+    /// Encode the entry as a `users/cdb` value: its six fields separated
+    /// by NUL bytes.
+    pub fn to_value(&self) -> Vec<u8> {
+        [
+            &self.name[..],
+            self.uid.to_string().as_bytes(),
+            self.gid.to_string().as_bytes(),
+            &self.home,
+            &self.dash,
+            &self.ext,
+        ]
+        .join(&0)
+    }
+
+    /// Decode a `users/cdb` value, returning an error of kind
+    /// [`io::ErrorKind::InvalidData`] if it is malformed.
+    pub fn from_value(value: &[u8]) -> Result<Self> {
+        let fields: Vec<&[u8]> = value.splitn(6, |&b| b == 0).collect();
+        if fields.len() != 6 {
+            return err_data("Malformed qmail user entry: too few fields");
+        }
+        let (uid, gid) = match (parse_u32(fields[1]), parse_u32(fields[2])) {
+            (Some(uid), Some(gid)) => (uid, gid),
+            _ => return err_data("Malformed qmail user entry: bad uid or gid"),
+        };
+        Ok(User {
+            name: fields[0].to_vec(),
+            uid,
+            gid,
+            home: fields[3].to_vec(),
+            dash: fields[4].to_vec(),
+            ext: fields[5].to_vec(),
+        })
+    }
+}
+
+/// A writer for qmail's `users/cdb`.
+///
+/// Finishing the writer adds the record of wildcard characters, so the
+/// file is complete only after [`finish`](Self::finish).
+#[derive(Debug)]
+pub struct UsersWriter {
+    writer: CDBWriter,
+    wildchars: Vec<u8>,
+}
+
+impl UsersWriter {
+    /// Create a new `users/cdb` at `path`, written to a temporary file and
+    /// renamed into place by [`finish`](Self::finish).
+    pub fn create<P: Into<PathBuf>>(path: P) -> Result<Self> {
+        Ok(UsersWriter {
+            writer: CDBWriter::create(path)?,
+            wildchars: Vec::new(),
+        })
+    }
+
+    /// Deliver mail for the local part `local`, in any case, to `user`,
+    /// as an `=` line of `users/assign` does.
+    pub fn add(&mut self, local: &[u8], user: &User) -> Result<()> {
+        self.writer.add(exact_key(local), user.to_value())
+    }
+
+    /// Deliver mail for every local part starting with `prefix`, in any
+    /// case, to `user`, as a `+` line of `users/assign` does. The rest of
+    /// the local part is appended to the user's extension.
+    ///
+    /// An exact entry takes priority, then the longest matching prefix.
+    pub fn add_wildcard(&mut self, prefix: &[u8], user: &User) -> Result<()> {
+        self.add_wildcard_value(prefix, &user.to_value())
+    }
+
+    fn add_wildcard_value(&mut self, prefix: &[u8], value: &[u8]) -> Result<()> {
+        if let Some(&last) = prefix.last() {
+            if !self.wildchars.contains(&last) {
+                self.wildchars.push(last);
+            }
+        }
+        self.writer.add(wildcard_key(prefix), value)
+    }
+
+    /// Add the entries of a `users/assign` file read from `reader`, as
+    /// `qmail-newu` does, returning how many were added.
+    ///
+    /// The file ends at a line starting with `.`, which it must have. The
+    /// fields after the local part are stored as they are written, so the
+    /// database is identical to the one `qmail-newu` would write.
+    pub fn add_from_assign<R: BufRead>(&mut self, mut reader: R) -> Result<u64> {
+        let mut count = 0;
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            reader.read_until(b'\n', &mut line)?;
+            if line.first() == Some(&b'.') {
+                return Ok(count);
+            }
+            if line.pop() != Some(b'\n') || line.contains(&0) {
+                return err_data("Malformed users/assign: unable to parse");
+            }
+            let colon = match line.iter().position(|&b| b == b':') {
+                Some(0) | None => return err_data("Malformed users/assign: unable to parse"),
+                Some(colon) => colon,
+            };
+            // The six fields after the local part, with their separators
+            // turned into NUL bytes, up to the sixth.
+            let mut value = line[colon + 1..].to_vec();
+            let mut colons = 0;
+            let end = value.iter_mut().position(|b| {
+                if *b != b':' {
+                    return false;
+                }
+                *b = 0;
+                colons += 1;
+                colons == 6
+            });
+            match end {
+                Some(end) => value.truncate(end),
+                None => return err_data("Malformed users/assign: unable to parse"),
+            }
+            let local = &line[1..colon];
+            if line[0] == b'+' {
+                self.add_wildcard_value(local, &value)?;
+            } else {
+                self.writer.add(exact_key(local), &value)?;
+            }
+            count += 1;
+        }
+    }
+
+    /// Add the record of wildcard characters and finish the database.
+    pub fn finish(mut self) -> Result<BuildStats> {
+        self.writer.add(b"", &self.wildchars)?;
+        self.writer.finish()
+    }
+}
+
+/// A `users/cdb` file opened for lookups.
+#[derive(Debug)]
+pub struct Users {
+    cdb: CDB,
+    wildchars: Vec<u8>,
+}
+
+impl Users {
+    /// Open the `users/cdb` at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Users::from_cdb(CDB::open(path)?)
+    }
+
+    /// Use an open database as a `users/cdb`, returning an error of kind
+    /// [`io::ErrorKind::InvalidData`] if it has no record of wildcard
+    /// characters.
+    pub fn from_cdb(cdb: CDB) -> Result<Self> {
+        let wildchars = match cdb.get(b"") {
+            Some(wildchars) => wildchars?,
+            None => return err_data("Malformed users/cdb: no wildcard characters"),
+        };
+        Ok(Users { cdb, wildchars })
+    }
+
+    /// The underlying database.
+    pub fn cdb(&self) -> &CDB {
+        &self.cdb
+    }
+
+    /// Find the user who takes the mail for the local part `local`, as
+    /// `qmail-lspawn` does.
+    ///
+    /// The exact entry is used if there is one. Otherwise the longest
+    /// wildcard prefix of `local` is, and the rest of `local` is appended
+    /// to the user's extension.
+    pub fn get(&self, local: &[u8]) -> Result<Option<User>> {
+        let key = exact_key(local);
+        if let Some(value) = self.cdb.get(&key) {
+            return User::from_value(&value?).map(Some);
+        }
+        // Try each prefix `!` + local[..i - 1] from the longest, skipping
+        // those which do not end with a wildcard character.
+        for i in (1..key.len()).rev() {
+            if i > 1 && !self.wildchars.contains(&key[i - 1]) {
+                continue;
+            }
+            if let Some(value) = self.cdb.get(&key[..i]) {
+                let mut user = User::from_value(&value?)?;
+                user.ext.extend_from_slice(&local[i - 1..]);
+                return Ok(Some(user));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A writer for qmail's `control/morercpthosts.cdb`.
+#[derive(Debug)]
+pub struct RcptHostsWriter {
+    writer: CDBWriter,
+}
+
+impl RcptHostsWriter {
+    /// Create a new `morercpthosts.cdb` at `path`, written to a temporary
+    /// file and renamed into place by [`finish`](Self::finish).
+    pub fn create<P: Into<PathBuf>>(path: P) -> Result<Self> {
+        Ok(RcptHostsWriter {
+            writer: CDBWriter::create(path)?,
+        })
+    }
+
+    /// Accept mail for `host`, in any case. A host starting with `.`
+    /// matches all of its subdomains.
+    pub fn add(&mut self, host: &[u8]) -> Result<()> {
+        self.writer.add(host.to_ascii_lowercase(), b"")
+    }
+
+    /// Add the hosts of a `control/morercpthosts` file read from `reader`,
+    /// as `qmail-newmrh` does, returning how many were added.
+    ///
+    /// Trailing spaces and tabs are removed, and empty lines and lines
+    /// starting with `#` are skipped.
+    pub fn add_from_control<R: BufRead>(&mut self, mut reader: R) -> Result<u64> {
+        let mut count = 0;
+        let mut line = Vec::new();
+        while reader.read_until(b'\n', &mut line)? > 0 {
+            while let Some(b' ' | b'\t' | b'\n') = line.last() {
+                line.pop();
+            }
+            if !line.is_empty() && line[0] != b'#' {
+                self.add(&line)?;
+                count += 1;
+            }
+            line.clear();
+        }
+        Ok(count)
+    }
+
+    /// Finish the database.
+    pub fn finish(self) -> Result<BuildStats> {
+        self.writer.finish()
+    }
+}
+
+/// A `control/morercpthosts.cdb` file opened for lookups.
+#[derive(Debug)]
+pub struct RcptHosts {
+    cdb: CDB,
+}
+
+impl RcptHosts {
+    /// Open the `morercpthosts.cdb` at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(RcptHosts::from_cdb(CDB::open(path)?))
+    }
+
+    /// Use an open database as a `morercpthosts.cdb`.
+    pub fn from_cdb(cdb: CDB) -> Self {
+        RcptHosts { cdb }
+    }
+
+    /// The underlying database.
+    pub fn cdb(&self) -> &CDB {
+        &self.cdb
+    }
+
+    /// Return whether mail for `address` is accepted, as `qmail-smtpd`
+    /// decides. `address` is a host name, or an address whose host part
+    /// follows the last `@`.
+    ///
+    /// The host matches if it is listed, or if one of its suffixes
+    /// starting with `.` is.
+    pub fn contains(&self, address: &[u8]) -> Result<bool> {
+        let host = match address.iter().rposition(|&b| b == b'@') {
+            Some(at) => &address[at + 1..],
+            None => address,
+        };
+        let host = host.to_ascii_lowercase();
+        for (i, &b) in host.iter().enumerate() {
+            if (i == 0 || b == b'.') && self.cdb.exists(&host[i..])? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
@@ -1,18 +1,397 @@
 use std::cmp::min;
 use std::fs::File;
 use std::io;
+use std::marker::PhantomData;
 use std::path;
+use std::sync::Arc;
 
-use memmap2::Mmap;
+#[cfg(not(target_arch = "wasm32"))]
+use memmap2::{Mmap, MmapOptions};
 
-use crate::hash::hash;
-use crate::uint32;
+use crate::bloom::Bloom;
+use crate::checksum::{self, CHECKSUM_SIZE};
+#[cfg(feature = "signing")]
+use crate::ed25519::VerifyingKey;
+#[cfg(feature = "encryption")]
+use crate::encrypt::{Cipher, EncryptionKey};
+use crate::error::{err_corrupt, Error};
+use crate::format::{Format, Format32, Format64};
+use crate::hash::{CDBKey, CdbHash, DjbHash};
+use crate::image::{Limits, Probe, Scan, Storage};
+use crate::sorted::{sorted_index_path, SortedIndex};
+use crate::trailer;
 
 pub use std::io::Result;
 
-const KEYSIZE: usize = 32;
+/// Options controlling how a CDB file is opened.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use cdb32::{OpenOptions, CDB};
+///
+/// let cdb = CDB::open_with_options("tests/test1.cdb", OpenOptions::new().mmap(false))?;
+/// assert_eq!(cdb.get(b"two").unwrap()?, b"Goodbye");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct OpenOptions {
+    mmap: bool,
+    access: AccessPattern,
+    populate: bool,
+    lock: bool,
+    verify_checksums: bool,
+    strict: bool,
+    untrusted: bool,
+    max_probes: Option<u64>,
+    max_value_len: u64,
+    max_find_records: u64,
+    sorted_index: bool,
+    #[cfg(feature = "encryption")]
+    encryption_key: Option<EncryptionKey>,
+    #[cfg(feature = "signing")]
+    verifying_key: Option<VerifyingKey>,
+}
+
+/// Expected pattern of access to a memory-mapped CDB, passed on to the
+/// kernel with `madvise` so it can tune readahead.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AccessPattern {
+    /// No particular pattern; the kernel default.
+    #[default]
+    Normal,
+    /// Point lookups scattered across the file, as with
+    /// [`find`](GenericCDB::find). Readahead is reduced.
+    Random,
+    /// A scan of the whole file, as with [`iter`](GenericCDB::iter).
+    /// Readahead is increased.
+    Sequential,
+}
 
-/// CDB file reader
+impl OpenOptions {
+    /// Create a new set of options, defaulting to a memory-mapped reader.
+    pub fn new() -> OpenOptions {
+        OpenOptions {
+            mmap: true,
+            access: AccessPattern::Normal,
+            populate: false,
+            lock: false,
+            verify_checksums: true,
+            strict: false,
+            untrusted: false,
+            max_probes: None,
+            max_value_len: u64::MAX,
+            max_find_records: u64::MAX,
+            sorted_index: false,
+            #[cfg(feature = "encryption")]
+            encryption_key: None,
+            #[cfg(feature = "signing")]
+            verifying_key: None,
+        }
+    }
+
+    /// Choose between memory-mapping the file (the default) and reading
+    /// it with positioned reads.
+    ///
+    /// Positioned reads are slower, but a file truncated underneath the
+    /// reader produces an error rather than a `SIGBUS`, which matters on
+    /// network and FUSE filesystems. The zero-copy `*_ref` methods are not
+    /// available without a mapping.
+    ///
+    /// There is no `mmap` on wasm targets, where the whole file is read
+    /// into memory instead.
+    pub fn mmap(&mut self, mmap: bool) -> &mut OpenOptions {
+        self.mmap = mmap;
+        self
+    }
+
+    /// Advise the kernel of the expected access pattern once the file is
+    /// mapped. See [`GenericCDB::advise`].
+    pub fn access_pattern(&mut self, access: AccessPattern) -> &mut OpenOptions {
+        self.access = access;
+        self
+    }
+
+    /// Fault the whole mapping in while opening (`MAP_POPULATE`), so that
+    /// later lookups do not wait on disk reads.
+    ///
+    /// This is only supported on Linux and is ignored elsewhere.
+    pub fn populate(&mut self, populate: bool) -> &mut OpenOptions {
+        self.populate = populate;
+        self
+    }
+
+    /// Lock the mapping into memory with `mlock`, so that its pages are
+    /// never evicted. Opening fails if the pages cannot be locked, for
+    /// instance because of `RLIMIT_MEMLOCK`.
+    ///
+    /// This is only supported on Unix and is ignored elsewhere.
+    pub fn lock(&mut self, lock: bool) -> &mut OpenOptions {
+        self.lock = lock;
+        self
+    }
+
+    /// Choose whether to check the checksum of every value read from a
+    /// database written with
+    /// [`GenericCDBMake::set_checksums`](crate::GenericCDBMake::set_checksums),
+    /// which is the default. A value which fails the check is returned
+    /// as an error of kind [`io::ErrorKind::InvalidData`].
+    ///
+    /// [`GenericCDB::verify`] checks every record regardless.
+    pub fn verify_checksums(&mut self, verify: bool) -> &mut OpenOptions {
+        self.verify_checksums = verify;
+        self
+    }
+
+    /// Check the header while opening, with
+    /// [`GenericCDB::check_header`], so that a malformed file fails to
+    /// open rather than failing in the middle of a lookup. Off by
+    /// default.
+    pub fn strict(&mut self, strict: bool) -> &mut OpenOptions {
+        self.strict = strict;
+        self
+    }
+
+    /// Treat the file as hostile, as for databases uploaded by third
+    /// parties. This implies [`strict`](Self::strict), checks that every
+    /// record a lookup finds lies within the data section, and limits
+    /// each lookup to [`max_probes`](Self::max_probes) hash table slots,
+    /// 4096 unless set otherwise. A file breaking these rules gives an
+    /// [`Error::Corrupt`] instead of a slow or wrong answer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::{OpenOptions, CDB};
+    ///
+    /// let mut options = OpenOptions::new();
+    /// options.untrusted(true).max_value_len(1 << 20);
+    /// let cdb = CDB::open_with_options("tests/test1.cdb", &options)?;
+    /// assert_eq!(cdb.get(b"two").unwrap()?, b"Goodbye");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn untrusted(&mut self, untrusted: bool) -> &mut OpenOptions {
+        self.untrusted = untrusted;
+        self
+    }
+
+    /// Fail a lookup which examines more than `probes` hash table slots
+    /// without finding a match. In a valid database chains are short, so
+    /// a long one means the file was crafted to make lookups slow.
+    pub fn max_probes(&mut self, probes: u64) -> &mut OpenOptions {
+        self.max_probes = Some(probes);
+        self
+    }
+
+    /// Fail on any record whose value is longer than `len` bytes, before
+    /// the value is read, so that a file claiming a huge value cannot
+    /// make a lookup or scan allocate it. The error is an
+    /// [`Error::LimitExceeded`]. In a compressed database the limit also
+    /// applies to the decompressed length, checked before decompressing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::{Error, OpenOptions, CDB};
+    ///
+    /// let cdb = CDB::open_with_options("tests/test1.cdb", OpenOptions::new().max_value_len(5))?;
+    /// assert_eq!(cdb.get(b"one").unwrap()?, b"Hello");
+    /// let err = Error::from(cdb.get(b"two").unwrap().unwrap_err());
+    /// assert!(matches!(err, Error::LimitExceeded { limit: "max_value_len", .. }));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn max_value_len(&mut self, len: u64) -> &mut OpenOptions {
+        self.max_value_len = len;
+        self
+    }
+
+    /// Fail a lookup, such as [`find`](GenericCDB::find), once it has
+    /// returned `records` records for its key, rather than returning
+    /// however many the file holds. The error is an
+    /// [`Error::LimitExceeded`].
+    pub fn max_find_records(&mut self, records: u64) -> &mut OpenOptions {
+        self.max_find_records = records;
+        self
+    }
+
+    /// Load the [`SortedIndex`] kept next to the file, named by
+    /// [`sorted_index_path`], and attach it to the reader for
+    /// [`iter_sorted`](GenericCDB::iter_sorted). Opening fails if the
+    /// index is missing or out of date. Off by default.
+    pub fn sorted_index(&mut self, sorted_index: bool) -> &mut OpenOptions {
+        self.sorted_index = sorted_index;
+        self
+    }
+
+    /// Decrypt values with `key`, for a database written with
+    /// [`GenericCDBMake::set_encryption`](crate::GenericCDBMake::set_encryption).
+    ///
+    /// Opening an encrypted database fails without the key, with an error
+    /// of kind [`io::ErrorKind::PermissionDenied`], and with the wrong
+    /// key. Opening a database which is not encrypted fails with the key,
+    /// so that a plaintext file cannot be swapped in unnoticed.
+    #[cfg(feature = "encryption")]
+    pub fn encryption_key(&mut self, key: &EncryptionKey) -> &mut OpenOptions {
+        self.encryption_key = Some(key.clone());
+        self
+    }
+
+    /// Check while opening that the database was signed by `key`, with
+    /// [`GenericCDB::verify_signature`], failing rather than serving
+    /// from a file which was not signed or has changed since. See
+    /// [`GenericCDB::open_verified`].
+    #[cfg(feature = "signing")]
+    pub fn verifying_key(&mut self, key: &VerifyingKey) -> &mut OpenOptions {
+        self.verifying_key = Some(*key);
+        self
+    }
+
+    fn limits(&self) -> Limits {
+        let default_probes = if self.untrusted { 4096 } else { u64::MAX };
+        Limits {
+            checked: self.untrusted,
+            max_probes: self.max_probes.unwrap_or(default_probes),
+            max_value_len: self.max_value_len,
+            max_records: self.max_find_records,
+        }
+    }
+}
+
+impl Default for OpenOptions {
+    fn default() -> OpenOptions {
+        OpenOptions::new()
+    }
+}
+
+#[derive(Debug)]
+enum Source {
+    #[cfg(not(target_arch = "wasm32"))]
+    Mmap(Arc<Mmap>),
+    Memory(Arc<Vec<u8>>),
+    File {
+        file: File,
+        header: Box<[u8]>,
+    },
+}
+
+/// Map the whole of `file` into memory.
+#[cfg(not(target_arch = "wasm32"))]
+fn map(file: &File, size: u64, options: &OpenOptions) -> Result<Source> {
+    let mut map_options = MmapOptions::new();
+    if options.populate {
+        map_options.populate();
+    }
+    let map = unsafe { map_options.map(file)? };
+    if map.len() as u64 != size {
+        return err_corrupt(map.len() as u64, "file changed size while opening");
+    }
+    if options.access != AccessPattern::Normal {
+        advise(&map, options.access)?;
+    }
+    if options.lock {
+        lock(&map)?;
+    }
+    Ok(Source::Mmap(Arc::new(map)))
+}
+
+/// There is no `mmap` on wasm, so read the whole of `file` into memory
+/// instead.
+#[cfg(target_arch = "wasm32")]
+fn map(mut file: &File, size: u64, _options: &OpenOptions) -> Result<Source> {
+    use std::io::Read;
+    let mut data = Vec::with_capacity(size as usize);
+    file.read_to_end(&mut data)?;
+    if data.len() as u64 != size {
+        return err_corrupt(data.len() as u64, "file changed size while opening");
+    }
+    Ok(Source::Memory(Arc::new(data)))
+}
+
+#[cfg(all(unix, not(target_arch = "wasm32")))]
+fn advise(map: &Mmap, access: AccessPattern) -> Result<()> {
+    use memmap2::Advice;
+    map.advise(match access {
+        AccessPattern::Normal => Advice::Normal,
+        AccessPattern::Random => Advice::Random,
+        AccessPattern::Sequential => Advice::Sequential,
+    })
+}
+
+#[cfg(all(not(unix), not(target_arch = "wasm32")))]
+fn advise(_map: &Mmap, _access: AccessPattern) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_arch = "wasm32")))]
+fn lock(map: &Mmap) -> Result<()> {
+    map.lock()
+}
+
+#[cfg(all(not(unix), not(target_arch = "wasm32")))]
+fn lock(_map: &Mmap) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn read_exact_at(file: &File, buf: &mut [u8], pos: u64) -> Result<()> {
+    use std::os::unix::fs::FileExt;
+    file.read_exact_at(buf, pos)
+}
+
+#[cfg(windows)]
+fn read_exact_at(file: &File, mut buf: &mut [u8], mut pos: u64) -> Result<()> {
+    use std::os::windows::fs::FileExt;
+    while !buf.is_empty() {
+        match file.seek_read(buf, pos) {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => {
+                buf = &mut buf[n..];
+                pos += n as u64;
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn read_exact_at(_file: &File, _buf: &mut [u8], _pos: u64) -> Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Positioned reads are not supported on this platform",
+    ))
+}
+
+/// A shared handle to the memory holding a database, which keeps it alive
+/// for as long as a [`Bytes`](bytes::Bytes) borrows from it.
+#[cfg(feature = "bytes")]
+enum SharedSource {
+    #[cfg(not(target_arch = "wasm32"))]
+    Mmap(Arc<Mmap>),
+    Memory(Arc<Vec<u8>>),
+}
+
+#[cfg(feature = "bytes")]
+impl AsRef<[u8]> for SharedSource {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            SharedSource::Mmap(map) => map,
+            SharedSource::Memory(data) => data,
+        }
+    }
+}
+
+/// CDB file reader, generic over the on-disk [`Format`].
+///
+/// Most code should use the [`CDB`] or [`CDB64`] aliases.
 ///
 /// # Example
 ///
@@ -28,17 +407,901 @@ const KEYSIZE: usize = 32;
 /// # }
 /// ```
 #[derive(Debug)]
-pub struct CDB {
-    file: Mmap,
-    size: usize,
+pub struct GenericCDB<F, H = DjbHash> {
+    source: Source,
+    size: u64,
+    bloom: Option<Bloom>,
+    compressed: bool,
+    #[cfg(feature = "encryption")]
+    cipher: Option<Cipher>,
+    checksums: Checksums,
+    limits: Limits,
+    pub(crate) sorted: Option<SortedIndex>,
+    format: PhantomData<F>,
+    hasher: PhantomData<H>,
+}
+
+/// Whether values are followed by checksums, and if so whether they are
+/// checked when read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Checksums {
+    None,
+    Unchecked,
+    Checked,
+}
+
+/// CDB file reader for the classic 32-bit format.
+pub type CDB<H = DjbHash> = GenericCDB<Format32, H>;
+
+/// CDB file reader for the 64-bit format.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// # let tmp_dir = tempfile::tempdir()?;
+/// # let tmp_path = tmp_dir.path();
+/// # std::env::set_current_dir(&tmp_path)?;
+/// use cdb32::{CDB64Writer, CDB64};
+///
+/// let mut cdb = CDB64Writer::create("temporary.cdb")?;
+/// cdb.add(b"one", b"Hello")?;
+/// cdb.finish()?;
+///
+/// let cdb = CDB64::open("temporary.cdb")?;
+/// assert_eq!(cdb.get(b"one").unwrap()?, b"Hello");
+/// # Ok(())
+/// # }
+/// ```
+pub type CDB64<H = DjbHash> = GenericCDB<Format64, H>;
+
+/// Check that a file of `size` bytes can hold a database.
+fn check_size<F: Format>(size: u64) -> Result<()> {
+    if size < F::HEADER_SIZE as u64 {
+        return err_corrupt(0, "file is smaller than the header");
+    }
+    if size > F::MAX_SIZE {
+        return err_corrupt(F::MAX_SIZE, "file is larger than the format allows");
+    }
+    Ok(())
 }
 
-fn err_badfile<T>() -> Result<T> {
-    Err(io::Error::new(io::ErrorKind::Other, "Invalid file format"))
+fn err_compressed<T>() -> Result<T> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Compressed or encrypted values cannot be borrowed",
+    ))
 }
 
-impl CDB {
-    /// Opens the named file and returns the CDB reader.
+impl<F: Format> GenericCDB<F> {
+    /// Opens the named file and returns the CDB reader.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::CDB;
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn open<P: AsRef<path::Path>>(filename: P) -> Result<Self> {
+        Self::open_with_options(filename, &OpenOptions::new())
+    }
+
+    /// Opens the named file with the given options and returns the CDB
+    /// reader.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::{OpenOptions, CDB};
+    ///
+    /// let cdb = CDB::open_with_options("tests/test1.cdb", OpenOptions::new().mmap(false))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn open_with_options<P: AsRef<path::Path>>(
+        filename: P,
+        options: &OpenOptions,
+    ) -> Result<Self> {
+        let mut cdb = Self::from_file(File::open(&filename)?, options)?;
+        if options.sorted_index {
+            cdb.set_sorted_index(SortedIndex::open(sorted_index_path(filename))?)?;
+        }
+        Ok(cdb)
+    }
+
+    /// Opens the named file like [`open`](Self::open), but fails unless
+    /// its header is valid. See [`OpenOptions::strict`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let cdb = cdb32::CDB::open_strict("tests/test1.cdb")?;
+    /// assert_eq!(cdb.get(b"two").unwrap()?, b"Goodbye");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn open_strict<P: AsRef<path::Path>>(filename: P) -> Result<Self> {
+        Self::open_with_options(filename, OpenOptions::new().strict(true))
+    }
+
+    /// Opens the named file like [`open`](Self::open), but fails unless
+    /// it was signed by `key` and has not changed since, reading the
+    /// whole file to check. See [`GenericCDB::verify_signature`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::{SigningKey, CDB};
+    ///
+    /// let key = SigningKey::from_seed([7; 32]);
+    /// let err = CDB::open_verified("tests/test1.cdb", &key.verifying_key()).unwrap_err();
+    /// assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "signing")]
+    pub fn open_verified<P: AsRef<path::Path>>(filename: P, key: &VerifyingKey) -> Result<Self> {
+        Self::open_with_options(filename, OpenOptions::new().verifying_key(key))
+    }
+
+    /// Create a CDB reader over a database held in memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::CDB;
+    ///
+    /// let data = std::fs::read("tests/test1.cdb")?;
+    /// let cdb = CDB::from_bytes(data)?;
+    /// assert_eq!(cdb.get(b"two").unwrap()?, b"Goodbye");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        Self::from_bytes_with_options(data, &OpenOptions::new())
+    }
+
+    /// Create a CDB reader over a database held in memory, with the given
+    /// options. Options about the file, such as
+    /// [`mmap`](OpenOptions::mmap), do not apply.
+    pub fn from_bytes_with_options(data: Vec<u8>, options: &OpenOptions) -> Result<Self> {
+        let size = data.len() as u64;
+        check_size::<F>(size)?;
+        GenericCDB {
+            source: Source::Memory(Arc::new(data)),
+            size,
+            bloom: None,
+            compressed: false,
+            #[cfg(feature = "encryption")]
+            cipher: None,
+            checksums: Checksums::None,
+            limits: Limits::NONE,
+            sorted: None,
+            format: PhantomData,
+            hasher: PhantomData,
+        }
+        .load_sections(options)
+    }
+
+    /// Create a CDB reader from a file which is already open.
+    pub(crate) fn from_file(file: File, options: &OpenOptions) -> Result<Self> {
+        let size = file.metadata()?.len();
+        check_size::<F>(size)?;
+        Self::from_file_unchecked(file, size, options)?.load_sections(options)
+    }
+
+    fn from_file_unchecked(file: File, size: u64, options: &OpenOptions) -> Result<Self> {
+        let source = if options.mmap {
+            map(&file, size, options)?
+        } else {
+            let mut header = vec![0; F::HEADER_SIZE].into_boxed_slice();
+            read_exact_at(&file, &mut header, 0)?;
+            Source::File { file, header }
+        };
+        Ok(GenericCDB {
+            source,
+            size,
+            bloom: None,
+            compressed: false,
+            #[cfg(feature = "encryption")]
+            cipher: None,
+            checksums: Checksums::None,
+            limits: Limits::NONE,
+            sorted: None,
+            format: PhantomData,
+            hasher: PhantomData,
+        })
+    }
+
+    /// Open the named file for salvaging with
+    /// [`salvage_iter`](Self::salvage_iter), even if it is truncated or
+    /// its header was never written.
+    ///
+    /// Lookups in a database opened this way may fail or find nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::CDB;
+    ///
+    /// let cdb = CDB::open_lenient("tests/test1.cdb")?;
+    /// let mut records = cdb.salvage_iter();
+    /// for result in &mut records {
+    ///     let (key, value) = result?;
+    ///     println!("{:?} => {:?}", key, value);
+    /// }
+    /// if let Some(pos) = records.damage() {
+    ///     println!("damaged from byte {}", pos);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn open_lenient<P: AsRef<path::Path>>(filename: P) -> Result<Self> {
+        let mut file = File::open(filename)?;
+        let size = file.metadata()?.len();
+        if size > F::MAX_SIZE {
+            return err_corrupt(F::MAX_SIZE, "file is larger than the format allows");
+        }
+        if size >= F::HEADER_SIZE as u64 {
+            return Self::from_file_unchecked(file, size, &OpenOptions::new());
+        }
+        // Too short to even hold the header, so there are no records
+        // to salvage, but still give a valid reader.
+        let mut data = Vec::with_capacity(F::HEADER_SIZE);
+        io::Read::read_to_end(&mut file, &mut data)?;
+        data.resize(F::HEADER_SIZE, 0);
+        Ok(GenericCDB {
+            source: Source::Memory(Arc::new(data)),
+            size: F::HEADER_SIZE as u64,
+            bloom: None,
+            compressed: false,
+            #[cfg(feature = "encryption")]
+            cipher: None,
+            checksums: Checksums::None,
+            limits: Limits::NONE,
+            sorted: None,
+            format: PhantomData,
+            hasher: PhantomData,
+        })
+    }
+}
+
+impl<F: Format, H: CdbHash> GenericCDB<F, H> {
+    /// Switch to looking keys up with the hash function `H2`, for files
+    /// written by tools which do not use the default [`DjbHash`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::{CdbHash, CDB};
+    ///
+    /// struct Fnv;
+    ///
+    /// impl CdbHash for Fnv {
+    ///     fn hash(key: &[u8]) -> u32 {
+    ///         key.iter()
+    ///             .fold(0x811c9dc5, |h, &c| (h ^ c as u32).wrapping_mul(0x01000193))
+    ///     }
+    /// }
+    ///
+    /// let cdb: CDB<Fnv> = CDB::open("tests/test1.cdb")?.with_hasher();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_hasher<H2: CdbHash>(self) -> GenericCDB<F, H2> {
+        GenericCDB {
+            source: self.source,
+            size: self.size,
+            bloom: self.bloom,
+            compressed: self.compressed,
+            #[cfg(feature = "encryption")]
+            cipher: self.cipher,
+            checksums: self.checksums,
+            limits: self.limits,
+            sorted: self.sorted,
+            format: PhantomData,
+            hasher: PhantomData,
+        }
+    }
+
+    /// Load any optional sections appended after the hash tables.
+    fn load_sections(mut self, options: &OpenOptions) -> Result<Self> {
+        if options.strict || options.untrusted {
+            self.check_header()?;
+        }
+        self.limits = options.limits();
+        let sections = trailer::sections(&self)?;
+        self.bloom = Bloom::load(&self, &sections)?;
+        self.compressed = trailer::compression(&self, &sections)?;
+        self.load_encryption(&sections, options)?;
+        self.checksums = match trailer::checksums(&self, &sections)? {
+            false => Checksums::None,
+            true if options.verify_checksums => Checksums::Checked,
+            true => Checksums::Unchecked,
+        };
+        #[cfg(feature = "signing")]
+        if let Some(key) = &options.verifying_key {
+            self.verify_signature(key)?;
+        }
+        Ok(self)
+    }
+
+    /// Set up decryption from the encryption section, if there is one.
+    fn load_encryption(
+        &mut self,
+        sections: &[trailer::Section],
+        options: &OpenOptions,
+    ) -> Result<()> {
+        let check = trailer::encryption(self, sections)?;
+        #[cfg(feature = "encryption")]
+        match (check, &options.encryption_key) {
+            (None, None) => {}
+            (None, Some(_)) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Database is not encrypted",
+                ))
+            }
+            (Some(_), None) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "Database is encrypted, and no key was given",
+                ))
+            }
+            (Some(check), Some(key)) => {
+                let cipher = Cipher::new(key);
+                if cipher.key_check()[..] != check[..] {
+                    return Err(io::Error::new(
+                        io::ErrorKind::PermissionDenied,
+                        "Wrong encryption key for the database",
+                    ));
+                }
+                self.cipher = Some(cipher);
+            }
+        }
+        #[cfg(not(feature = "encryption"))]
+        let _ = (check, options);
+        Ok(())
+    }
+
+    /// Return `true` if the record values are encrypted, as written after
+    /// `GenericCDBMake::set_encryption` with the `encryption` feature.
+    ///
+    /// Values are decrypted transparently by the methods returning owned
+    /// values, while those borrowing values from the mapping, like
+    /// [`get_ref`](Self::get_ref), return an error.
+    pub fn is_encrypted(&self) -> bool {
+        #[cfg(feature = "encryption")]
+        return self.cipher.is_some();
+        #[cfg(not(feature = "encryption"))]
+        false
+    }
+
+    /// Return `true` if values must be decoded before they are returned,
+    /// so cannot be borrowed.
+    fn is_encoded(&self) -> bool {
+        self.compressed || self.is_encrypted()
+    }
+
+    /// Return `true` if the record values are compressed, as written
+    /// after
+    /// [`GenericCDBMake::set_compression`](crate::GenericCDBMake::set_compression).
+    ///
+    /// Values are decompressed transparently by the methods returning
+    /// owned values, while those borrowing values from the mapping, like
+    /// [`get_ref`](Self::get_ref), return an error.
+    pub fn is_compressed(&self) -> bool {
+        self.compressed
+    }
+
+    /// Return `true` if every value is followed by a checksum of its
+    /// record, as written after
+    /// [`GenericCDBMake::set_checksums`](crate::GenericCDBMake::set_checksums).
+    ///
+    /// The checksums are stripped from the values returned, and checked
+    /// unless turned off with [`OpenOptions::verify_checksums`].
+    pub fn has_checksums(&self) -> bool {
+        self.checksums != Checksums::None
+    }
+
+    /// Return `true` if the database has a bloom filter, which lets
+    /// lookups of most absent keys skip the hash tables entirely.
+    ///
+    /// Writers add one when asked with
+    /// [`GenericCDBMake::set_bloom_filter`](crate::GenericCDBMake::set_bloom_filter).
+    pub fn has_bloom_filter(&self) -> bool {
+        self.bloom.is_some()
+    }
+
+    /// Start probing the hash table for `khash`, unless the bloom filter
+    /// shows that no key has that hash.
+    pub(crate) fn probe(&self, khash: u32) -> Probe {
+        match &self.bloom {
+            Some(bloom) if !bloom.may_contain(self, khash) => Probe::empty(),
+            _ => Probe::start(self, khash),
+        }
+    }
+
+    /// Return `true` if the database is mapped or held in memory, so
+    /// that it can be borrowed from with `slice`.
+    pub(crate) fn is_mapped(&self) -> bool {
+        !matches!(self.source, Source::File { .. })
+    }
+
+    /// Advise the kernel of the expected access pattern, for instance
+    /// switching to [`AccessPattern::Sequential`] before a full
+    /// [`iter`](Self::iter) of a large file.
+    ///
+    /// This has no effect on readers using positioned reads, or on
+    /// platforms without `madvise`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::{AccessPattern, CDB};
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// cdb.advise(AccessPattern::Sequential)?;
+    /// for result in cdb.iter() {
+    ///     result?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn advise(&self, access: AccessPattern) -> Result<()> {
+        match &self.source {
+            #[cfg(not(target_arch = "wasm32"))]
+            Source::Mmap(map) => advise(map, access),
+            _ => {
+                let _ = access;
+                Ok(())
+            }
+        }
+    }
+
+    fn header(&self) -> &[u8] {
+        match &self.source {
+            #[cfg(not(target_arch = "wasm32"))]
+            Source::Mmap(map) => &map[..F::HEADER_SIZE],
+            Source::Memory(data) => &data[..F::HEADER_SIZE],
+            Source::File { header, .. } => header,
+        }
+    }
+
+    /// Return the size of the file.
+    pub(crate) fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn check_bounds(&self, pos: u64, len: u64) -> Result<()> {
+        match pos.checked_add(len) {
+            Some(end) if end <= self.size => Ok(()),
+            _ => err_corrupt(pos, "read past the end of the file"),
+        }
+    }
+
+    pub(crate) fn read(&self, buf: &mut [u8], pos: u64) -> Result<usize> {
+        let len = buf.len();
+        match &self.source {
+            Source::File { file, .. } => {
+                self.check_bounds(pos, len as u64)?;
+                read_exact_at(file, buf, pos)?;
+            }
+            _ => buf.copy_from_slice(self.slice(pos, len as u64)?),
+        }
+        Ok(len)
+    }
+
+    pub(crate) fn read_vec(&self, pos: u64, len: u64) -> Result<Vec<u8>> {
+        // Check before allocating, so a corrupt length cannot trigger a
+        // huge allocation.
+        self.check_bounds(pos, len)?;
+        let mut result = vec![0; len as usize];
+        self.read(&mut result, pos)?;
+        Ok(result)
+    }
+
+    /// Read the value of the record with the given key, checking and
+    /// decompressing it if needed.
+    pub(crate) fn read_value(&self, key: &[u8], pos: u64, len: u64) -> Result<Vec<u8>> {
+        let mut value = self.read_vec(pos, len)?;
+        let len = self.check_value(key, pos, &value)?.len();
+        value.truncate(len);
+        #[cfg(feature = "encryption")]
+        if let Some(cipher) = &self.cipher {
+            value = cipher.open(key, pos, value)?;
+        }
+        #[cfg(feature = "lz4")]
+        if self.compressed {
+            return crate::compress::decode(value, pos, self.limits.max_value_len);
+        }
+        Ok(value)
+    }
+
+    /// Borrow the value of the record with the given key, which is
+    /// impossible if it may be compressed or is encrypted.
+    fn value_ref(&self, key: &[u8], pos: u64, len: u64) -> Result<&[u8]> {
+        if self.is_encoded() {
+            return err_compressed();
+        }
+        self.check_value(key, pos, self.slice(pos, len)?)
+    }
+
+    /// Return the value of the record with the given key as [`Bytes`],
+    /// sharing the memory holding the database if there is any.
+    ///
+    /// [`Bytes`]: bytes::Bytes
+    #[cfg(feature = "bytes")]
+    pub(crate) fn value_bytes(&self, key: &[u8], pos: u64, len: u64) -> Result<bytes::Bytes> {
+        let shared = match &self.source {
+            _ if self.is_encoded() => None,
+            #[cfg(not(target_arch = "wasm32"))]
+            Source::Mmap(map) => Some(SharedSource::Mmap(Arc::clone(map))),
+            Source::Memory(data) => Some(SharedSource::Memory(Arc::clone(data))),
+            Source::File { .. } => None,
+        };
+        match shared {
+            Some(shared) => {
+                let value = self.value_ref(key, pos, len)?;
+                Ok(bytes::Bytes::from_owner(shared).slice_ref(value))
+            }
+            None => self.read_value(key, pos, len).map(bytes::Bytes::from),
+        }
+    }
+
+    /// Strip the checksum from a value stored at `pos`, checking it if
+    /// asked to.
+    fn check_value<'v>(&self, key: &[u8], pos: u64, value: &'v [u8]) -> Result<&'v [u8]> {
+        if self.checksums == Checksums::None {
+            return Ok(value);
+        }
+        let (value, stored) = match value.len().checked_sub(CHECKSUM_SIZE as usize) {
+            Some(len) => value.split_at(len),
+            None => return err_corrupt(pos, "value is shorter than its checksum"),
+        };
+        if self.checksums == Checksums::Checked
+            && checksum::checksum(key, value).to_le_bytes() != stored
+        {
+            return Err(Error::ChecksumMismatch { offset: pos }.into());
+        }
+        Ok(value)
+    }
+
+    /// Return `true` if the checksum of a stored value is correct, or
+    /// there are no checksums.
+    pub(crate) fn checksum_matches(&self, key: &[u8], value: &[u8]) -> bool {
+        match self.checksums {
+            Checksums::None => true,
+            _ => match value.len().checked_sub(CHECKSUM_SIZE as usize) {
+                Some(len) => {
+                    let (value, stored) = value.split_at(len);
+                    checksum::checksum(key, value).to_le_bytes() == stored
+                }
+                None => false,
+            },
+        }
+    }
+
+    /// Like `read_vec`, but replacing the contents of `buf`.
+    fn read_into(&self, pos: u64, len: u64, buf: &mut Vec<u8>) -> Result<()> {
+        self.check_bounds(pos, len)?;
+        buf.clear();
+        buf.resize(len as usize, 0);
+        self.read(buf, pos)?;
+        Ok(())
+    }
+
+    pub(crate) fn slice(&self, pos: u64, len: u64) -> Result<&[u8]> {
+        self.check_bounds(pos, len)?;
+        let pos = pos as usize;
+        let len = len as usize;
+        match &self.source {
+            #[cfg(not(target_arch = "wasm32"))]
+            Source::Mmap(map) => Ok(&map[pos..pos + len]),
+            Source::Memory(data) => Ok(&data[pos..pos + len]),
+            Source::File { .. } => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Borrowed access requires a memory-mapped reader",
+            )),
+        }
+    }
+
+    /// Return the total number of records in the database.
+    ///
+    /// This is computed from the hash table sizes in the header, which
+    /// writers make twice the number of records in each table, so it
+    /// does not need to scan the file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::CDB;
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// assert_eq!(cdb.len(), 4);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn len(&self) -> usize {
+        let slots = (0..256).fold(0_u64, |acc, i| acc.saturating_add(self.table(i).1));
+        (slots / 2) as usize
+    }
+
+    /// Return `true` if the database contains no records.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Find the first record with the named key.
+    ///
+    /// The key may be anything which can be viewed as bytes, such as
+    /// `&str`, `String`, `Vec<u8>` or a byte array.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::CDB;
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// if let Some(record) = cdb.get(b"one") {
+    ///     println!("{:?}", record?);
+    /// }
+    /// assert_eq!(cdb.get("two").unwrap()?, b"Goodbye");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Option<Result<Vec<u8>>> {
+        self.find(key).next()
+    }
+
+    /// Find the first record with the named key, replacing the contents
+    /// of `buf` with its value.
+    ///
+    /// Returns the length of the value, or `None` if the key was not
+    /// found, in which case `buf` is left untouched. Reusing one buffer
+    /// across lookups avoids allocating for each value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::CDB;
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// let mut buf = Vec::new();
+    /// for key in [&b"one"[..], b"two"] {
+    ///     if let Some(len) = cdb.get_into(key, &mut buf)? {
+    ///         println!("{} bytes: {:?}", len, buf);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_into(&self, key: &[u8], buf: &mut Vec<u8>) -> Result<Option<usize>> {
+        match self.probe(H::hash(key)).next(self, key).transpose()? {
+            Some((dpos, dlen)) if self.is_encoded() => {
+                // Copy rather than replace, keeping the caller's buffer.
+                let value = self.read_value(key, dpos, dlen)?;
+                buf.clear();
+                buf.extend_from_slice(&value);
+                Ok(Some(buf.len()))
+            }
+            Some((dpos, dlen)) => {
+                self.read_into(dpos, dlen, buf)?;
+                let len = self.check_value(key, dpos, buf)?.len();
+                buf.truncate(len);
+                Ok(Some(len))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Find the first record for each of the named keys.
+    ///
+    /// The result has one entry per key, in the same order. The hash
+    /// table probes are made in file order rather than key order, which
+    /// gives better locality than calling [`get`](Self::get) repeatedly
+    /// on large batches.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::CDB;
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// let values = cdb.get_many(&[b"one", b"three", b"two"])?;
+    /// assert_eq!(values[0].as_deref(), Some(&b"Hello"[..]));
+    /// assert_eq!(values[1], None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_many(&self, keys: &[&[u8]]) -> Result<Vec<Option<Vec<u8>>>> {
+        let mut probes = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (i, self.probe(H::hash(key))))
+            .collect::<Vec<_>>();
+        probes.sort_by_key(|(_, probe)| probe.kpos);
+
+        let mut results = vec![None; keys.len()];
+        for (i, mut probe) in probes {
+            if let Some((dpos, dlen)) = probe.next(self, keys[i]).transpose()? {
+                results[i] = Some(self.read_value(keys[i], dpos, dlen)?);
+            }
+        }
+        Ok(results)
+    }
+
+    /// Find all records with the named key. The returned iterator
+    /// produces each value associated with the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::CDB;
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// for result in cdb.find(b"one") {
+    ///     println!("{:?}", result?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn find<K: AsRef<[u8]>>(&self, key: K) -> CDBValueIter<'_, F, H> {
+        let key = key.as_ref();
+        CDBValueIter::find(self, key, H::hash(key))
+    }
+
+    /// Find the first record with the given key, using its precomputed
+    /// hash.
+    ///
+    /// The key must have been hashed with this reader's hash function,
+    /// see [`CDBKey::with_hasher`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::{CDBKey, CDB};
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// if let Some(record) = cdb.get_with_key(&CDBKey::new(b"one")) {
+    ///     println!("{:?}", record?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_with_key(&self, key: &CDBKey) -> Option<Result<Vec<u8>>> {
+        self.find_with_key(key).next()
+    }
+
+    /// Find all records with the given key, using its precomputed hash.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::{CDBKey, CDB};
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// for result in cdb.find_with_key(&CDBKey::new(b"one")) {
+    ///     println!("{:?}", result?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn find_with_key(&self, key: &CDBKey) -> CDBValueIter<'_, F, H> {
+        self.find_with_hash(key.as_bytes(), key.hash_value())
+    }
+
+    /// Find all records with the given key, whose hash has already been
+    /// computed, for instance incrementally with a
+    /// [`DjbHasher`](crate::hash::DjbHasher).
+    ///
+    /// The hash must come from this reader's hash function, or nothing
+    /// will be found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::hash::DjbHasher;
+    /// use cdb32::CDB;
+    ///
+    /// let mut hasher = DjbHasher::new();
+    /// hasher.update(b"o");
+    /// hasher.update(b"ne");
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// for result in cdb.find_with_hash(b"one", hasher.value()) {
+    ///     println!("{:?}", result?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn find_with_hash(&self, key: &[u8], khash: u32) -> CDBValueIter<'_, F, H> {
+        CDBValueIter::find(self, key, khash)
+    }
+
+    /// Check whether any record has the named key, without reading its
+    /// value or allocating.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::CDB;
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// assert!(cdb.exists(b"one")?);
+    /// assert!(!cdb.exists(b"three")?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn exists(&self, key: &[u8]) -> Result<bool> {
+        let found = self.probe(H::hash(key)).next(self, key).transpose()?;
+        Ok(found.is_some())
+    }
+
+    /// Count the records with the named key, without reading their
+    /// values or allocating.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::CDB;
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// assert_eq!(cdb.count(b"one")?, 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn count(&self, key: &[u8]) -> Result<usize> {
+        let mut probe = self.probe(H::hash(key));
+        let mut count = 0;
+        while probe.next(self, key).transpose()?.is_some() {
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Find the first record with the named key, returning a slice
+    /// borrowed from the underlying mapping instead of a copy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::CDB;
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// if let Some(record) = cdb.get_ref(b"one") {
+    ///     println!("{:?}", record?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_ref(&self, key: &[u8]) -> Option<Result<&[u8]>> {
+        self.find_ref(key).next()
+    }
+
+    /// Find all records with the named key. The returned iterator
+    /// produces each value associated with the key as a borrowed slice.
     ///
     /// # Examples
     ///
@@ -47,80 +1310,235 @@ impl CDB {
     /// use cdb32::CDB;
     ///
     /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// for result in cdb.find_ref(b"one") {
+    ///     println!("{:?}", result?);
+    /// }
     /// # Ok(())
     /// # }
     /// ```
-    pub fn open<P: AsRef<path::Path>>(filename: P) -> Result<CDB> {
-        let file = File::open(filename)?;
-        let file = unsafe { Mmap::map(&file)? };
-        if file.len() < 2048 || file.len() > 0xffffffff {
-            return err_badfile();
+    pub fn find_ref<'k>(&self, key: &'k [u8]) -> CDBValueRefIter<'_, 'k, F, H> {
+        CDBValueRefIter {
+            cdb: self,
+            key,
+            probe: self.probe(H::hash(key)),
         }
-        let size = file.len();
-        Ok(CDB { file, size })
     }
 
-    fn read(&self, buf: &mut [u8], pos: u32) -> Result<usize> {
-        let len = buf.len();
-        let pos = pos as usize;
-        if pos + len > self.size {
-            return err_badfile();
+    /// Find the first record with the named key, returning a reader
+    /// over its value instead of a copy.
+    ///
+    /// The value is read from the database on demand, so large values
+    /// can be streamed without holding them in memory. For the same
+    /// reason, the checksum of a value is not checked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use std::io::Read;
+    /// use cdb32::CDB;
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// if let Some(reader) = cdb.get_reader(b"two") {
+    ///     let mut value = String::new();
+    ///     reader?.read_to_string(&mut value)?;
+    ///     assert_eq!(value, "Goodbye");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_reader(&self, key: &[u8]) -> Option<Result<CDBValueReader<'_, F, H>>> {
+        let (dpos, dlen) = match self.probe(H::hash(key)).next(self, key)? {
+            Ok(found) => found,
+            Err(err) => return Some(Err(err)),
+        };
+        if self.is_encoded() {
+            return Some(err_compressed());
         }
-        buf.copy_from_slice(&self.file[pos..pos + len]);
-        Ok(len)
+        if let Err(err) = self.check_bounds(dpos, dlen) {
+            return Some(Err(err));
+        }
+        let len = match self.checksums {
+            Checksums::None => dlen,
+            _ => match dlen.checked_sub(CHECKSUM_SIZE) {
+                Some(len) => len,
+                None => return Some(err_corrupt(dpos, "value is shorter than its checksum")),
+            },
+        };
+        Some(Ok(CDBValueReader {
+            cdb: self,
+            start: dpos,
+            len,
+            pos: 0,
+        }))
     }
 
-    fn hash_table(&self, khash: u32) -> (u32, u32, u32) {
-        let x = ((khash as usize) & 0xff) << 3;
-        let (hpos, hslots) = uint32::unpack2(&self.file[x..x + 8]);
-        let kpos = if hslots > 0 {
-            hpos.wrapping_add(((khash >> 8) % hslots) << 3)
-        } else {
-            0
-        };
-        (hpos, hslots, kpos)
+    /// Return a reader over the raw bytes of the data section, where the
+    /// records are stored, between the header and the first hash table.
+    ///
+    /// Positions in the reader are relative to the start of the data
+    /// section, which is at file offset [`Format::HEADER_SIZE`]; see
+    /// [`CDBDataReader::file_offset`]. Records are laid out as in the
+    /// original format, so checksums and compressed values are read as
+    /// they are stored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use std::io::Read;
+    /// use cdb32::CDB;
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// let mut data = cdb.data_reader();
+    /// let mut lengths = [0; 8];
+    /// data.read_exact(&mut lengths)?;
+    /// assert_eq!(lengths, [3, 0, 0, 0, 5, 0, 0, 0]);
+    /// assert_eq!(data.file_offset(), 2048 + 8);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn data_reader(&self) -> CDBDataReader<'_, F, H> {
+        let start = F::HEADER_SIZE as u64;
+        CDBDataReader(CDBValueReader {
+            cdb: self,
+            start,
+            len: self.data_end().saturating_sub(start),
+            pos: 0,
+        })
+    }
+
+    /// Iterate over all the `(key, value)` pairs in the database.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::CDB;
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// for result in cdb.iter() {
+    ///     let (key, value) = result?;
+    ///     println!("{:?} => {:?}", key, value);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ````
+    pub fn iter(&self) -> CDBKeyValueIter<'_, F, H> {
+        CDBKeyValueIter::start(self)
     }
 
-    fn match_key(&self, key: &[u8], pos: u32) -> Result<bool> {
-        let mut buf = [0_u8; KEYSIZE];
-        let mut len = key.len();
-        let mut pos = pos;
-        let mut keypos = 0;
+    /// Iterate over all the `(key, value)` pairs in the database as
+    /// slices borrowed from the underlying mapping.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::CDB;
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// for result in cdb.iter_ref() {
+    ///     let (key, value) = result?;
+    ///     println!("{:?} => {:?}", key, value);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn iter_ref(&self) -> CDBKeyValueRefIter<'_, F, H> {
+        CDBKeyValueRefIter(CDBKeyValueIter::start(self))
+    }
 
-        while len > 0 {
-            let n = min(len, buf.len());
-            self.read(&mut buf[..n], pos)?;
-            if buf[..n] != key[keypos..keypos + n] {
-                return Ok(false);
-            }
-            pos += n as u32;
-            keypos += n;
-            len -= n;
+    /// Resume iterating over the `(key, value)` pairs in the database
+    /// from a cursor saved with [`CDBKeyValueIter::cursor`].
+    ///
+    /// The cursor must come from an iterator over this same file. A
+    /// cursor from a different file will produce garbage or errors, but
+    /// is otherwise harmless.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::{CDBCursor, CDB};
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// let mut records = cdb.iter();
+    /// records.next().unwrap()?;
+    /// // Save the position somewhere durable, as a plain integer.
+    /// let saved = records.cursor().position();
+    ///
+    /// let resumed = cdb.resume_at(CDBCursor::from_position(saved))?;
+    /// assert_eq!(resumed.count(), 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resume_at(&self, cursor: CDBCursor) -> Result<CDBKeyValueIter<'_, F, H>> {
+        let data_end = self.data_end();
+        if !(F::HEADER_SIZE as u64..=data_end).contains(&cursor.0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Cursor is outside the data section",
+            ));
         }
-        Ok(true)
+        Ok(CDBKeyValueIter {
+            cdb: self,
+            scan: Scan::range(cursor.0, data_end),
+        })
     }
 
-    /// Find the first record with the named key.
+    /// Find all records with the named key, returning an iterator that
+    /// owns a reference-counted handle to the reader instead of
+    /// borrowing it.
     ///
     /// # Examples
     ///
     /// ```
     /// # fn main() -> std::io::Result<()> {
+    /// use std::sync::Arc;
     /// use cdb32::CDB;
     ///
-    /// let cdb = CDB::open("tests/test1.cdb")?;
-    /// if let Some(record) = cdb.get(b"one") {
-    ///     println!("{:?}", record?);
+    /// let cdb = Arc::new(CDB::open("tests/test1.cdb")?);
+    /// let values = Arc::clone(&cdb).into_find(b"one");
+    /// let handle = std::thread::spawn(move || values.collect::<Vec<_>>());
+    /// assert_eq!(handle.join().unwrap().len(), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn into_find(self: Arc<Self>, key: &[u8]) -> CDBOwnedValueIter<F, H> {
+        let probe = self.probe(H::hash(key));
+        CDBOwnedValueIter {
+            cdb: self,
+            key: key.to_vec(),
+            probe,
+        }
+    }
+
+    /// Iterate over all the `(key, value)` pairs in the database, owning
+    /// a reference-counted handle to the reader instead of borrowing it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use std::sync::Arc;
+    /// use cdb32::CDB;
+    ///
+    /// let cdb = Arc::new(CDB::open("tests/test1.cdb")?);
+    /// for result in Arc::clone(&cdb).into_records() {
+    ///     let (key, value) = result?;
+    ///     println!("{:?} => {:?}", key, value);
     /// }
     /// # Ok(())
     /// # }
     /// ```
-    pub fn get(&self, key: &[u8]) -> Option<Result<Vec<u8>>> {
-        self.find(key).next()
+    pub fn into_records(self: Arc<Self>) -> CDBOwnedKeyValueIter<F, H> {
+        let scan = Scan::start(&*self);
+        CDBOwnedKeyValueIter { cdb: self, scan }
     }
 
-    /// Find all records with the named key. The returned iterator
-    /// produces each value associated with the key.
+    /// Iterate over the keys of all the records in the database, without
+    /// reading their values. A key appears once for each of its records.
     ///
     /// # Examples
     ///
@@ -129,17 +1547,21 @@ impl CDB {
     /// use cdb32::CDB;
     ///
     /// let cdb = CDB::open("tests/test1.cdb")?;
-    /// for result in cdb.find(b"one") {
+    /// for result in cdb.keys() {
     ///     println!("{:?}", result?);
     /// }
     /// # Ok(())
     /// # }
     /// ```
-    pub fn find(&self, key: &[u8]) -> CDBValueIter {
-        CDBValueIter::find(self, key)
+    pub fn keys(&self) -> CDBKeyIter<'_, F, H> {
+        CDBKeyIter(CDBKeyValueIter::start(self))
     }
 
-    /// Iterate over all the `(key, value)` pairs in the database.
+    /// Iterate over each distinct key in the database exactly once.
+    ///
+    /// A key is produced when the scan reaches the record that a lookup
+    /// of that key finds first, so this costs one lookup per record but
+    /// needs no memory to remember keys already seen.
     ///
     /// # Examples
     ///
@@ -148,59 +1570,170 @@ impl CDB {
     /// use cdb32::CDB;
     ///
     /// let cdb = CDB::open("tests/test1.cdb")?;
-    /// for result in cdb.iter() {
-    ///     let (key, value) = result?;
-    ///     println!("{:?} => {:?}", key, value);
+    /// for result in cdb.unique_keys() {
+    ///     println!("{:?}", result?);
     /// }
     /// # Ok(())
     /// # }
-    /// ````
-    pub fn iter(&self) -> CDBKeyValueIter {
-        CDBKeyValueIter::start(self)
+    /// ```
+    pub fn unique_keys(&self) -> CDBUniqueKeyIter<'_, F, H> {
+        CDBUniqueKeyIter(CDBKeyValueIter::start(self))
     }
 }
 
 /// Type alias for [`CDBValueIter`]
-pub type CDBIter<'a> = CDBValueIter<'a>;
+pub type CDBIter<'a, F = Format32, H = DjbHash> = CDBValueIter<'a, F, H>;
+
+impl<F: Format, H: CdbHash> Storage<F> for GenericCDB<F, H> {
+    type Error = io::Error;
+
+    fn header(&self) -> &[u8] {
+        GenericCDB::header(self)
+    }
+
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn read(&self, buf: &mut [u8], pos: u64) -> Result<()> {
+        GenericCDB::read(self, buf, pos).map(drop)
+    }
+
+    fn corrupt(offset: u64, reason: &'static str) -> io::Error {
+        Error::Corrupt { offset, reason }.into()
+    }
+
+    fn over_limit(offset: u64, limit: &'static str) -> io::Error {
+        Error::LimitExceeded { offset, limit }.into()
+    }
+
+    fn limits(&self) -> Limits {
+        self.limits
+    }
+}
+
+/// Reader over the value of one record, returned by
+/// [`GenericCDB::get_reader`].
+#[derive(Debug)]
+pub struct CDBValueReader<'a, F = Format32, H = DjbHash> {
+    cdb: &'a GenericCDB<F, H>,
+    start: u64,
+    len: u64,
+    pos: u64,
+}
+
+impl<'a, F, H> CDBValueReader<'a, F, H> {
+    /// Return the total length of the value.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Return `true` if the value is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<'a, F: Format, H: CdbHash> io::Read for CDBValueReader<'a, F, H> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let remaining = self.len.saturating_sub(self.pos);
+        let n = min(buf.len() as u64, remaining) as usize;
+        if n > 0 {
+            self.cdb.read(&mut buf[..n], self.start + self.pos)?;
+            self.pos += n as u64;
+        }
+        Ok(n)
+    }
+}
+
+impl<'a, F: Format, H: CdbHash> io::Seek for CDBValueReader<'a, F, H> {
+    fn seek(&mut self, pos: io::SeekFrom) -> Result<u64> {
+        let (base, offset) = match pos {
+            io::SeekFrom::Start(offset) => {
+                self.pos = offset;
+                return Ok(offset);
+            }
+            io::SeekFrom::End(offset) => (self.len, offset),
+            io::SeekFrom::Current(offset) => (self.pos, offset),
+        };
+        match base.checked_add_signed(offset) {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+/// Reader over the raw data section of a database, returned by
+/// [`GenericCDB::data_reader`].
+#[derive(Debug)]
+pub struct CDBDataReader<'a, F = Format32, H = DjbHash>(CDBValueReader<'a, F, H>);
+
+impl<'a, F, H> CDBDataReader<'a, F, H> {
+    /// Return the length of the data section.
+    pub fn len(&self) -> u64 {
+        self.0.len
+    }
+
+    /// Return `true` if the data section is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.len == 0
+    }
+
+    /// Return the file offset of the current position, as used by the
+    /// hash tables to locate records.
+    pub fn file_offset(&self) -> u64 {
+        self.0.start.saturating_add(self.0.pos)
+    }
+}
+
+impl<'a, F: Format, H: CdbHash> io::Read for CDBDataReader<'a, F, H> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl<'a, F: Format, H: CdbHash> io::Seek for CDBDataReader<'a, F, H> {
+    fn seek(&mut self, pos: io::SeekFrom) -> Result<u64> {
+        self.0.seek(pos)
+    }
+}
 
 /// Iterator over a set of records in the CDB with the same key.
 ///
-/// See [`CDB::find`]
+/// See [`GenericCDB::find`]
 #[derive(Debug)]
-pub struct CDBValueIter<'a> {
-    cdb: &'a CDB,
+pub struct CDBValueIter<'a, F = Format32, H = DjbHash> {
+    cdb: &'a GenericCDB<F, H>,
     key: Vec<u8>,
-    khash: u32,
-    kloop: u32,
-    kpos: u32,
-    hpos: u32,
-    hslots: u32,
-    dpos: u32,
-    dlen: u32,
+    probe: Probe,
+    dpos: u64,
+    dlen: u64,
 }
 
-impl<'a> CDBValueIter<'a> {
-    fn find(cdb: &'a CDB, key: &[u8]) -> Self {
-        let khash = hash(key);
-        let (hpos, hslots, kpos) = cdb.hash_table(khash);
-
+impl<'a, F: Format, H: CdbHash> CDBValueIter<'a, F, H> {
+    fn find(cdb: &'a GenericCDB<F, H>, key: &[u8], khash: u32) -> Self {
         CDBValueIter {
             cdb,
             key: key.to_vec(),
-            khash,
-            kloop: 0,
-            kpos,
-            hpos,
-            hslots,
+            probe: cdb.probe(khash),
             dpos: 0,
             dlen: 0,
         }
     }
 
-    fn read_vec(&self) -> Result<Vec<u8>> {
-        let mut result = vec![0; self.dlen as usize];
-        self.cdb.read(&mut result[..], self.dpos)?;
-        Ok(result)
+    pub(crate) fn read_vec(&self) -> Result<Vec<u8>> {
+        self.cdb.read_value(&self.key, self.dpos, self.dlen)
+    }
+
+    #[cfg(feature = "bytes")]
+    pub(crate) fn read_bytes(&self) -> Result<bytes::Bytes> {
+        self.cdb.value_bytes(&self.key, self.dpos, self.dlen)
     }
 }
 
@@ -215,96 +1748,194 @@ macro_rules! iter_try {
     };
 }
 
-macro_rules! iter_checked {
-    ( $e:expr ) => {
-        match $e {
-            None => {
-                return Some(err_badfile());
-            }
-            Some(y) => y,
-        }
-    };
+impl<'a, F: Format, H: CdbHash> CDBValueIter<'a, F, H> {
+    /// Advance to the next matching record, leaving its data position
+    /// and length in `dpos` and `dlen`.
+    pub(crate) fn next_record(&mut self) -> Option<Result<()>> {
+        let (dpos, dlen) = iter_try!(self.probe.next(self.cdb, &self.key)?);
+        self.dpos = dpos;
+        self.dlen = dlen;
+        Some(Ok(()))
+    }
 }
 
-impl<'a> Iterator for CDBValueIter<'a> {
+impl<'a, F: Format, H: CdbHash> Iterator for CDBValueIter<'a, F, H> {
     type Item = Result<Vec<u8>>;
     fn next(&mut self) -> Option<Self::Item> {
-        while self.kloop < self.hslots {
-            let mut buf = [0_u8; 8];
-            let kpos = self.kpos;
-            iter_try!(self.cdb.read(&mut buf, kpos));
-            let (khash, pos) = uint32::unpack2(&buf);
-            if pos == 0 {
-                return None;
-            }
-            self.kloop += 1;
-            self.kpos += 8;
-            if self.kpos == iter_checked!(self.hpos.checked_add(self.hslots << 3)) {
-                self.kpos = self.hpos;
-            }
-            if khash == self.khash {
-                iter_try!(self.cdb.read(&mut buf, pos));
-                let (klen, dlen) = uint32::unpack2(&buf);
-                if klen as usize == self.key.len()
-                    && iter_try!(self.cdb.match_key(&self.key[..], pos + 8))
-                {
-                    self.dlen = dlen;
-                    self.dpos = pos + 8 + self.key.len() as u32;
-                    return Some(self.read_vec());
-                }
-            }
-        }
-        None
+        iter_try!(self.next_record()?);
+        Some(self.read_vec())
+    }
+}
+
+/// Iterator over a set of records in the CDB with the same key,
+/// producing slices borrowed from the underlying mapping. It borrows the
+/// key too, so that lookups do not allocate.
+///
+/// See [`GenericCDB::find_ref`]
+#[derive(Debug)]
+pub struct CDBValueRefIter<'a, 'k, F = Format32, H = DjbHash> {
+    cdb: &'a GenericCDB<F, H>,
+    key: &'k [u8],
+    probe: Probe,
+}
+
+impl<'a, 'k, F: Format, H: CdbHash> Iterator for CDBValueRefIter<'a, 'k, F, H> {
+    type Item = Result<&'a [u8]>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let (dpos, dlen) = iter_try!(self.probe.next(self.cdb, self.key)?);
+        Some(self.cdb.value_ref(self.key, dpos, dlen))
+    }
+}
+
+/// Saved position of a [`CDBKeyValueIter`], from which iteration can be
+/// resumed with [`GenericCDB::resume_at`].
+///
+/// The position can be stored as an integer to resume after a restart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CDBCursor(u64);
+
+impl CDBCursor {
+    /// Return the file offset of the next record to be read.
+    pub fn position(&self) -> u64 {
+        self.0
+    }
+
+    /// Recreate a cursor from a position returned by
+    /// [`position`](Self::position).
+    pub fn from_position(pos: u64) -> Self {
+        CDBCursor(pos)
     }
 }
 
 /// Iterator over all the records in the CDB.
 ///
-/// See [`CDB::iter`]
+/// See [`GenericCDB::iter`]
 #[derive(Debug)]
-pub struct CDBKeyValueIter<'a> {
-    cdb: &'a CDB,
-    pos: u32,
-    data_end: u32,
+pub struct CDBKeyValueIter<'a, F = Format32, H = DjbHash> {
+    cdb: &'a GenericCDB<F, H>,
+    scan: Scan,
 }
 
-impl<'a> CDBKeyValueIter<'a> {
-    fn start(cdb: &'a CDB) -> Self {
-        let data_end = uint32::unpack(&cdb.file[0..4]).min(cdb.size as u32);
+impl<'a, F: Format, H: CdbHash> CDBKeyValueIter<'a, F, H> {
+    fn start(cdb: &'a GenericCDB<F, H>) -> Self {
         Self {
             cdb,
-            pos: 2048,
-            data_end,
+            scan: Scan::start(cdb),
         }
     }
+
+    fn next_record(&mut self) -> Option<Result<(u64, u64, u64, u64)>> {
+        self.scan.next(self.cdb)
+    }
+
+    /// Return the position of the next record, to resume from later
+    /// with [`GenericCDB::resume_at`].
+    pub fn cursor(&self) -> CDBCursor {
+        CDBCursor(self.scan.pos())
+    }
 }
 
-impl<'a> Iterator for CDBKeyValueIter<'a> {
+impl<'a, F: Format, H: CdbHash> Iterator for CDBKeyValueIter<'a, F, H> {
     type Item = Result<(Vec<u8>, Vec<u8>)>;
     fn next(&mut self) -> Option<Self::Item> {
-        if self.pos + 8 >= self.data_end {
-            None
-        } else {
-            let (klen, dlen) =
-                uint32::unpack2(&self.cdb.file[self.pos as usize..self.pos as usize + 8]);
-            let total_len = self
-                .pos
-                .saturating_add(8)
-                .saturating_add(klen)
-                .saturating_add(dlen);
-            if total_len > self.data_end {
-                Some(err_badfile())
-            } else {
-                let kpos = (self.pos + 8) as usize;
-                let dpos = kpos + klen as usize;
-                let mut key = vec![0; klen as usize];
-                let mut value = vec![0; dlen as usize];
-                // Copied from CDB::read
-                key.copy_from_slice(&self.cdb.file[kpos..kpos + klen as usize]);
-                value.copy_from_slice(&self.cdb.file[dpos..dpos + dlen as usize]);
-                self.pos += 8 + klen + dlen;
-                Some(Ok((key, value)))
+        let (kpos, klen, dpos, dlen) = iter_try!(self.next_record()?);
+        let key = iter_try!(self.cdb.read_vec(kpos, klen));
+        let value = iter_try!(self.cdb.read_value(&key, dpos, dlen));
+        Some(Ok((key, value)))
+    }
+}
+
+/// Iterator over all the records in the CDB, producing slices
+/// borrowed from the underlying mapping.
+///
+/// See [`GenericCDB::iter_ref`]
+#[derive(Debug)]
+pub struct CDBKeyValueRefIter<'a, F = Format32, H = DjbHash>(CDBKeyValueIter<'a, F, H>);
+
+impl<'a, F: Format, H: CdbHash> Iterator for CDBKeyValueRefIter<'a, F, H> {
+    type Item = Result<(&'a [u8], &'a [u8])>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let (kpos, klen, dpos, dlen) = iter_try!(self.0.next_record()?);
+        let key = iter_try!(self.0.cdb.slice(kpos, klen));
+        let value = iter_try!(self.0.cdb.value_ref(key, dpos, dlen));
+        Some(Ok((key, value)))
+    }
+}
+
+/// Iterator over the keys of all the records in the CDB.
+///
+/// See [`GenericCDB::keys`]
+#[derive(Debug)]
+pub struct CDBKeyIter<'a, F = Format32, H = DjbHash>(CDBKeyValueIter<'a, F, H>);
+
+impl<'a, F: Format, H: CdbHash> Iterator for CDBKeyIter<'a, F, H> {
+    type Item = Result<Vec<u8>>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let (kpos, klen, _, _) = iter_try!(self.0.next_record()?);
+        Some(self.0.cdb.read_vec(kpos, klen))
+    }
+}
+
+/// Iterator over the distinct keys in the CDB.
+///
+/// See [`GenericCDB::unique_keys`]
+#[derive(Debug)]
+pub struct CDBUniqueKeyIter<'a, F = Format32, H = DjbHash>(CDBKeyValueIter<'a, F, H>);
+
+impl<'a, F: Format, H: CdbHash> Iterator for CDBUniqueKeyIter<'a, F, H> {
+    type Item = Result<Vec<u8>>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (kpos, klen, dpos, _) = iter_try!(self.0.next_record()?);
+            let key = iter_try!(self.0.cdb.read_vec(kpos, klen));
+            match Probe::start(self.0.cdb, H::hash(&key)).next(self.0.cdb, &key) {
+                Some(Ok((first, _))) if first == dpos => return Some(Ok(key)),
+                Some(Ok(_)) => {}
+                Some(Err(err)) => return Some(Err(err)),
+                None => {
+                    let pos = kpos - F::PAIR_SIZE as u64;
+                    return Some(err_corrupt(pos, "record is not in the hash table"));
+                }
             }
         }
     }
 }
+
+/// Iterator over a set of records with the same key, owning a
+/// reference-counted handle to the CDB.
+///
+/// See [`GenericCDB::into_find`]
+#[derive(Debug)]
+pub struct CDBOwnedValueIter<F = Format32, H = DjbHash> {
+    cdb: Arc<GenericCDB<F, H>>,
+    key: Vec<u8>,
+    probe: Probe,
+}
+
+impl<F: Format, H: CdbHash> Iterator for CDBOwnedValueIter<F, H> {
+    type Item = Result<Vec<u8>>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let (dpos, dlen) = iter_try!(self.probe.next(&*self.cdb, &self.key)?);
+        Some(self.cdb.read_value(&self.key, dpos, dlen))
+    }
+}
+
+/// Iterator over all the records in the CDB, owning a reference-counted
+/// handle to it.
+///
+/// See [`GenericCDB::into_records`]
+#[derive(Debug)]
+pub struct CDBOwnedKeyValueIter<F = Format32, H = DjbHash> {
+    cdb: Arc<GenericCDB<F, H>>,
+    scan: Scan,
+}
+
+impl<F: Format, H: CdbHash> Iterator for CDBOwnedKeyValueIter<F, H> {
+    type Item = Result<(Vec<u8>, Vec<u8>)>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let (kpos, klen, dpos, dlen) = iter_try!(self.scan.next(&*self.cdb)?);
+        let key = iter_try!(self.cdb.read_vec(kpos, klen));
+        let value = iter_try!(self.cdb.read_value(&key, dpos, dlen));
+        Some(Ok((key, value)))
+    }
+}
@@ -0,0 +1,140 @@
+use std::fs::{self, File, Metadata};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+use crate::format::{Format, Format32, Format64};
+use crate::reader::{GenericCDB, OpenOptions, Result};
+
+/// What identifies one version of a file, so a replacement can be
+/// noticed without reading it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct FileStamp {
+    #[cfg(unix)]
+    dev: u64,
+    #[cfg(unix)]
+    ino: u64,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl FileStamp {
+    fn new(meta: &Metadata) -> Self {
+        #[cfg(unix)]
+        use std::os::unix::fs::MetadataExt;
+        FileStamp {
+            #[cfg(unix)]
+            dev: meta.dev(),
+            #[cfg(unix)]
+            ino: meta.ino(),
+            len: meta.len(),
+            modified: meta.modified().ok(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Loaded<F> {
+    cdb: Arc<GenericCDB<F>>,
+    stamp: FileStamp,
+}
+
+/// A CDB reader which re-opens its file when it is replaced, generic
+/// over the on-disk [`Format`].
+///
+/// This suits databases which are rebuilt periodically and atomically
+/// renamed into place, as [`GenericCDBWriter`](crate::GenericCDBWriter)
+/// does. [`current`](Self::current) hands out the loaded database as an
+/// `Arc`, so readers holding on to an old version keep it alive while
+/// new lookups go to the new one. Most code should use the
+/// [`ReloadableCDB`] or [`ReloadableCDB64`] aliases.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use cdb32::ReloadableCDB;
+///
+/// let cdb = ReloadableCDB::open("tests/test1.cdb")?;
+/// // Call this periodically, for example once per request or from a timer.
+/// cdb.reload_if_changed()?;
+/// assert_eq!(cdb.current().get(b"two").unwrap()?, b"Goodbye");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct GenericReloadableCDB<F> {
+    path: PathBuf,
+    options: OpenOptions,
+    loaded: RwLock<Loaded<F>>,
+}
+
+/// Reloadable CDB file reader for the classic 32-bit format.
+pub type ReloadableCDB = GenericReloadableCDB<Format32>;
+
+/// Reloadable CDB file reader for the 64-bit format.
+pub type ReloadableCDB64 = GenericReloadableCDB<Format64>;
+
+impl<F: Format> GenericReloadableCDB<F> {
+    /// Open the named file, to be re-opened whenever it changes.
+    pub fn open<P: Into<PathBuf>>(filename: P) -> Result<Self> {
+        Self::open_with_options(filename, &OpenOptions::new())
+    }
+
+    /// Open the named file with the given options, which are also used
+    /// whenever it is re-opened.
+    pub fn open_with_options<P: Into<PathBuf>>(filename: P, options: &OpenOptions) -> Result<Self> {
+        let path = filename.into();
+        let loaded = load(&path, options)?;
+        Ok(GenericReloadableCDB {
+            path,
+            options: options.clone(),
+            loaded: RwLock::new(loaded),
+        })
+    }
+
+    /// Return the path of the file being watched.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Return the currently loaded database.
+    ///
+    /// The returned reader is unaffected by any later reload.
+    pub fn current(&self) -> Arc<GenericCDB<F>> {
+        self.loaded.read().unwrap().cdb.clone()
+    }
+
+    /// Re-open the file if its inode, size or modification time has
+    /// changed since it was last loaded, returning `true` if it was
+    /// re-opened.
+    ///
+    /// If the new file cannot be opened, the error is returned and the
+    /// previously loaded database stays in use.
+    pub fn reload_if_changed(&self) -> Result<bool> {
+        let stamp = FileStamp::new(&fs::metadata(&self.path)?);
+        if stamp == self.loaded.read().unwrap().stamp {
+            return Ok(false);
+        }
+        self.refresh()?;
+        Ok(true)
+    }
+
+    /// Unconditionally re-open the file.
+    pub fn refresh(&self) -> Result<()> {
+        let loaded = load(&self.path, &self.options)?;
+        *self.loaded.write().unwrap() = loaded;
+        Ok(())
+    }
+}
+
+fn load<F: Format>(path: &Path, options: &OpenOptions) -> Result<Loaded<F>> {
+    let file = File::open(path)?;
+    // Take the stamp from the open file rather than the path, so a
+    // rename racing with this cannot pair old contents with a new stamp.
+    let stamp = FileStamp::new(&file.metadata()?);
+    Ok(Loaded {
+        cdb: Arc::new(GenericCDB::from_file(file, options)?),
+        stamp,
+    })
+}
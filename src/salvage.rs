@@ -0,0 +1,96 @@
+use crate::format::{Format, Format32};
+use crate::hash::{CdbHash, DjbHash};
+use crate::image::Storage;
+use crate::reader::{GenericCDB, Result};
+
+impl<F: Format, H: CdbHash> GenericCDB<F, H> {
+    /// Iterate over as many complete records as can be read from a
+    /// damaged database, usually one opened with
+    /// [`open_lenient`](Self::open_lenient).
+    ///
+    /// The records are found by walking the data section, ignoring the
+    /// hash tables. The walk ends at the first record which does not fit
+    /// in the file, whose position is then available from
+    /// [`CDBSalvageIter::damage`]. If the header is missing, as when the
+    /// writer never finished, the walk continues to the end of the file
+    /// and may read the hash tables, if any, as damaged records.
+    pub fn salvage_iter(&self) -> CDBSalvageIter<'_, F, H> {
+        let table_pos = self.table(0).0;
+        let end = if (F::HEADER_SIZE as u64..=self.size()).contains(&table_pos) {
+            table_pos
+        } else {
+            self.size()
+        };
+        CDBSalvageIter {
+            cdb: self,
+            pos: F::HEADER_SIZE as u64,
+            end,
+            damage: None,
+        }
+    }
+}
+
+/// Iterator over the complete records of a damaged database.
+///
+/// See [`GenericCDB::salvage_iter`]
+#[derive(Debug)]
+pub struct CDBSalvageIter<'a, F = Format32, H = DjbHash> {
+    cdb: &'a GenericCDB<F, H>,
+    pos: u64,
+    end: u64,
+    damage: Option<u64>,
+}
+
+impl<'a, F: Format, H: CdbHash> CDBSalvageIter<'a, F, H> {
+    /// Return the position of the first damaged record, once the
+    /// iteration has reached it, or `None` if no damage was found.
+    pub fn damage(&self) -> Option<u64> {
+        self.damage
+    }
+
+    /// Return the number of bytes of the data section which could not be
+    /// salvaged, once the iteration has reached the damage.
+    pub fn bytes_lost(&self) -> u64 {
+        self.damage.map_or(0, |pos| self.end - pos)
+    }
+}
+
+impl<'a, F: Format, H: CdbHash> Iterator for CDBSalvageIter<'a, F, H> {
+    type Item = Result<(Vec<u8>, Vec<u8>)>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.damage.is_some() || self.pos == self.end {
+            return None;
+        }
+        let pair = F::PAIR_SIZE as u64;
+        if self.end - self.pos < pair {
+            self.damage = Some(self.pos);
+            return None;
+        }
+        let mut buf = [0_u8; 16];
+        let buf = &mut buf[..F::PAIR_SIZE];
+        if let Err(err) = self.cdb.read(buf, self.pos) {
+            self.damage = Some(self.pos);
+            return Some(Err(err));
+        }
+        let (klen, dlen) = F::unpack2(buf);
+        let total_len = self
+            .pos
+            .saturating_add(pair)
+            .saturating_add(klen)
+            .saturating_add(dlen);
+        if total_len > self.end {
+            self.damage = Some(self.pos);
+            return None;
+        }
+        let kpos = self.pos + pair;
+        let record = self
+            .cdb
+            .read_vec(kpos, klen)
+            .and_then(|key| Ok((key, self.cdb.read_vec(kpos + klen, dlen)?)));
+        match record {
+            Ok(_) => self.pos = total_len,
+            Err(_) => self.damage = Some(self.pos),
+        }
+        Some(record)
+    }
+}
@@ -0,0 +1,315 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use crate::format::{Format, Format32, Format64};
+use crate::reader::{CDBValueIter, GenericCDB, Result};
+use crate::sharded::{shard_index, shard_name};
+use crate::writer::{replace_file, Durability};
+
+/// How the members of a [`GenericCDBSet`] divide the records between
+/// them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SetLayout {
+    /// Each key is in exactly one member, chosen by its hash as
+    /// [`GenericShardedCDBWriter`](crate::GenericShardedCDBWriter) does.
+    Shards,
+    /// Members are searched in order, and a key in one member hides the
+    /// same key in those after it, so the first member is the top layer.
+    Layers,
+}
+
+/// The list of member files of a [`GenericCDBSet`].
+///
+/// The manifest is a text file. Its first line is `shards` or `layers`,
+/// naming the [`SetLayout`], and each following line is the path of a
+/// member, relative to the directory holding the manifest unless it is
+/// absolute. Empty lines and lines starting with `#` are ignored.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use cdb32::{CDBSetManifest, SetLayout};
+///
+/// let text = b"# overrides first\nlayers\noverrides.cdb\nbase.cdb\n";
+/// let manifest = CDBSetManifest::parse(&text[..])?;
+/// assert_eq!(manifest.layout, SetLayout::Layers);
+/// assert_eq!(manifest.members.len(), 2);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CDBSetManifest {
+    /// How the members divide the records.
+    pub layout: SetLayout,
+    /// The paths of the members, in order.
+    pub members: Vec<PathBuf>,
+}
+
+impl CDBSetManifest {
+    /// Create a manifest with no members.
+    pub fn new(layout: SetLayout) -> Self {
+        CDBSetManifest {
+            layout,
+            members: Vec::new(),
+        }
+    }
+
+    /// Create a manifest for the `shards` files named `base.0`, `base.1`
+    /// and so on, as written by
+    /// [`GenericShardedCDBWriter`](crate::GenericShardedCDBWriter).
+    pub fn shards<P: AsRef<Path>>(base: P, shards: usize) -> Self {
+        CDBSetManifest {
+            layout: SetLayout::Shards,
+            members: (0..shards).map(|i| shard_name(base.as_ref(), i)).collect(),
+        }
+    }
+
+    /// Parse a manifest read from `reader`.
+    pub fn parse<R: BufRead>(reader: R) -> Result<Self> {
+        let mut layout = None;
+        let mut members = Vec::new();
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if layout.is_none() {
+                layout = match line {
+                    "shards" => Some(SetLayout::Shards),
+                    "layers" => Some(SetLayout::Layers),
+                    _ => return err_manifest(&format!("unknown layout in line {}", number + 1)),
+                };
+            } else {
+                members.push(PathBuf::from(line));
+            }
+        }
+        match layout {
+            Some(layout) => Ok(CDBSetManifest { layout, members }),
+            None => err_manifest("no layout"),
+        }
+    }
+
+    /// Read the manifest at `path`. Member paths are returned as written.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::parse(BufReader::new(fs::File::open(path)?))
+    }
+
+    /// Write the manifest to `path`, through a temporary file renamed into
+    /// place, so readers see either the old manifest or the new one.
+    ///
+    /// Member paths must be valid UTF-8 and must not contain line breaks.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut text = match self.layout {
+            SetLayout::Shards => "shards\n".to_string(),
+            SetLayout::Layers => "layers\n".to_string(),
+        };
+        for member in &self.members {
+            match member.to_str() {
+                Some(name) if !name.is_empty() && !name.contains(['\n', '\r']) => {
+                    text.push_str(name);
+                    text.push('\n');
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("Cannot write {:?} in a CDB set manifest", member),
+                    ))
+                }
+            }
+        }
+        replace_file(path.as_ref(), text.as_bytes(), Durability::File)
+    }
+}
+
+fn err_manifest<T>(msg: &str) -> Result<T> {
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Malformed CDB set manifest: {}", msg),
+    ))
+}
+
+/// Several CDB files read as one database, generic over the on-disk
+/// [`Format`].
+///
+/// A set is described by a [`CDBSetManifest`], which lists its members
+/// and how they divide the records: as [shards](SetLayout::Shards), to go
+/// past the size limit of one file, or as [layers](SetLayout::Layers),
+/// for instance to put a small file of overrides in front of a large one
+/// which is rebuilt rarely.
+///
+/// Most code should use the [`CDBSet`] or [`CDB64Set`] aliases.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// # let tmp_dir = tempfile::tempdir()?;
+/// # let dir = tmp_dir.path();
+/// use cdb32::{CDBSet, CDBSetManifest, CDBWriter, SetLayout};
+///
+/// CDBWriter::from_iter(dir.join("base.cdb"), [("one", "1"), ("two", "2")])?;
+/// CDBWriter::from_iter(dir.join("overrides.cdb"), [("two", "deux")])?;
+/// let mut manifest = CDBSetManifest::new(SetLayout::Layers);
+/// manifest.members = vec!["overrides.cdb".into(), "base.cdb".into()];
+/// manifest.write(dir.join("set"))?;
+///
+/// let set = CDBSet::open(dir.join("set"))?;
+/// assert_eq!(set.get(b"one").unwrap()?, b"1");
+/// assert_eq!(set.get(b"two").unwrap()?, b"deux");
+/// assert_eq!(set.iter().count(), 2);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct GenericCDBSet<F> {
+    layout: SetLayout,
+    members: Vec<GenericCDB<F>>,
+}
+
+/// CDB set reader for the classic 32-bit format.
+pub type CDBSet = GenericCDBSet<Format32>;
+
+/// CDB set reader for the 64-bit format.
+pub type CDB64Set = GenericCDBSet<Format64>;
+
+impl<F: Format> GenericCDBSet<F> {
+    /// Open the members listed in the manifest at `path`.
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidData`] if the
+    /// manifest is malformed or lists no members.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let manifest = CDBSetManifest::read(path)?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let members = manifest
+            .members
+            .iter()
+            .map(|member| GenericCDB::open(dir.join(member)))
+            .collect::<Result<Vec<_>>>()?;
+        if members.is_empty() {
+            return err_manifest("no members");
+        }
+        Ok(Self::from_members(manifest.layout, members))
+    }
+
+    /// Read from already opened members, in manifest order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no members.
+    pub fn from_members(layout: SetLayout, members: Vec<GenericCDB<F>>) -> Self {
+        assert!(!members.is_empty(), "at least one member is needed");
+        GenericCDBSet { layout, members }
+    }
+
+    /// Return how the members divide the records.
+    pub fn layout(&self) -> SetLayout {
+        self.layout
+    }
+
+    /// Return the members, in manifest order.
+    pub fn members(&self) -> &[GenericCDB<F>] {
+        &self.members
+    }
+
+    /// Return the members which may hold `key`, in search order.
+    fn candidates(&self, key: &[u8]) -> &[GenericCDB<F>] {
+        match self.layout {
+            SetLayout::Shards => {
+                let i = shard_index(key, self.members.len());
+                &self.members[i..i + 1]
+            }
+            SetLayout::Layers => &self.members,
+        }
+    }
+
+    /// Find the first record with the named key.
+    pub fn get(&self, key: &[u8]) -> Option<Result<Vec<u8>>> {
+        self.find(key).next()
+    }
+
+    /// Find all records with the named key, in the first member holding
+    /// any.
+    pub fn find(&self, key: &[u8]) -> CDBSetValueIter<'_, F> {
+        CDBSetValueIter {
+            members: self.candidates(key),
+            key: key.to_vec(),
+            iter: None,
+            found: false,
+        }
+    }
+
+    /// Return `true` if a record with the named key exists.
+    pub fn exists(&self, key: &[u8]) -> Result<bool> {
+        for member in self.candidates(key) {
+            if member.exists(key)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Iterate over the `(key, value)` pairs of every member in turn,
+    /// leaving out records hidden by a layer above.
+    pub fn iter(&self) -> impl Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + '_ {
+        self.members
+            .iter()
+            .enumerate()
+            .flat_map(move |(i, member)| {
+                let above = match self.layout {
+                    SetLayout::Shards => &[][..],
+                    SetLayout::Layers => &self.members[..i],
+                };
+                member.iter().filter_map(move |record| {
+                    let (key, value) = match record {
+                        Ok(record) => record,
+                        Err(err) => return Some(Err(err)),
+                    };
+                    for layer in above {
+                        match layer.exists(&key) {
+                            Ok(false) => {}
+                            Ok(true) => return None,
+                            Err(err) => return Some(Err(err)),
+                        }
+                    }
+                    Some(Ok((key, value)))
+                })
+            })
+    }
+}
+
+/// Iterator over the values of one key in a [`GenericCDBSet`].
+///
+/// See [`GenericCDBSet::find`].
+#[derive(Debug)]
+pub struct CDBSetValueIter<'a, F> {
+    members: &'a [GenericCDB<F>],
+    key: Vec<u8>,
+    iter: Option<CDBValueIter<'a, F>>,
+    found: bool,
+}
+
+impl<'a, F: Format> Iterator for CDBSetValueIter<'a, F> {
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(iter) = &mut self.iter {
+                match iter.next() {
+                    Some(value) => {
+                        self.found = true;
+                        return Some(value);
+                    }
+                    None if self.found => return None,
+                    None => {}
+                }
+            }
+            let (member, rest) = self.members.split_first()?;
+            self.members = rest;
+            self.iter = Some(member.find(&self.key));
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use std::path::{Path, PathBuf};
+
+use crate::format::{Format, Format32, Format64};
+use crate::hash::hash;
+use crate::reader::{CDBValueIter, GenericCDB, Result};
+use crate::writer::{Durability, GenericCDBWriter};
+
+/// Return the index of the shard holding `key`, out of `shards`.
+///
+/// The key hash is scrambled first (Fibonacci hashing), as its low byte
+/// chooses the hash table within each shard and its high bits vary
+/// little between short keys.
+pub(crate) fn shard_index(key: &[u8], shards: usize) -> usize {
+    let mixed = (hash(key) as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 32;
+    ((mixed * shards as u64) >> 32) as usize
+}
+
+/// Return the name of shard `i` of the database named `base`, which is
+/// `base` with `.i` appended.
+pub(crate) fn shard_name(base: &Path, i: usize) -> PathBuf {
+    let mut name = base.as_os_str().to_os_string();
+    name.push(format!(".{}", i));
+    name.into()
+}
+
+/// A writer which splits a database across several CDB files by key
+/// hash, generic over the on-disk [`Format`].
+///
+/// Each shard is an ordinary CDB file, so splitting keeps each under the
+/// 4 GiB limit of the classic format, and the shards can be built in
+/// parallel through [`shards_mut`](Self::shards_mut). Read them back with
+/// [`GenericShardedCDB`].
+///
+/// Most code should use the [`ShardedCDBWriter`] or
+/// [`ShardedCDB64Writer`] aliases.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// # let tmp_dir = tempfile::tempdir()?;
+/// # let path = tmp_dir.path().join("sharded.cdb");
+/// use cdb32::{ShardedCDB, ShardedCDBWriter};
+///
+/// let mut cdb = ShardedCDBWriter::create(&path, 4)?;
+/// cdb.add(b"one", b"Hello")?;
+/// cdb.add(b"two", b"Goodbye")?;
+/// cdb.finish()?;
+///
+/// let cdb = ShardedCDB::open(&path, 4)?;
+/// assert_eq!(cdb.get(b"two").unwrap()?, b"Goodbye");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct GenericShardedCDBWriter<F> {
+    shards: Vec<GenericCDBWriter<F>>,
+}
+
+/// Sharded CDB writer for the classic 32-bit format.
+pub type ShardedCDBWriter = GenericShardedCDBWriter<Format32>;
+
+/// Sharded CDB writer for the 64-bit format.
+pub type ShardedCDB64Writer = GenericShardedCDBWriter<Format64>;
+
+impl<F: Format> GenericShardedCDBWriter<F> {
+    /// Safely create `shards` new CDB files, named `base.0`, `base.1` and
+    /// so on.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shards` is zero.
+    pub fn create<P: AsRef<Path>>(base: P, shards: usize) -> Result<Self> {
+        assert!(shards > 0, "at least one shard is needed");
+        let base = base.as_ref();
+        Self::from_writers((0..shards).map(|i| GenericCDBWriter::create(shard_name(base, i))))
+    }
+
+    /// Write the shards with the given writers, in shard order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no writers.
+    pub fn from_writers<I: IntoIterator<Item = Result<GenericCDBWriter<F>>>>(
+        writers: I,
+    ) -> Result<Self> {
+        let shards = writers.into_iter().collect::<Result<Vec<_>>>()?;
+        assert!(!shards.is_empty(), "at least one shard is needed");
+        Ok(GenericShardedCDBWriter { shards })
+    }
+
+    /// Return the index of the shard which holds `key`.
+    pub fn shard_for(&self, key: &[u8]) -> usize {
+        shard_index(key, self.shards.len())
+    }
+
+    /// Return the writers for each shard.
+    ///
+    /// Records must only be added to the shard given by
+    /// [`shard_for`](Self::shard_for), or readers will not find them.
+    pub fn shards_mut(&mut self) -> &mut [GenericCDBWriter<F>] {
+        &mut self.shards
+    }
+
+    /// Add a record to the shard for its key.
+    pub fn add(&mut self, key: &[u8], data: &[u8]) -> Result<()> {
+        let i = self.shard_for(key);
+        self.shards[i].add(key, data)
+    }
+
+    /// Append a bloom filter to every shard. See
+    /// [`GenericCDBMake::set_bloom_filter`](crate::GenericCDBMake::set_bloom_filter).
+    pub fn set_bloom_filter(&mut self, bits_per_key: u32) {
+        for shard in &mut self.shards {
+            shard.set_bloom_filter(bits_per_key);
+        }
+    }
+
+    /// Choose how much to sync to disk when finishing each shard.
+    pub fn set_durability(&mut self, durability: Durability) {
+        for shard in &mut self.shards {
+            shard.set_durability(durability);
+        }
+    }
+
+    /// Finish all the shards.
+    ///
+    /// Every shard is completely written before any is renamed into
+    /// place, so an error while writing leaves all the old files intact.
+    /// Only a failure partway through the renames can leave a mix of old
+    /// and new shards.
+    pub fn finish(mut self) -> Result<()> {
+        for shard in &mut self.shards {
+            shard.prepare()?;
+        }
+        for shard in &mut self.shards {
+            shard.publish()?;
+        }
+        Ok(())
+    }
+}
+
+/// A reader for a database split across several CDB files by
+/// [`GenericShardedCDBWriter`], generic over the on-disk [`Format`].
+///
+/// Lookups go to the one shard which can hold the key.
+///
+/// Most code should use the [`ShardedCDB`] or [`ShardedCDB64`] aliases.
+#[derive(Debug)]
+pub struct GenericShardedCDB<F> {
+    shards: Vec<GenericCDB<F>>,
+}
+
+/// Sharded CDB reader for the classic 32-bit format.
+pub type ShardedCDB = GenericShardedCDB<Format32>;
+
+/// Sharded CDB reader for the 64-bit format.
+pub type ShardedCDB64 = GenericShardedCDB<Format64>;
+
+impl<F: Format> GenericShardedCDB<F> {
+    /// Open the `shards` files named `base.0`, `base.1` and so on.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shards` is zero.
+    pub fn open<P: AsRef<Path>>(base: P, shards: usize) -> Result<Self> {
+        let base = base.as_ref();
+        let shards = (0..shards)
+            .map(|i| GenericCDB::open(shard_name(base, i)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::from_shards(shards))
+    }
+
+    /// Read from already opened shards, in shard order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no shards.
+    pub fn from_shards(shards: Vec<GenericCDB<F>>) -> Self {
+        assert!(!shards.is_empty(), "at least one shard is needed");
+        GenericShardedCDB { shards }
+    }
+
+    /// Return the shards.
+    pub fn shards(&self) -> &[GenericCDB<F>] {
+        &self.shards
+    }
+
+    /// Return the shard which holds `key`.
+    pub fn shard_for(&self, key: &[u8]) -> &GenericCDB<F> {
+        &self.shards[shard_index(key, self.shards.len())]
+    }
+
+    /// Find the first record with the named key.
+    pub fn get(&self, key: &[u8]) -> Option<Result<Vec<u8>>> {
+        self.shard_for(key).get(key)
+    }
+
+    /// Find all records with the named key.
+    pub fn find(&self, key: &[u8]) -> CDBValueIter<'_, F> {
+        self.shard_for(key).find(key)
+    }
+
+    /// Return `true` if a record with the named key exists.
+    pub fn exists(&self, key: &[u8]) -> Result<bool> {
+        self.shard_for(key).exists(key)
+    }
+
+    /// Return the total number of records in all shards.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(GenericCDB::len).sum()
+    }
+
+    /// Return `true` if no shard has any records.
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(GenericCDB::is_empty)
+    }
+
+    /// Iterate over all the `(key, value)` pairs of every shard in turn.
+    pub fn iter(&self) -> impl Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + '_ {
+        self.shards.iter().flat_map(GenericCDB::iter)
+    }
+}
@@ -0,0 +1,68 @@
+use bytes::Bytes;
+
+use crate::format::{Format, Format32};
+use crate::hash::{CdbHash, DjbHash};
+use crate::reader::{CDBValueIter, GenericCDB, Result};
+
+impl<F: Format, H: CdbHash> GenericCDB<F, H> {
+    /// Find the first record with the named key, returning its value as
+    /// [`Bytes`].
+    ///
+    /// When the database is memory-mapped or held in memory, the value
+    /// is not copied: the [`Bytes`] shares the memory with the reader and
+    /// keeps it alive, even after the reader is dropped. Otherwise, and
+    /// for compressed databases, the value is read into a new buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::CDB;
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// let value = cdb.get_bytes(b"two").unwrap()?;
+    /// drop(cdb);
+    /// assert_eq!(value, "Goodbye");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_bytes(&self, key: &[u8]) -> Option<Result<Bytes>> {
+        self.find_bytes(key).next()
+    }
+
+    /// Find all records with the named key, producing each value as
+    /// [`Bytes`]. See [`get_bytes`](Self::get_bytes).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::CDB;
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// let values = cdb.find_bytes(b"one").collect::<std::io::Result<Vec<_>>>()?;
+    /// assert_eq!(values, ["Hello", ", World!"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn find_bytes(&self, key: &[u8]) -> CDBBytesIter<'_, F, H> {
+        CDBBytesIter(self.find(key))
+    }
+}
+
+/// Iterator over a set of records in the CDB with the same key,
+/// producing each value as [`Bytes`].
+///
+/// See [`GenericCDB::find_bytes`]
+#[derive(Debug)]
+pub struct CDBBytesIter<'a, F = Format32, H = DjbHash>(CDBValueIter<'a, F, H>);
+
+impl<'a, F: Format, H: CdbHash> Iterator for CDBBytesIter<'a, F, H> {
+    type Item = Result<Bytes>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(err) = self.0.next_record()? {
+            return Some(Err(err));
+        }
+        Some(self.0.read_bytes())
+    }
+}
@@ -0,0 +1,125 @@
+use std::io;
+
+use sha2::{Digest, Sha256};
+
+use crate::ed25519::{SigningKey, VerifyingKey};
+use crate::error::{err_corrupt, Error};
+use crate::format::Format;
+use crate::hash::CdbHash;
+use crate::image::Storage;
+use crate::reader::{GenericCDB, Result};
+use crate::trailer::{self, SIGNATURE_MAGIC};
+
+// A signed database ends with a signature section, holding the algorithm
+// identifier, the public key and the signature. What is signed covers
+// the whole file before the section: the header and the bytes from the
+// first hash table on are hashed directly, and the records through the
+// content digest, which depends only on the keys and the plaintext
+// values.
+
+/// Identifier of Ed25519 in the signature section.
+pub(crate) const ED25519_ID: u8 = 1;
+
+const BODY_SIZE: u64 = 1 + 32 + 64;
+
+const DOMAIN: &[u8] = b"cdb32 signed database\0";
+
+/// Return the message signed, given the header, the content digest and
+/// the hash of everything from the first hash table to the signature
+/// section.
+pub(crate) fn message(header: &[u8], digest: &[u8; 32], tail: [u8; 32]) -> Vec<u8> {
+    [DOMAIN, header, digest, &tail].concat()
+}
+
+/// Return the body of the signature section over `message`.
+pub(crate) fn section_body(key: &SigningKey, message: &[u8]) -> Vec<u8> {
+    let mut body = vec![ED25519_ID];
+    body.extend_from_slice(&key.verifying_key().to_bytes());
+    body.extend_from_slice(&key.sign(message));
+    body
+}
+
+fn bad_signature<T>() -> Result<T> {
+    Err(Error::BadSignature.into())
+}
+
+impl<F: Format, H: CdbHash> GenericCDB<F, H> {
+    /// Check that the database was signed by `key`, as written after
+    /// `GenericCDBMake::set_signing_key`, and has not changed since.
+    ///
+    /// This reads the whole file. A signature which does not match, or
+    /// which was made by another key, gives an [`Error::BadSignature`],
+    /// and a file without a signature an error of kind
+    /// [`io::ErrorKind::InvalidData`]. For encrypted databases the reader
+    /// must have the encryption key, as the signature covers the
+    /// plaintext values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::{SigningKey, CDB};
+    ///
+    /// let key = SigningKey::from_seed([7; 32]);
+    /// let mut cdb = cdb32::CDBMake::in_memory()?;
+    /// cdb.set_signing_key(&key);
+    /// cdb.add(b"one", b"Hello")?;
+    /// let cdb = CDB::from_bytes(cdb.into_vec()?)?;
+    /// cdb.verify_signature(&key.verifying_key())?;
+    ///
+    /// let other = SigningKey::from_seed([8; 32]);
+    /// assert!(cdb.verify_signature(&other.verifying_key()).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn verify_signature(&self, key: &VerifyingKey) -> Result<()> {
+        let sections = trailer::sections(self)?;
+        let section = match sections.first() {
+            Some(section) if section.magic == SIGNATURE_MAGIC => *section,
+            // Anything appended after the signature is not covered by it.
+            _ if sections.iter().any(|s| s.magic == SIGNATURE_MAGIC) => return bad_signature(),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Database is not signed",
+                ))
+            }
+        };
+        let body = self.read_vec(section.pos, section.len)?;
+        if body.first() != Some(&ED25519_ID) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("Unsupported signature {}", body.first().unwrap_or(&0)),
+            ));
+        }
+        if section.len != BODY_SIZE {
+            return err_corrupt(section.pos, "signature section has the wrong length");
+        }
+        if body[1..33] != key.to_bytes() {
+            return bad_signature();
+        }
+
+        let data_end = self.data_end();
+        if data_end > section.pos {
+            return bad_signature();
+        }
+        let mut tail = Sha256::new();
+        let mut buf = vec![0; 1 << 16];
+        let mut pos = data_end;
+        while pos < section.pos {
+            let n = (section.pos - pos).min(buf.len() as u64) as usize;
+            self.read(&mut buf[..n], pos)?;
+            tail.update(&buf[..n]);
+            pos += n as u64;
+        }
+        let message = message(
+            Storage::header(self),
+            &self.digest()?,
+            tail.finalize().into(),
+        );
+        if !key.verify(&message, body[33..].try_into().unwrap()) {
+            return bad_signature();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,558 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io;
+use std::ops::{Bound, RangeBounds};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[cfg(not(target_arch = "wasm32"))]
+use memmap2::Mmap;
+
+use crate::error::err_corrupt;
+use crate::format::{Format, Format32};
+use crate::hash::{CdbHash, DjbHash};
+use crate::image::{Scan, Storage};
+use crate::reader::{GenericCDB, Result};
+use crate::writer::{replace_file, Durability};
+
+const MAGIC: &[u8; 8] = b"CDBSORT1";
+
+/// Size of the header: the magic, the size of the database and the
+/// number of entries.
+const HEADER_SIZE: usize = 24;
+
+/// Size of the fixed part of an entry: the record position and the key
+/// length.
+const ENTRY_SIZE: usize = 12;
+
+/// Return the path of the sorted index of the database at `path`, which
+/// is the same path with `.sorted` appended.
+///
+/// # Examples
+///
+/// ```
+/// use std::path::Path;
+///
+/// assert_eq!(
+///     cdb32::sorted_index_path("data/users.cdb"),
+///     Path::new("data/users.cdb.sorted")
+/// );
+/// ```
+pub fn sorted_index_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut path = path.as_ref().as_os_str().to_owned();
+    path.push(".sorted");
+    path.into()
+}
+
+#[derive(Clone, Debug)]
+enum Data {
+    #[cfg(not(target_arch = "wasm32"))]
+    Mmap(Arc<Mmap>),
+    Memory(Arc<Vec<u8>>),
+}
+
+/// The keys of a database in sorted order, kept in a file of its own next
+/// to the database so that the database itself is unchanged.
+///
+/// A CDB stores its records in the order they were added and finds them
+/// by hash, so it cannot list its keys in order. The sorted index holds
+/// every key with the position of its record, sorted by key and then by
+/// position, which is what [`iter_sorted`](GenericCDB::iter_sorted) and
+/// the prefix and range scans need. It is written at build time with
+/// [`GenericCDBWriter::set_sorted_index`](crate::GenericCDBWriter::set_sorted_index),
+/// or later with [`build`](Self::build) and [`write`](Self::write), and
+/// attached when opening with
+/// [`OpenOptions::sorted_index`](crate::OpenOptions::sorted_index).
+///
+/// The file is named by [`sorted_index_path`]. It records the size of
+/// the database it was built from, and an index which does not match
+/// its database is refused.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// # let tmp_dir = tempfile::tempdir()?;
+/// # let path = tmp_dir.path().join("fruit.cdb");
+/// use cdb32::{CDBWriter, OpenOptions, CDB};
+///
+/// let mut writer = CDBWriter::create(&path)?;
+/// writer.set_sorted_index(true);
+/// writer.add(b"pear", b"green")?;
+/// writer.add(b"apple", b"red")?;
+/// writer.finish()?;
+///
+/// let cdb = CDB::open_with_options(&path, OpenOptions::new().sorted_index(true))?;
+/// let keys = cdb
+///     .iter_sorted()
+///     .map(|record| record.map(|(key, _)| key))
+///     .collect::<std::io::Result<Vec<_>>>()?;
+/// assert_eq!(keys, [&b"apple"[..], b"pear"]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SortedIndex {
+    data: Data,
+    db_size: u64,
+    len: usize,
+}
+
+impl SortedIndex {
+    /// Build the sorted index of `cdb` in memory.
+    pub fn build<F: Format, H: CdbHash>(cdb: &GenericCDB<F, H>) -> Result<Self> {
+        let mut entries = Vec::new();
+        let mut scan = Scan::start(cdb);
+        loop {
+            let pos = scan.pos();
+            match scan.next(cdb) {
+                Some(record) => {
+                    let (kpos, klen, _, _) = record?;
+                    entries.push((cdb.read_vec(kpos, klen)?, pos));
+                }
+                None => break,
+            }
+        }
+        entries.sort_unstable();
+
+        let keys = entries.iter().map(|(key, _)| key.len()).sum::<usize>();
+        let table = HEADER_SIZE + entries.len() * 8;
+        let mut data = Vec::with_capacity(table + entries.len() * ENTRY_SIZE + keys);
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&cdb.size().to_le_bytes());
+        data.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+        let mut offset = table;
+        for (key, _) in &entries {
+            data.extend_from_slice(&(offset as u64).to_le_bytes());
+            offset += ENTRY_SIZE + key.len();
+        }
+        for (key, pos) in &entries {
+            let klen = u32::try_from(key.len())
+                .or_else(|_| err_corrupt(*pos, "key too long for a sorted index"))?;
+            data.extend_from_slice(&pos.to_le_bytes());
+            data.extend_from_slice(&klen.to_le_bytes());
+            data.extend_from_slice(key);
+        }
+        Self::load(Data::Memory(Arc::new(data)))
+    }
+
+    /// Open the sorted index at `path`, which is memory-mapped.
+    ///
+    /// Returns an [`Error::Corrupt`](crate::Error::Corrupt) if the file is
+    /// not a valid sorted index.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path)?;
+        #[cfg(not(target_arch = "wasm32"))]
+        let data = {
+            if file.metadata()?.len() < HEADER_SIZE as u64 {
+                return err_corrupt(0, "sorted index is too short");
+            }
+            Data::Mmap(Arc::new(unsafe { Mmap::map(&file)? }))
+        };
+        #[cfg(target_arch = "wasm32")]
+        let data = {
+            let mut data = Vec::new();
+            io::Read::read_to_end(&mut &file, &mut data)?;
+            Data::Memory(Arc::new(data))
+        };
+        Self::load(data)
+    }
+
+    /// Check the layout of `data`, so that later reads of it need no
+    /// checks.
+    fn load(data: Data) -> Result<Self> {
+        let mut index = SortedIndex {
+            data,
+            db_size: 0,
+            len: 0,
+        };
+        let bytes = index.bytes();
+        if bytes.len() < HEADER_SIZE || &bytes[..8] != MAGIC {
+            return err_corrupt(0, "not a sorted index");
+        }
+        let db_size = read_u64(bytes, 8);
+        let len = read_u64(bytes, 16);
+        let table_end = (len.checked_mul(8))
+            .and_then(|table| table.checked_add(HEADER_SIZE as u64))
+            .filter(|&end| end <= bytes.len() as u64);
+        let Some(table_end) = table_end else {
+            return err_corrupt(16, "sorted index entry count is too large");
+        };
+        let mut prev: Option<(&[u8], u64)> = None;
+        for i in 0..len as usize {
+            let at = HEADER_SIZE + i * 8;
+            let offset = read_u64(bytes, at);
+            if offset < table_end || offset > (bytes.len() - ENTRY_SIZE) as u64 {
+                return err_corrupt(at as u64, "sorted index entry is out of bounds");
+            }
+            let offset = offset as usize;
+            let klen = read_u32(bytes, offset + 8) as usize;
+            let Some(key) = bytes.get(offset + ENTRY_SIZE..offset + ENTRY_SIZE + klen) else {
+                return err_corrupt(offset as u64, "sorted index key is out of bounds");
+            };
+            let entry = (key, read_u64(bytes, offset));
+            if prev.is_some_and(|prev| prev > entry) {
+                return err_corrupt(offset as u64, "sorted index is not in order");
+            }
+            prev = Some(entry);
+        }
+        index.db_size = db_size;
+        index.len = len as usize;
+        Ok(index)
+    }
+
+    /// Write the index to `path`, through a temporary file renamed into
+    /// place.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.write_durable(path.as_ref(), Durability::default())
+    }
+
+    pub(crate) fn write_durable(&self, path: &Path, durability: Durability) -> Result<()> {
+        replace_file(path, self.bytes(), durability)
+    }
+
+    /// Return the size of the database the index was built from.
+    pub fn db_size(&self) -> u64 {
+        self.db_size
+    }
+
+    /// Return the number of keys in the index, which is the number of
+    /// records in the database.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return `true` if the index holds no keys.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match &self.data {
+            #[cfg(not(target_arch = "wasm32"))]
+            Data::Mmap(map) => map,
+            Data::Memory(data) => data,
+        }
+    }
+
+    /// Return the key and record position of entry `i`.
+    pub(crate) fn entry(&self, i: usize) -> (&[u8], u64) {
+        let bytes = self.bytes();
+        let offset = read_u64(bytes, HEADER_SIZE + i * 8) as usize;
+        let klen = read_u32(bytes, offset + 8) as usize;
+        let key = &bytes[offset + ENTRY_SIZE..offset + ENTRY_SIZE + klen];
+        (key, read_u64(bytes, offset))
+    }
+
+    /// Return the number of leading entries whose keys satisfy `pred`,
+    /// which must hold for a prefix of the keys in order.
+    pub(crate) fn partition_point<P: Fn(&[u8]) -> bool>(&self, pred: P) -> usize {
+        let (mut lo, mut hi) = (0, self.len);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if pred(self.entry(mid).0) {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+impl<F: Format, H: CdbHash> GenericCDB<F, H> {
+    /// Attach a sorted index to the database, to be used by
+    /// [`iter_sorted`](Self::iter_sorted).
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidData`] if the
+    /// index was not built from this database.
+    pub fn set_sorted_index(&mut self, index: SortedIndex) -> Result<()> {
+        if index.db_size != self.size() || index.len != self.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Sorted index is out of date",
+            ));
+        }
+        self.sorted = Some(index);
+        Ok(())
+    }
+
+    /// Return the attached sorted index, if any.
+    pub fn sorted_index(&self) -> Option<&SortedIndex> {
+        self.sorted.as_ref()
+    }
+
+    /// Iterate over all the `(key, value)` pairs in the database in order
+    /// of their keys, and records with the same key in the order they were
+    /// added.
+    ///
+    /// Without an attached [`SortedIndex`] the index is built in memory
+    /// first, which reads every key; an error doing so is returned as the
+    /// first item.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::CDB;
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// for result in cdb.iter_sorted() {
+    ///     let (key, value) = result?;
+    ///     println!("{:?} => {:?}", key, value);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn iter_sorted(&self) -> CDBSortedIter<'_, F, H> {
+        match self.sorted_or_build() {
+            Ok(index) => CDBSortedIter::new(self, index, 0, usize::MAX),
+            Err(err) => CDBSortedIter::failed(self, err),
+        }
+    }
+
+    /// Find all records whose key starts with `prefix`.
+    ///
+    /// With an attached [`SortedIndex`] the matching keys are found by
+    /// binary search and returned in order, as by
+    /// [`iter_sorted`](Self::iter_sorted). Otherwise every record is
+    /// scanned, and the matching ones are returned in the order they were
+    /// added.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::{CDBMake, CDB};
+    ///
+    /// let mut cdb = CDBMake::in_memory()?;
+    /// cdb.add(b"example.com/www", b"10.0.0.1")?;
+    /// cdb.add(b"example.org/www", b"10.0.0.2")?;
+    /// cdb.add(b"example.com/mail", b"10.0.0.3")?;
+    /// let cdb = CDB::from_bytes(cdb.into_vec()?)?;
+    ///
+    /// let hosts = cdb.find_prefix(b"example.com/").collect::<std::io::Result<Vec<_>>>()?;
+    /// assert_eq!(hosts.len(), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn find_prefix(&self, prefix: &[u8]) -> CDBPrefixIter<'_, F, H> {
+        let inner = match &self.sorted {
+            Some(index) => {
+                let start = index.partition_point(|key| key < prefix);
+                let end = index.partition_point(|key| key < prefix || key.starts_with(prefix));
+                PrefixInner::Sorted(CDBSortedIter::new(self, Cow::Borrowed(index), start, end))
+            }
+            None => PrefixInner::Scan(Scan::start(self)),
+        };
+        CDBPrefixIter {
+            cdb: self,
+            prefix: prefix.to_vec(),
+            inner,
+        }
+    }
+
+    /// Iterate over the records whose keys fall within `range`, in order
+    /// of their keys as by [`iter_sorted`](Self::iter_sorted).
+    ///
+    /// Keys are compared as byte strings. The ends of the range are found
+    /// by binary search in the attached [`SortedIndex`], which is built
+    /// in memory first if there is none; an error doing so is returned as
+    /// the first item.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::{CDBMake, CDB};
+    ///
+    /// let mut cdb = CDBMake::in_memory()?;
+    /// for day in ["2024-01-30", "2024-02-01", "2024-02-14", "2024-03-01"] {
+    ///     cdb.add(day, b"")?;
+    /// }
+    /// let cdb = CDB::from_bytes(cdb.into_vec()?)?;
+    ///
+    /// let february = cdb.range("2024-02".."2024-03").collect::<std::io::Result<Vec<_>>>()?;
+    /// assert_eq!(february.len(), 2);
+    /// assert_eq!(cdb.range("2024-02-14"..).count(), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn range<K, R>(&self, range: R) -> CDBSortedIter<'_, F, H>
+    where
+        K: AsRef<[u8]> + ?Sized,
+        R: RangeBounds<K>,
+    {
+        let index = match self.sorted_or_build() {
+            Ok(index) => index,
+            Err(err) => return CDBSortedIter::failed(self, err),
+        };
+        let start = match range.start_bound() {
+            Bound::Included(start) => index.partition_point(|key| key < start.as_ref()),
+            Bound::Excluded(start) => index.partition_point(|key| key <= start.as_ref()),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => index.partition_point(|key| key <= end.as_ref()),
+            Bound::Excluded(end) => index.partition_point(|key| key < end.as_ref()),
+            Bound::Unbounded => index.len(),
+        };
+        CDBSortedIter::new(self, index, start, end)
+    }
+
+    /// Return the attached sorted index, or build one.
+    pub(crate) fn sorted_or_build(&self) -> Result<Cow<'_, SortedIndex>> {
+        match &self.sorted {
+            Some(index) => Ok(Cow::Borrowed(index)),
+            None => SortedIndex::build(self).map(Cow::Owned),
+        }
+    }
+}
+
+/// Iterator over the records of the CDB in order of their keys.
+///
+/// See [`GenericCDB::iter_sorted`] and [`GenericCDB::range`].
+#[derive(Debug)]
+pub struct CDBSortedIter<'a, F = Format32, H = DjbHash> {
+    cdb: &'a GenericCDB<F, H>,
+    index: Option<Cow<'a, SortedIndex>>,
+    start: usize,
+    end: usize,
+    error: Option<io::Error>,
+}
+
+impl<'a, F: Format, H: CdbHash> CDBSortedIter<'a, F, H> {
+    /// Iterate over entries `start..end` of `index`, with `end` clamped
+    /// to its length.
+    pub(crate) fn new(
+        cdb: &'a GenericCDB<F, H>,
+        index: Cow<'a, SortedIndex>,
+        start: usize,
+        end: usize,
+    ) -> Self {
+        let end = end.min(index.len());
+        CDBSortedIter {
+            cdb,
+            index: Some(index),
+            start: start.min(end),
+            end,
+            error: None,
+        }
+    }
+
+    /// Return only `err`.
+    pub(crate) fn failed(cdb: &'a GenericCDB<F, H>, err: io::Error) -> Self {
+        CDBSortedIter {
+            cdb,
+            index: None,
+            start: 0,
+            end: 0,
+            error: Some(err),
+        }
+    }
+
+    /// Read the record of entry `i`.
+    fn record(&self, i: usize) -> Result<(Vec<u8>, Vec<u8>)> {
+        let (key, pos) = self.index.as_ref().unwrap().entry(i);
+        let mut scan = Scan::range(pos, self.cdb.data_end());
+        let (kpos, klen, dpos, dlen) = match scan.next(self.cdb) {
+            Some(record) => record?,
+            None => return err_corrupt(pos, "sorted index points past the records"),
+        };
+        if self.cdb.read_vec(kpos, klen)? != key {
+            return err_corrupt(pos, "sorted index does not match the record");
+        }
+        let value = self.cdb.read_value(key, dpos, dlen)?;
+        Ok((key.to_vec(), value))
+    }
+}
+
+impl<'a, F: Format, H: CdbHash> Iterator for CDBSortedIter<'a, F, H> {
+    type Item = Result<(Vec<u8>, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(err) = self.error.take() {
+            return Some(Err(err));
+        }
+        if self.start == self.end {
+            return None;
+        }
+        self.start += 1;
+        Some(self.record(self.start - 1))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.start + self.error.is_some() as usize;
+        (len, Some(len))
+    }
+}
+
+impl<'a, F: Format, H: CdbHash> DoubleEndedIterator for CDBSortedIter<'a, F, H> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if let Some(err) = self.error.take() {
+            return Some(Err(err));
+        }
+        if self.start == self.end {
+            return None;
+        }
+        self.end -= 1;
+        Some(self.record(self.end))
+    }
+}
+
+impl<'a, F: Format, H: CdbHash> ExactSizeIterator for CDBSortedIter<'a, F, H> {}
+
+/// Iterator over the records of the CDB whose keys start with a prefix.
+///
+/// See [`GenericCDB::find_prefix`].
+#[derive(Debug)]
+pub struct CDBPrefixIter<'a, F = Format32, H = DjbHash> {
+    cdb: &'a GenericCDB<F, H>,
+    prefix: Vec<u8>,
+    inner: PrefixInner<'a, F, H>,
+}
+
+#[derive(Debug)]
+enum PrefixInner<'a, F, H> {
+    Sorted(CDBSortedIter<'a, F, H>),
+    Scan(Scan),
+}
+
+impl<'a, F: Format, H: CdbHash> Iterator for CDBPrefixIter<'a, F, H> {
+    type Item = Result<(Vec<u8>, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let scan = match &mut self.inner {
+            PrefixInner::Sorted(iter) => return iter.next(),
+            PrefixInner::Scan(scan) => scan,
+        };
+        loop {
+            let (kpos, klen, dpos, dlen) = match scan.next(self.cdb)? {
+                Ok(record) => record,
+                Err(err) => return Some(Err(err)),
+            };
+            if klen < self.prefix.len() as u64 {
+                continue;
+            }
+            let key = match self.cdb.read_vec(kpos, klen) {
+                Ok(key) => key,
+                Err(err) => return Some(Err(err)),
+            };
+            if key.starts_with(&self.prefix) {
+                return Some(
+                    self.cdb
+                        .read_value(&key, dpos, dlen)
+                        .map(|value| (key, value)),
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::fs;
+use std::io::{self, prelude::*};
+use std::path::{Path, PathBuf};
+
+use crate::bloom;
+use crate::reader::Result;
+use crate::writer::{create_exclusive, HashPos};
+
+// A hash entry as spilled: the key hash then the record position, both
+// little endian.
+const ENTRY_SIZE: usize = 12;
+
+/// The hash entries of a CDB being built, kept in a temporary file rather
+/// than in memory.
+///
+/// Finishing needs the entries of each hash table in the order they were
+/// added, so the file is read once per batch of tables, holding at most
+/// `max_entries` in memory at a time (or one whole table, if larger).
+#[derive(Debug)]
+pub(crate) struct Spill {
+    path: PathBuf,
+    file: io::BufWriter<fs::File>,
+    counts: Vec<usize>,
+    max_entries: usize,
+}
+
+impl Spill {
+    /// Create a temporary file for the entries in `dir`.
+    pub(crate) fn new(dir: &Path, max_entries: usize) -> Result<Self> {
+        let (path, file) = create_exclusive(dir, ".cdb-entries.XXXXXX")?;
+        Ok(Spill {
+            path,
+            file: io::BufWriter::new(file),
+            counts: vec![0; 256],
+            max_entries,
+        })
+    }
+
+    pub(crate) fn push(&mut self, entry: HashPos) -> Result<()> {
+        let mut buf = [0_u8; ENTRY_SIZE];
+        buf[..4].copy_from_slice(&entry.hash.to_le_bytes());
+        buf[4..].copy_from_slice(&entry.pos.to_le_bytes());
+        self.file.write_all(&buf)?;
+        self.counts[(entry.hash & 0xff) as usize] += 1;
+        Ok(())
+    }
+
+    /// Return the number of entries in each hash table.
+    pub(crate) fn counts(&self) -> &[usize] {
+        &self.counts
+    }
+
+    /// Read every entry back in the order they were added, passing each
+    /// to `f`.
+    fn scan<G: FnMut(HashPos)>(&mut self, mut f: G) -> Result<()> {
+        self.file.flush()?;
+        let mut reader = io::BufReader::new(self.file.get_ref());
+        reader.seek(io::SeekFrom::Start(0))?;
+        let mut buf = [0_u8; ENTRY_SIZE];
+        for _ in 0..self.counts.iter().sum::<usize>() {
+            reader.read_exact(&mut buf)?;
+            f(HashPos {
+                hash: u32::from_le_bytes(buf[..4].try_into().unwrap()),
+                pos: u64::from_le_bytes(buf[4..].try_into().unwrap()),
+            });
+        }
+        Ok(())
+    }
+
+    /// Load the entries of the tables from `start` on into `entries`, as
+    /// many tables as fit, returning the index of the first table not
+    /// loaded.
+    pub(crate) fn load(&mut self, start: usize, entries: &mut [Vec<HashPos>]) -> Result<usize> {
+        let mut end = start + 1;
+        let mut total = self.counts[start];
+        while end < 256 && total + self.counts[end] <= self.max_entries {
+            total += self.counts[end];
+            end += 1;
+        }
+        for (table, &count) in entries[start..end].iter_mut().zip(&self.counts[start..end]) {
+            table.reserve_exact(count);
+        }
+        self.scan(|entry| {
+            let i = (entry.hash & 0xff) as usize;
+            if (start..end).contains(&i) {
+                entries[i].push(entry);
+            }
+        })?;
+        Ok(end)
+    }
+
+    /// Build a bloom filter section body over the spilled key hashes.
+    pub(crate) fn bloom(&mut self, count: usize, bits_per_key: u32) -> Result<Vec<u8>> {
+        let mut body = bloom::empty(count, bits_per_key);
+        self.scan(|entry| bloom::insert(&mut body, entry.hash))?;
+        Ok(body)
+    }
+}
+
+impl Drop for Spill {
+    #[allow(unused_must_use)]
+    fn drop(&mut self) {
+        fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,93 @@
+use crate::error::err_corrupt;
+use crate::format::Format;
+use crate::hash::CdbHash;
+use crate::image::Storage;
+use crate::reader::{GenericCDB, Result};
+
+/// Statistics about the layout of a CDB file.
+///
+/// See [`GenericCDB::stats`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct CDBStats {
+    /// Number of records reachable through the hash tables.
+    pub records: u64,
+    /// Size in bytes of the data section holding the records.
+    pub data_size: u64,
+    /// Number of slots in each of the 256 hash tables.
+    pub table_slots: Vec<u64>,
+    /// Largest number of slots examined to reach any record.
+    pub max_probe: u64,
+    /// Average number of slots examined to reach a record.
+    pub avg_probe: f64,
+    /// Fraction of all hash table slots which are in use.
+    pub fill_factor: f64,
+}
+
+impl<F: Format, H: CdbHash> GenericCDB<F, H> {
+    /// Gather statistics about the hash tables and data section, to help
+    /// diagnose badly distributed keys or badly built files.
+    ///
+    /// This reads every hash table slot but none of the records.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::CDB;
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// let stats = cdb.stats()?;
+    /// println!("{} records, longest probe {}", stats.records, stats.max_probe);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stats(&self) -> Result<CDBStats> {
+        let pair = F::PAIR_SIZE as u64;
+        let mut buf = [0_u8; 16];
+        let buf = &mut buf[..F::PAIR_SIZE];
+
+        let mut records = 0_u64;
+        let mut total_slots = 0_u64;
+        let mut total_probe = 0_u64;
+        let mut max_probe = 0_u64;
+        let mut table_slots = Vec::with_capacity(256);
+        for i in 0..256 {
+            let (hpos, hslots) = self.table(i);
+            table_slots.push(hslots);
+            total_slots = total_slots.saturating_add(hslots);
+            for slot in 0..hslots {
+                let spos = match slot.checked_mul(pair).and_then(|off| hpos.checked_add(off)) {
+                    Some(spos) => spos,
+                    None => return err_corrupt(hpos, "hash table overflows"),
+                };
+                self.read(buf, spos)?;
+                let (khash, pos) = F::unpack2(buf);
+                if pos == 0 {
+                    continue;
+                }
+                let start = (khash >> 8) % hslots;
+                let probe = (slot + hslots - start) % hslots + 1;
+                records += 1;
+                total_probe += probe;
+                max_probe = max_probe.max(probe);
+            }
+        }
+
+        Ok(CDBStats {
+            records,
+            data_size: self.data_end().saturating_sub(F::HEADER_SIZE as u64),
+            table_slots,
+            max_probe,
+            avg_probe: if records > 0 {
+                total_probe as f64 / records as f64
+            } else {
+                0.0
+            },
+            fill_factor: if total_slots > 0 {
+                records as f64 / total_slots as f64
+            } else {
+                0.0
+            },
+        })
+    }
+}
@@ -0,0 +1,275 @@
+//! The text format of D. J. Bernstein's `cdbmake` tool.
+//!
+//! Each record is written as `+klen,dlen:key->data` followed by a
+//! newline, where `klen` and `dlen` are the lengths of the key and data
+//! in decimal, and the input ends with an empty line. Keys and data may
+//! hold any bytes, including newlines. [`records`] parses this format,
+//! and [`GenericCDBMake::add_from_text`] adds the records it finds, so
+//! existing pipelines which produce `cdbmake` input can build databases
+//! with this crate.
+//!
+//! The `cdbdump` tool writes the same format, as does
+//! [`GenericCDB::dump_to`], so its output can be compared with that of
+//! the original tools or fed back into them.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> std::io::Result<()> {
+//! use cdb32::{CDBMake, CDB};
+//!
+//! let input = b"+3,5:one->Hello\n+3,7:two->Goodbye\n\n";
+//! let mut cdb = CDBMake::in_memory()?;
+//! assert_eq!(cdb.add_from_text(&input[..])?, 2);
+//! let cdb = CDB::from_bytes(cdb.into_vec()?)?;
+//! assert_eq!(cdb.get(b"two").unwrap()?, b"Goodbye");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::{self, BufRead, Read, Seek, Write};
+
+use crate::format::Format;
+use crate::hash::CdbHash;
+use crate::reader::{GenericCDB, Result};
+use crate::writer::{GenericCDBMake, GenericCDBWriter};
+
+/// Iterator over the `(key, data)` records of `cdbmake` input, created by
+/// [`records`].
+///
+/// Iteration stops at the empty line ending the input, leaving anything
+/// after it unread. Malformed input, including input which ends without
+/// the empty line, is reported as an error, after which the iterator
+/// returns nothing more.
+#[derive(Debug)]
+pub struct Records<R> {
+    reader: R,
+    record: u64,
+    done: bool,
+}
+
+/// Parse `cdbmake` input from `reader`.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// let input = b"+1,2:a->bc\n+0,0:->\n\n";
+/// let records = cdb32::text::records(&input[..]).collect::<std::io::Result<Vec<_>>>()?;
+/// assert_eq!(records, [(b"a".to_vec(), b"bc".to_vec()), (vec![], vec![])]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn records<R: BufRead>(reader: R) -> Records<R> {
+    Records {
+        reader,
+        record: 0,
+        done: false,
+    }
+}
+
+impl<R: BufRead> Records<R> {
+    fn err_format<T>(&self, expected: &str) -> Result<T> {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Malformed cdbmake input in record {}: expected {}",
+                self.record + 1,
+                expected
+            ),
+        ))
+    }
+
+    fn err_eof<T>(&self) -> Result<T> {
+        Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!(
+                "Truncated cdbmake input in record {}: missing end of input",
+                self.record + 1
+            ),
+        ))
+    }
+
+    fn read_byte(&mut self) -> Result<Option<u8>> {
+        let mut byte = [0];
+        match self.reader.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    }
+
+    fn expect(&mut self, expected: &[u8]) -> Result<()> {
+        for &want in expected {
+            match self.read_byte()? {
+                Some(byte) if byte == want => {}
+                Some(_) => return self.err_format(&format!("{:?}", want as char)),
+                None => return self.err_eof(),
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a decimal length ended by `end`.
+    fn read_len(&mut self, end: u8) -> Result<u64> {
+        let mut len = None::<u64>;
+        loop {
+            match self.read_byte()? {
+                Some(byte @ b'0'..=b'9') => {
+                    let digit = (byte - b'0') as u64;
+                    len = match len.unwrap_or(0).checked_mul(10) {
+                        Some(len) => len.checked_add(digit),
+                        None => None,
+                    };
+                    if len.is_none() {
+                        return self.err_format("a smaller length");
+                    }
+                }
+                Some(byte) if byte == end => match len {
+                    Some(len) => return Ok(len),
+                    None => return self.err_format("a length"),
+                },
+                Some(_) => return self.err_format(&format!("a digit or {:?}", end as char)),
+                None => return self.err_eof(),
+            }
+        }
+    }
+
+    fn read_bytes(&mut self, len: u64) -> Result<Vec<u8>> {
+        // Read through `take` rather than allocating `len` bytes up front,
+        // so a bogus length fails at the end of the input.
+        let mut buf = Vec::new();
+        if (&mut self.reader).take(len).read_to_end(&mut buf)? as u64 != len {
+            return self.err_eof();
+        }
+        Ok(buf)
+    }
+
+    fn read_record(&mut self) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+        match self.read_byte()? {
+            Some(b'+') => {}
+            Some(b'\n') => return Ok(None),
+            Some(_) => return self.err_format("'+' or the empty line ending the input"),
+            None => return self.err_eof(),
+        }
+        let klen = self.read_len(b',')?;
+        let dlen = self.read_len(b':')?;
+        let key = self.read_bytes(klen)?;
+        self.expect(b"->")?;
+        let data = self.read_bytes(dlen)?;
+        self.expect(b"\n")?;
+        self.record += 1;
+        Ok(Some((key, data)))
+    }
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = Result<(Vec<u8>, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let record = self.read_record().transpose();
+        if !matches!(record, Some(Ok(_))) {
+            self.done = true;
+        }
+        record
+    }
+}
+
+/// Write one record in `cdbmake` format.
+pub fn write_record<W: Write>(mut writer: W, key: &[u8], data: &[u8]) -> Result<()> {
+    write!(writer, "+{},{}:", key.len(), data.len())?;
+    writer.write_all(key)?;
+    writer.write_all(b"->")?;
+    writer.write_all(data)?;
+    writer.write_all(b"\n")
+}
+
+impl<F: Format, H: CdbHash> GenericCDB<F, H> {
+    /// Write every record in the database to `writer` in the format of
+    /// `cdbdump`, returning how many were written. See the
+    /// [`text`](crate::text) module.
+    ///
+    /// Records are written in the order they are stored, followed by the
+    /// empty line ending the input, exactly as `cdbdump` writes them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let cdb = cdb32::CDB::open("tests/test1.cdb")?;
+    /// let mut out = Vec::new();
+    /// cdb.dump_to(&mut out)?;
+    /// assert!(out.starts_with(b"+3,5:one->Hello\n"));
+    /// assert!(out.ends_with(b"\n\n"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn dump_to<W: Write>(&self, mut writer: W) -> Result<u64> {
+        let mut count = 0;
+        for record in self.iter() {
+            let (key, data) = record?;
+            write_record(&mut writer, &key, &data)?;
+            count += 1;
+        }
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        Ok(count)
+    }
+}
+
+impl<F: Format, H: CdbHash, W: Write + Seek> GenericCDBMake<F, H, W> {
+    /// Add the records of `cdbmake` input read from `reader`, returning
+    /// how many were added. See the [`text`](crate::text) module.
+    ///
+    /// Records before any malformed input are still added.
+    pub fn add_from_text<R: BufRead>(&mut self, reader: R) -> Result<u64> {
+        let mut count = 0;
+        for record in records(reader) {
+            let (key, data) = record?;
+            self.add(key, data)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+impl<F: Format, H: CdbHash> GenericCDBWriter<F, H> {
+    /// Add the records of `cdbmake` input read from `reader`, returning
+    /// how many were added. See [`GenericCDBMake::add_from_text`].
+    pub fn add_from_text<R: BufRead>(&mut self, reader: R) -> Result<u64> {
+        let mut count = 0;
+        for record in records(reader) {
+            let (key, data) = record?;
+            self.add(key, data)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+#[test]
+fn malformed() {
+    let parse = |input: &[u8]| records(input).collect::<Result<Vec<_>>>();
+    assert_eq!(parse(b"\n").unwrap(), []);
+    assert_eq!(
+        parse(b"+2,3:a\n->b\nc\n\nignored").unwrap(),
+        [(b"a\n".to_vec(), b"b\nc".to_vec())]
+    );
+    for input in [
+        &b""[..],
+        b"+1,1:a->b\n",
+        b"+1,1:a->b",
+        b"+1,2:a->b\n\n",
+        b"+1,1:a=>b\n\n",
+        b"+,1:->b\n\n",
+        b"+1;1:a->b\n\n",
+        b"+99999999999999999999,1:a->b\n\n",
+        b"-1,1:a->b\n\n",
+    ] {
+        assert!(parse(input).is_err(), "{:?}", input);
+    }
+    let err = parse(b"+1,1:a->b\n").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
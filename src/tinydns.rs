@@ -0,0 +1,533 @@
+//! Import of `tinydns-data` files, for D. J. Bernstein's DNS server.
+//!
+//! `tinydns` answers queries from `data.cdb`, which the `tinydns-data`
+//! tool builds from a text file named `data`. [`records`] parses that
+//! text file and produces the same records, in the same order, so
+//! [`build`] writes a `data.cdb` identical to the one `tinydns-data`
+//! would write, and [`GenericCDBMake::add_from_tinydns`] adds the records
+//! to any database.
+//!
+//! Every line type of `tinydns-data` is supported: `%` locations, `Z`
+//! SOA records, `.` and `&` name servers, `=` and `+` addresses, `@` mail
+//! exchangers, `'` text, `^` pointers, `C` aliases and `:` records of any
+//! other type, along with `#` comments and `-` disabled lines. Each record
+//! is stored under the lower-cased wire format of its owner name, with a
+//! value made of its type, a location marker, its TTL, an 8 byte
+//! timestamp and its data.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> std::io::Result<()> {
+//! use cdb32::{CDBMake, CDB};
+//!
+//! let data = b"=www.example.com:192.0.2.1:300\n";
+//! let mut cdb = CDBMake::in_memory()?;
+//! assert_eq!(cdb.add_from_tinydns(&data[..], 1)?, 2);
+//! let cdb = CDB::from_bytes(cdb.into_vec()?)?;
+//!
+//! let a = cdb.get(b"\x03www\x07example\x03com\x00").unwrap()?;
+//! assert_eq!(a[..3], *b"\x00\x01=");
+//! assert_eq!(a[a.len() - 4..], [192, 0, 2, 1]);
+//! assert!(cdb.exists(b"\x011\x012\x010\x03192\x07in-addr\x04arpa\x00")?);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Seek, Write};
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::format::Format;
+use crate::hash::CdbHash;
+use crate::reader::Result;
+use crate::writer::{CDBWriter, GenericCDBMake, GenericCDBWriter};
+
+const TYPE_A: u16 = 1;
+const TYPE_NS: u16 = 2;
+const TYPE_CNAME: u16 = 5;
+const TYPE_SOA: u16 = 6;
+const TYPE_PTR: u16 = 12;
+const TYPE_MX: u16 = 15;
+const TYPE_TXT: u16 = 16;
+const TYPE_AXFR: u16 = 252;
+
+const TTL_NS: u32 = 259200;
+const TTL_POSITIVE: u32 = 86400;
+const TTL_NEGATIVE: u32 = 2560;
+
+/// The number of `:` separated fields after the leading character.
+const FIELDS: usize = 15;
+
+/// Iterator over the `(key, value)` records of a `tinydns-data` file,
+/// created by [`records`].
+///
+/// Malformed input is reported as an error, after which the iterator
+/// returns nothing more.
+#[derive(Debug)]
+pub struct Records<R> {
+    reader: R,
+    soa: [u8; 20],
+    line: u64,
+    pending: VecDeque<(Vec<u8>, Vec<u8>)>,
+    done: bool,
+}
+
+/// Parse a `tinydns-data` file from `reader`.
+///
+/// `serial` is the serial number of SOA records which do not give one,
+/// for which `tinydns-data` uses the modification time of the file; as
+/// there, `0` is replaced by `1`.
+pub fn records<R: BufRead>(reader: R, serial: u32) -> Records<R> {
+    let mut soa = [0; 20];
+    soa[..4].copy_from_slice(&serial.max(1).to_be_bytes());
+    // The refresh, retry, expire and minimum times of tinydns-data.
+    for (i, time) in [16384_u32, 2048, 1048576, 2560].iter().enumerate() {
+        soa[4 + 4 * i..8 + 4 * i].copy_from_slice(&time.to_be_bytes());
+    }
+    Records {
+        reader,
+        soa,
+        line: 0,
+        pending: VecDeque::new(),
+        done: false,
+    }
+}
+
+/// Parse a decimal number as `scan_ulong` does, returning the number of
+/// digits and the value, which wraps on overflow.
+fn scan_ulong(text: &[u8]) -> (usize, u64) {
+    let digits = text.iter().take_while(|b| b.is_ascii_digit()).count();
+    let value = text[..digits].iter().fold(0_u64, |value, &b| {
+        value.wrapping_mul(10).wrapping_add((b - b'0') as u64)
+    });
+    (digits, value)
+}
+
+/// Parse a dotted IPv4 address, returning `None` if it is not one.
+fn ip4(text: &[u8]) -> Option<[u8; 4]> {
+    let mut ip = [0; 4];
+    let mut rest = text;
+    for (i, byte) in ip.iter_mut().enumerate() {
+        if i > 0 {
+            rest = rest.strip_prefix(b".")?;
+        }
+        let (digits, value) = scan_ulong(rest);
+        if digits == 0 {
+            return None;
+        }
+        *byte = value as u8;
+        rest = &rest[digits..];
+    }
+    Some(ip)
+}
+
+/// Decode the escape after a backslash: up to three octal digits, or else
+/// the next byte as it is. Return `None` at the end of the text.
+fn escape<I: Iterator<Item = u8>>(bytes: &mut Peekable<I>) -> Option<u8> {
+    let mut b = bytes.next()?;
+    if (b'0'..=b'7').contains(&b) {
+        b -= b'0';
+        for _ in 0..2 {
+            match bytes.peek() {
+                Some(&d @ b'0'..=b'7') => {
+                    b = (b << 3).wrapping_add(d - b'0');
+                    bytes.next();
+                }
+                _ => break,
+            }
+        }
+    }
+    Some(b)
+}
+
+/// Decode the backslash escapes of text fields.
+fn unescape(text: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len());
+    let mut bytes = text.iter().copied().peekable();
+    while let Some(mut b) = bytes.next() {
+        if b == b'\\' {
+            b = match escape(&mut bytes) {
+                Some(b) => b,
+                None => break,
+            };
+        }
+        out.push(b);
+    }
+    out
+}
+
+/// Convert a dotted name to DNS wire format, as `dns_domain_fromdot`
+/// does, returning `None` if a label or the name is too long.
+fn domain(text: &[u8]) -> Option<Vec<u8>> {
+    fn push_label(name: &mut Vec<u8>, label: &mut Vec<u8>) -> Option<()> {
+        if !label.is_empty() {
+            if name.len() + label.len() + 1 > 255 {
+                return None;
+            }
+            name.push(label.len() as u8);
+            name.append(label);
+        }
+        Some(())
+    }
+
+    let mut name = Vec::new();
+    let mut label = Vec::new();
+    let mut bytes = text.iter().copied().peekable();
+    while let Some(mut b) = bytes.next() {
+        if b == b'.' {
+            push_label(&mut name, &mut label)?;
+            continue;
+        }
+        if b == b'\\' {
+            b = match escape(&mut bytes) {
+                Some(b) => b,
+                None => break,
+            };
+        }
+        if label.len() >= 63 {
+            return None;
+        }
+        label.push(b);
+    }
+    push_label(&mut name, &mut label)?;
+    if name.len() + 1 > 255 {
+        return None;
+    }
+    name.push(0);
+    Some(name)
+}
+
+/// The name of the `in-addr.arpa` pointer record for `ip`.
+fn reverse_domain(ip: [u8; 4]) -> Vec<u8> {
+    let mut name = Vec::new();
+    for byte in ip.iter().rev() {
+        let label = byte.to_string();
+        name.push(label.len() as u8);
+        name.extend_from_slice(label.as_bytes());
+    }
+    name.extend_from_slice(b"\x07in-addr\x04arpa\x00");
+    name
+}
+
+/// The time-to-die field, a timestamp of up to 16 lower case hex digits.
+fn ttd(text: &[u8]) -> Option<[u8; 8]> {
+    let mut ttd = [0; 8];
+    for (i, &b) in text.iter().take(16).enumerate() {
+        let digit = match b {
+            b'0'..=b'9' => b - b'0',
+            b'a'..=b'f' => b - b'a' + 10,
+            _ => return None,
+        };
+        ttd[i / 2] |= if i % 2 == 0 { digit << 4 } else { digit };
+    }
+    Some(ttd)
+}
+
+/// The location field, the first two bytes of the text.
+fn location(text: &[u8]) -> [u8; 2] {
+    [
+        text.first().copied().unwrap_or(0),
+        text.get(1).copied().unwrap_or(0),
+    ]
+}
+
+/// The TTL, timestamp and location of a record.
+struct Meta {
+    ttl: u32,
+    ttd: [u8; 8],
+    loc: [u8; 2],
+}
+
+impl<R: BufRead> Records<R> {
+    fn err_line<T>(&self, msg: &str) -> Result<T> {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Malformed tinydns data in line {}: {}", self.line, msg),
+        ))
+    }
+
+    fn domain(&self, text: &[u8]) -> Result<Vec<u8>> {
+        match domain(text) {
+            Some(name) => Ok(name),
+            None => self.err_line("name too long"),
+        }
+    }
+
+    /// Parse the TTL, timestamp and location fields starting at `fields`.
+    fn meta(&self, fields: &[&[u8]], default_ttl: u32) -> Result<Meta> {
+        let ttl = match scan_ulong(fields[0]) {
+            (0, _) => default_ttl,
+            (_, ttl) => ttl as u32,
+        };
+        let ttd = match ttd(fields[1]) {
+            Some(ttd) => ttd,
+            None => return self.err_line("malformed time"),
+        };
+        Ok(Meta {
+            ttl,
+            ttd,
+            loc: location(fields[2]),
+        })
+    }
+
+    /// Start the value of a record.
+    fn start(&self, rtype: u16, ttl: u32, meta: &Meta) -> Vec<u8> {
+        let mut value = rtype.to_be_bytes().to_vec();
+        if meta.loc == [0, 0] {
+            value.push(b'=');
+        } else {
+            value.push(b'>');
+            value.extend_from_slice(&meta.loc);
+        }
+        value.extend_from_slice(&ttl.to_be_bytes());
+        value.extend_from_slice(&meta.ttd);
+        value
+    }
+
+    /// Queue a finished record for `owner`. A leading `*` label makes it
+    /// a wildcard record for the rest of the name.
+    fn finish(&mut self, owner: &[u8], mut value: Vec<u8>) {
+        let mut owner = owner;
+        if owner.starts_with(b"\x01*") {
+            owner = &owner[2..];
+            // `=` becomes `*`, and `>` becomes `+`.
+            value[2] -= 19;
+        }
+        self.pending.push_back((owner.to_ascii_lowercase(), value));
+    }
+
+    /// Parse one line, queueing its records, and return `false` at the
+    /// end of the input.
+    fn read_line(&mut self) -> Result<bool> {
+        let mut line = Vec::new();
+        if self.reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(false);
+        }
+        self.line += 1;
+        while let Some(b' ' | b'\t' | b'\n') = line.last() {
+            line.pop();
+        }
+        let (&kind, rest) = match line.split_first() {
+            Some((b'#' | b'-', _)) | None => return Ok(true),
+            Some(split) => split,
+        };
+        let mut fields = [&b""[..]; FIELDS];
+        for (field, text) in fields.iter_mut().zip(rest.split(|&b| b == b':')) {
+            *field = text;
+        }
+
+        match kind {
+            b'%' => {
+                let mut key = b"\0%".to_vec();
+                let mut prefix = fields[1];
+                loop {
+                    prefix = prefix.strip_prefix(b".").unwrap_or(prefix);
+                    match scan_ulong(prefix) {
+                        (0, _) => break,
+                        (digits, value) => {
+                            key.push(value as u8);
+                            prefix = &prefix[digits..];
+                        }
+                    }
+                }
+                self.pending.push_back((key, location(fields[0]).to_vec()));
+            }
+            b'Z' => {
+                let owner = self.domain(fields[0])?;
+                let mut soa = self.soa;
+                for i in 0..5 {
+                    let (digits, value) = scan_ulong(fields[3 + i]);
+                    if digits > 0 {
+                        soa[4 * i..4 * i + 4].copy_from_slice(&(value as u32).to_be_bytes());
+                    }
+                }
+                let meta = self.meta(&fields[8..], TTL_NEGATIVE)?;
+                let mut value = self.start(TYPE_SOA, meta.ttl, &meta);
+                value.extend(self.domain(fields[1])?);
+                value.extend(self.domain(fields[2])?);
+                value.extend_from_slice(&soa);
+                self.finish(&owner, value);
+            }
+            b'.' | b'&' => {
+                let owner = self.domain(fields[0])?;
+                let meta = self.meta(&fields[3..], TTL_NS)?;
+                let server = if fields[2].contains(&b'.') {
+                    self.domain(fields[2])?
+                } else {
+                    self.domain(&[fields[2], b".ns.", fields[0]].concat())?
+                };
+                if kind == b'.' {
+                    let ttl = if meta.ttl > 0 { TTL_NEGATIVE } else { 0 };
+                    let mut value = self.start(TYPE_SOA, ttl, &meta);
+                    value.extend_from_slice(&server);
+                    value.extend_from_slice(b"\x0ahostmaster");
+                    value.extend_from_slice(&owner);
+                    value.extend_from_slice(&self.soa);
+                    self.finish(&owner, value);
+                }
+                let mut value = self.start(TYPE_NS, meta.ttl, &meta);
+                value.extend_from_slice(&server);
+                self.finish(&owner, value);
+                if let Some(ip) = ip4(fields[1]) {
+                    let mut value = self.start(TYPE_A, meta.ttl, &meta);
+                    value.extend_from_slice(&ip);
+                    self.finish(&server, value);
+                }
+            }
+            b'+' | b'=' => {
+                let owner = self.domain(fields[0])?;
+                let meta = self.meta(&fields[2..], TTL_POSITIVE)?;
+                if let Some(ip) = ip4(fields[1]) {
+                    let mut value = self.start(TYPE_A, meta.ttl, &meta);
+                    value.extend_from_slice(&ip);
+                    self.finish(&owner, value);
+                    if kind == b'=' {
+                        let mut value = self.start(TYPE_PTR, meta.ttl, &meta);
+                        value.extend_from_slice(&owner);
+                        self.finish(&reverse_domain(ip), value);
+                    }
+                }
+            }
+            b'@' => {
+                let owner = self.domain(fields[0])?;
+                let meta = self.meta(&fields[4..], TTL_POSITIVE)?;
+                let exchanger = if fields[2].contains(&b'.') {
+                    self.domain(fields[2])?
+                } else {
+                    self.domain(&[fields[2], b".mx.", fields[0]].concat())?
+                };
+                let distance = scan_ulong(fields[3]).1 as u16;
+                let mut value = self.start(TYPE_MX, meta.ttl, &meta);
+                value.extend_from_slice(&distance.to_be_bytes());
+                value.extend_from_slice(&exchanger);
+                self.finish(&owner, value);
+                if let Some(ip) = ip4(fields[1]) {
+                    let mut value = self.start(TYPE_A, meta.ttl, &meta);
+                    value.extend_from_slice(&ip);
+                    self.finish(&exchanger, value);
+                }
+            }
+            b'^' | b'C' => {
+                let owner = self.domain(fields[0])?;
+                let target = self.domain(fields[1])?;
+                let meta = self.meta(&fields[2..], TTL_POSITIVE)?;
+                let rtype = if kind == b'C' { TYPE_CNAME } else { TYPE_PTR };
+                let mut value = self.start(rtype, meta.ttl, &meta);
+                value.extend_from_slice(&target);
+                self.finish(&owner, value);
+            }
+            b'\'' => {
+                let owner = self.domain(fields[0])?;
+                let meta = self.meta(&fields[2..], TTL_POSITIVE)?;
+                let mut value = self.start(TYPE_TXT, meta.ttl, &meta);
+                // Split the text into character strings of at most 127
+                // bytes, as tinydns-data does.
+                for chunk in unescape(fields[1]).chunks(127) {
+                    value.push(chunk.len() as u8);
+                    value.extend_from_slice(chunk);
+                }
+                self.finish(&owner, value);
+            }
+            b':' => {
+                let owner = self.domain(fields[0])?;
+                let meta = self.meta(&fields[3..], TTL_POSITIVE)?;
+                let rtype = scan_ulong(fields[1]).1 as u16;
+                let prohibited = match rtype {
+                    TYPE_AXFR => Some("AXFR"),
+                    0 => Some("0"),
+                    TYPE_SOA => Some("SOA"),
+                    TYPE_NS => Some("NS"),
+                    TYPE_CNAME => Some("CNAME"),
+                    TYPE_PTR => Some("PTR"),
+                    TYPE_MX => Some("MX"),
+                    _ => None,
+                };
+                if let Some(name) = prohibited {
+                    return self.err_line(&format!("type {} prohibited", name));
+                }
+                let mut value = self.start(rtype, meta.ttl, &meta);
+                value.extend(unescape(fields[2]));
+                self.finish(&owner, value);
+            }
+            _ => return self.err_line("unrecognized leading character"),
+        }
+        Ok(true)
+    }
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = Result<(Vec<u8>, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(record) = self.pending.pop_front() {
+                return Some(Ok(record));
+            }
+            if self.done {
+                return None;
+            }
+            match self.read_line() {
+                Ok(true) => {}
+                Ok(false) => self.done = true,
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// Build a `data.cdb` file at `cdb` from the `tinydns-data` file `data`,
+/// as `tinydns-data` does, returning the number of records written.
+///
+/// The default SOA serial number is the modification time of `data`.
+/// The database is written to a temporary file and renamed into place,
+/// so `tinydns` never sees a partly written file.
+pub fn build<P: AsRef<Path>, Q: Into<PathBuf>>(data: P, cdb: Q) -> Result<u64> {
+    let file = File::open(data)?;
+    let modified = file.metadata()?.modified()?;
+    let serial = modified
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs() as u32);
+    let mut writer = CDBWriter::create(cdb)?;
+    let count = writer.add_from_tinydns(BufReader::new(file), serial)?;
+    writer.finish()?;
+    Ok(count)
+}
+
+impl<F: Format, H: CdbHash, W: Write + Seek> GenericCDBMake<F, H, W> {
+    /// Add the records of a `tinydns-data` file read from `reader`,
+    /// returning how many were added. `serial` is the default SOA serial
+    /// number. See the [`tinydns`](crate::tinydns) module.
+    ///
+    /// Records before any malformed line are still added.
+    pub fn add_from_tinydns<R: BufRead>(&mut self, reader: R, serial: u32) -> Result<u64> {
+        let mut count = 0;
+        for record in records(reader, serial) {
+            let (key, value) = record?;
+            self.add(key, value)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+impl<F: Format, H: CdbHash> GenericCDBWriter<F, H> {
+    /// Add the records of a `tinydns-data` file read from `reader`,
+    /// returning how many were added. See
+    /// [`GenericCDBMake::add_from_tinydns`].
+    pub fn add_from_tinydns<R: BufRead>(&mut self, reader: R, serial: u32) -> Result<u64> {
+        let mut count = 0;
+        for record in records(reader, serial) {
+            let (key, value) = record?;
+            self.add(key, value)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
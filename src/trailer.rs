@@ -0,0 +1,173 @@
+use std::io::{self, Write};
+
+use crate::checksum::CRC32_ID;
+use crate::error::err_corrupt;
+use crate::format::Format;
+use crate::hash::CdbHash;
+use crate::image::Storage;
+use crate::reader::{GenericCDB, Result};
+
+// Optional sections may be appended after the last hash table, where
+// classic readers never look. Each section is its body followed by a
+// footer holding the body length (64-bit little endian) and an 8-byte
+// magic number, so they can be found by walking backwards from the end
+// of the file.
+
+pub(crate) const FOOTER_SIZE: u64 = 16;
+
+pub(crate) type Magic = [u8; 8];
+
+pub(crate) const BLOOM_MAGIC: Magic = *b"CDBBLOOM";
+
+pub(crate) const COMPRESSION_MAGIC: Magic = *b"CDBCOMPR";
+
+pub(crate) const CHECKSUM_MAGIC: Magic = *b"CDBCKSUM";
+
+pub(crate) const METADATA_MAGIC: Magic = *b"CDBMETAD";
+
+pub(crate) const ENCRYPTION_MAGIC: Magic = *b"CDBCRYPT";
+
+pub(crate) const SIGNATURE_MAGIC: Magic = *b"CDBSIGNS";
+
+const KNOWN_MAGICS: &[Magic] = &[
+    BLOOM_MAGIC,
+    COMPRESSION_MAGIC,
+    CHECKSUM_MAGIC,
+    METADATA_MAGIC,
+    ENCRYPTION_MAGIC,
+    SIGNATURE_MAGIC,
+];
+
+/// Location of the body of one appended section.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Section {
+    pub(crate) magic: Magic,
+    pub(crate) pos: u64,
+    pub(crate) len: u64,
+}
+
+/// Return the end of the last hash table, where appended sections start.
+pub(crate) fn tables_end<F: Format, S: Storage<F>>(cdb: &S) -> Option<u64> {
+    (0..256).try_fold(F::HEADER_SIZE as u64, |end, i| {
+        let (pos, slots) = cdb.table(i);
+        let table_end = slots.checked_mul(F::PAIR_SIZE as u64)?.checked_add(pos)?;
+        Some(end.max(table_end))
+    })
+}
+
+/// Find the sections appended to a database, last first.
+pub(crate) fn sections<F: Format, H: CdbHash>(cdb: &GenericCDB<F, H>) -> Result<Vec<Section>> {
+    let start = match tables_end(cdb) {
+        Some(start) => start,
+        None => return err_corrupt(0, "hash table overflows"),
+    };
+    let mut end = cdb.size();
+    let mut sections = Vec::new();
+    while end >= start.saturating_add(FOOTER_SIZE) {
+        let mut footer = [0_u8; FOOTER_SIZE as usize];
+        cdb.read(&mut footer, end - FOOTER_SIZE)?;
+        let magic: Magic = footer[8..].try_into().unwrap();
+        if !KNOWN_MAGICS.contains(&magic) {
+            break;
+        }
+        let len = u64::from_le_bytes(footer[..8].try_into().unwrap());
+        let pos = match (end - FOOTER_SIZE).checked_sub(len) {
+            Some(pos) if pos >= start => pos,
+            _ => return err_corrupt(end - FOOTER_SIZE, "section is longer than the file"),
+        };
+        sections.push(Section { magic, pos, len });
+        end = pos;
+    }
+    Ok(sections)
+}
+
+/// Read the one-byte body of the section with the given magic, which
+/// identifies an algorithm, if there is such a section.
+fn algorithm<F: Format, H: CdbHash>(
+    cdb: &GenericCDB<F, H>,
+    sections: &[Section],
+    magic: Magic,
+) -> Result<Option<u8>> {
+    let section = match sections.iter().find(|s| s.magic == magic) {
+        Some(section) => section,
+        None => return Ok(None),
+    };
+    if section.len != 1 {
+        return err_corrupt(section.pos, "algorithm section is not one byte");
+    }
+    let mut id = [0_u8];
+    cdb.read(&mut id, section.pos)?;
+    Ok(Some(id[0]))
+}
+
+/// Return `true` if the compression section shows that the values are
+/// compressed, failing if they are compressed in a way this build
+/// cannot decompress.
+pub(crate) fn compression<F: Format, H: CdbHash>(
+    cdb: &GenericCDB<F, H>,
+    sections: &[Section],
+) -> Result<bool> {
+    let id = match algorithm(cdb, sections, COMPRESSION_MAGIC)? {
+        Some(id) => id,
+        None => return Ok(false),
+    };
+    #[cfg(feature = "lz4")]
+    if id == crate::compress::LZ4_ID {
+        return Ok(true);
+    }
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("Unsupported value compression {}", id),
+    ))
+}
+
+/// Return `true` if the checksum section shows that the values are
+/// followed by checksums.
+pub(crate) fn checksums<F: Format, H: CdbHash>(
+    cdb: &GenericCDB<F, H>,
+    sections: &[Section],
+) -> Result<bool> {
+    match algorithm(cdb, sections, CHECKSUM_MAGIC)? {
+        None => Ok(false),
+        Some(CRC32_ID) => Ok(true),
+        Some(id) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Unsupported record checksum {}", id),
+        )),
+    }
+}
+
+/// Return the key check of the encryption section if the values are
+/// encrypted, failing if they are encrypted in a way this build cannot
+/// decrypt.
+pub(crate) fn encryption<F: Format, H: CdbHash>(
+    cdb: &GenericCDB<F, H>,
+    sections: &[Section],
+) -> Result<Option<Vec<u8>>> {
+    let section = match sections.iter().find(|s| s.magic == ENCRYPTION_MAGIC) {
+        Some(section) => section,
+        None => return Ok(None),
+    };
+    let body = cdb.read_vec(section.pos, section.len)?;
+    #[cfg(feature = "encryption")]
+    if body.len() == 1 + crate::encrypt::KEY_CHECK_SIZE
+        && body[0] == crate::encrypt::CHACHA20_POLY1305_ID
+    {
+        return Ok(Some(body[1..].to_vec()));
+    }
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "Unsupported value encryption {}",
+            body.first().unwrap_or(&0)
+        ),
+    ))
+}
+
+/// Write a section with the given body, returning its total size.
+pub(crate) fn write_section<W: Write>(w: &mut W, magic: &Magic, body: &[u8]) -> Result<u64> {
+    w.write_all(body)?;
+    w.write_all(&(body.len() as u64).to_le_bytes())?;
+    w.write_all(magic)?;
+    Ok(body.len() as u64 + FOOTER_SIZE)
+}
@@ -0,0 +1,56 @@
+pub(crate) fn unpack(data: &[u8]) -> u64 {
+    u64::from_le_bytes(data.try_into().unwrap())
+}
+
+pub(crate) fn unpack2(buf: &[u8]) -> (u64, u64) {
+    assert!(buf.len() >= 16);
+    (unpack(&buf[0..8]), unpack(&buf[8..16]))
+}
+
+pub(crate) fn pack(data: &mut [u8], src: u64) {
+    data[..8].copy_from_slice(&src.to_le_bytes());
+}
+
+pub(crate) fn pack2(data: &mut [u8], src0: u64, src1: u64) {
+    assert!(data.len() >= 16);
+    pack(&mut data[0..8], src0);
+    pack(&mut data[8..16], src1);
+}
+
+#[test]
+fn test_unpack() {
+    let data = [0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+    assert_eq!(unpack(&data), 1);
+}
+
+#[test]
+fn test_pack() {
+    let mut data = [0; 8];
+    pack(&mut data, 0x0100000001);
+    assert_eq!(data, [0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00]);
+}
+
+#[test]
+fn test_unpack2() {
+    let mut data = [0; 16];
+    data[0] = 0x01;
+    data[8] = 0x02;
+    assert_eq!(unpack2(&data), (1, 2));
+}
+
+#[test]
+fn test_pack2() {
+    let mut data = [0; 16];
+    pack2(&mut data, 1, 2);
+    assert_eq!(data[..8], [0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    assert_eq!(data[8..], [0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+}
+
+#[test]
+fn test_unpack2_overflow() {
+    let data = [0; 15];
+    assert!(std::panic::catch_unwind(|| {
+        let _ = unpack2(&data);
+    })
+    .is_err());
+}
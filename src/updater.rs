@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::format::{Format, Format32, Format64};
+use crate::reader::{GenericCDB, Result};
+use crate::writer::{BuildStats, GenericCDBWriter};
+
+/// Changes to apply to an existing CDB file, generic over the on-disk
+/// [`Format`].
+///
+/// CDB files cannot be modified in place, so changes are made by writing
+/// a new file. This collects insertions and removals, then
+/// [`commit`](Self::commit) streams the unchanged records of the old file
+/// followed by the inserted ones into a new file, which atomically
+/// replaces the old one. Only the inserted records are held in memory.
+///
+/// Most code should use the [`CDBUpdater`] or [`CDB64Updater`] aliases.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// # let tmp_dir = tempfile::tempdir()?;
+/// # let path = tmp_dir.path().join("update.cdb");
+/// # std::fs::copy("tests/test1.cdb", &path)?;
+/// use cdb32::{CDBUpdater, CDB};
+///
+/// let mut update = CDBUpdater::open(&path)?;
+/// update.remove(b"one");
+/// update.replace(b"two", b"Hello again");
+/// update.insert(b"three", b"New");
+/// update.commit()?;
+///
+/// let cdb = CDB::open(&path)?;
+/// assert!(cdb.get(b"one").is_none());
+/// assert_eq!(cdb.get(b"two").unwrap()?, b"Hello again");
+/// assert_eq!(cdb.get(b"three").unwrap()?, b"New");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct GenericCDBUpdater<F> {
+    path: PathBuf,
+    source: GenericCDB<F>,
+    removed: HashSet<Vec<u8>>,
+    inserted: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+/// Updater for CDB files in the classic 32-bit format.
+pub type CDBUpdater = GenericCDBUpdater<Format32>;
+
+/// Updater for CDB files in the 64-bit format.
+pub type CDB64Updater = GenericCDBUpdater<Format64>;
+
+impl<F: Format> GenericCDBUpdater<F> {
+    /// Open the named file for updating.
+    pub fn open<P: Into<PathBuf>>(filename: P) -> Result<Self> {
+        let path = filename.into();
+        let source = GenericCDB::open(&path)?;
+        Ok(GenericCDBUpdater {
+            path,
+            source,
+            removed: HashSet::new(),
+            inserted: Vec::new(),
+        })
+    }
+
+    /// Return the path of the file being updated.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Return the database as it was opened, without the pending changes.
+    pub fn source(&self) -> &GenericCDB<F> {
+        &self.source
+    }
+
+    /// Add a record, keeping any records already present with the same
+    /// key.
+    pub fn insert(&mut self, key: &[u8], value: &[u8]) {
+        self.inserted.push((key.to_vec(), value.to_vec()));
+    }
+
+    /// Remove every record with the named key, including any inserted
+    /// before this call.
+    pub fn remove(&mut self, key: &[u8]) {
+        self.inserted.retain(|(k, _)| k != key);
+        self.removed.insert(key.to_vec());
+    }
+
+    /// Replace every record with the named key by a single new one.
+    pub fn replace(&mut self, key: &[u8], value: &[u8]) {
+        self.remove(key);
+        self.insert(key, value);
+    }
+
+    /// Write the updated database over the original file.
+    ///
+    /// The new file is built with [`GenericCDBWriter`] and renamed into
+    /// place, so readers see either the old or the new contents. On
+    /// platforms which cannot rename over an open file, such as Windows,
+    /// use [`commit_to`](Self::commit_to) instead.
+    pub fn commit(self) -> Result<BuildStats> {
+        let path = self.path.clone();
+        self.commit_to(path)
+    }
+
+    /// Write the updated database to a different file.
+    pub fn commit_to<P: Into<PathBuf>>(self, filename: P) -> Result<BuildStats> {
+        let mut writer = GenericCDBWriter::<F>::create(filename)?;
+        for record in self.source.iter() {
+            let (key, value) = record?;
+            if !self.removed.contains(&key) {
+                writer.add(&key, &value)?;
+            }
+        }
+        for (key, value) in &self.inserted {
+            writer.add(key, value)?;
+        }
+        writer.finish()
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::error::err_corrupt;
+use crate::format::Format;
+use crate::hash::CdbHash;
+use crate::image::{Probe, Storage};
+use crate::reader::{GenericCDB, Result};
+
+/// A problem found by [`GenericCDB::verify`].
+///
+/// Positions are byte offsets from the start of the file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerifyProblem {
+    /// A hash table does not lie between the end of the data section and
+    /// the end of the file.
+    TableOutOfBounds { table: usize, pos: u64, slots: u64 },
+    /// A hash table slot holds a hash which belongs in another table.
+    WrongTable { table: usize, slot: u64, hash: u64 },
+    /// A hash table slot points outside the data section.
+    SlotOutOfBounds { table: usize, slot: u64, pos: u64 },
+    /// The hash stored in a slot is not the hash of the record's key.
+    HashMismatch { pos: u64, stored: u64, actual: u32 },
+    /// A record is referenced by a slot, but looking up its key does not
+    /// find it.
+    Unreachable { pos: u64 },
+    /// A record in the data section is not referenced by any slot.
+    Unreferenced { pos: u64 },
+    /// A record extends past the end of the data section.
+    RecordOutOfBounds { pos: u64 },
+    /// The checksum stored after a record's value does not match the
+    /// record, in a database with checksums.
+    ChecksumMismatch { pos: u64 },
+    /// A hash table slot points into the data section, but not at the
+    /// start of a record, so the record it finds overlaps real ones.
+    NotRecordStart { table: usize, slot: u64, pos: u64 },
+    /// A hash table slot points at a record already referenced by another
+    /// slot, so lookups return it twice.
+    DuplicateReference { table: usize, slot: u64, pos: u64 },
+}
+
+/// The result of [`GenericCDB::verify`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Number of records found in the data section.
+    pub records: u64,
+    /// Problems found, in the order they were found.
+    pub problems: Vec<VerifyProblem>,
+}
+
+impl VerifyReport {
+    /// Return `true` if no problems were found.
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl<F: Format, H: CdbHash> GenericCDB<F, H> {
+    /// Check that the header is consistent with the file: every hash
+    /// table lies within the file after the data section, and no two
+    /// tables overlap. This reads only the header, unlike
+    /// [`verify`](Self::verify).
+    ///
+    /// A problem is returned as an [`Error::Corrupt`](crate::Error::Corrupt)
+    /// whose offset is that of the table's pointer in the header.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut data = std::fs::read("tests/test1.cdb")?;
+    /// data[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+    /// let cdb = cdb32::CDB::from_bytes(data)?;
+    /// assert!(cdb.check_header().is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn check_header(&self) -> Result<()> {
+        let pair = F::PAIR_SIZE as u64;
+        let (data_end, _) = self.table(0);
+        if data_end < F::HEADER_SIZE as u64 || data_end > self.size() {
+            return err_corrupt(0, "data section ends outside the file");
+        }
+        let mut tables = Vec::with_capacity(256);
+        for table in 0..256 {
+            let offset = (table * F::PAIR_SIZE) as u64;
+            let (hpos, slots) = self.table(table);
+            let end = match slots
+                .checked_mul(pair)
+                .and_then(|len| hpos.checked_add(len))
+            {
+                Some(end) => end,
+                None => return err_corrupt(offset, "hash table overflows"),
+            };
+            if hpos < data_end {
+                return err_corrupt(offset, "hash table overlaps the data section");
+            }
+            if end > self.size() {
+                return err_corrupt(offset, "hash table extends past the end of the file");
+            }
+            if slots > 0 {
+                tables.push((hpos, end, offset));
+            }
+        }
+        tables.sort_unstable();
+        for pair in tables.windows(2) {
+            if pair[1].0 < pair[0].1 {
+                return err_corrupt(pair[1].2, "hash tables overlap");
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the integrity of the whole database.
+    ///
+    /// Every hash table slot is checked to point at the start of its own
+    /// record in the data section, whose key has the stored hash and can
+    /// be found by a lookup. The records are checked to fill the data
+    /// section end to end, and each to be referenced by a slot. In a database with checksums, every record's checksum is
+    /// checked too. Problems with the file's contents are collected into the
+    /// returned report rather than stopping at the first one; only I/O
+    /// errors are returned as errors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::CDB;
+    ///
+    /// let cdb = CDB::open("tests/test1.cdb")?;
+    /// let report = cdb.verify()?;
+    /// assert!(report.is_ok(), "{:?}", report.problems);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn verify(&self) -> Result<VerifyReport> {
+        let pair = F::PAIR_SIZE as u64;
+        let data_start = F::HEADER_SIZE as u64;
+        let data_end = self.data_end();
+        let mut buf = [0_u8; 16];
+        let buf = &mut buf[..F::PAIR_SIZE];
+        let mut report = VerifyReport::default();
+        let mut referenced = BTreeMap::new();
+
+        for table in 0..256 {
+            let (hpos, slots) = self.table(table);
+            if slots == 0 {
+                continue;
+            }
+            let table_end = slots
+                .checked_mul(pair)
+                .and_then(|len| hpos.checked_add(len));
+            if hpos < data_end || table_end.map_or(true, |end| end > self.size()) {
+                report.problems.push(VerifyProblem::TableOutOfBounds {
+                    table,
+                    pos: hpos,
+                    slots,
+                });
+                continue;
+            }
+
+            for slot in 0..slots {
+                self.read(buf, hpos + slot * pair)?;
+                let (stored, pos) = F::unpack2(buf);
+                if pos == 0 {
+                    continue;
+                }
+                if stored & 0xff != table as u64 {
+                    report.problems.push(VerifyProblem::WrongTable {
+                        table,
+                        slot,
+                        hash: stored,
+                    });
+                    continue;
+                }
+                if pos < data_start || pos.checked_add(pair).map_or(true, |end| end > data_end) {
+                    report
+                        .problems
+                        .push(VerifyProblem::SlotOutOfBounds { table, slot, pos });
+                    continue;
+                }
+                if referenced.insert(pos, (table, slot)).is_some() {
+                    report
+                        .problems
+                        .push(VerifyProblem::DuplicateReference { table, slot, pos });
+                    continue;
+                }
+
+                self.read(buf, pos)?;
+                let (klen, dlen) = F::unpack2(buf);
+                let end = (pos + pair)
+                    .checked_add(klen)
+                    .and_then(|p| p.checked_add(dlen));
+                if end.map_or(true, |end| end > data_end) {
+                    report
+                        .problems
+                        .push(VerifyProblem::RecordOutOfBounds { pos });
+                    continue;
+                }
+
+                let key = self.read_vec(pos + pair, klen)?;
+                let actual = H::hash(&key);
+                if actual as u64 != stored {
+                    report.problems.push(VerifyProblem::HashMismatch {
+                        pos,
+                        stored,
+                        actual,
+                    });
+                    continue;
+                }
+
+                let dpos = pos + pair + klen;
+                let mut probe = Probe::start(self, actual);
+                loop {
+                    match probe.next(self, &key).transpose()? {
+                        Some((found, _)) if found == dpos => break,
+                        Some(_) => {}
+                        None => {
+                            report.problems.push(VerifyProblem::Unreachable { pos });
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Walk the records from the start of the data section, each
+        // starting where the last ended.
+        let mut starts = HashSet::new();
+        let mut pos = data_start;
+        while pos < data_end {
+            starts.insert(pos);
+            if pos + pair > data_end {
+                report
+                    .problems
+                    .push(VerifyProblem::RecordOutOfBounds { pos });
+                break;
+            }
+            self.read(buf, pos)?;
+            let (klen, dlen) = F::unpack2(buf);
+            let end = match (pos + pair)
+                .checked_add(klen)
+                .and_then(|p| p.checked_add(dlen))
+            {
+                Some(end) if end <= data_end => end,
+                _ => {
+                    report
+                        .problems
+                        .push(VerifyProblem::RecordOutOfBounds { pos });
+                    break;
+                }
+            };
+            report.records += 1;
+            if !referenced.contains_key(&pos) {
+                report.problems.push(VerifyProblem::Unreferenced { pos });
+            }
+            if self.has_checksums() {
+                let key = self.read_vec(pos + pair, klen)?;
+                let value = self.read_vec(pos + pair + klen, dlen)?;
+                if !self.checksum_matches(&key, &value) {
+                    report
+                        .problems
+                        .push(VerifyProblem::ChecksumMismatch { pos });
+                }
+            }
+            pos = end;
+        }
+
+        // Slots past a damaged record cannot be checked, as the walk
+        // stopped there.
+        for (&slot_pos, &(table, slot)) in referenced.range(..pos) {
+            if !starts.contains(&slot_pos) {
+                report.problems.push(VerifyProblem::NotRecordStart {
+                    table,
+                    slot,
+                    pos: slot_pos,
+                });
+            }
+        }
+
+        Ok(report)
+    }
+}
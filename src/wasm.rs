@@ -0,0 +1,93 @@
+//! JavaScript bindings, generated with `wasm-bindgen`.
+//!
+//! Build the `cdb32-js` workspace member for `wasm32-unknown-unknown` and
+//! run `wasm-bindgen` over it to get a module exporting [`CdbReader`]:
+//!
+//! ```text
+//! cargo build --release -p cdb32-js --target wasm32-unknown-unknown
+//! wasm-bindgen --target web --out-dir js/pkg --out-name cdb32 \
+//!     target/wasm32-unknown-unknown/release/cdb32_js.wasm
+//! ```
+//!
+//! ```js
+//! import init, { CdbReader } from "./pkg/cdb32.js";
+//!
+//! await init();
+//! const cdb = CdbReader.fromBytes(new Uint8Array(await (await fetch("/data.cdb")).arrayBuffer()));
+//! cdb.get("one");                  // Uint8Array, or undefined
+//! for (const [key, value] of cdb.entries()) { ... }
+//! cdb.free();
+//! ```
+//!
+//! Keys may be `Uint8Array`s or strings, which are encoded as UTF-8.
+//! Values are returned as `Uint8Array`s. Failures throw an `Error`.
+
+use std::io;
+
+use js_sys::{Array, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::CDB;
+
+fn js_error(err: io::Error) -> JsError {
+    JsError::new(&err.to_string())
+}
+
+fn key_bytes(key: &JsValue) -> Result<Vec<u8>, JsError> {
+    if let Some(key) = key.as_string() {
+        return Ok(key.into_bytes());
+    }
+    match key.dyn_ref::<Uint8Array>() {
+        Some(key) => Ok(key.to_vec()),
+        None => Err(JsError::new("key must be a string or a Uint8Array")),
+    }
+}
+
+/// A database held in memory.
+#[wasm_bindgen]
+pub struct CdbReader {
+    cdb: CDB,
+}
+
+#[wasm_bindgen]
+impl CdbReader {
+    /// Open the database in `bytes`, which are copied.
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<CdbReader, JsError> {
+        let cdb = CDB::from_bytes(bytes.to_vec()).map_err(js_error)?;
+        Ok(CdbReader { cdb })
+    }
+
+    /// Return the first value of `key`, or `undefined` if it has none.
+    pub fn get(&self, key: &JsValue) -> Result<Option<Vec<u8>>, JsError> {
+        self.cdb.get(key_bytes(key)?).transpose().map_err(js_error)
+    }
+
+    /// Return an array of every value of `key`, in the order added.
+    pub fn find(&self, key: &JsValue) -> Result<Array, JsError> {
+        let values = Array::new();
+        for value in self.cdb.find(key_bytes(key)?) {
+            values.push(&Uint8Array::from(&value.map_err(js_error)?[..]));
+        }
+        Ok(values)
+    }
+
+    /// Return whether `key` has any values.
+    pub fn has(&self, key: &JsValue) -> Result<bool, JsError> {
+        self.cdb.exists(&key_bytes(key)?).map_err(js_error)
+    }
+
+    /// Return an array of the `[key, value]` pairs of every record.
+    pub fn entries(&self) -> Result<Array, JsError> {
+        let records = Array::new();
+        for record in self.cdb.iter() {
+            let (key, value) = record.map_err(js_error)?;
+            records.push(&Array::of2(
+                &Uint8Array::from(&key[..]),
+                &Uint8Array::from(&value[..]),
+            ));
+        }
+        Ok(records)
+    }
+}
@@ -1,31 +1,96 @@
 use std::{
     cmp::max,
+    collections::HashSet,
     ffi::OsString,
-    fs,
+    fmt, fs,
     io::{self, prelude::*, Result},
     iter,
-    path::PathBuf,
+    marker::PhantomData,
+    mem,
+    path::{Path, PathBuf},
+    time::SystemTime,
 };
 
-use crate::{hash::hash, uint32};
+use sha2::{Digest, Sha256};
+
+use crate::bloom;
+use crate::checksum::{self, Crc32, CHECKSUM_SIZE, CRC32_ID};
+#[cfg(feature = "lz4")]
+use crate::compress::{self, Compression};
+#[cfg(feature = "signing")]
+use crate::ed25519::SigningKey;
+#[cfg(feature = "encryption")]
+use crate::encrypt::{self, Cipher, EncryptionKey};
+use crate::error::Error;
+use crate::format::{Format, Format32, Format64};
+use crate::hash::{CDBKey, CdbHash, DjbHash};
+use crate::metadata::{ContentDigest, MetadataBuilder};
+use crate::reader::{GenericCDB, OpenOptions};
+#[cfg(feature = "signing")]
+use crate::signature;
+use crate::sorted::{sorted_index_path, SortedIndex};
+use crate::spill::Spill;
+use crate::trailer;
+
+/// A writer which updates the checksum and digest of a record's value
+/// with everything written through it.
+struct ValueWriter<'a, W: Write> {
+    inner: &'a mut io::BufWriter<W>,
+    crc: Crc32,
+    digest: Option<Sha256>,
+}
+
+impl<'a, W: Write> Write for ValueWriter<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.crc.update(&buf[..n]);
+        if let Some(digest) = &mut self.digest {
+            digest.update(&buf[..n]);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
 
 #[derive(Clone, Copy, Debug)]
-struct HashPos {
-    hash: u32,
-    pos: u32,
+pub(crate) struct HashPos {
+    pub(crate) hash: u32,
+    pub(crate) pos: u64,
 }
 
 impl HashPos {
-    fn pack(&self, buf: &mut [u8]) {
-        uint32::pack2(buf, self.hash, self.pos);
+    fn pack<F: Format>(&self, buf: &mut [u8]) {
+        F::pack2(buf, self.hash as u64, self.pos);
     }
 }
 
+const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+
+/// The version of the byte-for-byte output of the writers.
+///
+/// Given the same [`Format`], hash function, options and sequence of
+/// records, the writers produce exactly the same bytes on every platform
+/// and in every release of this crate with the same value of this
+/// constant, so built files can be content-addressed. It is increased if
+/// the layout of any file ever changes. Without any options the output
+/// is also identical to that of D. J. Bernstein's `cdbmake`.
+///
+/// The one exception is [`GenericCDBMake::set_metadata`] without a
+/// `created` time and without `SOURCE_DATE_EPOCH` set, which records the
+/// time the database was finished.
+pub const REPRODUCIBLE_FORMAT_VERSION: u32 = 1;
+
 fn err_toobig<T>() -> Result<T> {
-    Err(io::Error::new(io::ErrorKind::Other, "File too big"))
+    Err(Error::TooBig.into())
 }
 
-/// Base interface for making a CDB file.
+/// Base interface for making a CDB file, generic over the on-disk
+/// [`Format`].
+///
+/// Most code should use the [`CDBMake`] or [`CDB64Make`] aliases.
 ///
 /// # Example
 ///
@@ -43,115 +108,1069 @@ fn err_toobig<T>() -> Result<T> {
 /// # }
 /// ```
 #[derive(Debug)]
-pub struct CDBMake {
+pub struct GenericCDBMake<F, H = DjbHash, W: Write = fs::File> {
     entries: Vec<Vec<HashPos>>,
-    pos: u32,
-    file: io::BufWriter<fs::File>,
+    spill: Option<Spill>,
+    pos: u64,
+    file: io::BufWriter<W>,
+    bloom_bits_per_key: u32,
+    duplicates: DuplicatePolicy,
+    seen: HashSet<Vec<u8>>,
+    stats: BuildStats,
+    progress: Option<ProgressHook>,
+    #[cfg(feature = "lz4")]
+    compression: Option<(Compression, usize)>,
+    #[cfg(feature = "encryption")]
+    cipher: Option<Cipher>,
+    #[cfg(feature = "signing")]
+    signing_key: Option<SigningKey>,
+    checksums: bool,
+    metadata: Option<MetadataBuilder>,
+    digest: Option<ContentDigest>,
+    /// The first error from [`Extend::extend`], which cannot return it.
+    extend_error: Option<io::Error>,
+    format: PhantomData<F>,
+    hasher: PhantomData<H>,
 }
 
-impl CDBMake {
+/// Progress of a CDB build, reported to the callback given to
+/// [`GenericCDBMake::set_progress`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BuildProgress {
+    /// A record has been written.
+    Record {
+        /// The number of records written so far.
+        records: u64,
+        /// The size of the file so far, in bytes.
+        bytes: u64,
+    },
+    /// One of the 256 hash tables has been written while finishing.
+    Table {
+        /// The number of tables written so far.
+        tables: usize,
+        /// The size of the file so far, in bytes.
+        bytes: u64,
+    },
+}
+
+/// Statistics about a finished CDB build, returned by
+/// [`GenericCDBMake::finish`] and [`GenericCDBWriter::finish`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BuildStats {
+    /// The number of records.
+    pub records: u64,
+    /// The total length of the keys, in bytes.
+    pub keys_bytes: u64,
+    /// The total length of the values as stored, in bytes.
+    pub values_bytes: u64,
+    /// The size of the file, in bytes.
+    pub file_size: u64,
+    /// The most hash table slots a lookup of a present key examines.
+    pub max_chain: u64,
+    /// The digest of the records, if asked for with
+    /// [`GenericCDBMake::set_digest`] or
+    /// [`GenericCDBMake::set_metadata`]. See [`GenericCDB::digest`].
+    pub digest: Option<[u8; 32]>,
+}
+
+struct ProgressHook(Box<dyn FnMut(BuildProgress) + Send>);
+
+impl fmt::Debug for ProgressHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressHook")
+    }
+}
+
+/// What to do when a record is added with the same key as an earlier
+/// one. See [`GenericCDBMake::set_duplicate_policy`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Store every record, as CDB allows. Readers see the first one with
+    /// [`get`](crate::GenericCDB::get) and all of them with
+    /// [`find`](crate::GenericCDB::find).
+    #[default]
+    Allow,
+    /// Silently drop records whose key has already been added.
+    KeepFirst,
+    /// Return an error for records whose key has already been added.
+    Reject,
+}
+
+/// Interface for making a CDB file in the classic 32-bit format.
+pub type CDBMake<H = DjbHash, W = fs::File> = GenericCDBMake<Format32, H, W>;
+
+/// Interface for making a CDB file in the 64-bit format.
+pub type CDB64Make<H = DjbHash, W = fs::File> = GenericCDBMake<Format64, H, W>;
+
+impl<F: Format> GenericCDBMake<F> {
     /// Create a new CDB maker.
-    pub fn new(file: fs::File) -> Result<CDBMake> {
-        let mut w = io::BufWriter::new(file);
-        let buf = [0; 2048];
+    pub fn new(file: fs::File) -> Result<Self> {
+        Self::with_writer(file)
+    }
+}
+
+impl<F: Format> GenericCDBMake<F, DjbHash, io::Cursor<Vec<u8>>> {
+    /// Create a new CDB maker which builds the database in memory.
+    ///
+    /// Use [`into_vec`](Self::into_vec) to finish it and take the bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut cdb = cdb32::CDBMake::in_memory()?;
+    /// cdb.add(b"one", b"Hello,")?;
+    /// cdb.add(b"two", b"world!")?;
+    /// let bytes = cdb.into_vec()?;
+    ///
+    /// let cdb = cdb32::CDB::from_bytes(bytes)?;
+    /// assert_eq!(cdb.get(b"two").unwrap()?, b"world!");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn in_memory() -> Result<Self> {
+        Self::with_writer(io::Cursor::new(Vec::new()))
+    }
+}
+
+impl<F: Format, W: Write + Seek> GenericCDBMake<F, DjbHash, W> {
+    /// Create a new CDB maker writing to any seekable writer.
+    pub fn with_writer(file: W) -> Result<Self> {
+        Self::with_capacity(DEFAULT_BUFFER_SIZE, file)
+    }
+
+    /// Create a new CDB maker writing to any seekable writer through a
+    /// buffer of `capacity` bytes.
+    pub fn with_capacity(capacity: usize, file: W) -> Result<Self> {
+        let mut w = io::BufWriter::with_capacity(capacity, file);
+        let buf = vec![0; F::HEADER_SIZE];
         w.seek(io::SeekFrom::Start(0))?;
         w.write_all(&buf)?;
-        Ok(CDBMake {
+        Ok(GenericCDBMake {
             entries: iter::repeat(vec![]).take(256).collect::<Vec<_>>(),
-            pos: 2048,
+            spill: None,
+            pos: F::HEADER_SIZE as u64,
             file: w,
+            bloom_bits_per_key: 0,
+            duplicates: DuplicatePolicy::Allow,
+            seen: HashSet::new(),
+            stats: BuildStats::default(),
+            progress: None,
+            #[cfg(feature = "lz4")]
+            compression: None,
+            #[cfg(feature = "encryption")]
+            cipher: None,
+            #[cfg(feature = "signing")]
+            signing_key: None,
+            checksums: false,
+            metadata: None,
+            digest: None,
+            extend_error: None,
+            format: PhantomData,
+            hasher: PhantomData,
         })
     }
+}
 
-    fn pos_plus(&mut self, len: u32) -> Result<()> {
-        if self.pos + len < len {
-            err_toobig()
-        } else {
-            self.pos += len;
-            Ok(())
+impl<F: Format, H: CdbHash, W: Write + Seek> GenericCDBMake<F, H, W> {
+    /// Switch to hashing keys with the hash function `H2` instead of the
+    /// default [`DjbHash`], to make files for readers using the same
+    /// function.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any records have already been added.
+    pub fn with_hasher<H2: CdbHash>(self) -> GenericCDBMake<F, H2, W> {
+        assert_eq!(
+            self.pos,
+            F::HEADER_SIZE as u64,
+            "hasher must be chosen before adding records"
+        );
+        GenericCDBMake {
+            entries: self.entries,
+            spill: self.spill,
+            pos: self.pos,
+            file: self.file,
+            bloom_bits_per_key: self.bloom_bits_per_key,
+            duplicates: self.duplicates,
+            seen: self.seen,
+            stats: self.stats,
+            progress: self.progress,
+            #[cfg(feature = "lz4")]
+            compression: self.compression,
+            #[cfg(feature = "encryption")]
+            cipher: self.cipher,
+            #[cfg(feature = "signing")]
+            signing_key: self.signing_key,
+            checksums: self.checksums,
+            metadata: self.metadata,
+            digest: self.digest,
+            extend_error: self.extend_error,
+            format: PhantomData,
+            hasher: PhantomData,
         }
     }
 
-    fn add_end(&mut self, keylen: u32, datalen: u32, hash: u32) -> Result<()> {
-        self.entries[(hash & 0xff) as usize].push(HashPos {
+    fn pos_plus(&mut self, len: u64) -> Result<()> {
+        match self.pos.checked_add(len) {
+            Some(pos) if pos <= F::MAX_SIZE => {
+                self.pos = pos;
+                Ok(())
+            }
+            _ => err_toobig(),
+        }
+    }
+
+    fn add_end(&mut self, keylen: u64, datalen: u64, hash: u32) -> Result<()> {
+        let entry = HashPos {
             hash,
             pos: self.pos,
-        });
-        self.pos_plus(8)?;
+        };
+        match &mut self.spill {
+            Some(spill) => spill.push(entry)?,
+            None => self.entries[(hash & 0xff) as usize].push(entry),
+        }
+        self.pos_plus(F::PAIR_SIZE as u64)?;
         self.pos_plus(keylen)?;
         self.pos_plus(datalen)?;
+        self.stats.records += 1;
+        self.stats.keys_bytes += keylen;
+        self.stats.values_bytes += datalen;
+        self.report(BuildProgress::Record {
+            records: self.stats.records,
+            bytes: self.pos,
+        });
         Ok(())
     }
 
-    fn add_begin(&mut self, keylen: u32, datalen: u32) -> Result<()> {
-        let mut buf = [0; 8];
-        uint32::pack2(&mut buf[0..8], keylen, datalen);
-        self.file.write_all(&buf)?;
+    fn report(&mut self, progress: BuildProgress) {
+        if let Some(hook) = &mut self.progress {
+            (hook.0)(progress);
+        }
+    }
+
+    fn add_begin(&mut self, keylen: u64, datalen: u64) -> Result<()> {
+        let mut buf = [0; 16];
+        F::pack2(&mut buf, keylen, datalen);
+        self.file.write_all(&buf[..F::PAIR_SIZE])?;
         Ok(())
     }
 
     /// Add a record to the CDB file.
-    pub fn add(&mut self, key: &[u8], data: &[u8]) -> Result<()> {
-        if key.len() >= 0xffffffff || data.len() >= 0xffffffff {
-            return Err(io::Error::new(io::ErrorKind::Other, "Key or data too big"));
+    ///
+    /// The key and value may be anything which can be viewed as bytes,
+    /// such as `&str`, `String`, `Vec<u8>` or a byte array.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut cdb = cdb32::CDBMake::in_memory()?;
+    /// cdb.add("one", "Hello")?;
+    /// cdb.add(String::from("two"), vec![1, 2, 3])?;
+    /// cdb.add(b"three", [4, 5, 6])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add<K: AsRef<[u8]>, V: AsRef<[u8]>>(&mut self, key: K, data: V) -> Result<()> {
+        let key = key.as_ref();
+        self.add_hashed(key, data.as_ref(), H::hash(key))
+    }
+
+    /// Add a record to the CDB file, using the precomputed hash of its
+    /// key.
+    pub fn add_with_key(&mut self, key: &CDBKey, data: &[u8]) -> Result<()> {
+        self.add_hashed(key.as_bytes(), data, key.hash_value())
+    }
+
+    /// Check a new key against the duplicate policy, returning `false`
+    /// if the record should be dropped.
+    fn admit(&mut self, key: &[u8]) -> Result<bool> {
+        if self.duplicates == DuplicatePolicy::Allow || self.seen.insert(key.to_vec()) {
+            return Ok(true);
+        }
+        match self.duplicates {
+            DuplicatePolicy::Reject => Err(Error::DuplicateKey.into()),
+            _ => Ok(false),
+        }
+    }
+
+    fn add_hashed(&mut self, key: &[u8], data: &[u8], hash: u32) -> Result<()> {
+        if key.len() as u64 >= F::MAX_SIZE {
+            return Err(Error::KeyTooLong.into());
         }
-        self.add_begin(key.len() as u32, data.len() as u32)?;
+        if data.len() as u64 >= F::MAX_SIZE {
+            return Err(Error::ValueTooLong.into());
+        }
+        if !self.admit(key)? {
+            return Ok(());
+        }
+        if let Some(digest) = &mut self.digest {
+            digest.add(key, Sha256::digest(data).into());
+        }
+        #[cfg(feature = "lz4")]
+        let encoded;
+        #[cfg(feature = "lz4")]
+        let data = match self.compression {
+            Some((Compression::Lz4, threshold)) => {
+                encoded = compress::encode(data, threshold);
+                if encoded.len() as u64 >= F::MAX_SIZE {
+                    return Err(Error::ValueTooLong.into());
+                }
+                &encoded[..]
+            }
+            None => data,
+        };
+        #[cfg(feature = "encryption")]
+        let sealed;
+        #[cfg(feature = "encryption")]
+        let data = match &self.cipher {
+            Some(cipher) => {
+                sealed = cipher.seal(key, data);
+                if sealed.len() as u64 >= F::MAX_SIZE {
+                    return Err(Error::ValueTooLong.into());
+                }
+                &sealed[..]
+            }
+            None => data,
+        };
+        let checksum = self.checksums.then(|| checksum::checksum(key, data));
+        let dlen = data.len() as u64 + checksum.map_or(0, |_| CHECKSUM_SIZE);
+        if dlen >= F::MAX_SIZE {
+            return Err(Error::ValueTooLong.into());
+        }
+        self.add_begin(key.len() as u64, dlen)?;
         self.file.write_all(key)?;
         self.file.write_all(data)?;
-        self.add_end(key.len() as u32, data.len() as u32, hash(key))
+        if let Some(checksum) = checksum {
+            self.file.write_all(&checksum.to_le_bytes())?;
+        }
+        self.add_end(key.len() as u64, dlen, hash)
     }
 
-    /// Set the permissions on the underlying file.
-    pub fn set_permissions(&self, perm: fs::Permissions) -> Result<()> {
-        self.file.get_ref().set_permissions(perm)
+    /// Add a record to the CDB file, copying exactly `len` bytes of its
+    /// value from `reader`.
+    ///
+    /// The value is never held in memory all at once, so this suits
+    /// values too large to buffer. If `reader` ends early an error is
+    /// returned, and the partially written file should be discarded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut cdb = cdb32::CDBMake::in_memory()?;
+    /// cdb.add_stream(b"big", &mut std::io::repeat(b'x'), 100_000)?;
+    /// let cdb = cdb32::CDB::from_bytes(cdb.into_vec()?)?;
+    /// assert_eq!(cdb.get(b"big").unwrap()?.len(), 100_000);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_stream<R: Read + ?Sized>(
+        &mut self,
+        key: &[u8],
+        reader: &mut R,
+        len: u64,
+    ) -> Result<()> {
+        if key.len() as u64 >= F::MAX_SIZE {
+            return Err(Error::KeyTooLong.into());
+        }
+        if len >= F::MAX_SIZE {
+            return Err(Error::ValueTooLong.into());
+        }
+        #[cfg(feature = "encryption")]
+        if self.cipher.is_some() {
+            let mut data = Vec::new();
+            reader.take(len).read_to_end(&mut data)?;
+            if data.len() as u64 != len {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Stream ended before the record length",
+                ));
+            }
+            return self.add_hashed(key, &data, H::hash(key));
+        }
+        if !self.admit(key)? {
+            return Ok(());
+        }
+        let stored = len + self.value_overhead();
+        if stored >= F::MAX_SIZE {
+            return Err(Error::ValueTooLong.into());
+        }
+        self.add_begin(key.len() as u64, stored)?;
+        self.file.write_all(key)?;
+        let (copied, _) = self.write_stream(key, &mut reader.take(len))?;
+        if copied != len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Stream ended before the record length",
+            ));
+        }
+        self.add_end(key.len() as u64, stored, H::hash(key))
+    }
+
+    /// Add a record to the CDB file, copying its value from `reader`
+    /// until it ends.
+    ///
+    /// This is like [`add_stream`](Self::add_stream) for values whose
+    /// length is not known in advance. The record header is written
+    /// once the value has been copied, by seeking back to it.
+    pub fn add_stream_unsized<R: Read + ?Sized>(
+        &mut self,
+        key: &[u8],
+        reader: &mut R,
+    ) -> Result<()> {
+        if key.len() as u64 >= F::MAX_SIZE {
+            return Err(Error::KeyTooLong.into());
+        }
+        #[cfg(feature = "encryption")]
+        if self.cipher.is_some() {
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data)?;
+            return self.add_hashed(key, &data, H::hash(key));
+        }
+        if !self.admit(key)? {
+            return Ok(());
+        }
+        self.add_begin(key.len() as u64, 0)?;
+        self.file.write_all(key)?;
+        let (_, len) = self.write_stream(key, reader)?;
+        if len >= F::MAX_SIZE {
+            return Err(Error::ValueTooLong.into());
+        }
+        self.file.seek(io::SeekFrom::Start(self.pos))?;
+        self.add_begin(key.len() as u64, len)?;
+        self.file
+            .seek(io::SeekFrom::Current((key.len() as u64 + len) as i64))?;
+        self.add_end(key.len() as u64, len, H::hash(key))
+    }
+
+    /// Return what to write before a value which is stored as is.
+    fn raw_prefix(&self) -> &'static [u8] {
+        #[cfg(feature = "lz4")]
+        if self.compression.is_some() {
+            return &[compress::RAW];
+        }
+        &[]
+    }
+
+    /// Return how many bytes longer a value is when stored as is.
+    fn value_overhead(&self) -> u64 {
+        let checksum = if self.checksums { CHECKSUM_SIZE } else { 0 };
+        self.raw_prefix().len() as u64 + checksum
+    }
+
+    /// Write the value of a record from `reader`, returning the number
+    /// of bytes copied and the length of the stored value.
+    fn write_stream<R: Read + ?Sized>(&mut self, key: &[u8], reader: &mut R) -> Result<(u64, u64)> {
+        let prefix = self.raw_prefix();
+        self.file.write_all(prefix)?;
+        let mut crc = Crc32::new();
+        crc.update(key);
+        crc.update(prefix);
+        let mut out = ValueWriter {
+            inner: &mut self.file,
+            crc,
+            digest: self.digest.as_ref().map(|_| Sha256::new()),
+        };
+        let copied = io::copy(reader, &mut out)?;
+        let (crc, digest) = (out.crc, out.digest);
+        if self.checksums {
+            self.file.write_all(&crc.finish().to_le_bytes())?;
+        }
+        if let (Some(content), Some(digest)) = (&mut self.digest, digest) {
+            content.add(key, digest.finalize().into());
+        }
+        Ok((copied, copied + self.value_overhead()))
+    }
+
+    /// Follow every value with a checksum of its record, which readers
+    /// of this crate check to detect corruption.
+    ///
+    /// The checksum is a CRC-32 of the key and the stored value. Readers
+    /// check it on every read unless told not to with
+    /// [`OpenOptions::verify_checksums`](crate::OpenOptions::verify_checksums),
+    /// and [`GenericCDB::verify`](crate::GenericCDB::verify) checks every
+    /// record. Other CDB readers see the checksum as the last four bytes
+    /// of each value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any records have already been added.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut cdb = cdb32::CDBMake::in_memory()?;
+    /// cdb.set_checksums(true);
+    /// cdb.add(b"one", b"Hello")?;
+    /// let cdb = cdb32::CDB::from_bytes(cdb.into_vec()?)?;
+    /// assert!(cdb.has_checksums());
+    /// assert_eq!(cdb.get(b"one").unwrap()?, b"Hello");
+    /// assert!(cdb.verify()?.is_ok());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_checksums(&mut self, checksums: bool) {
+        assert_eq!(
+            self.pos,
+            F::HEADER_SIZE as u64,
+            "checksums must be chosen before adding records"
+        );
+        self.checksums = checksums;
+    }
+
+    /// Append a [`Metadata`](crate::Metadata) section when finishing, recording the
+    /// number of records, a digest of their contents, `version` as the
+    /// version of the application building the database, and `created`
+    /// as the build time, or the time of finishing if `None`.
+    ///
+    /// Passing a fixed `created` time keeps the output identical between
+    /// builds of the same records. So does setting the
+    /// `SOURCE_DATE_EPOCH` environment variable to a number of seconds
+    /// since the Unix epoch, which is used in place of the time of
+    /// finishing, as for other reproducible build tools. Readers which do not know about the
+    /// section ignore it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any records have already been added.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// let created = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    /// let mut cdb = cdb32::CDBMake::in_memory()?;
+    /// cdb.set_metadata(env!("CARGO_PKG_VERSION"), Some(created));
+    /// cdb.add(b"one", b"Hello")?;
+    /// let cdb = cdb32::CDB::from_bytes(cdb.into_vec()?)?;
+    /// assert_eq!(cdb.metadata()?.unwrap().created, created);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_metadata(&mut self, version: &str, created: Option<SystemTime>) {
+        assert_eq!(
+            self.pos,
+            F::HEADER_SIZE as u64,
+            "metadata must be chosen before adding records"
+        );
+        self.metadata = Some(MetadataBuilder::new(version, created));
+        self.set_digest(true);
+    }
+
+    /// Keep a running digest of the records as they are added, returned
+    /// in [`BuildStats::digest`] when finishing. It equals the
+    /// [`GenericCDB::digest`] of the finished database, so a build can be
+    /// compared with an existing file without reading the file back.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any records have already been added.
+    pub fn set_digest(&mut self, digest: bool) {
+        assert_eq!(
+            self.pos,
+            F::HEADER_SIZE as u64,
+            "digest must be chosen before adding records"
+        );
+        // The metadata and signature sections need the digest.
+        #[cfg(feature = "signing")]
+        let digest = digest || self.signing_key.is_some();
+        self.digest = (digest || self.metadata.is_some()).then(ContentDigest::new);
+    }
+
+    /// Sign the database with `key` when finishing, appending an Ed25519
+    /// signature over the content digest and the rest of the file, so
+    /// that readers holding the public key can check it with
+    /// [`GenericCDB::open_verified`].
+    ///
+    /// The signature section must stay last: anything appended to the
+    /// file afterwards makes the check fail. Readers which do not know
+    /// about signatures ignore it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any records have already been added.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// # let tmp_dir = tempfile::tempdir()?;
+    /// # std::env::set_current_dir(tmp_dir.path())?;
+    /// use cdb32::{SigningKey, CDB};
+    ///
+    /// let key = SigningKey::from_seed([7; 32]);
+    /// let mut cdb = cdb32::CDBWriter::create("signed.cdb")?;
+    /// cdb.set_signing_key(&key);
+    /// cdb.add(b"one", b"Hello")?;
+    /// cdb.finish()?;
+    ///
+    /// let cdb = CDB::open_verified("signed.cdb", &key.verifying_key())?;
+    /// assert_eq!(cdb.get(b"one").unwrap()?, b"Hello");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "signing")]
+    pub fn set_signing_key(&mut self, key: &SigningKey) {
+        assert_eq!(
+            self.pos,
+            F::HEADER_SIZE as u64,
+            "signing key must be chosen before adding records"
+        );
+        self.signing_key = Some(key.clone());
+        self.set_digest(true);
+    }
+
+    /// Compress values of at least `threshold` bytes with `compression`,
+    /// where that makes them smaller.
+    ///
+    /// Readers of this crate built with the `lz4` feature decompress the
+    /// values transparently, while others refuse to open the file.
+    /// Values added with [`add_stream`](Self::add_stream) are not
+    /// compressed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any records have already been added.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::Compression;
+    ///
+    /// let mut cdb = cdb32::CDBMake::in_memory()?;
+    /// cdb.set_compression(Compression::Lz4, 64);
+    /// cdb.add(b"text", "all work and no play ".repeat(100).as_bytes())?;
+    /// let bytes = cdb.into_vec()?;
+    /// assert!(bytes.len() < 2048 + 200);
+    ///
+    /// let cdb = cdb32::CDB::from_bytes(bytes)?;
+    /// assert_eq!(cdb.get(b"text").unwrap()?.len(), 2100);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "lz4")]
+    pub fn set_compression(&mut self, compression: Compression, threshold: usize) {
+        assert_eq!(
+            self.pos,
+            F::HEADER_SIZE as u64,
+            "compression must be chosen before adding records"
+        );
+        self.compression = Some((compression, threshold));
+    }
+
+    /// Encrypt every value with ChaCha20-Poly1305 under `key`, so that
+    /// the file holds no plaintext values.
+    ///
+    /// Each value is sealed together with its record key, and its nonce
+    /// is derived from the record, so rebuilding from the same records
+    /// gives the same file. Keys are stored in the clear, since lookups
+    /// hash them. Readers of this crate built with the `encryption`
+    /// feature decrypt the values transparently when opened with the key
+    /// through [`OpenOptions::encryption_key`](crate::OpenOptions::encryption_key),
+    /// while others refuse to open the file. Values added with
+    /// [`add_stream`](Self::add_stream) are read into memory to be
+    /// encrypted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any records have already been added.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::{EncryptionKey, OpenOptions, CDB};
+    ///
+    /// let key = EncryptionKey::new([0x42; 32]);
+    /// let mut cdb = cdb32::CDBMake::in_memory()?;
+    /// cdb.set_encryption(&key);
+    /// cdb.add(b"password", b"hunter2")?;
+    /// let bytes = cdb.into_vec()?;
+    /// assert!(!bytes.windows(7).any(|window| window == b"hunter2"));
+    ///
+    /// let cdb = CDB::from_bytes_with_options(bytes, OpenOptions::new().encryption_key(&key))?;
+    /// assert_eq!(cdb.get(b"password").unwrap()?, b"hunter2");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "encryption")]
+    pub fn set_encryption(&mut self, key: &EncryptionKey) {
+        assert_eq!(
+            self.pos,
+            F::HEADER_SIZE as u64,
+            "encryption must be chosen before adding records"
+        );
+        self.cipher = Some(Cipher::new(key));
+    }
+
+    /// Append a bloom filter over the keys when finishing, using
+    /// `bits_per_key` bits of space for each record, or none if zero.
+    ///
+    /// Readers use the filter to answer most lookups of absent keys
+    /// without reading the hash tables. Ten bits per key give a false
+    /// positive rate of about 1%. Readers which do not know about the
+    /// filter ignore it.
+    pub fn set_bloom_filter(&mut self, bits_per_key: u32) {
+        self.bloom_bits_per_key = bits_per_key;
+    }
+
+    /// Choose what happens to records added with a key already added,
+    /// for records added from now on.
+    ///
+    /// Any policy other than [`DuplicatePolicy::Allow`] keeps a copy of
+    /// every key in memory until the file is finished.
+    pub fn set_duplicate_policy(&mut self, duplicates: DuplicatePolicy) {
+        self.duplicates = duplicates;
+    }
+
+    /// Keep the hash table entries in a temporary file in `dir` instead
+    /// of in memory.
+    ///
+    /// Each record normally costs 16 bytes of memory until the file is
+    /// finished, which adds up for hundreds of millions of records. With
+    /// this set, entries are written to disk and read back while
+    /// finishing, holding no more than `max_entries` of them in memory at
+    /// once, unless a single hash table has more. Finishing then reads
+    /// the temporary file once for each batch of tables that fits.
+    ///
+    /// Entries already added are moved to the temporary file.
+    pub fn spill_to_disk<P: AsRef<Path>>(&mut self, dir: P, max_entries: usize) -> Result<()> {
+        let mut spill = Spill::new(dir.as_ref(), max_entries)?;
+        for entry in self.entries.iter_mut().flat_map(mem::take) {
+            spill.push(entry)?;
+        }
+        self.spill = Some(spill);
+        Ok(())
+    }
+
+    /// Call `callback` as the build progresses: after every record, and
+    /// after each hash table written while finishing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use cdb32::BuildProgress;
+    ///
+    /// let mut cdb = cdb32::CDBMake::in_memory()?;
+    /// cdb.set_progress(|progress| match progress {
+    ///     BuildProgress::Record { records, bytes } if records % 100_000 == 0 => {
+    ///         eprintln!("{} records, {} bytes", records, bytes)
+    ///     }
+    ///     BuildProgress::Table { tables, .. } => eprintln!("{}/256 tables", tables),
+    ///     _ => {}
+    /// });
+    /// cdb.add(b"one", b"Hello")?;
+    /// cdb.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_progress<P: FnMut(BuildProgress) + Send + 'static>(&mut self, callback: P) {
+        self.progress = Some(ProgressHook(Box::new(callback)));
     }
 
-    /// Finish writing to the CDB file and flush its contents.
-    pub fn finish(mut self) -> Result<()> {
-        let mut buf = [0; 8];
+    /// Finish writing to the CDB file and flush its contents, returning
+    /// statistics about the database built.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut cdb = cdb32::CDBMake::in_memory()?;
+    /// cdb.add(b"one", b"Hello")?;
+    /// cdb.add(b"two", b"Goodbye")?;
+    /// let stats = cdb.finish()?;
+    /// assert_eq!(stats.records, 2);
+    /// assert_eq!(stats.keys_bytes, 6);
+    /// assert_eq!(stats.values_bytes, 12);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn finish(mut self) -> Result<BuildStats> {
+        self.write_tables()?;
+        Ok(self.stats())
+    }
+
+    /// Finish writing the CDB and return the underlying writer.
+    pub fn into_inner(self) -> Result<W> {
+        Ok(self.finish_inner()?.0)
+    }
+
+    /// Finish writing the CDB and return the underlying writer, along
+    /// with the statistics of the build.
+    pub(crate) fn finish_inner(mut self) -> Result<(W, BuildStats)> {
+        self.write_tables()?;
+        let stats = self.stats();
+        let file = self.file.into_inner().map_err(|err| err.into_error())?;
+        Ok((file, stats))
+    }
 
-        let maxsize = self.entries.iter().fold(1, |acc, e| max(acc, e.len() * 2));
-        let count = self.entries.iter().fold(0, |acc, e| acc + e.len());
-        if maxsize + count > (0xffffffff / 8) {
+    fn write_tables(&mut self) -> Result<()> {
+        let header = self.write_index()?;
+        self.file.flush()?;
+        self.file.seek(io::SeekFrom::Start(0))?;
+        self.file.write_all(&header)?;
+        self.file.flush()?;
+        Ok(())
+    }
+
+    /// Write the hash tables and any appended sections after the
+    /// records, returning the header to be written at the start.
+    pub(crate) fn write_index(&mut self) -> Result<Vec<u8>> {
+        if let Some(err) = self.extend_error.take() {
+            return Err(err);
+        }
+        let mut buf = [0; 16];
+        let buf = &mut buf[..F::PAIR_SIZE];
+
+        let counts = match &self.spill {
+            Some(spill) => spill.counts().to_vec(),
+            None => self.entries.iter().map(Vec::len).collect(),
+        };
+        let maxsize = counts.iter().fold(1, |acc, &n| max(acc, n * 2));
+        let count = counts.iter().sum::<usize>();
+        if (maxsize + count) as u64 > (F::MAX_SIZE / F::PAIR_SIZE as u64) {
             return err_toobig();
         }
 
+        let bloom = match (&mut self.spill, self.bloom_bits_per_key) {
+            (_, 0) => None,
+            (Some(spill), bits_per_key) => Some(spill.bloom(count, bits_per_key)?),
+            (None, bits_per_key) => {
+                let hashes = self.entries.iter().flatten().map(|e| e.hash);
+                Some(bloom::build(hashes, count, bits_per_key))
+            }
+        };
+
         let mut table = vec![HashPos { hash: 0, pos: 0 }; maxsize];
 
-        let mut header = [0_u8; 2048];
+        // Everything from the first hash table on is signed through its
+        // hash.
+        #[cfg(feature = "signing")]
+        let mut tail = self.signing_key.as_ref().map(|_| Sha256::new());
+        #[cfg(not(feature = "signing"))]
+        let mut tail: Option<Sha256> = None;
+
+        let mut header = vec![0_u8; F::HEADER_SIZE];
+        let mut loaded = 0;
         for i in 0..256 {
-            let len = self.entries[i].len() * 2;
-            let j = i * 8;
-            uint32::pack2(&mut header[j..j + 8], self.pos, len as u32);
+            if i == loaded {
+                loaded = match &mut self.spill {
+                    Some(spill) => spill.load(i, &mut self.entries)?,
+                    None => 256,
+                };
+            }
+            let entries = mem::take(&mut self.entries[i]);
+            let len = entries.len() * 2;
+            let j = i * F::PAIR_SIZE;
+            F::pack2(&mut header[j..j + F::PAIR_SIZE], self.pos, len as u64);
 
-            for e in self.entries[i].iter() {
+            for e in entries.iter() {
                 let mut wh = (e.hash as usize >> 8) % len;
+                let mut chain = 1;
                 while table[wh].pos != 0 {
                     wh += 1;
                     if wh == len {
                         wh = 0;
                     }
+                    chain += 1;
                 }
                 table[wh] = *e;
+                self.stats.max_chain = max(self.stats.max_chain, chain);
             }
 
             for hp in table.iter_mut().take(len) {
-                hp.pack(&mut buf);
-                self.file.write_all(&buf)?;
-                self.pos_plus(8)?;
+                hp.pack::<F>(buf);
+                self.file.write_all(buf)?;
+                if let Some(tail) = &mut tail {
+                    tail.update(&*buf);
+                }
+                self.pos_plus(F::PAIR_SIZE as u64)?;
                 *hp = HashPos { hash: 0, pos: 0 };
             }
+            self.report(BuildProgress::Table {
+                tables: i + 1,
+                bytes: self.pos,
+            });
+        }
+
+        if let Some(body) = bloom {
+            self.write_section(&trailer::BLOOM_MAGIC, &body, &mut tail)?;
+        }
+
+        #[cfg(feature = "lz4")]
+        if let Some((Compression::Lz4, _)) = self.compression {
+            self.write_section(&trailer::COMPRESSION_MAGIC, &[compress::LZ4_ID], &mut tail)?;
+        }
+
+        #[cfg(feature = "encryption")]
+        if let Some(key_check) = self.cipher.as_ref().map(Cipher::key_check) {
+            let mut body = vec![encrypt::CHACHA20_POLY1305_ID];
+            body.extend_from_slice(&key_check);
+            self.write_section(&trailer::ENCRYPTION_MAGIC, &body, &mut tail)?;
+        }
+
+        if self.checksums {
+            self.write_section(&trailer::CHECKSUM_MAGIC, &[CRC32_ID], &mut tail)?;
+        }
+
+        self.stats.digest = self.digest.take().map(ContentDigest::finish);
+        if let Some(metadata) = self.metadata.take() {
+            let body = metadata.finish(self.stats.records, self.stats.digest.unwrap());
+            self.write_section(&trailer::METADATA_MAGIC, &body, &mut tail)?;
+        }
+
+        #[cfg(feature = "signing")]
+        if let (Some(key), Some(tail)) = (&self.signing_key, tail) {
+            let message =
+                signature::message(&header, &self.stats.digest.unwrap(), tail.finalize().into());
+            let body = signature::section_body(key, &message);
+            let len = trailer::write_section(&mut self.file, &trailer::SIGNATURE_MAGIC, &body)?;
+            self.pos_plus(len)?;
         }
 
         self.file.flush()?;
-        self.file.seek(io::SeekFrom::Start(0))?;
-        self.file.write_all(&header)?;
-        self.file.flush()?;
+        self.stats.file_size = self.pos;
+        Ok(header)
+    }
+
+    /// Write an appended section, adding it to `tail` if the database is
+    /// signed.
+    fn write_section(
+        &mut self,
+        magic: &trailer::Magic,
+        body: &[u8],
+        tail: &mut Option<Sha256>,
+    ) -> Result<()> {
+        let mut section = Vec::with_capacity(body.len() + trailer::FOOTER_SIZE as usize);
+        trailer::write_section(&mut section, magic, body)?;
+        if let Some(tail) = tail {
+            tail.update(&section);
+        }
+        self.file.write_all(&section)?;
+        self.pos_plus(section.len() as u64)
+    }
+
+    /// Return the statistics of the build, which are complete once the
+    /// index is written.
+    pub(crate) fn stats(&self) -> BuildStats {
+        self.stats
+    }
+
+    /// Return the underlying writer, which holds everything written so
+    /// far if the maker was created without a buffer.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn get_mut(&mut self) -> &mut W {
+        self.file.get_mut()
+    }
+}
+
+impl<F: Format, H: CdbHash> GenericCDBMake<F, H> {
+    /// Set the permissions on the underlying file.
+    pub fn set_permissions(&self, perm: fs::Permissions) -> Result<()> {
+        self.file.get_ref().set_permissions(perm)
+    }
+
+    /// Allocate disk space for a database of about `approx_bytes` up
+    /// front.
+    ///
+    /// Allocating the space in one go keeps the file from fragmenting as
+    /// it grows, and a full disk is reported here rather than late in the
+    /// build. The file's size is not changed, so an estimate which is too
+    /// small or too large does no harm, but space reserved beyond the end
+    /// of the finished file stays allocated until the file is truncated,
+    /// which [`GenericCDBWriter`] does when finishing.
+    ///
+    /// This uses `fallocate` on Linux, and does nothing on other
+    /// platforms or on filesystems which do not support it.
+    pub fn reserve(&self, approx_bytes: u64) -> Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::io::AsRawFd;
+
+            let len = approx_bytes.min(i64::MAX as u64) as libc::off_t;
+            let fd = self.file.get_ref().as_raw_fd();
+            let ret = unsafe { libc::fallocate(fd, libc::FALLOC_FL_KEEP_SIZE, 0, len) };
+            if ret != 0 {
+                let err = io::Error::last_os_error();
+                if err.raw_os_error() != Some(libc::EOPNOTSUPP) {
+                    return Err(err);
+                }
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        let _ = approx_bytes;
         Ok(())
     }
 }
 
-/// A CDB file writer which handles atomic updating.
+impl<F: Format, H: CdbHash> GenericCDBMake<F, H, io::Cursor<Vec<u8>>> {
+    /// Finish building an in-memory CDB and return its bytes.
+    pub fn into_vec(self) -> Result<Vec<u8>> {
+        Ok(self.into_inner()?.into_inner())
+    }
+}
+
+/// Add each `(key, value)` pair as a record.
+///
+/// As `extend` cannot return errors, the first error stops adding
+/// records and is returned when finishing instead.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// let mut cdb = cdb32::CDBMake::in_memory()?;
+/// cdb.extend([("one", "Hello"), ("two", "Goodbye")]);
+/// let cdb = cdb32::CDB::from_bytes(cdb.into_vec()?)?;
+/// assert_eq!(cdb.len(), 2);
+/// # Ok(())
+/// # }
+/// ```
+impl<F: Format, H: CdbHash, W: Write + Seek, K: AsRef<[u8]>, V: AsRef<[u8]>> Extend<(K, V)>
+    for GenericCDBMake<F, H, W>
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, records: I) {
+        if self.extend_error.is_some() {
+            return;
+        }
+        for (key, value) in records {
+            if let Err(err) = self.add(key, value) {
+                self.extend_error = Some(err);
+                return;
+            }
+        }
+    }
+}
+
+/// How much [`GenericCDBWriter::finish`] does to make sure the new file
+/// survives a crash.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Durability {
+    /// Leave flushing to the operating system. A crash shortly after
+    /// finishing may lose the new file or leave it empty.
+    #[default]
+    None,
+    /// Sync the temporary file to disk before renaming it into place, so
+    /// the destination never refers to incomplete contents.
+    File,
+    /// Also sync the destination directory after the rename, so the
+    /// rename itself is persisted. Directories are only synced on Unix.
+    FileAndDir,
+}
+
+/// A CDB file writer which handles atomic updating, generic over the
+/// on-disk [`Format`].
+///
+/// Most code should use the [`CDBWriter`] or [`CDB64Writer`] aliases.
 ///
 /// Using this type, a CDB file is safely written by first creating a
 /// temporary file, building the CDB structure into that temporary file,
@@ -175,34 +1194,182 @@ impl CDBMake {
 /// # }
 /// ```
 #[derive(Debug)]
-pub struct CDBWriter {
+pub struct GenericCDBWriter<F, H = DjbHash> {
     dstname: PathBuf,
     tmpname: PathBuf,
-    cdb: Option<CDBMake>,
+    durability: Durability,
+    /// Whether `tmpname` names our temporary file and should be removed
+    /// if it is not renamed. An anonymous file has no name until
+    /// finishing links it.
+    temp_exists: bool,
+    /// Whether space was reserved which should be released when finishing.
+    reserved: bool,
+    /// Whether to write a sorted index next to the file when finishing.
+    sorted_index: bool,
+    /// The key values are encrypted under, needed to open the file again
+    /// to build the sorted index.
+    #[cfg(feature = "encryption")]
+    encryption_key: Option<EncryptionKey>,
+    cdb: Option<GenericCDBMake<F, H>>,
 }
 
-impl CDBWriter {
+/// A CDB file writer for the classic 32-bit format.
+pub type CDBWriter<H = DjbHash> = GenericCDBWriter<Format32, H>;
+
+/// A CDB file writer for the 64-bit format.
+///
+/// This shares the temporary file and rename handling of [`CDBWriter`],
+/// and produces files which may grow beyond 4 GiB.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// # let tmp_dir = tempfile::tempdir()?;
+/// # let path = tmp_dir.path().join("large.cdb");
+/// use cdb32::{CDB64Writer, CDB64};
+///
+/// let mut cdb = CDB64Writer::create(&path)?;
+/// cdb.add(b"one", b"Hello")?;
+/// cdb.finish()?;
+///
+/// let cdb = CDB64::open(&path)?;
+/// assert_eq!(cdb.get(b"one").unwrap()?, b"Hello");
+/// # Ok(())
+/// # }
+/// ```
+pub type CDB64Writer<H = DjbHash> = GenericCDBWriter<Format64, H>;
+
+impl<F: Format> GenericCDBWriter<F> {
     /// Safely create a new CDB file.
     ///
     /// The suffix for the temporary file defaults to `".tmp"`.
-    pub fn create<P: Into<PathBuf>>(filename: P) -> Result<CDBWriter> {
-        CDBWriter::with_suffix(filename, ".tmp")
+    pub fn create<P: Into<PathBuf>>(filename: P) -> Result<Self> {
+        Self::with_suffix(filename, ".tmp")
+    }
+
+    /// Safely create a new CDB file holding the `(key, value)` pairs of
+    /// `records`, finishing it in one call and returning statistics
+    /// about the database built.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// # let tmp_dir = tempfile::tempdir()?;
+    /// # let path = tmp_dir.path().join("users.cdb");
+    /// use cdb32::{CDBWriter, CDB};
+    ///
+    /// let users = vec![("root", "0"), ("daemon", "1")];
+    /// CDBWriter::from_iter(&path, users)?;
+    ///
+    /// assert_eq!(CDB::open(&path)?.get("daemon").unwrap()?, b"1");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_iter<P, I, K, V>(filename: P, records: I) -> Result<BuildStats>
+    where
+        P: Into<PathBuf>,
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let mut cdb = Self::create(filename)?;
+        for (key, value) in records {
+            cdb.add(key, value)?;
+        }
+        cdb.finish()
+    }
+
+    /// Safely create a new CDB file holding the entries of a map, such as
+    /// a [`HashMap`](std::collections::HashMap) or
+    /// [`BTreeMap`](std::collections::BTreeMap), and return statistics
+    /// about the database built.
+    ///
+    /// Records are written in order of their key bytes, so the same map
+    /// always produces the same file whatever its iteration order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// # let tmp_dir = tempfile::tempdir()?;
+    /// # let path = tmp_dir.path().join("users.cdb");
+    /// use std::collections::HashMap;
+    ///
+    /// use cdb32::{CDBWriter, CDB};
+    ///
+    /// let users = HashMap::from([("root", "0"), ("daemon", "1")]);
+    /// CDBWriter::from_map(&path, &users)?;
+    ///
+    /// assert_eq!(CDB::open(&path)?.get("daemon").unwrap()?, b"1");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_map<'a, P, M, K, V>(filename: P, map: &'a M) -> Result<BuildStats>
+    where
+        P: Into<PathBuf>,
+        M: ?Sized,
+        &'a M: IntoIterator<Item = (&'a K, &'a V)>,
+        K: AsRef<[u8]> + ?Sized + 'a,
+        V: AsRef<[u8]> + ?Sized + 'a,
+    {
+        let mut records = map.into_iter().collect::<Vec<_>>();
+        records.sort_by(|a, b| a.0.as_ref().cmp(b.0.as_ref()));
+        Self::from_iter(filename, records)
+    }
+
+    /// Safely create a new CDB file from a map holding several values
+    /// for each key, such as a `HashMap<K, Vec<V>>`, and return
+    /// statistics about the database built.
+    ///
+    /// Keys are written in order of their bytes, as with
+    /// [`from_map`](Self::from_map), and the values of each key in the
+    /// order they are held, which is the order
+    /// [`find`](crate::GenericCDB::find) returns them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// # let tmp_dir = tempfile::tempdir()?;
+    /// # let path = tmp_dir.path().join("groups.cdb");
+    /// use std::collections::HashMap;
+    ///
+    /// use cdb32::{CDBWriter, CDB};
+    ///
+    /// let groups = HashMap::from([("wheel", vec!["root", "admin"])]);
+    /// CDBWriter::from_multimap(&path, &groups)?;
+    ///
+    /// let cdb = CDB::open(&path)?;
+    /// let members = cdb.find("wheel").collect::<std::io::Result<Vec<_>>>()?;
+    /// assert_eq!(members, [b"root".to_vec(), b"admin".to_vec()]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_multimap<'a, P, M, K, VS, V>(filename: P, map: &'a M) -> Result<BuildStats>
+    where
+        P: Into<PathBuf>,
+        M: ?Sized,
+        &'a M: IntoIterator<Item = (&'a K, &'a VS)>,
+        K: AsRef<[u8]> + ?Sized + 'a,
+        VS: ?Sized + 'a,
+        &'a VS: IntoIterator<Item = &'a V>,
+        V: AsRef<[u8]> + ?Sized + 'a,
+    {
+        let mut keys = map.into_iter().collect::<Vec<_>>();
+        keys.sort_by(|a, b| a.0.as_ref().cmp(b.0.as_ref()));
+        let records = keys
+            .into_iter()
+            .flat_map(|(key, values)| values.into_iter().map(move |value| (key, value)));
+        Self::from_iter(filename, records)
     }
 
     /// Safely create a new CDB file, using a specific suffix for the temporary file.
-    pub fn with_suffix<P: Into<PathBuf>>(filename: P, suffix: &str) -> Result<CDBWriter> {
+    pub fn with_suffix<P: Into<PathBuf>>(filename: P, suffix: &str) -> Result<Self> {
         let filename = filename.into();
-        let mut tmpname = filename.clone();
-        let new_extension = match tmpname.extension() {
-            Some(ext) => {
-                let mut ext = ext.to_os_string();
-                ext.push(suffix);
-                ext
-            }
-            None => OsString::from(suffix),
-        };
-        tmpname.set_extension(new_extension);
-        CDBWriter::with_filenames(filename, &tmpname)
+        let tmpname = suffix_name(&filename, suffix);
+        Self::with_filenames(filename, tmpname)
     }
 
     /// Safely create a new CDB file, using two specific file names.
@@ -212,25 +1379,196 @@ impl CDBWriter {
     pub fn with_filenames<P: Into<PathBuf>, Q: Into<PathBuf>>(
         filename: P,
         tmpname: Q,
-    ) -> Result<CDBWriter> {
-        let dstname = filename.into();
+    ) -> Result<Self> {
         let tmpname = tmpname.into();
         let file = fs::File::create(&tmpname)?;
-        let cdb = CDBMake::new(file)?;
-        Ok(CDBWriter {
+        let cdb = GenericCDBMake::new(file)?;
+        Ok(Self::with_temp_file(filename.into(), tmpname, cdb))
+    }
+
+    /// Safely create a new CDB file, using a temporary file with a
+    /// random name in the directory `dir`.
+    ///
+    /// The temporary file is named after the destination, like
+    /// `.file.cdb.Ab3xZ9`. See [`with_temp_pattern`](Self::with_temp_pattern).
+    pub fn with_temp_dir<P: Into<PathBuf>, Q: AsRef<Path>>(filename: P, dir: Q) -> Result<Self> {
+        let filename = filename.into();
+        let pattern = default_pattern(&filename);
+        Self::with_temp_pattern(filename, dir, &pattern)
+    }
+
+    /// Safely create a new CDB file, using a temporary file in the
+    /// directory `dir` named after `pattern`, with any trailing `X`
+    /// characters replaced by random letters and digits.
+    ///
+    /// The temporary file is created exclusively, retrying with another
+    /// name if one already exists, so concurrent writers do not collide.
+    /// An error is returned if `dir` is not on the same filesystem as
+    /// the destination, as the final rename would fail.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// # let tmp_dir = tempfile::tempdir()?;
+    /// # let path = tmp_dir.path().join("file.cdb");
+    /// use cdb32::CDBWriter;
+    ///
+    /// let mut cdb = CDBWriter::with_temp_pattern(&path, tmp_dir.path(), ".file.cdb.XXXXXX")?;
+    /// cdb.add(b"one", b"Hello")?;
+    /// cdb.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_temp_pattern<P: Into<PathBuf>, Q: AsRef<Path>>(
+        filename: P,
+        dir: Q,
+        pattern: &str,
+    ) -> Result<Self> {
+        let dstname = filename.into();
+        let (tmpname, file) = create_temp(&dstname, dir.as_ref(), pattern)?;
+        let cdb = GenericCDBMake::new(file)?;
+        Ok(Self::with_temp_file(dstname, tmpname, cdb))
+    }
+
+    /// Start building a new CDB file with more options than the other
+    /// constructors offer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// # let tmp_dir = tempfile::tempdir()?;
+    /// # let path = tmp_dir.path().join("file.cdb");
+    /// use cdb32::{CDBWriter, DuplicatePolicy, Durability};
+    ///
+    /// let mut cdb = CDBWriter::builder(&path)
+    ///     .durability(Durability::FileAndDir)
+    ///     .duplicates(DuplicatePolicy::Reject)
+    ///     .buffer_size(1 << 20)
+    ///     .create()?;
+    /// cdb.add(b"one", b"Hello")?;
+    /// assert!(cdb.add(b"one", b"again").is_err());
+    /// cdb.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder<P: Into<PathBuf>>(filename: P) -> GenericCDBWriterBuilder<F> {
+        GenericCDBWriterBuilder::new(filename)
+    }
+
+    fn with_temp_file(dstname: PathBuf, tmpname: PathBuf, cdb: GenericCDBMake<F>) -> Self {
+        GenericCDBWriter {
             dstname,
             tmpname,
+            durability: Durability::default(),
+            temp_exists: true,
+            reserved: false,
+            sorted_index: false,
+            #[cfg(feature = "encryption")]
+            encryption_key: None,
+            cdb: Some(cdb),
+        }
+    }
+
+    /// Safely create a new CDB file, building it in an unnamed temporary
+    /// file in the destination directory.
+    ///
+    /// The file is opened with `O_TMPFILE`, so it never appears in the
+    /// directory until [`finish`](GenericCDBWriter::finish) links it in
+    /// and renames it over the destination. An aborted build leaves
+    /// nothing behind, and concurrent writers cannot collide on a
+    /// temporary name. The filesystem must support `O_TMPFILE`.
+    #[cfg(target_os = "linux")]
+    pub fn create_anonymous<P: Into<PathBuf>>(filename: P) -> Result<Self> {
+        use std::os::unix::{fs::OpenOptionsExt, io::AsRawFd};
+
+        let dstname = filename.into();
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_TMPFILE)
+            .open(parent_dir(&dstname))?;
+        // The name the file is linked under just before the rename, made
+        // unique to this process and file descriptor.
+        let mut tmpname = dstname.clone().into_os_string();
+        tmpname.push(format!(".tmp.{}.{}", std::process::id(), file.as_raw_fd()));
+        let cdb = GenericCDBMake::new(file)?;
+        Ok(GenericCDBWriter {
+            dstname,
+            tmpname: tmpname.into(),
+            durability: Durability::default(),
+            temp_exists: false,
+            reserved: false,
+            sorted_index: false,
+            #[cfg(feature = "encryption")]
+            encryption_key: None,
             cdb: Some(cdb),
         })
     }
+}
 
-    /// Add a record to the CDB file.
-    pub fn add(&mut self, key: &[u8], data: &[u8]) -> Result<()> {
+impl<F: Format, H: CdbHash> GenericCDBWriter<F, H> {
+    /// Switch to hashing keys with the hash function `H2` instead of the
+    /// default [`DjbHash`]. See [`GenericCDBMake::with_hasher`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if any records have already been added.
+    pub fn with_hasher<H2: CdbHash>(mut self) -> GenericCDBWriter<F, H2> {
+        GenericCDBWriter {
+            dstname: mem::take(&mut self.dstname),
+            tmpname: mem::take(&mut self.tmpname),
+            durability: self.durability,
+            temp_exists: mem::replace(&mut self.temp_exists, false),
+            reserved: self.reserved,
+            sorted_index: self.sorted_index,
+            #[cfg(feature = "encryption")]
+            encryption_key: self.encryption_key.take(),
+            cdb: Some(self.cdb.take().unwrap().with_hasher()),
+        }
+    }
+
+    /// Add a record to the CDB file. See [`GenericCDBMake::add`].
+    pub fn add<K: AsRef<[u8]>, V: AsRef<[u8]>>(&mut self, key: K, data: V) -> Result<()> {
         // The unwrap() is safe here, as the internal cdb is only ever
         // None during finish(), which does not call this.
         self.cdb.as_mut().unwrap().add(key, data)
     }
 
+    /// Add a record to the CDB file, using the precomputed hash of its
+    /// key.
+    pub fn add_with_key(&mut self, key: &CDBKey, data: &[u8]) -> Result<()> {
+        self.cdb.as_mut().unwrap().add_with_key(key, data)
+    }
+
+    /// Add a record to the CDB file, copying exactly `len` bytes of its
+    /// value from `reader`. See [`GenericCDBMake::add_stream`].
+    pub fn add_stream<R: Read + ?Sized>(
+        &mut self,
+        key: &[u8],
+        reader: &mut R,
+        len: u64,
+    ) -> Result<()> {
+        self.cdb.as_mut().unwrap().add_stream(key, reader, len)
+    }
+
+    /// Add a record to the CDB file, copying its value from `reader`
+    /// until it ends. See [`GenericCDBMake::add_stream_unsized`].
+    pub fn add_stream_unsized<R: Read + ?Sized>(
+        &mut self,
+        key: &[u8],
+        reader: &mut R,
+    ) -> Result<()> {
+        self.cdb.as_mut().unwrap().add_stream_unsized(key, reader)
+    }
+
+    /// Append a bloom filter over the keys when finishing. See
+    /// [`GenericCDBMake::set_bloom_filter`].
+    pub fn set_bloom_filter(&mut self, bits_per_key: u32) {
+        self.cdb.as_mut().unwrap().set_bloom_filter(bits_per_key)
+    }
+
     /// Set permissions on the temporary file.
     ///
     /// This must be done before the file is finished, as the temporary
@@ -239,17 +1577,476 @@ impl CDBWriter {
         self.cdb.as_ref().unwrap().set_permissions(perm)
     }
 
-    pub fn finish(mut self) -> Result<()> {
-        self.cdb.take().unwrap().finish()?;
+    /// Allocate disk space for a database of about `approx_bytes` up
+    /// front. See [`GenericCDBMake::reserve`].
+    ///
+    /// Any space reserved beyond the end of the database is released when
+    /// it is finished.
+    pub fn reserve(&mut self, approx_bytes: u64) -> Result<()> {
+        self.cdb.as_ref().unwrap().reserve(approx_bytes)?;
+        self.reserved = true;
+        Ok(())
+    }
+
+    /// Abandon the CDB file, removing the temporary file.
+    ///
+    /// Dropping an unfinished writer does the same, but ignores any
+    /// error from removing the file.
+    pub fn abort(mut self) -> Result<()> {
+        drop(self.cdb.take());
+        if self.temp_exists {
+            self.temp_exists = false;
+            fs::remove_file(&self.tmpname)?;
+        }
+        Ok(())
+    }
+
+    /// Choose what happens to records added with a key already added.
+    /// See [`GenericCDBMake::set_duplicate_policy`].
+    pub fn set_duplicate_policy(&mut self, duplicates: DuplicatePolicy) {
+        self.cdb.as_mut().unwrap().set_duplicate_policy(duplicates)
+    }
+
+    /// Keep the hash table entries in a temporary file next to the
+    /// temporary CDB file instead of in memory, holding no more than
+    /// `max_entries` in memory while finishing. See
+    /// [`GenericCDBMake::spill_to_disk`].
+    pub fn spill_to_disk(&mut self, max_entries: usize) -> Result<()> {
+        let dir = parent_dir(&self.tmpname);
+        self.cdb.as_mut().unwrap().spill_to_disk(dir, max_entries)
+    }
+
+    /// Call `callback` as the build progresses. See
+    /// [`GenericCDBMake::set_progress`].
+    pub fn set_progress<P: FnMut(BuildProgress) + Send + 'static>(&mut self, callback: P) {
+        self.cdb.as_mut().unwrap().set_progress(callback)
+    }
+
+    /// Compress values of at least `threshold` bytes. See
+    /// [`GenericCDBMake::set_compression`].
+    #[cfg(feature = "lz4")]
+    pub fn set_compression(&mut self, compression: Compression, threshold: usize) {
+        self.cdb
+            .as_mut()
+            .unwrap()
+            .set_compression(compression, threshold)
+    }
+
+    /// Encrypt every value under `key`. See
+    /// [`GenericCDBMake::set_encryption`].
+    #[cfg(feature = "encryption")]
+    pub fn set_encryption(&mut self, key: &EncryptionKey) {
+        self.encryption_key = Some(key.clone());
+        self.cdb.as_mut().unwrap().set_encryption(key)
+    }
+
+    /// Sign the database with `key` when finishing. See
+    /// [`GenericCDBMake::set_signing_key`].
+    #[cfg(feature = "signing")]
+    pub fn set_signing_key(&mut self, key: &SigningKey) {
+        self.cdb.as_mut().unwrap().set_signing_key(key)
+    }
+
+    /// Append a metadata section when finishing. See
+    /// [`GenericCDBMake::set_metadata`].
+    pub fn set_metadata(&mut self, version: &str, created: Option<SystemTime>) {
+        self.cdb.as_mut().unwrap().set_metadata(version, created)
+    }
+
+    /// Keep a running digest of the records. See
+    /// [`GenericCDBMake::set_digest`].
+    pub fn set_digest(&mut self, digest: bool) {
+        self.cdb.as_mut().unwrap().set_digest(digest)
+    }
+
+    /// Follow every value with a checksum of its record. See
+    /// [`GenericCDBMake::set_checksums`].
+    pub fn set_checksums(&mut self, checksums: bool) {
+        self.cdb.as_mut().unwrap().set_checksums(checksums)
+    }
+
+    /// Choose how much to sync to disk when finishing. The default is
+    /// [`Durability::None`].
+    pub fn set_durability(&mut self, durability: Durability) {
+        self.durability = durability;
+    }
+
+    /// Write a [`SortedIndex`] of the keys when finishing, to the path
+    /// given by [`sorted_index_path`], so that the database can be read in
+    /// key order. The index replaces any old one just before the database
+    /// is renamed into place. Off by default.
+    pub fn set_sorted_index(&mut self, sorted_index: bool) {
+        self.sorted_index = sorted_index;
+    }
+
+    /// Finish writing the temporary file and rename it over the
+    /// destination, returning statistics about the database built.
+    pub fn finish(mut self) -> Result<BuildStats> {
+        let stats = self.prepare()?;
+        if self.sorted_index {
+            #[allow(unused_mut)]
+            let mut options = OpenOptions::new();
+            #[cfg(feature = "encryption")]
+            if let Some(key) = &self.encryption_key {
+                options.encryption_key(key);
+            }
+            let cdb = GenericCDB::<F>::open_with_options(&self.tmpname, &options)?;
+            let index = SortedIndex::build(&cdb.with_hasher::<H>())?;
+            index.write_durable(&sorted_index_path(&self.dstname), self.durability)?;
+        }
+        self.publish()?;
+        Ok(stats)
+    }
+
+    /// Finish writing the temporary file, leaving it to be renamed.
+    pub(crate) fn prepare(&mut self) -> Result<BuildStats> {
+        let (file, stats) = self.cdb.take().unwrap().finish_inner()?;
+        if self.reserved {
+            file.set_len(stats.file_size)?;
+        }
+        if self.durability != Durability::None {
+            file.sync_all()?;
+        }
+        #[cfg(target_os = "linux")]
+        if !self.temp_exists {
+            link_anonymous(&file, &self.tmpname)?;
+            self.temp_exists = true;
+        }
+        Ok(stats)
+    }
+
+    /// Rename the prepared temporary file over the destination.
+    pub(crate) fn publish(&mut self) -> Result<()> {
         fs::rename(&self.tmpname, &self.dstname)?;
+        self.temp_exists = false;
+        if self.durability == Durability::FileAndDir {
+            sync_parent(&self.dstname)?;
+        }
         Ok(())
     }
 }
 
-impl Drop for CDBWriter {
+/// Add each `(key, value)` pair as a record. See the `Extend`
+/// implementation of [`GenericCDBMake`].
+impl<F: Format, H: CdbHash, K: AsRef<[u8]>, V: AsRef<[u8]>> Extend<(K, V)>
+    for GenericCDBWriter<F, H>
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, records: I) {
+        self.cdb.as_mut().unwrap().extend(records)
+    }
+}
+
+/// Options for creating a [`GenericCDBWriter`], started with
+/// [`GenericCDBWriter::builder`].
+///
+/// Most code should use the [`CDBWriterBuilder`] or
+/// [`CDB64WriterBuilder`] aliases.
+#[derive(Debug)]
+pub struct GenericCDBWriterBuilder<F> {
+    dstname: PathBuf,
+    suffix: String,
+    temp_dir: Option<PathBuf>,
+    temp_pattern: Option<String>,
+    permissions: Option<fs::Permissions>,
+    durability: Durability,
+    duplicates: DuplicatePolicy,
+    buffer_size: usize,
+    spill: Option<usize>,
+    reserve: Option<u64>,
+    format: PhantomData<F>,
+}
+
+/// Options for creating a [`CDBWriter`].
+pub type CDBWriterBuilder = GenericCDBWriterBuilder<Format32>;
+
+/// Options for creating a [`CDB64Writer`].
+pub type CDB64WriterBuilder = GenericCDBWriterBuilder<Format64>;
+
+impl<F: Format> GenericCDBWriterBuilder<F> {
+    /// Create a new set of options for writing the named file, with the
+    /// same defaults as [`GenericCDBWriter::create`].
+    pub fn new<P: Into<PathBuf>>(filename: P) -> Self {
+        GenericCDBWriterBuilder {
+            dstname: filename.into(),
+            suffix: ".tmp".to_string(),
+            temp_dir: None,
+            temp_pattern: None,
+            permissions: None,
+            durability: Durability::default(),
+            duplicates: DuplicatePolicy::default(),
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            spill: None,
+            reserve: None,
+            format: PhantomData,
+        }
+    }
+
+    /// Name the temporary file after the destination with this suffix
+    /// appended, `".tmp"` by default. See
+    /// [`GenericCDBWriter::with_suffix`].
+    ///
+    /// This is ignored if [`temp_dir`](Self::temp_dir) or
+    /// [`temp_pattern`](Self::temp_pattern) is set.
+    pub fn suffix(&mut self, suffix: &str) -> &mut Self {
+        self.suffix = suffix.to_string();
+        self
+    }
+
+    /// Create the temporary file with a random name in this directory.
+    /// See [`GenericCDBWriter::with_temp_dir`].
+    pub fn temp_dir<P: Into<PathBuf>>(&mut self, dir: P) -> &mut Self {
+        self.temp_dir = Some(dir.into());
+        self
+    }
+
+    /// Name the temporary file after this pattern, placing it in the
+    /// [`temp_dir`](Self::temp_dir) or else next to the destination. See
+    /// [`GenericCDBWriter::with_temp_pattern`].
+    pub fn temp_pattern(&mut self, pattern: &str) -> &mut Self {
+        self.temp_pattern = Some(pattern.to_string());
+        self
+    }
+
+    /// Set the permissions of the new file.
+    pub fn permissions(&mut self, perm: fs::Permissions) -> &mut Self {
+        self.permissions = Some(perm);
+        self
+    }
+
+    /// Choose how much to sync to disk when finishing.
+    pub fn durability(&mut self, durability: Durability) -> &mut Self {
+        self.durability = durability;
+        self
+    }
+
+    /// Choose what happens to records added with a key already added.
+    pub fn duplicates(&mut self, duplicates: DuplicatePolicy) -> &mut Self {
+        self.duplicates = duplicates;
+        self
+    }
+
+    /// Set the size of the write buffer, 8 KiB by default.
+    pub fn buffer_size(&mut self, size: usize) -> &mut Self {
+        self.buffer_size = size;
+        self
+    }
+
+    /// Keep the hash table entries on disk rather than in memory, holding
+    /// no more than `max_entries` in memory while finishing. See
+    /// [`GenericCDBMake::spill_to_disk`].
+    pub fn spill_to_disk(&mut self, max_entries: usize) -> &mut Self {
+        self.spill = Some(max_entries);
+        self
+    }
+
+    /// Allocate disk space for a database of about `approx_bytes` when
+    /// creating the file. See [`GenericCDBMake::reserve`].
+    pub fn reserve(&mut self, approx_bytes: u64) -> &mut Self {
+        self.reserve = Some(approx_bytes);
+        self
+    }
+
+    /// Create the temporary file and return a writer for it.
+    pub fn create(&self) -> Result<GenericCDBWriter<F>> {
+        let (tmpname, file) = if self.temp_dir.is_some() || self.temp_pattern.is_some() {
+            let dir = match &self.temp_dir {
+                Some(dir) => dir,
+                None => parent_dir(&self.dstname),
+            };
+            let pattern = match &self.temp_pattern {
+                Some(pattern) => pattern.clone(),
+                None => default_pattern(&self.dstname),
+            };
+            create_temp(&self.dstname, dir, &pattern)?
+        } else {
+            let tmpname = suffix_name(&self.dstname, &self.suffix);
+            let file = fs::File::create(&tmpname)?;
+            (tmpname, file)
+        };
+        let cdb = GenericCDBMake::with_capacity(self.buffer_size, file)?;
+        let mut writer = GenericCDBWriter::with_temp_file(self.dstname.clone(), tmpname, cdb);
+        if let Some(perm) = &self.permissions {
+            writer.set_permissions(perm.clone())?;
+        }
+        writer.set_durability(self.durability);
+        writer.set_duplicate_policy(self.duplicates);
+        if let Some(max_entries) = self.spill {
+            writer.spill_to_disk(max_entries)?;
+        }
+        if let Some(approx_bytes) = self.reserve {
+            writer.reserve(approx_bytes)?;
+        }
+        Ok(writer)
+    }
+}
+
+/// Return the destination name with `suffix` appended.
+fn suffix_name(filename: &Path, suffix: &str) -> PathBuf {
+    let mut tmpname = filename.to_path_buf();
+    let new_extension = match tmpname.extension() {
+        Some(ext) => {
+            let mut ext = ext.to_os_string();
+            ext.push(suffix);
+            ext
+        }
+        None => OsString::from(suffix),
+    };
+    tmpname.set_extension(new_extension);
+    tmpname
+}
+
+/// Return the temporary name pattern used for a destination, such as
+/// `.file.cdb.XXXXXX`.
+fn default_pattern(filename: &Path) -> String {
+    let mut pattern = OsString::from(".");
+    pattern.push(filename.file_name().unwrap_or_default());
+    pattern.push(".XXXXXX");
+    pattern.to_string_lossy().into_owned()
+}
+
+/// Exclusively create a temporary file in `dir` named after `pattern`.
+fn create_temp(dstname: &Path, dir: &Path, pattern: &str) -> Result<(PathBuf, fs::File)> {
+    check_same_filesystem(dir, parent_dir(dstname))?;
+    create_exclusive(dir, pattern)
+}
+
+/// Create a new file in `dir` named after `pattern`, with any trailing
+/// `X` characters replaced by random letters and digits.
+pub(crate) fn create_exclusive(dir: &Path, pattern: &str) -> Result<(PathBuf, fs::File)> {
+    let stem = pattern.trim_end_matches('X');
+    let random_len = pattern.len() - stem.len();
+    let mut attempts = 0;
+    loop {
+        let tmpname = dir.join(format!("{}{}", stem, random_chars(random_len)));
+        match fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&tmpname)
+        {
+            Ok(file) => return Ok((tmpname, file)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists && random_len > 0 => {
+                attempts += 1;
+                if attempts == 100 {
+                    return Err(err);
+                }
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Replace the file at `path` with `contents`, through a temporary file
+/// renamed over it, synced as `durability` asks.
+pub(crate) fn replace_file(path: &Path, contents: &[u8], durability: Durability) -> Result<()> {
+    let (tmpname, mut file) = create_exclusive(parent_dir(path), &default_pattern(path))?;
+    let written = file
+        .write_all(contents)
+        .and_then(|()| match durability {
+            Durability::None => Ok(()),
+            _ => file.sync_all(),
+        })
+        .and_then(|()| fs::rename(&tmpname, path));
+    if let Err(err) = written {
+        let _ = fs::remove_file(&tmpname);
+        return Err(err);
+    }
+    if durability == Durability::FileAndDir {
+        sync_parent(path)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+/// Return `len` random letters and digits for a temporary file name.
+fn random_chars(len: usize) -> String {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+    use std::time::SystemTime;
+
+    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    if let Ok(now) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        hasher.write_u128(now.as_nanos());
+    }
+    let mut bits = hasher.finish();
+    (0..len)
+        .map(|i| {
+            if i > 0 && i % 10 == 0 {
+                hasher.write_usize(i);
+                bits = hasher.finish();
+            }
+            let c = CHARS[(bits % CHARS.len() as u64) as usize];
+            bits /= CHARS.len() as u64;
+            c as char
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+fn check_same_filesystem(tmpdir: &Path, dstdir: &Path) -> Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    if fs::metadata(tmpdir)?.dev() != fs::metadata(dstdir)?.dev() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "Temporary directory is not on the same filesystem as the destination",
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_same_filesystem(_tmpdir: &Path, _dstdir: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> Result<()> {
+    fs::File::open(parent_dir(path))?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Give a file opened with `O_TMPFILE` a name.
+#[cfg(target_os = "linux")]
+fn link_anonymous(file: &fs::File, path: &Path) -> Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::{ffi::OsStrExt, io::AsRawFd};
+
+    // Linking the file descriptor directly with AT_EMPTY_PATH needs
+    // privileges, but following its /proc entry does not.
+    let src = CString::new(format!("/proc/self/fd/{}", file.as_raw_fd()))?;
+    let dst = CString::new(path.as_os_str().as_bytes())?;
+    let ret = unsafe {
+        libc::linkat(
+            libc::AT_FDCWD,
+            src.as_ptr(),
+            libc::AT_FDCWD,
+            dst.as_ptr(),
+            libc::AT_SYMLINK_FOLLOW,
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+impl<F, H> Drop for GenericCDBWriter<F, H> {
     #[allow(unused_must_use)]
     fn drop(&mut self) {
-        if self.cdb.is_some() {
+        if self.temp_exists {
             fs::remove_file(&self.tmpname);
         }
     }
@@ -0,0 +1,60 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use cdb32::{CDBMake, CDB};
+
+/// An allocator counting the allocations made by the current thread, so
+/// that tests running in parallel do not disturb each other.
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let result = f();
+    (result, ALLOCATIONS.with(Cell::get) - before)
+}
+
+#[test]
+fn test_ref_lookups_do_not_allocate() {
+    let mut checked = CDBMake::in_memory().unwrap();
+    checked.set_checksums(true);
+    checked.add(b"one", b"Hello").unwrap();
+    checked.add(b"one", b", World!").unwrap();
+    let checked = CDB::from_bytes(checked.into_vec().unwrap()).unwrap();
+
+    for cdb in [CDB::open("tests/test1.cdb").unwrap(), checked] {
+        let (value, n) = allocations(|| cdb.get_ref(b"one").unwrap().unwrap());
+        assert_eq!((value, n), (&b"Hello"[..], 0));
+
+        let (found, n) = allocations(|| {
+            let mut found = 0;
+            for value in cdb.find_ref(b"one") {
+                found += value.unwrap().len();
+            }
+            found
+        });
+        assert_eq!((found, n), (13, 0));
+
+        let (missing, n) = allocations(|| cdb.get_ref(b"missing").is_none());
+        assert!(missing);
+        assert_eq!(n, 0);
+        assert_eq!(allocations(|| cdb.count(b"one").unwrap()), (2, 0));
+    }
+}
@@ -0,0 +1,63 @@
+#![cfg(feature = "tokio")]
+
+use std::future::poll_fn;
+use std::pin::Pin;
+
+use cdb32::{AsyncCDB, AsyncCDBWriter, Durability, OpenOptions, CDB};
+use futures_core::Stream;
+
+#[tokio::test]
+async fn test_async_get() {
+    let cdb = AsyncCDB::open("tests/test1.cdb").await.unwrap();
+    assert_eq!(cdb.get(b"two").await.unwrap().unwrap(), b"Goodbye");
+    assert!(cdb.get(b"three").await.is_none());
+}
+
+#[tokio::test]
+async fn test_async_find() {
+    for options in [OpenOptions::new(), OpenOptions::new().mmap(false).clone()] {
+        let cdb = AsyncCDB::open_with_options("tests/test1.cdb", &options)
+            .await
+            .unwrap();
+        let mut values = cdb.find(b"one");
+        let mut found = vec![];
+        while let Some(value) = poll_fn(|cx| Pin::new(&mut values).poll_next(cx)).await {
+            found.push(value.unwrap());
+        }
+        assert_eq!(found, [&b"Hello"[..], b", World!"]);
+        assert!(poll_fn(|cx| Pin::new(&mut values).poll_next(cx))
+            .await
+            .is_none());
+    }
+}
+
+#[tokio::test]
+async fn test_async_writer() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("async.cdb");
+    let mut cdb = AsyncCDBWriter::create(&path).await.unwrap();
+    cdb.set_bloom_filter(10);
+    cdb.set_checksums(true);
+    cdb.set_durability(Durability::FileAndDir);
+    for i in 0..10_000 {
+        cdb.add(format!("key{}", i), vec![b'x'; i % 50])
+            .await
+            .unwrap();
+    }
+    assert!(!path.exists());
+    cdb.finish().await.unwrap();
+
+    let cdb = CDB::open(&path).unwrap();
+    assert!(cdb.verify().unwrap().is_ok());
+    assert!(cdb.has_bloom_filter());
+    assert_eq!(cdb.len(), 10_000);
+    assert_eq!(cdb.get("key1234").unwrap().unwrap(), vec![b'x'; 34]);
+
+    let mut cdb = AsyncCDBWriter::create(dir.path().join("aborted.cdb"))
+        .await
+        .unwrap();
+    cdb.add(b"one", b"1").await.unwrap();
+    cdb.abort().await.unwrap();
+    drop(AsyncCDBWriter::create(dir.path().join("dropped.cdb")).await);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
@@ -0,0 +1,119 @@
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use cdb32::{CDBWriter, CacheOptions, CacheStats, CachedCDB, OpenOptions, CDB};
+
+fn open(dir: &tempfile::TempDir) -> CDB {
+    let path = dir.path().join("cache.cdb");
+    CDBWriter::from_iter(&path, (0..10).map(|i| (format!("k{}", i), "x".repeat(i)))).unwrap();
+    CDB::open_with_options(&path, OpenOptions::new().mmap(false)).unwrap()
+}
+
+#[test]
+fn test_cache_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    let cdb = CachedCDB::new(open(&dir), CacheOptions::new().max_entries(2));
+    assert_eq!(*cdb.get(b"k1").unwrap().unwrap(), b"x");
+    assert_eq!(*cdb.get(b"k2").unwrap().unwrap(), b"xx");
+    assert_eq!(*cdb.get(b"k1").unwrap().unwrap(), b"x");
+    // k2 is now the least recently used, so k3 replaces it.
+    assert_eq!(*cdb.get(b"k3").unwrap().unwrap(), b"xxx");
+    assert!(cdb.get(b"k1").is_some());
+    assert!(cdb.get(b"k2").is_some());
+    assert_eq!(
+        cdb.stats(),
+        CacheStats {
+            hits: 2,
+            misses: 4,
+            entries: 2,
+            bytes: 2 + 1 + 2 + 2,
+        }
+    );
+
+    assert!(cdb.get(b"missing").is_none());
+    assert!(cdb.get(b"missing").is_none());
+    assert_eq!(cdb.stats().hits, 3);
+    cdb.clear();
+    assert_eq!(cdb.stats().entries, 0);
+}
+
+#[test]
+fn test_cache_bytes() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut options = CacheOptions::new();
+    options.max_bytes(10).cache_misses(false);
+    let cdb = CachedCDB::new(open(&dir), &options);
+    for key in [b"k4", b"k5", b"k4", b"k5", b"k9"] {
+        cdb.get(key).unwrap().unwrap();
+    }
+    // k4 and k5 take 6 and 7 bytes, so only one fits at a time, and k9 on
+    // its own is too big to keep.
+    let stats = cdb.stats();
+    assert_eq!((stats.hits, stats.misses), (0, 5));
+    assert_eq!((stats.entries, stats.bytes), (1, 7));
+
+    assert!(cdb.get(b"missing").is_none());
+    assert!(cdb.get(b"missing").is_none());
+    assert_eq!(cdb.stats().misses, 7);
+}
+
+#[test]
+fn test_cache_decoder() {
+    let dir = tempfile::tempdir().unwrap();
+    let decoded = Arc::new(AtomicUsize::new(0));
+    let counter = decoded.clone();
+    let cdb = CachedCDB::with_decoder(open(&dir), &CacheOptions::new(), move |value| {
+        counter.fetch_add(1, Ordering::Relaxed);
+        match value.len() {
+            0 => Err(io::Error::new(io::ErrorKind::InvalidData, "empty")),
+            len => Ok(len),
+        }
+    });
+    let cdb = Arc::new(cdb);
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let cdb = cdb.clone();
+            std::thread::spawn(move || {
+                for i in 1..10 {
+                    let key = format!("k{}", i);
+                    assert_eq!(*cdb.get(key.as_bytes()).unwrap().unwrap(), i);
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert!(decoded.load(Ordering::Relaxed) >= 9);
+    let before = decoded.load(Ordering::Relaxed);
+    assert_eq!(*cdb.get(b"k9").unwrap().unwrap(), 9);
+    assert_eq!(decoded.load(Ordering::Relaxed), before);
+
+    // Errors are returned each time rather than cached.
+    for _ in 0..2 {
+        let err = cdb.get(b"k0").unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+    assert_eq!(decoded.load(Ordering::Relaxed), before + 2);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_cache_deserialized() {
+    use std::collections::BTreeMap;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("serde.cdb");
+    let mut writer = CDBWriter::create(&path).unwrap();
+    let mut map = BTreeMap::new();
+    map.insert("port".to_string(), 8080_u32);
+    writer.add_ser(b"config", &map).unwrap();
+    writer.finish().unwrap();
+
+    let cdb: CachedCDB<BTreeMap<String, u32>> =
+        CachedCDB::deserialized(CDB::open(&path).unwrap(), &CacheOptions::new());
+    assert_eq!(cdb.get(b"config").unwrap().unwrap()["port"], 8080);
+    assert_eq!(*cdb.get(b"config").unwrap().unwrap(), map);
+    assert_eq!(cdb.stats().hits, 1);
+}
@@ -0,0 +1,25 @@
+#![cfg(feature = "compat")]
+
+use std::io;
+
+use cdb32::compat::{self, TestVector};
+
+#[test]
+fn test_compat_vectors() {
+    compat::check().unwrap();
+    assert_eq!(compat::VECTORS.len(), 2);
+}
+
+#[test]
+fn test_compat_mismatch() {
+    let mut database = compat::VECTORS[0].database.to_vec();
+    let last = database.len() - 1;
+    database[last] ^= 1;
+    let vector = TestVector {
+        database: Box::leak(database.into_boxed_slice()),
+        ..compat::VECTORS[0]
+    };
+    let err = vector.check().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("differs at byte"), "{}", err);
+}
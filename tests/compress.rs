@@ -0,0 +1,89 @@
+#![cfg(feature = "lz4")]
+
+use cdb32::{CDBMake, CDBWriter, Compression, Error, OpenOptions, CDB};
+
+#[test]
+fn test_compressed_values() {
+    let long = "a value which repeats, ".repeat(200).into_bytes();
+    let mut cdb = CDBMake::in_memory().unwrap();
+    cdb.set_compression(Compression::Lz4, 32);
+    cdb.add(b"long", &long).unwrap();
+    cdb.add(b"short", b"tiny").unwrap();
+    cdb.add(b"long", b"second").unwrap();
+    cdb.add_stream(b"stream", &mut &b"streamed"[..], 8).unwrap();
+    let bytes = cdb.into_vec().unwrap();
+    assert!(bytes.len() < long.len());
+
+    let cdb = CDB::from_bytes(bytes).unwrap();
+    assert!(cdb.is_compressed());
+    assert_eq!(cdb.get(b"long").unwrap().unwrap(), long);
+    assert_eq!(cdb.get(b"short").unwrap().unwrap(), b"tiny");
+    assert_eq!(cdb.get(b"stream").unwrap().unwrap(), b"streamed");
+    let values = cdb.find(b"long").collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(values, [long.clone(), b"second".to_vec()]);
+
+    let mut buf = Vec::with_capacity(1 << 16);
+    let ptr = buf.as_ptr();
+    assert_eq!(cdb.get_into(b"long", &mut buf).unwrap(), Some(long.len()));
+    assert_eq!(buf, long);
+    assert_eq!(cdb.get_into(b"short", &mut buf).unwrap(), Some(4));
+    assert_eq!(buf, b"tiny");
+    assert_eq!((buf.as_ptr(), buf.capacity()), (ptr, 1 << 16));
+
+    let records = cdb.iter().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(records.len(), 4);
+    assert_eq!(records[1], (b"short".to_vec(), b"tiny".to_vec()));
+
+    let err = cdb.get_ref(b"short").unwrap().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+}
+
+#[test]
+fn test_compressed_writer() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("compressed.cdb");
+    let mut cdb = CDBWriter::create(&path).unwrap();
+    cdb.set_compression(Compression::Lz4, 0);
+    cdb.set_checksums(true);
+    cdb.add(b"key", [7; 4096]).unwrap();
+    cdb.finish().unwrap();
+
+    assert!(std::fs::metadata(&path).unwrap().len() < 4096);
+    let cdb = CDB::open(&path).unwrap();
+    assert!(cdb.verify().unwrap().is_ok());
+    assert_eq!(cdb.get(b"key").unwrap().unwrap(), [7; 4096]);
+}
+
+/// Return a compressed database holding `b"k"` with a value whose stored
+/// bytes are replaced by `stored`, which must be six bytes long.
+fn with_stored_value(stored: &[u8; 6]) -> Vec<u8> {
+    let mut cdb = CDBMake::in_memory().unwrap();
+    cdb.set_compression(Compression::Lz4, 1024);
+    cdb.add(b"k", b"12345").unwrap();
+    let mut bytes = cdb.into_vec().unwrap();
+    let pos = 2048 + 8 + 1;
+    assert_eq!(&bytes[pos..pos + 6], b"\x0012345");
+    bytes[pos..pos + 6].copy_from_slice(stored);
+    bytes
+}
+
+#[test]
+fn test_compressed_length_is_checked() {
+    // An LZ4 value claiming to decompress to 4 GiB from one byte.
+    let cdb = CDB::from_bytes(with_stored_value(b"\x01\xff\xff\xff\xff\x50")).unwrap();
+    let err = Error::from(cdb.get(b"k").unwrap().unwrap_err());
+    assert!(matches!(err, Error::Corrupt { offset: 2057, .. }));
+    assert!(cdb.iter().next().unwrap().is_err());
+
+    // A plausible length over the reader's limit.
+    let bytes = with_stored_value(b"\x01\x64\x00\x00\x00\x50");
+    let cdb = CDB::from_bytes_with_options(bytes, OpenOptions::new().max_value_len(50)).unwrap();
+    let err = Error::from(cdb.get(b"k").unwrap().unwrap_err());
+    assert!(matches!(
+        err,
+        Error::LimitExceeded {
+            limit: "max_value_len",
+            ..
+        }
+    ));
+}
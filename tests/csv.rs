@@ -0,0 +1,39 @@
+#![cfg(feature = "csv")]
+
+use cdb32::csv::CsvOptions;
+use cdb32::{CDBWriter, Encoding, CDB};
+
+#[test]
+fn test_csv_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("csv.cdb");
+    let input = b"key\tvalue\n00ff\t\"tab\there\"\n6869\tline\r\n\n";
+    let mut options = CsvOptions::new();
+    options
+        .delimiter(b'\t')
+        .headers(true)
+        .key_encoding(Encoding::Hex);
+    let mut cdb = CDBWriter::create(&path).unwrap();
+    assert_eq!(cdb.add_from_csv(&input[..], &options).unwrap(), 2);
+    cdb.finish().unwrap();
+
+    let cdb = CDB::open(&path).unwrap();
+    assert_eq!(cdb.get(b"\x00\xff").unwrap().unwrap(), b"tab\there");
+    assert_eq!(cdb.get(b"hi").unwrap().unwrap(), b"line");
+
+    let mut out = Vec::new();
+    assert_eq!(cdb.dump_csv(&mut out, &options).unwrap(), 2);
+    assert_eq!(out, b"key\tvalue\n00ff\t\"tab\there\"\n6869\tline\n");
+
+    let mut options = CsvOptions::new();
+    options.value_encoding(Encoding::Base64);
+    let mut out = Vec::new();
+    cdb.dump_csv(&mut out, &options).unwrap();
+    assert_eq!(out, b"\x00\xff,dGFiCWhlcmU=\nhi,bGluZQ==\n");
+
+    let mut cdb = CDBWriter::create(&path).unwrap();
+    let err = cdb
+        .add_from_csv(&b"a,1\nb\n"[..], &CsvOptions::new())
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
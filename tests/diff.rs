@@ -0,0 +1,101 @@
+use cdb32::diff::{diff, Change, DiffSummary};
+use cdb32::{CDBMake, CDB};
+
+fn build(records: &[(&[u8], &[u8])]) -> CDB {
+    let mut cdb = CDBMake::in_memory().unwrap();
+    for (key, value) in records {
+        cdb.add(key, value).unwrap();
+    }
+    CDB::from_bytes(cdb.into_vec().unwrap()).unwrap()
+}
+
+fn change(kind: &str, key: &[u8], value: &[u8]) -> Change {
+    let (key, value) = (key.to_vec(), value.to_vec());
+    match kind {
+        "+" => Change::Added { key, value },
+        _ => Change::Removed { key, value },
+    }
+}
+
+#[test]
+fn test_diff_multiple_values() {
+    let old = build(&[
+        (b"same", b"1"),
+        (b"multi", b"a"),
+        (b"multi", b"b"),
+        (b"multi", b"b"),
+        (b"same", b"2"),
+    ]);
+    let new = build(&[
+        (b"multi", b"b"),
+        (b"same", b"1"),
+        (b"multi", b"c"),
+        (b"same", b"2"),
+        (b"new", b"x"),
+        (b"new", b"y"),
+    ]);
+    let changes = diff(&old, &new).collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(
+        changes,
+        [
+            change("-", b"multi", b"a"),
+            change("-", b"multi", b"b"),
+            change("+", b"multi", b"c"),
+            change("+", b"new", b"x"),
+            change("+", b"new", b"y"),
+        ]
+    );
+
+    assert_eq!(diff(&old, &old).count(), 0);
+    assert_eq!(
+        diff(&new, &old).summary().unwrap(),
+        DiffSummary {
+            added: 2,
+            removed: 3,
+            changed: 0
+        }
+    );
+}
+
+#[test]
+fn test_content_eq() {
+    let old = build(&[(b"a", b"1"), (b"b", b"2"), (b"b", b"3")]);
+    let reordered = build(&[(b"b", b"3"), (b"a", b"1"), (b"b", b"2")]);
+    assert!(old.content_eq(&reordered).unwrap());
+    assert!(old.is_subset_of(&reordered).unwrap());
+
+    let fewer = build(&[(b"a", b"1"), (b"b", b"3")]);
+    assert!(!old.content_eq(&fewer).unwrap());
+    assert!(fewer.is_subset_of(&old).unwrap());
+    assert!(!old.is_subset_of(&fewer).unwrap());
+
+    // Repeated values must be repeated in the other database too.
+    let repeated = build(&[(b"a", b"1"), (b"a", b"1")]);
+    let single = build(&[(b"a", b"1")]);
+    assert!(single.is_subset_of(&repeated).unwrap());
+    assert!(!repeated.is_subset_of(&single).unwrap());
+
+    let changed = build(&[(b"a", b"1"), (b"b", b"2"), (b"b", b"4")]);
+    assert!(!old.content_eq(&changed).unwrap());
+    assert!(build(&[]).is_subset_of(&old).unwrap());
+}
+
+#[test]
+fn test_compare_first_missing() {
+    use cdb32::compare::{first_missing, is_subset, is_superset};
+
+    let old = build(&[(b"a", b"1"), (b"b", b"2"), (b"b", b"2")]);
+    let mut new = cdb32::CDB64Make::in_memory().unwrap();
+    new.add(b"b", b"2").unwrap();
+    new.add(b"a", b"1").unwrap();
+    let new = cdb32::CDB64::from_bytes(new.into_vec().unwrap()).unwrap();
+
+    assert!(is_subset(&new, &old).unwrap());
+    assert!(is_superset(&old, &new).unwrap());
+    assert!(!is_subset(&old, &new).unwrap());
+    assert_eq!(
+        first_missing(&old, &new).unwrap(),
+        Some((b"b".to_vec(), b"2".to_vec()))
+    );
+    assert_eq!(first_missing(&new, &old).unwrap(), None);
+}
@@ -0,0 +1,163 @@
+#![cfg(feature = "encryption")]
+
+use std::io;
+
+use cdb32::{CDB64Writer, CDBMake, CDBWriter, EncryptionKey, Error, OpenOptions, CDB, CDB64};
+
+fn key() -> EncryptionKey {
+    EncryptionKey::new([0x42; 32])
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[test]
+fn test_encrypted_values() {
+    let long = vec![0xff; 1000];
+    let mut cdb = CDBMake::in_memory().unwrap();
+    cdb.set_encryption(&key());
+    cdb.add(b"k", &long).unwrap();
+    cdb.add(b"short", b"secret").unwrap();
+    cdb.add(b"k", b"second").unwrap();
+    cdb.add(b"empty", b"").unwrap();
+    cdb.add_stream(b"stream", &mut &b"streamed"[..], 8).unwrap();
+    cdb.add_stream_unsized(b"unsized", &mut &b"unsized value"[..])
+        .unwrap();
+    let bytes = cdb.into_vec().unwrap();
+    for plain in [&b"secret"[..], b"second", b"streamed", b"unsized value"] {
+        assert!(!bytes.windows(plain.len()).any(|window| window == plain));
+    }
+
+    // The first record is stored as the nonce, the ciphertext and the
+    // tag, which match an independent ChaCha20-Poly1305 implementation.
+    let stored = &bytes[2048 + 8 + 1..][..1000 + 28];
+    assert_eq!(hex(&stored[..12]), "2081f9b26da3f3b43cede1c4");
+    assert_eq!(hex(&stored[12..28]), "72dfc7df5b6f2b298e2a925cc3f03d25");
+    assert_eq!(hex(&stored[1012..]), "1bf7fbf1ca935f200f28b40126e7b938");
+
+    let cdb =
+        CDB::from_bytes_with_options(bytes, OpenOptions::new().encryption_key(&key())).unwrap();
+    assert!(cdb.is_encrypted());
+    assert_eq!(cdb.get(b"k").unwrap().unwrap(), long);
+    assert_eq!(cdb.get(b"short").unwrap().unwrap(), b"secret");
+    assert_eq!(cdb.get(b"empty").unwrap().unwrap(), b"");
+    assert_eq!(cdb.get(b"stream").unwrap().unwrap(), b"streamed");
+    assert_eq!(cdb.get(b"unsized").unwrap().unwrap(), b"unsized value");
+    let values = cdb.find(b"k").collect::<io::Result<Vec<_>>>().unwrap();
+    assert_eq!(values, [long.clone(), b"second".to_vec()]);
+
+    let mut buf = Vec::new();
+    assert_eq!(cdb.get_into(b"short", &mut buf).unwrap(), Some(6));
+    assert_eq!(buf, b"secret");
+    assert_eq!(cdb.iter().count(), 6);
+    assert!(cdb.get_ref(b"short").unwrap().is_err());
+}
+
+#[test]
+fn test_encrypted_open() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("secrets.cdb");
+    let mut writer = CDB64Writer::create(&path).unwrap();
+    writer.set_encryption(&key());
+    writer.set_checksums(true);
+    writer.add(b"api", b"token").unwrap();
+    writer.finish().unwrap();
+
+    let cdb = CDB64::open_with_options(&path, OpenOptions::new().encryption_key(&key())).unwrap();
+    assert_eq!(cdb.get(b"api").unwrap().unwrap(), b"token");
+
+    let err = CDB64::open(&path).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    let wrong = EncryptionKey::new([0x43; 32]);
+    let err =
+        CDB64::open_with_options(&path, OpenOptions::new().encryption_key(&wrong)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+
+    let err = CDB::open_with_options("tests/test1.cdb", OpenOptions::new().encryption_key(&key()))
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_encrypted_tampering() {
+    let mut cdb = CDBMake::in_memory().unwrap();
+    cdb.set_encryption(&key());
+    cdb.add(b"one", b"first").unwrap();
+    cdb.add(b"two", b"other").unwrap();
+    let mut bytes = cdb.into_vec().unwrap();
+
+    // Flip a bit of the first ciphertext.
+    bytes[2048 + 8 + 3 + 12] ^= 1;
+    let cdb =
+        CDB::from_bytes_with_options(bytes.clone(), OpenOptions::new().encryption_key(&key()))
+            .unwrap();
+    let err = Error::from(cdb.get(b"one").unwrap().unwrap_err());
+    assert!(matches!(err, Error::Corrupt { offset: 2059, .. }));
+    assert_eq!(cdb.get(b"two").unwrap().unwrap(), b"other");
+
+    // Swap the values of the two records, which have the same length.
+    bytes[2048 + 8 + 3 + 12] ^= 1;
+    let record = 8 + 3 + 5 + 28;
+    let (first, second) = bytes[2048..].split_at_mut(record);
+    first[11..record].swap_with_slice(&mut second[11..record]);
+    let cdb =
+        CDB::from_bytes_with_options(bytes, OpenOptions::new().encryption_key(&key())).unwrap();
+    assert!(cdb.get(b"one").unwrap().is_err());
+    assert!(cdb.get(b"two").unwrap().is_err());
+}
+
+#[test]
+fn test_encrypted_reproducible() {
+    let build = || {
+        let mut cdb = CDBMake::in_memory().unwrap();
+        cdb.set_encryption(&key());
+        cdb.add(b"one", b"1").unwrap();
+        cdb.add(b"two", b"2").unwrap();
+        cdb.into_vec().unwrap()
+    };
+    assert_eq!(build(), build());
+}
+
+#[cfg(feature = "lz4")]
+#[test]
+fn test_encrypted_and_compressed() {
+    let long = "a value which repeats, ".repeat(200).into_bytes();
+    let mut cdb = CDBMake::in_memory().unwrap();
+    cdb.set_compression(cdb32::Compression::Lz4, 32);
+    cdb.set_encryption(&key());
+    cdb.add(b"long", &long).unwrap();
+    let bytes = cdb.into_vec().unwrap();
+    assert!(bytes.len() < long.len());
+
+    let cdb =
+        CDB::from_bytes_with_options(bytes, OpenOptions::new().encryption_key(&key())).unwrap();
+    assert!(cdb.is_compressed() && cdb.is_encrypted());
+    assert_eq!(cdb.get(b"long").unwrap().unwrap(), long);
+}
+
+#[test]
+fn test_encrypted_sorted_index() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sorted.cdb");
+    let mut writer = CDBWriter::create(&path).unwrap();
+    writer.set_encryption(&key());
+    writer.set_sorted_index(true);
+    for (key, value) in [("pear", "1"), ("apple", "2"), ("fig", "3")] {
+        writer.add(key, value).unwrap();
+    }
+    writer.finish().unwrap();
+
+    let mut options = OpenOptions::new();
+    options.encryption_key(&key()).sorted_index(true);
+    let cdb = CDB::open_with_options(&path, &options).unwrap();
+    let records = cdb.iter_sorted().collect::<io::Result<Vec<_>>>().unwrap();
+    assert_eq!(
+        records,
+        [
+            (b"apple".to_vec(), b"2".to_vec()),
+            (b"fig".to_vec(), b"3".to_vec()),
+            (b"pear".to_vec(), b"1".to_vec()),
+        ]
+    );
+}
@@ -0,0 +1,91 @@
+#![cfg(feature = "ffi")]
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_void;
+use std::slice;
+
+use cdb32::ffi::*;
+
+unsafe fn data(cdbp: *const cdb) -> Vec<u8> {
+    slice::from_raw_parts(cdb_getdata(cdbp).cast::<u8>(), cdb_datalen(cdbp) as usize).to_vec()
+}
+
+#[test]
+fn test_ffi_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let name = CString::new(dir.path().join("ffi.cdb").to_str().unwrap()).unwrap();
+    unsafe {
+        let cdbmp = cdb_make_start(name.as_ptr());
+        assert!(!cdbmp.is_null());
+        for (key, val) in [
+            (&b"one"[..], &b"Hello"[..]),
+            (b"one", b", world"),
+            (b"", b""),
+        ] {
+            let ret = cdb_make_add(
+                cdbmp,
+                key.as_ptr().cast::<c_void>(),
+                key.len() as u32,
+                val.as_ptr().cast::<c_void>(),
+                val.len() as u32,
+            );
+            assert_eq!(ret, 0);
+        }
+        assert_eq!(cdb_make_finish(cdbmp), 0);
+
+        let cdbp = cdb_open(name.as_ptr());
+        assert!(!cdbp.is_null());
+        assert_eq!(cdb_find(cdbp, b"one".as_ptr().cast(), 3), 1);
+        assert_eq!(data(cdbp), b"Hello");
+        assert_eq!(cdb_findnext(cdbp), 1);
+        assert_eq!(data(cdbp), b", world");
+        assert_eq!(cdb_findnext(cdbp), 0);
+        assert_eq!(cdb_find(cdbp, std::ptr::null(), 0), 1);
+        assert_eq!(cdb_datalen(cdbp), 0);
+        assert_eq!(cdb_find(cdbp, b"two".as_ptr().cast(), 3), 0);
+
+        let mut records = Vec::new();
+        assert_eq!(cdb_seqinit(cdbp), 0);
+        while cdb_seqnext(cdbp) == 1 {
+            let key =
+                slice::from_raw_parts(cdb_getkey(cdbp).cast::<u8>(), cdb_keylen(cdbp) as usize);
+            records.push((key.to_vec(), data(cdbp)));
+        }
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1], (b"one".to_vec(), b", world".to_vec()));
+        assert_eq!(cdb_seqnext(cdbp), 0);
+        cdb_free(cdbp);
+    }
+}
+
+#[test]
+fn test_ffi_open_bytes() {
+    let bytes = std::fs::read("tests/test1.cdb").unwrap();
+    unsafe {
+        let cdbp = cdb_open_bytes(bytes.as_ptr().cast(), bytes.len());
+        assert!(!cdbp.is_null());
+        assert_eq!(cdb_find(cdbp, b"two".as_ptr().cast(), 3), 1);
+        assert_eq!(data(cdbp), b"Goodbye");
+        cdb_free(cdbp);
+
+        assert!(cdb_open_bytes(bytes.as_ptr().cast(), 100).is_null());
+    }
+}
+
+#[test]
+fn test_ffi_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let name = CString::new(dir.path().join("missing.cdb").to_str().unwrap()).unwrap();
+    unsafe {
+        assert!(cdb_open(name.as_ptr()).is_null());
+        assert!(!cdb_strerror().is_null());
+        assert!(cdb_open(std::ptr::null()).is_null());
+        let msg = CStr::from_ptr(cdb_strerror());
+        assert_eq!(msg.to_str().unwrap(), "NULL file name");
+
+        let cdbmp = cdb_make_start(name.as_ptr());
+        cdb_make_free(cdbmp);
+        assert!(cdb_open(name.as_ptr()).is_null());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
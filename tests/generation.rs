@@ -0,0 +1,98 @@
+use std::fs;
+use std::io;
+
+use cdb32::{GenerationStore, OpenOptions};
+
+fn publish(store: &GenerationStore, value: &str) -> u64 {
+    let mut writer = store.create().unwrap();
+    writer.add(b"key", value.as_bytes()).unwrap();
+    let generation = writer.generation();
+    writer.finish().unwrap();
+    generation
+}
+
+#[test]
+fn test_generations() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut store = GenerationStore::new(dir.path(), "db");
+    store.set_keep(2);
+    assert_eq!(store.current_generation().unwrap(), None);
+    assert_eq!(
+        store.open_current().unwrap_err().kind(),
+        io::ErrorKind::NotFound
+    );
+    assert_eq!(
+        store.rollback().unwrap_err().kind(),
+        io::ErrorKind::NotFound
+    );
+
+    for (i, value) in ["a", "b", "c", "d"].iter().enumerate() {
+        assert_eq!(publish(&store, value), i as u64 + 1);
+    }
+    assert_eq!(store.generations().unwrap(), [3, 4]);
+    assert_eq!(fs::read_to_string(store.pointer_path()).unwrap(), "4\n");
+    assert!(!store.path(2).exists());
+    let cdb = store.open_current().unwrap();
+    assert_eq!(cdb.get(b"key").unwrap().unwrap(), b"d");
+
+    // A reader keeps its generation however the pointer moves.
+    assert_eq!(store.rollback().unwrap(), 3);
+    assert_eq!(
+        store.open_current().unwrap().get(b"key").unwrap().unwrap(),
+        b"c"
+    );
+    assert_eq!(cdb.get(b"key").unwrap().unwrap(), b"d");
+    assert_eq!(
+        store.rollback().unwrap_err().kind(),
+        io::ErrorKind::NotFound
+    );
+
+    // The next build goes above the newest, and a rolled back current
+    // generation is pruned only once it is no longer current.
+    assert_eq!(publish(&store, "e"), 5);
+    assert_eq!(publish(&store, "f"), 6);
+    assert_eq!(store.generations().unwrap(), [5, 6]);
+    store.set_keep(1);
+    store.set_current(5).unwrap();
+    assert!(store.prune().unwrap().is_empty());
+    store.set_current(6).unwrap();
+    assert_eq!(store.prune().unwrap(), [5]);
+    assert_eq!(publish(&store, "g"), 7);
+    assert_eq!(store.generations().unwrap(), [7]);
+
+    let options = OpenOptions::new();
+    let cdb = store.open_with_options(7, &options).unwrap();
+    assert_eq!(cdb.get(b"key").unwrap().unwrap(), b"g");
+    assert_eq!(
+        store.set_current(1).unwrap_err().kind(),
+        io::ErrorKind::NotFound
+    );
+    assert_eq!(store.current_generation().unwrap(), Some(7));
+}
+
+#[test]
+fn test_generations_ignore_other_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = GenerationStore::new(dir.path(), "db");
+    for name in [
+        "db.cdb",
+        "db.x.cdb",
+        "db.+1.cdb",
+        "other.7.cdb",
+        "db.2.cdb.tmp",
+    ] {
+        fs::write(dir.path().join(name), b"").unwrap();
+    }
+    assert!(store.generations().unwrap().is_empty());
+
+    let writer = store.create().unwrap();
+    assert_eq!(writer.generation(), 1);
+    drop(writer);
+    assert_eq!(store.current_generation().unwrap(), None);
+
+    fs::write(store.pointer_path(), "latest\n").unwrap();
+    assert_eq!(
+        store.current_generation().unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+}
@@ -0,0 +1,57 @@
+use cdb32::{CDB64Image, CDB64Writer, CDBImage, InvalidFormat, CDB};
+
+static TEST1: &[u8] = include_bytes!("test1.cdb");
+
+#[test]
+fn test_one() {
+    let cdb = CDBImage::new(TEST1).unwrap();
+    let mut i = cdb.find(b"one");
+    assert_eq!(i.next(), Some(Ok(&b"Hello"[..])));
+    assert_eq!(i.next(), Some(Ok(&b", World!"[..])));
+    assert_eq!(i.next(), None);
+    assert_eq!(cdb.get(b"two"), Some(Ok(&b"Goodbye"[..])));
+    assert_eq!(cdb.get(b"three"), None);
+    assert_eq!(cdb.exists(b"one"), Ok(true));
+    assert_eq!(cdb.len(), 4);
+}
+
+#[test]
+fn test_iter_matches_reader() {
+    let data = std::fs::read("tests/test2.cdb").unwrap();
+    let image = CDBImage::new(&data).unwrap();
+    let cdb = CDB::open("tests/test2.cdb").unwrap();
+    let expected = cdb.iter().map(Result::unwrap).collect::<Vec<_>>();
+    let actual = image
+        .iter()
+        .map(|r| r.map(|(k, v)| (k.to_vec(), v.to_vec())).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(actual, expected);
+    for (key, value) in &expected {
+        assert!(image.find(key).any(|v| v.unwrap() == value.as_slice()));
+    }
+}
+
+#[test]
+fn test_image64() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test64.cdb");
+    let mut cdb = CDB64Writer::create(&path).unwrap();
+    cdb.add(b"one", b"Hello").unwrap();
+    cdb.add(b"two", b"Goodbye").unwrap();
+    cdb.finish().unwrap();
+
+    let data = std::fs::read(&path).unwrap();
+    let cdb = CDB64Image::new(&data).unwrap();
+    assert_eq!(cdb.get(b"two"), Some(Ok(&b"Goodbye"[..])));
+    assert_eq!(cdb.iter().count(), 2);
+}
+
+#[test]
+fn test_invalid() {
+    assert_eq!(CDBImage::new(&[0; 100]).unwrap_err(), InvalidFormat);
+
+    let mut data = TEST1.to_vec();
+    data.truncate(2100);
+    let cdb = CDBImage::new(&data).unwrap();
+    assert_eq!(cdb.get(b"one"), Some(Err(InvalidFormat)));
+}
@@ -0,0 +1,91 @@
+use std::io;
+
+use cdb32::{invert, CDB64Writer, CDBMake, CDBWriter, CDB, CDB64};
+
+#[test]
+fn test_invert() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("hosts.cdb");
+    CDBWriter::from_iter(
+        &path,
+        [
+            ("web1", "10.0.0.1"),
+            ("web2", "10.0.0.2"),
+            ("www", "10.0.0.1"),
+        ],
+    )
+    .unwrap();
+    let hosts = CDB::open(&path).unwrap();
+    let index_path = dir.path().join("hosts.by-ip.cdb");
+    assert_eq!(invert::build(&hosts, &index_path).unwrap(), 3);
+
+    let index = CDB::open(&index_path).unwrap();
+    let names = index
+        .find(b"10.0.0.1")
+        .collect::<io::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(names, [&b"web1"[..], b"www"]);
+    assert!(index.get(b"web1").is_none());
+
+    let records = invert::lookup(&index, &hosts, b"10.0.0.2")
+        .collect::<io::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(records, [(b"web2".to_vec(), b"10.0.0.2".to_vec())]);
+}
+
+#[test]
+fn test_invert_by() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("users.cdb");
+    let mut users = CDB64Writer::create(&path).unwrap();
+    users.add(b"alice", b"alice@example.com:staff").unwrap();
+    users.add(b"bob", b"bob@example.com:").unwrap();
+    users.add(b"carol", b"carol@example.com:staff").unwrap();
+    users.finish().unwrap();
+    let users = CDB64::open(&path).unwrap();
+
+    let index_path = dir.path().join("users.by-group.cdb");
+    let count = invert::build_by(&users, &index_path, |value| {
+        let group = value.split(|&b| b == b':').nth(1).unwrap_or_default();
+        Ok(if group.is_empty() {
+            vec![]
+        } else {
+            vec![group.to_vec()]
+        })
+    })
+    .unwrap();
+    assert_eq!(count, 2);
+
+    // The index has the same format as the database it indexes.
+    let index = CDB64::open(&index_path).unwrap();
+    let staff = invert::lookup(&index, &users, b"staff")
+        .map(|record| record.map(|(key, _)| key))
+        .collect::<io::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(staff, [&b"alice"[..], b"carol"]);
+
+    let mut make = CDBMake::in_memory().unwrap();
+    let err = make
+        .add_inverted_by(&users, |_| {
+            Err(io::Error::new(io::ErrorKind::InvalidData, "bad value"))
+        })
+        .unwrap_err();
+    assert_eq!(err.to_string(), "bad value");
+}
+
+#[test]
+fn test_lookup_stale_index() {
+    let mut make = CDBMake::in_memory().unwrap();
+    make.add(b"one", b"1").unwrap();
+    let cdb = CDB::from_bytes(make.into_vec().unwrap()).unwrap();
+
+    let mut index = CDBMake::in_memory().unwrap();
+    index.add(b"1", b"gone").unwrap();
+    assert_eq!(index.add_inverted(&cdb).unwrap(), 1);
+    let index = CDB::from_bytes(index.into_vec().unwrap()).unwrap();
+
+    let records = invert::lookup(&index, &cdb, b"1")
+        .collect::<io::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(records, [(b"one".to_vec(), b"1".to_vec())]);
+}
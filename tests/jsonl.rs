@@ -0,0 +1,55 @@
+#![cfg(feature = "jsonl")]
+
+use cdb32::jsonl::JsonlOptions;
+use cdb32::{CDBMake, CDBWriter, Encoding, CDB};
+
+#[test]
+fn test_jsonl_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("jsonl.cdb");
+    let input =
+        b"{\"key\": \"00ff\", \"value\": \"AAE=\"}\n\n{\"value\": \"aGk=\", \"key\": \"6869\"}\n";
+    let mut options = JsonlOptions::new();
+    options
+        .key_encoding(Encoding::Hex)
+        .value_encoding(Encoding::Base64);
+    let mut cdb = CDBWriter::create(&path).unwrap();
+    assert_eq!(cdb.add_from_jsonl(&input[..], &options).unwrap(), 2);
+    cdb.finish().unwrap();
+
+    let cdb = CDB::open(&path).unwrap();
+    assert_eq!(cdb.get(b"\x00\xff").unwrap().unwrap(), b"\x00\x01");
+    assert_eq!(cdb.get(b"hi").unwrap().unwrap(), b"hi");
+
+    let mut out = Vec::new();
+    assert_eq!(cdb.dump_jsonl(&mut out, &options).unwrap(), 2);
+    assert_eq!(
+        out,
+        b"{\"key\":\"00ff\",\"value\":\"AAE=\"}\n{\"key\":\"6869\",\"value\":\"aGk=\"}\n"
+    );
+
+    // Binary data cannot be written as text.
+    let err = cdb
+        .dump_jsonl(Vec::new(), &JsonlOptions::new())
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_jsonl_malformed() {
+    let options = JsonlOptions::new();
+    for input in [
+        &b"{\"key\": \"a\"}\n"[..],
+        b"{\"key\": \"a\", \"value\": \"b\"\n",
+        b"[\"a\", \"b\"]\n",
+    ] {
+        let mut cdb = CDBMake::in_memory().unwrap();
+        let err = cdb.add_from_jsonl(input, &options).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+    let mut cdb = CDBMake::in_memory().unwrap();
+    let input = b"{\"key\": \"a\", \"value\": \"not hex\"}\n";
+    let mut options = JsonlOptions::new();
+    options.value_encoding(Encoding::Hex);
+    assert!(cdb.add_from_jsonl(&input[..], &options).is_err());
+}
@@ -1,6 +1,9 @@
 use std::fs;
 
-use cdb32::{CDBWriter, CDB};
+use cdb32::{
+    BuildProgress, CDB64Make, CDB64Writer, CDBKey, CDBMake, CDBWriter, CdbHash, DuplicatePolicy,
+    Durability, OpenOptions, CDB, CDB64,
+};
 
 macro_rules! noerr {
     ( $e:expr ) => {
@@ -51,3 +54,536 @@ fn test_make() {
 
     noerr!(fs::remove_file(filename));
 }
+
+#[test]
+fn test_make64() {
+    let filename = "tests/make64.cdb";
+
+    let mut cdb = CDB64Writer::create(filename).unwrap();
+    noerr!(cdb.add(b"one", b"Hello"));
+    noerr!(cdb.add(b"two", b"Goodbye"));
+    noerr!(cdb.add(b"one", b", World!"));
+    noerr!(cdb.add(b"this key will be split across two reads", b"Got it."));
+    noerr!(cdb.finish());
+
+    // The header holds 256 pairs of 64-bit integers, and the first
+    // record follows immediately with a 64-bit key and value length.
+    let raw = fs::read(filename).unwrap();
+    assert_eq!(
+        raw[4096..4112],
+        [3, 0, 0, 0, 0, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0]
+    );
+    assert_eq!(&raw[4112..4120], b"oneHello");
+
+    for options in [OpenOptions::new(), OpenOptions::new().mmap(false).clone()] {
+        let cdb = CDB64::open_with_options(filename, &options).unwrap();
+        assert_eq!(cdb.find(b"two").next().unwrap().unwrap(), b"Goodbye");
+        assert_eq!(
+            cdb.get(b"this key will be split across two reads")
+                .unwrap()
+                .unwrap(),
+            b"Got it."
+        );
+        let mut i = cdb.find(b"one");
+        assert_eq!(i.next().unwrap().unwrap(), b"Hello");
+        assert_eq!(i.next().unwrap().unwrap(), b", World!");
+        assert!(i.next().is_none());
+        assert!(cdb.get(b"three").is_none());
+
+        let records = cdb.iter().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[1], (b"two".to_vec(), b"Goodbye".to_vec()));
+    }
+
+    // The 32-bit reader must not find anything in a 64-bit file.
+    let cdb = CDB::open(filename).unwrap();
+    assert!(cdb.get(b"one").is_none());
+
+    noerr!(fs::remove_file(filename));
+}
+
+#[test]
+fn test_make_in_memory() {
+    let mut cdb = CDBMake::in_memory().unwrap();
+    noerr!(cdb.add(b"one", b"Hello"));
+    noerr!(cdb.add(b"two", b"Goodbye"));
+    let bytes = cdb.into_vec().unwrap();
+
+    let filename = "tests/make_in_memory.cdb";
+    let mut cdb = CDBWriter::create(filename).unwrap();
+    noerr!(cdb.add(b"one", b"Hello"));
+    noerr!(cdb.add(b"two", b"Goodbye"));
+    noerr!(cdb.finish());
+    assert_eq!(bytes, fs::read(filename).unwrap());
+    noerr!(fs::remove_file(filename));
+
+    let mut cdb = CDB64Make::in_memory().unwrap();
+    noerr!(cdb.add(b"one", b"Hello"));
+    let cdb = CDB64::from_bytes(cdb.into_vec().unwrap()).unwrap();
+    assert_eq!(cdb.get(b"one").unwrap().unwrap(), b"Hello");
+}
+
+#[test]
+fn test_make_stream() {
+    let big = (0..100_000).map(|i| i as u8).collect::<Vec<_>>();
+    let mut cdb = CDBMake::in_memory().unwrap();
+    noerr!(cdb.add(b"one", b"Hello"));
+    noerr!(cdb.add_stream(b"big", &mut &big[..], big.len() as u64));
+    noerr!(cdb.add_stream_unsized(b"unsized", &mut &big[..]));
+    noerr!(cdb.add(b"two", b"Goodbye"));
+    let cdb = CDB::from_bytes(cdb.into_vec().unwrap()).unwrap();
+    assert_eq!(cdb.get(b"one").unwrap().unwrap(), b"Hello");
+    assert_eq!(cdb.get(b"big").unwrap().unwrap(), big);
+    assert_eq!(cdb.get(b"unsized").unwrap().unwrap(), big);
+    assert_eq!(cdb.get(b"two").unwrap().unwrap(), b"Goodbye");
+
+    let mut cdb = CDBMake::in_memory().unwrap();
+    assert!(cdb.add_stream(b"short", &mut &b"abc"[..], 4).is_err());
+}
+
+#[test]
+fn test_make_durable() {
+    let filename = "tests/make_durable.cdb";
+    for durability in [Durability::None, Durability::File, Durability::FileAndDir] {
+        let mut cdb = CDBWriter::create(filename).unwrap();
+        cdb.set_durability(durability);
+        noerr!(cdb.add(b"one", b"Hello"));
+        noerr!(cdb.finish());
+        let cdb = CDB::open(filename).unwrap();
+        assert_eq!(cdb.get(b"one").unwrap().unwrap(), b"Hello");
+    }
+    noerr!(fs::remove_file(filename));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_make_anonymous() {
+    let dir = tempfile::tempdir().unwrap();
+    let filename = dir.path().join("anonymous.cdb");
+    let mut cdb = CDBWriter::create_anonymous(&filename).unwrap();
+    noerr!(cdb.add(b"one", b"Hello"));
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    noerr!(cdb.finish());
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    let cdb = CDB::open(&filename).unwrap();
+    assert_eq!(cdb.get(b"one").unwrap().unwrap(), b"Hello");
+
+    let mut cdb = CDBWriter::create_anonymous(&filename).unwrap();
+    noerr!(cdb.add(b"one", b"Goodbye"));
+    drop(cdb);
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn test_make_temp_pattern() {
+    let dir = tempfile::tempdir().unwrap();
+    let tmp_dir = dir.path().join("tmp");
+    noerr!(fs::create_dir(&tmp_dir));
+    let filename = dir.path().join("file.cdb");
+
+    let mut cdb = CDBWriter::with_temp_dir(&filename, &tmp_dir).unwrap();
+    let mut other = CDBWriter::with_temp_pattern(&filename, &tmp_dir, "build-XXXX").unwrap();
+    let names = fs::read_dir(&tmp_dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names.len(), 2);
+    assert!(names
+        .iter()
+        .any(|n| n.starts_with(".file.cdb.") && n.len() == 16));
+    assert!(names
+        .iter()
+        .any(|n| n.starts_with("build-") && n.len() == 10));
+
+    noerr!(other.add(b"one", b"Goodbye"));
+    noerr!(other.finish());
+    noerr!(cdb.add(b"one", b"Hello"));
+    noerr!(cdb.finish());
+    assert_eq!(fs::read_dir(&tmp_dir).unwrap().count(), 0);
+    let cdb = CDB::open(&filename).unwrap();
+    assert_eq!(cdb.get(b"one").unwrap().unwrap(), b"Hello");
+}
+
+#[test]
+fn test_make_abort() {
+    let filename = "tests/make_abort.cdb";
+    let mut cdb = CDBWriter::with_suffix(filename, ".abort").unwrap();
+    noerr!(cdb.add(b"one", b"Hello"));
+    assert!(fs::metadata("tests/make_abort.cdb.abort").is_ok());
+    noerr!(cdb.abort());
+    assert!(fs::metadata("tests/make_abort.cdb.abort").is_err());
+    assert!(fs::metadata(filename).is_err());
+
+    let cdb = CDBWriter::with_suffix(filename, ".abort").unwrap();
+    noerr!(fs::remove_file("tests/make_abort.cdb.abort"));
+    assert!(cdb.abort().is_err());
+}
+
+#[test]
+fn test_make_builder() {
+    let dir = tempfile::tempdir().unwrap();
+    let filename = dir.path().join("built.cdb");
+
+    let mut cdb = CDBWriter::builder(&filename)
+        .suffix(".part")
+        .duplicates(DuplicatePolicy::KeepFirst)
+        .buffer_size(16)
+        .create()
+        .unwrap();
+    assert!(fs::metadata(dir.path().join("built.cdb.part")).is_ok());
+    noerr!(cdb.add(b"one", b"Hello"));
+    noerr!(cdb.add(b"one", b"again"));
+    noerr!(cdb.add(b"two", b"Goodbye"));
+    noerr!(cdb.finish());
+    let cdb = CDB::open(&filename).unwrap();
+    assert_eq!(cdb.find(b"one").count(), 1);
+    assert_eq!(cdb.get(b"two").unwrap().unwrap(), b"Goodbye");
+
+    let mut cdb = CDB64Writer::builder(&filename)
+        .temp_pattern("build.XXXX")
+        .duplicates(DuplicatePolicy::Reject)
+        .create()
+        .unwrap();
+    noerr!(cdb.add(b"one", b"Hello"));
+    assert!(cdb.add(b"one", b"again").is_err());
+    noerr!(cdb.finish());
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    let cdb = CDB64::open(&filename).unwrap();
+    assert_eq!(cdb.find(b"one").count(), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_make_builder_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let filename = dir.path().join("private.cdb");
+    let cdb = CDBWriter::builder(&filename)
+        .permissions(fs::Permissions::from_mode(0o600))
+        .create()
+        .unwrap();
+    noerr!(cdb.finish());
+    let mode = fs::metadata(&filename).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+}
+
+#[test]
+fn test_make_progress() {
+    use std::sync::{Arc, Mutex};
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let mut cdb = CDBMake::in_memory().unwrap();
+    let log = seen.clone();
+    cdb.set_progress(move |progress| log.lock().unwrap().push(progress));
+    noerr!(cdb.add(b"one", b"Hello"));
+    noerr!(cdb.add(b"two", b"Goodbye"));
+    let size = cdb.into_vec().unwrap().len() as u64;
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 2 + 256);
+    assert_eq!(
+        seen[0],
+        BuildProgress::Record {
+            records: 1,
+            bytes: 2048 + 8 + 3 + 5
+        }
+    );
+    assert_eq!(
+        seen[1],
+        BuildProgress::Record {
+            records: 2,
+            bytes: 2048 + 8 + 3 + 5 + 8 + 3 + 7
+        }
+    );
+    assert_eq!(
+        seen[257],
+        BuildProgress::Table {
+            tables: 256,
+            bytes: size
+        }
+    );
+}
+
+#[test]
+fn test_make_spill() {
+    let dir = tempfile::tempdir().unwrap();
+    let build = |spill: Option<usize>| {
+        let mut cdb = CDBMake::in_memory().unwrap();
+        cdb.set_bloom_filter(10);
+        for i in 0..500 {
+            noerr!(cdb.add(format!("key{}", i % 300).as_bytes(), [i as u8]));
+            if i == 100 {
+                if let Some(max_entries) = spill {
+                    noerr!(cdb.spill_to_disk(dir.path(), max_entries));
+                }
+            }
+        }
+        cdb.into_vec().unwrap()
+    };
+    let expected = build(None);
+    assert_eq!(build(Some(0)), expected);
+    assert_eq!(build(Some(50)), expected);
+    assert_eq!(build(Some(1000)), expected);
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+
+    let filename = dir.path().join("spilled.cdb");
+    let mut cdb = CDBWriter::builder(&filename)
+        .spill_to_disk(10)
+        .create()
+        .unwrap();
+    noerr!(cdb.add(b"one", b"Hello"));
+    noerr!(cdb.add(b"one", b"again"));
+    noerr!(cdb.finish());
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    let cdb = CDB::open(&filename).unwrap();
+    let values = cdb.find(b"one").collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(values, [&b"Hello"[..], &b"again"[..]]);
+}
+
+#[test]
+fn test_make_with_key() {
+    let filename = "tests/make_with_key.cdb";
+
+    let one = CDBKey::new(b"one");
+    let two = CDBKey::new(b"two");
+    let mut cdb = CDBWriter::create(filename).unwrap();
+    noerr!(cdb.add_with_key(&one, b"Hello"));
+    noerr!(cdb.add_with_key(&two, b"Goodbye"));
+    noerr!(cdb.add(b"one", b", World!"));
+    noerr!(cdb.finish());
+
+    let cdb = CDB::open(filename).unwrap();
+    assert_eq!(cdb.get_with_key(&two).unwrap().unwrap(), b"Goodbye");
+    let mut i = cdb.find_with_key(&one);
+    assert_eq!(i.next().unwrap().unwrap(), b"Hello");
+    assert_eq!(i.next().unwrap().unwrap(), b", World!");
+    assert!(i.next().is_none());
+    assert!(cdb.get_with_key(&CDBKey::new(b"three")).is_none());
+
+    noerr!(fs::remove_file(filename));
+}
+
+#[test]
+fn test_make_empty_record() {
+    let filename = "tests/make_empty_record.cdb";
+
+    let mut cdb = CDBWriter::create(filename).unwrap();
+    noerr!(cdb.add(b"one", b"Hello"));
+    noerr!(cdb.add(b"", b""));
+    noerr!(cdb.finish());
+
+    let cdb = CDB::open(filename).unwrap();
+    assert_eq!(cdb.get(b"").unwrap().unwrap(), b"");
+    let records = cdb.iter().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[1], (vec![], vec![]));
+
+    noerr!(fs::remove_file(filename));
+}
+
+/// 32-bit FNV-1a.
+struct Fnv;
+
+impl CdbHash for Fnv {
+    fn hash(key: &[u8]) -> u32 {
+        key.iter()
+            .fold(0x811c9dc5, |h, &c| (h ^ c as u32).wrapping_mul(0x01000193))
+    }
+}
+
+#[test]
+fn test_make_with_hasher() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("fnv.cdb");
+    let mut cdb = CDBWriter::create(&path).unwrap().with_hasher::<Fnv>();
+    for i in 0..100 {
+        noerr!(cdb.add(format!("key{}", i).as_bytes(), b"value"));
+    }
+    noerr!(cdb.finish());
+
+    let cdb: CDB<Fnv> = CDB::open(&path).unwrap().with_hasher();
+    for i in 0..100 {
+        let key = format!("key{}", i);
+        assert_eq!(cdb.get(key.as_bytes()).unwrap().unwrap(), b"value");
+        let key = CDBKey::with_hasher::<Fnv>(key.as_bytes());
+        assert_eq!(cdb.get_with_key(&key).unwrap().unwrap(), b"value");
+    }
+    assert!(cdb.verify().unwrap().is_ok());
+
+    let cdb = CDB::open(&path).unwrap();
+    assert!(!cdb.verify().unwrap().is_ok());
+}
+
+#[test]
+fn test_make_metadata() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let created = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+    let build = |streamed: bool, checksums: bool| {
+        let mut cdb = CDBMake::in_memory().unwrap();
+        cdb.set_metadata("test 1.0", Some(created));
+        cdb.set_checksums(checksums);
+        cdb.add(b"one", b"Hello").unwrap();
+        if streamed {
+            cdb.add_stream_unsized(b"two", &mut &b"Goodbye"[..])
+                .unwrap();
+        } else {
+            cdb.add(b"two", b"Goodbye").unwrap();
+        }
+        CDB::from_bytes(cdb.into_vec().unwrap()).unwrap()
+    };
+
+    let metadata = build(false, false).metadata().unwrap().unwrap();
+    assert_eq!(metadata.digest, build(true, true).digest().unwrap());
+    assert_eq!(metadata.records, 2);
+    assert_eq!(metadata.created, created);
+    assert_eq!(metadata.version, "test 1.0");
+    // The digest depends only on the records.
+    for cdb in [build(true, false), build(false, true), build(true, true)] {
+        assert_eq!(cdb.metadata().unwrap().unwrap(), metadata);
+        assert_eq!(cdb.get(b"two").unwrap().unwrap(), b"Goodbye");
+    }
+
+    let mut cdb = CDBMake::in_memory().unwrap();
+    cdb.set_metadata("test 1.0", Some(created));
+    cdb.add(b"one", b"Hello").unwrap();
+    cdb.add(b"two", b"Goodbye!").unwrap();
+    let cdb = CDB::from_bytes(cdb.into_vec().unwrap()).unwrap();
+    assert_ne!(cdb.metadata().unwrap().unwrap().digest, metadata.digest);
+
+    assert_eq!(
+        CDB::open("tests/test1.cdb").unwrap().metadata().unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_make_digest() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("digest.cdb");
+    let mut cdb = CDBWriter::create(&path).unwrap();
+    cdb.set_digest(true);
+    cdb.add(b"one", b"Hello").unwrap();
+    cdb.add_stream(b"two", &mut &b"Goodbye"[..], 7).unwrap();
+    let stats = cdb.finish().unwrap();
+    let digest = CDB::open(&path).unwrap().digest().unwrap();
+    assert_eq!(stats.digest, Some(digest));
+
+    let mut cdb = CDB64Make::in_memory().unwrap();
+    cdb.add(b"one", b"Hello").unwrap();
+    cdb.add(b"two", b"Goodbye").unwrap();
+    let cdb = CDB64::from_bytes(cdb.into_vec().unwrap()).unwrap();
+    assert_eq!(cdb.digest().unwrap(), digest);
+
+    // The order of the records matters.
+    let mut cdb = CDBMake::in_memory().unwrap();
+    cdb.add(b"two", b"Goodbye").unwrap();
+    cdb.add(b"one", b"Hello").unwrap();
+    let cdb = CDB::from_bytes(cdb.into_vec().unwrap()).unwrap();
+    assert_ne!(cdb.digest().unwrap(), digest);
+
+    let cdb = CDBWriter::create(&path).unwrap();
+    assert_eq!(cdb.finish().unwrap().digest, None);
+}
+
+#[test]
+fn test_make_extend() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("extend.cdb");
+    let mut cdb = CDBWriter::create(&path).unwrap();
+    cdb.extend([("one", "1"), ("two", "2")]);
+    cdb.extend((3..6).map(|i| (i.to_string(), vec![i as u8])));
+    cdb.finish().unwrap();
+    let cdb = CDB::open(&path).unwrap();
+    assert_eq!(cdb.len(), 5);
+    assert_eq!(cdb.get("4").unwrap().unwrap(), [4]);
+
+    // The first error stops the extension, and is returned by finish.
+    let mut cdb = CDBMake::in_memory().unwrap();
+    cdb.set_duplicate_policy(DuplicatePolicy::Reject);
+    cdb.extend([("a", "1"), ("a", "2"), ("b", "3")]);
+    let err = cdb.finish().unwrap_err();
+    assert!(matches!(
+        cdb32::Error::from(err),
+        cdb32::Error::DuplicateKey
+    ));
+
+    CDBWriter::from_iter(&path, [(b"key", b"value")]).unwrap();
+    assert_eq!(CDB::open(&path).unwrap().len(), 1);
+}
+
+#[test]
+fn test_make_stats() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("stats.cdb");
+    let mut cdb = CDBWriter::create(&path).unwrap();
+    for i in 0..1000 {
+        cdb.add(format!("{:04}", i), "value").unwrap();
+    }
+    let stats = cdb.finish().unwrap();
+    assert_eq!(stats.records, 1000);
+    assert_eq!(stats.keys_bytes, 4000);
+    assert_eq!(stats.values_bytes, 5000);
+    assert_eq!(stats.file_size, fs::metadata(&path).unwrap().len());
+    assert!(stats.max_chain >= 1);
+
+    let stats = CDBMake::in_memory().unwrap().finish().unwrap();
+    assert_eq!(stats.records, 0);
+    assert_eq!(stats.file_size, 2048);
+    assert_eq!(stats.max_chain, 0);
+}
+
+#[test]
+fn test_make_reserve() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("reserve.cdb");
+    let mut cdb = CDBWriter::builder(&path).reserve(1 << 20).create().unwrap();
+    cdb.add(b"one", b"Hello").unwrap();
+    let stats = cdb.finish().unwrap();
+    assert_eq!(stats.file_size, fs::metadata(&path).unwrap().len());
+    let cdb = CDB::open(&path).unwrap();
+    assert_eq!(cdb.get(b"one").unwrap().unwrap(), b"Hello");
+
+    // A reservation smaller than the database does no harm.
+    let mut cdb = CDBWriter::create(&path).unwrap();
+    cdb.reserve(16).unwrap();
+    for i in 0..100 {
+        cdb.add(format!("{:03}", i), "value").unwrap();
+    }
+    let stats = cdb.finish().unwrap();
+    assert_eq!(stats.file_size, fs::metadata(&path).unwrap().len());
+    assert_eq!(CDB::open(&path).unwrap().len(), 100);
+}
+
+#[test]
+fn test_make_from_map() {
+    use std::collections::{BTreeMap, HashMap};
+
+    let dir = tempfile::tempdir().unwrap();
+    let hashed = dir.path().join("hashed.cdb");
+    let sorted = dir.path().join("sorted.cdb");
+    let map = (0..100)
+        .map(|i| (format!("key{}", i), vec![i as u8; i]))
+        .collect::<HashMap<_, _>>();
+    let stats = CDBWriter::from_map(&hashed, &map).unwrap();
+    assert_eq!(stats.records, 100);
+    CDBWriter::from_map(
+        &sorted,
+        &map.clone().into_iter().collect::<BTreeMap<_, _>>(),
+    )
+    .unwrap();
+    assert_eq!(fs::read(&hashed).unwrap(), fs::read(&sorted).unwrap());
+    let cdb = CDB::open(&hashed).unwrap();
+    assert_eq!(cdb.get("key42").unwrap().unwrap(), [42; 42]);
+
+    let mut multi = HashMap::new();
+    multi.insert(b"one".to_vec(), vec!["a", "b", "c"]);
+    multi.insert(b"none".to_vec(), vec![]);
+    let stats = CDB64Writer::from_multimap(&hashed, &multi).unwrap();
+    assert_eq!(stats.records, 3);
+    let cdb = CDB64::open(&hashed).unwrap();
+    let values = cdb.find(b"one").collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(values, [b"a", b"b", b"c"]);
+    assert!(cdb.get(b"none").is_none());
+}
@@ -0,0 +1,35 @@
+#![cfg(feature = "rayon")]
+
+use cdb32::{CDBWriter, CDB};
+use rayon::prelude::*;
+
+#[test]
+fn test_par_iter() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("parallel.cdb");
+    let mut cdb = CDBWriter::create(&path).unwrap();
+    for i in 0..20000 {
+        let key = format!("key{}", i % 15000);
+        cdb.add(key.as_bytes(), vec![b'x'; i % 100]).unwrap();
+    }
+    cdb.add(b"", b"").unwrap();
+    cdb.finish().unwrap();
+
+    let cdb = CDB::open(&path).unwrap();
+    let mut expected = cdb.iter().collect::<std::io::Result<Vec<_>>>().unwrap();
+    let mut actual = cdb
+        .par_iter()
+        .unwrap()
+        .collect::<std::io::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(actual.len(), 20001);
+    expected.sort();
+    actual.sort();
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_par_iter_small() {
+    let cdb = CDB::open("tests/test1.cdb").unwrap();
+    assert_eq!(cdb.par_iter().unwrap().count(), 4);
+}
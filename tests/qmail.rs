@@ -0,0 +1,100 @@
+use std::io;
+
+use cdb32::qmail::{RcptHosts, RcptHostsWriter, User, Users, UsersWriter};
+use cdb32::CDB;
+
+#[test]
+fn test_users() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cdb");
+    let joe = User::new(b"joe", 1001, 100, b"/home/joe");
+    let alias = User::new(b"alias", 7790, 2108, b"/var/qmail/alias").with_ext(b"");
+    let mut users = UsersWriter::create(&path).unwrap();
+    users.add(b"Joe", &joe).unwrap();
+    users
+        .add_wildcard(b"joe-", &joe.clone().with_ext(b""))
+        .unwrap();
+    users.add_wildcard(b"", &alias).unwrap();
+    users.finish().unwrap();
+
+    let cdb = CDB::open(&path).unwrap();
+    assert_eq!(
+        cdb.get(b"!joe\0").unwrap().unwrap(),
+        b"joe\x001001\x00100\x00/home/joe\x00\x00"
+    );
+    assert_eq!(cdb.get(b"").unwrap().unwrap(), b"-");
+
+    let users = Users::open(&path).unwrap();
+    assert_eq!(users.get(b"JOE").unwrap(), Some(joe.clone()));
+    assert_eq!(
+        users.get(b"joe-Lists").unwrap(),
+        Some(joe.with_ext(b"Lists"))
+    );
+    assert_eq!(
+        users.get(b"postmaster").unwrap(),
+        Some(alias.with_ext(b"postmaster"))
+    );
+}
+
+#[test]
+fn test_users_from_assign() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cdb");
+    let assign = b"=Joe:joe:1001:100:/home/joe:::\n\
+        +joe-:joe:1001:100:/home/joe:-::\n\
+        .\n\
+        =ignored:x:1:1:/:::\n";
+    let mut users = UsersWriter::create(&path).unwrap();
+    assert_eq!(users.add_from_assign(&assign[..]).unwrap(), 2);
+    users.finish().unwrap();
+
+    let cdb = CDB::open(&path).unwrap();
+    let records = cdb.iter().collect::<io::Result<Vec<_>>>().unwrap();
+    assert_eq!(
+        records,
+        [
+            (
+                b"!joe\0".to_vec(),
+                b"joe\x001001\x00100\x00/home/joe\x00\x00".to_vec()
+            ),
+            (
+                b"!joe-".to_vec(),
+                b"joe\x001001\x00100\x00/home/joe\x00-\x00".to_vec()
+            ),
+            (b"".to_vec(), b"-".to_vec()),
+        ]
+    );
+
+    let users = Users::from_cdb(cdb).unwrap();
+    let user = users.get(b"joe-Foo").unwrap().unwrap();
+    assert_eq!((&user.dash[..], &user.ext[..]), (&b"-"[..], &b"Foo"[..]));
+    assert_eq!(users.get(b"jo").unwrap(), None);
+
+    for bad in [
+        &b"=joe:joe:1001:100:/home/joe:::\n"[..],
+        b"=joe:joe:1001:100:/home/joe::\n.\n",
+        b":joe:1001:100:/home/joe:::\n.\n",
+    ] {
+        let mut users = UsersWriter::create(dir.path().join("bad")).unwrap();
+        let err = users.add_from_assign(bad).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
+
+#[test]
+fn test_rcpthosts() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("morercpthosts.cdb");
+    let control = b"# local\nExample.COM \n\n.example.net\t\nexample.org";
+    let mut hosts = RcptHostsWriter::create(&path).unwrap();
+    assert_eq!(hosts.add_from_control(&control[..]).unwrap(), 3);
+    hosts.finish().unwrap();
+
+    let hosts = RcptHosts::open(&path).unwrap();
+    assert!(hosts.contains(b"joe@EXAMPLE.com").unwrap());
+    assert!(!hosts.contains(b"joe@mail.example.com").unwrap());
+    assert!(hosts.contains(b"mail.example.net").unwrap());
+    assert!(!hosts.contains(b"example.net").unwrap());
+    assert!(hosts.contains(b"a@b@example.org").unwrap());
+    assert!(!hosts.contains(b"").unwrap());
+}
@@ -1,6 +1,9 @@
 use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::Arc;
 
-use cdb32::{CDBWriter, CDB};
+use cdb32::hash::DjbHasher;
+use cdb32::{AccessPattern, CDBCursor, CDBMake, CDBWriter, Error, OpenOptions, CDB};
 
 #[test]
 fn test_one() {
@@ -41,8 +44,430 @@ fn test_empty_cdb() {
     // check that get works
     let get_result = cdb.get(b"key");
     assert!(get_result.is_none());
+    assert!(!cdb.exists(b"key").unwrap());
 
     assert_eq!(cdb.iter().count(), 0);
+    assert_eq!(cdb.len(), 0);
+    assert!(cdb.is_empty());
 
     let _ = fs::remove_file(filename);
 }
+
+#[test]
+fn test_ref() {
+    let cdb = CDB::open("tests/test1.cdb").unwrap();
+    assert_eq!(cdb.get_ref(b"two").unwrap().unwrap(), b"Goodbye");
+    assert!(cdb.get_ref(b"missing").is_none());
+    let mut i = cdb.find_ref(b"one");
+    assert_eq!(i.next().unwrap().unwrap(), b"Hello");
+    assert_eq!(i.next().unwrap().unwrap(), b", World!");
+    assert!(i.next().is_none());
+
+    let owned = cdb.iter().collect::<Result<Vec<_>, _>>().unwrap();
+    let borrowed = cdb.iter_ref().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(owned.len(), borrowed.len());
+    for ((k1, v1), (k2, v2)) in owned.iter().zip(borrowed) {
+        assert_eq!(k1, k2);
+        assert_eq!(v1, v2);
+    }
+}
+
+#[test]
+fn test_positioned_read() {
+    let cdb = CDB::open_with_options("tests/test1.cdb", OpenOptions::new().mmap(false)).unwrap();
+    let mut i = cdb.find(b"one");
+    assert_eq!(i.next().unwrap().unwrap(), b"Hello");
+    assert_eq!(i.next().unwrap().unwrap(), b", World!");
+    assert!(i.next().is_none());
+    assert_eq!(
+        cdb.get(b"this key will be split across two reads")
+            .unwrap()
+            .unwrap(),
+        b"Got it."
+    );
+
+    let mapped = CDB::open("tests/test1.cdb").unwrap();
+    let expected = mapped.iter().collect::<Result<Vec<_>, _>>().unwrap();
+    let actual = cdb.iter().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(expected, actual);
+
+    assert!(cdb.get_ref(b"two").unwrap().is_err());
+    assert!(cdb.exists(b"two").unwrap());
+}
+
+#[test]
+fn test_keys() {
+    let cdb = CDB::open("tests/test1.cdb").unwrap();
+    let keys = cdb.keys().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(
+        keys,
+        [
+            &b"one"[..],
+            b"two",
+            b"one",
+            b"this key will be split across two reads"
+        ]
+    );
+}
+
+#[test]
+fn test_unique_keys() {
+    let cdb = CDB::open("tests/test1.cdb").unwrap();
+    let keys = cdb.unique_keys().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(
+        keys,
+        [
+            &b"one"[..],
+            b"two",
+            b"this key will be split across two reads"
+        ]
+    );
+
+    let cdb = CDB::open("tests/test2.cdb").unwrap();
+    let mut keys = cdb.unique_keys().collect::<Result<Vec<_>, _>>().unwrap();
+    let count = keys.len();
+    keys.sort();
+    keys.dedup();
+    assert_eq!(keys.len(), count);
+    assert!(keys.contains(&b"one".to_vec()));
+}
+
+#[test]
+fn test_exists() {
+    let cdb = CDB::open("tests/test1.cdb").unwrap();
+    assert!(cdb.exists(b"one").unwrap());
+    assert!(cdb
+        .exists(b"this key will be split across two reads")
+        .unwrap());
+    assert!(!cdb.exists(b"on").unwrap());
+    assert!(!cdb.exists(b"").unwrap());
+}
+
+#[test]
+fn test_count() {
+    let cdb = CDB::open("tests/test1.cdb").unwrap();
+    assert_eq!(cdb.count(b"one").unwrap(), 2);
+    assert_eq!(cdb.count(b"two").unwrap(), 1);
+    assert_eq!(cdb.count(b"three").unwrap(), 0);
+
+    let cdb = CDB::open("tests/test2.cdb").unwrap();
+    assert_eq!(cdb.count(b"one").unwrap(), 1000);
+}
+
+#[test]
+fn test_len() {
+    let cdb = CDB::open("tests/test1.cdb").unwrap();
+    assert_eq!(cdb.len(), 4);
+    assert!(!cdb.is_empty());
+
+    let cdb = CDB::open("tests/test2.cdb").unwrap();
+    assert_eq!(cdb.len(), cdb.iter().count());
+}
+
+#[test]
+fn test_get_many() {
+    let cdb = CDB::open("tests/test2.cdb").unwrap();
+    let keys: Vec<&[u8]> = vec![b"two", b"missing", b"one", b"two"];
+    let values = cdb.get_many(&keys).unwrap();
+    assert_eq!(values.len(), keys.len());
+    for (key, value) in keys.iter().zip(values) {
+        assert_eq!(value, cdb.get(key).transpose().unwrap());
+    }
+    assert!(cdb.get_many(&[]).unwrap().is_empty());
+}
+
+fn owned_values(cdb: Arc<CDB>, key: &[u8]) -> impl Iterator<Item = std::io::Result<Vec<u8>>> {
+    cdb.into_find(key)
+}
+
+#[test]
+fn test_owned_iterators() {
+    let cdb = Arc::new(CDB::open("tests/test1.cdb").unwrap());
+    let values = owned_values(Arc::clone(&cdb), b"one");
+    let records = Arc::clone(&cdb).into_records();
+    let handle = std::thread::spawn(move || {
+        (
+            values.collect::<Result<Vec<_>, _>>().unwrap(),
+            records.collect::<Result<Vec<_>, _>>().unwrap(),
+        )
+    });
+    let (values, records) = handle.join().unwrap();
+    assert_eq!(values, [&b"Hello"[..], b", World!"]);
+    assert_eq!(records, cdb.iter().collect::<Result<Vec<_>, _>>().unwrap());
+}
+
+#[test]
+fn test_access_pattern() {
+    let cdb = CDB::open_with_options(
+        "tests/test1.cdb",
+        OpenOptions::new().access_pattern(AccessPattern::Random),
+    )
+    .unwrap();
+    assert_eq!(cdb.get(b"two").unwrap().unwrap(), b"Goodbye");
+    cdb.advise(AccessPattern::Sequential).unwrap();
+    assert_eq!(cdb.iter().count(), 4);
+    cdb.advise(AccessPattern::Normal).unwrap();
+}
+
+#[test]
+fn test_populate_and_lock() {
+    let cdb = CDB::open_with_options(
+        "tests/test1.cdb",
+        OpenOptions::new().populate(true).lock(true),
+    )
+    .unwrap();
+    assert_eq!(cdb.get(b"two").unwrap().unwrap(), b"Goodbye");
+}
+
+#[test]
+fn test_stats() {
+    let cdb = CDB::open("tests/test1.cdb").unwrap();
+    let stats = cdb.stats().unwrap();
+    assert_eq!(stats.records, 4);
+    assert_eq!(stats.data_size, 16 + 18 + 19 + 54);
+    assert_eq!(stats.table_slots.len(), 256);
+    assert_eq!(stats.table_slots.iter().sum::<u64>(), 8);
+    assert_eq!(stats.fill_factor, 0.5);
+    assert!(stats.max_probe >= 1);
+    assert!(stats.avg_probe >= 1.0 && stats.avg_probe <= stats.max_probe as f64);
+
+    let cdb = CDB::open("tests/test2.cdb").unwrap();
+    let stats = cdb.stats().unwrap();
+    assert_eq!(stats.records, cdb.len() as u64);
+}
+
+#[test]
+fn test_from_bytes() {
+    let data = std::fs::read("tests/test1.cdb").unwrap();
+    let cdb = CDB::from_bytes(data).unwrap();
+    assert_eq!(cdb.get(b"two").unwrap().unwrap(), b"Goodbye");
+    assert_eq!(cdb.get_ref(b"one").unwrap().unwrap(), b"Hello");
+    assert_eq!(cdb.iter().count(), 4);
+
+    assert!(CDB::from_bytes(vec![0; 100]).is_err());
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn test_bytes() {
+    let cdb = CDB::open("tests/test1.cdb").unwrap();
+    let value = cdb.get_bytes(b"two").unwrap().unwrap();
+    assert_eq!(value, "Goodbye");
+    // The value shares the mapping rather than being copied.
+    assert_eq!(
+        value.as_ptr(),
+        cdb.get_ref(b"two").unwrap().unwrap().as_ptr()
+    );
+    let values = cdb
+        .find_bytes(b"one")
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(values, ["Hello", ", World!"]);
+    assert!(cdb.get_bytes(b"missing").is_none());
+    drop(cdb);
+    assert_eq!(value, "Goodbye");
+
+    let cdb = CDB::from_bytes(fs::read("tests/test1.cdb").unwrap()).unwrap();
+    let value = cdb.get_bytes(b"two").unwrap().unwrap();
+    assert_eq!(
+        value.as_ptr(),
+        cdb.get_ref(b"two").unwrap().unwrap().as_ptr()
+    );
+
+    let cdb = CDB::open_with_options("tests/test1.cdb", OpenOptions::new().mmap(false)).unwrap();
+    assert_eq!(cdb.get_bytes(b"two").unwrap().unwrap(), "Goodbye");
+
+    let mut make = CDBMake::in_memory().unwrap();
+    make.set_checksums(true);
+    make.add(b"key", b"value").unwrap();
+    let cdb = CDB::from_bytes(make.into_vec().unwrap()).unwrap();
+    assert_eq!(cdb.get_bytes(b"key").unwrap().unwrap(), "value");
+}
+
+#[test]
+fn test_data_reader() {
+    let file = fs::read("tests/test1.cdb").unwrap();
+    let data_end = u32::from_le_bytes(file[..4].try_into().unwrap()) as usize;
+    for options in [OpenOptions::new(), OpenOptions::new().mmap(false).clone()] {
+        let cdb = CDB::open_with_options("tests/test1.cdb", &options).unwrap();
+        let mut data = cdb.data_reader();
+        assert_eq!(data.len(), (data_end - 2048) as u64);
+        let mut raw = Vec::new();
+        data.read_to_end(&mut raw).unwrap();
+        assert_eq!(raw, &file[2048..data_end]);
+        assert_eq!(data.file_offset(), data_end as u64);
+
+        data.seek(SeekFrom::Start(8)).unwrap();
+        let mut key = [0; 3];
+        data.read_exact(&mut key).unwrap();
+        assert_eq!(&key, b"one");
+        assert_eq!(data.seek(SeekFrom::End(0)).unwrap(), data.len());
+        assert_eq!(data.read(&mut key).unwrap(), 0);
+    }
+}
+
+#[test]
+fn test_read_limits() {
+    let mut options = OpenOptions::new();
+    options.max_find_records(1);
+    let cdb = CDB::open_with_options("tests/test1.cdb", &options).unwrap();
+    let mut values = cdb.find(b"one");
+    assert_eq!(values.next().unwrap().unwrap(), b"Hello");
+    let err = values.next().unwrap().unwrap_err();
+    assert!(matches!(
+        Error::from(err),
+        Error::LimitExceeded {
+            limit: "max_find_records",
+            ..
+        }
+    ));
+    // Each lookup has its own count.
+    assert_eq!(cdb.get(b"one").unwrap().unwrap(), b"Hello");
+    assert_eq!(cdb.find(b"two").count(), 1);
+
+    let cdb =
+        CDB::open_with_options("tests/test1.cdb", OpenOptions::new().max_value_len(7)).unwrap();
+    assert_eq!(cdb.get(b"two").unwrap().unwrap(), b"Goodbye");
+    let err = cdb.find(b"one").nth(1).unwrap().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    // A scan carries on past a record over the limit.
+    let records = cdb.iter().collect::<Vec<_>>();
+    assert_eq!(records.len(), 4);
+    assert!(records[0].is_ok());
+    assert!(records[2].is_err());
+}
+
+#[test]
+fn test_corrupt_error() {
+    let mut data = std::fs::read("tests/test1.cdb").unwrap();
+    // Make the first record's data run past the data section.
+    data[2052..2056].copy_from_slice(&u32::MAX.to_le_bytes());
+    let cdb = CDB::from_bytes(data).unwrap();
+    let err = cdb.iter().find_map(|r| r.err()).unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    match Error::from(err) {
+        Error::Corrupt { offset, .. } => assert_eq!(offset, 2048),
+        err => panic!("unexpected error {:?}", err),
+    }
+
+    let err = CDB::open("tests/missing.cdb").unwrap_err();
+    assert!(matches!(Error::from(err), Error::Io(_)));
+}
+
+#[test]
+fn test_get_reader() {
+    for options in [OpenOptions::new(), OpenOptions::new().mmap(false).clone()] {
+        let cdb = CDB::open_with_options("tests/test1.cdb", &options).unwrap();
+        let mut reader = cdb.get_reader(b"one").unwrap().unwrap();
+        assert_eq!(reader.len(), 5);
+        let mut buf = [0; 3];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"Hel");
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"lo");
+
+        reader.seek(SeekFrom::End(-4)).unwrap();
+        rest.clear();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"ello");
+        assert!(reader.seek(SeekFrom::Current(-10)).is_err());
+        assert_eq!(reader.seek(SeekFrom::Start(10)).unwrap(), 10);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+
+        assert!(cdb.get_reader(b"three").is_none());
+    }
+}
+
+#[test]
+fn test_get_into() {
+    for options in [OpenOptions::new(), OpenOptions::new().mmap(false).clone()] {
+        let cdb = CDB::open_with_options("tests/test1.cdb", &options).unwrap();
+        let mut buf = b"previous contents".to_vec();
+        assert_eq!(cdb.get_into(b"two", &mut buf).unwrap(), Some(7));
+        assert_eq!(buf, b"Goodbye");
+        assert_eq!(cdb.get_into(b"one", &mut buf).unwrap(), Some(5));
+        assert_eq!(buf, b"Hello");
+        assert_eq!(cdb.get_into(b"three", &mut buf).unwrap(), None);
+        assert_eq!(buf, b"Hello");
+    }
+}
+
+#[test]
+fn test_bloom_filter() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("bloom.cdb");
+    let mut cdb = CDBWriter::create(&path).unwrap();
+    cdb.set_bloom_filter(10);
+    for i in 0..1000 {
+        cdb.add(format!("key{}", i).as_bytes(), i.to_string().into_bytes())
+            .unwrap();
+    }
+    cdb.finish().unwrap();
+
+    for options in [OpenOptions::new(), OpenOptions::new().mmap(false).clone()] {
+        let cdb = CDB::open_with_options(&path, &options).unwrap();
+        assert!(cdb.has_bloom_filter());
+        assert_eq!(cdb.len(), 1000);
+        assert_eq!(cdb.iter().count(), 1000);
+        for i in 0..1000 {
+            let value = cdb.get(format!("key{}", i).as_bytes()).unwrap().unwrap();
+            assert_eq!(value, i.to_string().into_bytes());
+        }
+        for i in 1000..2000 {
+            assert!(cdb.get(format!("key{}", i).as_bytes()).is_none());
+        }
+        assert!(cdb.verify().unwrap().is_ok());
+    }
+
+    let cdb = CDB::open("tests/test1.cdb").unwrap();
+    assert!(!cdb.has_bloom_filter());
+}
+
+#[test]
+fn test_resume_at() {
+    let cdb = CDB::open("tests/test2.cdb").unwrap();
+    let all = cdb.iter().map(Result::unwrap).collect::<Vec<_>>();
+
+    let mut records = cdb.iter();
+    let mut seen = Vec::new();
+    for _ in 0..all.len() / 2 {
+        seen.push(records.next().unwrap().unwrap());
+    }
+    let cursor = records.cursor();
+
+    let resumed = cdb
+        .resume_at(CDBCursor::from_position(cursor.position()))
+        .unwrap();
+    seen.extend(resumed.map(Result::unwrap));
+    assert_eq!(seen, all);
+
+    let mut records = cdb.iter();
+    while records.next().is_some() {}
+    assert_eq!(cdb.resume_at(records.cursor()).unwrap().count(), 0);
+    assert!(cdb.resume_at(CDBCursor::from_position(0)).is_err());
+}
+
+#[test]
+fn test_find_with_hash() {
+    let cdb = CDB::open("tests/test1.cdb").unwrap();
+    let mut hasher = DjbHasher::new();
+    hasher.update(b"this key will be split");
+    hasher.update(b" across two reads");
+    let mut i = cdb.find_with_hash(b"this key will be split across two reads", hasher.value());
+    assert_eq!(i.next().unwrap().unwrap(), b"Got it.");
+    assert!(cdb.find_with_hash(b"one", hasher.value()).next().is_none());
+}
+
+#[test]
+fn test_as_ref_keys() {
+    let mut cdb = CDBMake::in_memory().unwrap();
+    cdb.add("str", String::from("string")).unwrap();
+    cdb.add(vec![1, 2], [3, 4]).unwrap();
+    let cdb = CDB::from_bytes(cdb.into_vec().unwrap()).unwrap();
+
+    assert_eq!(cdb.get("str").unwrap().unwrap(), b"string");
+    assert_eq!(cdb.get(String::from("str")).unwrap().unwrap(), b"string");
+    assert_eq!(cdb.get([1, 2]).unwrap().unwrap(), [3, 4]);
+    assert_eq!(cdb.find(vec![1_u8, 2]).count(), 1);
+}
@@ -0,0 +1,55 @@
+use std::fs;
+use std::sync::Arc;
+
+use cdb32::{CDBWriter, ReloadableCDB};
+
+fn build(path: &std::path::Path, value: &[u8]) {
+    let mut cdb = CDBWriter::create(path).unwrap();
+    cdb.add(b"key", value).unwrap();
+    cdb.finish().unwrap();
+}
+
+#[test]
+fn test_reload_if_changed() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("reload.cdb");
+    build(&path, b"first");
+
+    let cdb = ReloadableCDB::open(&path).unwrap();
+    assert!(!cdb.reload_if_changed().unwrap());
+    let old = cdb.current();
+    assert_eq!(old.get(b"key").unwrap().unwrap(), b"first");
+
+    build(&path, b"second, longer");
+    assert!(cdb.reload_if_changed().unwrap());
+    assert!(!cdb.reload_if_changed().unwrap());
+    assert_eq!(
+        cdb.current().get(b"key").unwrap().unwrap(),
+        b"second, longer"
+    );
+    assert!(!Arc::ptr_eq(&old, &cdb.current()));
+
+    // The old version stays readable for as long as it is held.
+    assert_eq!(old.get(b"key").unwrap().unwrap(), b"first");
+}
+
+#[test]
+fn test_failed_reload_keeps_current() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("reload.cdb");
+    build(&path, b"first");
+
+    let cdb = ReloadableCDB::open(&path).unwrap();
+    // Replace the file the same way a writer would, as truncating it in
+    // place would pull the data out from under the existing mapping.
+    let tmp = dir.path().join("reload.tmp");
+    fs::write(&tmp, b"not a cdb").unwrap();
+    fs::rename(&tmp, &path).unwrap();
+    assert!(cdb.reload_if_changed().is_err());
+    assert!(cdb.refresh().is_err());
+    assert_eq!(cdb.current().get(b"key").unwrap().unwrap(), b"first");
+
+    build(&path, b"third");
+    cdb.refresh().unwrap();
+    assert_eq!(cdb.current().get(b"key").unwrap().unwrap(), b"third");
+}
@@ -0,0 +1,80 @@
+use std::fs;
+use std::io::{Seek, Write};
+use std::time::{Duration, UNIX_EPOCH};
+
+use cdb32::{CDB64Make, CDBMake, DjbHash, Format, GenericCDBMake, REPRODUCIBLE_FORMAT_VERSION};
+
+// The expected files were written by this crate and must never change
+// while REPRODUCIBLE_FORMAT_VERSION stays the same.
+const _: () = assert!(REPRODUCIBLE_FORMAT_VERSION == 1);
+
+/// Add records covering duplicates, binary and empty keys and values.
+fn add_records<F: Format, W: Write + Seek>(cdb: &mut GenericCDBMake<F, DjbHash, W>) {
+    cdb.add(b"", b"empty key").unwrap();
+    cdb.add(b"empty value", b"").unwrap();
+    for i in 0..200_u32 {
+        let key = format!("key{}", i % 150);
+        cdb.add(key.as_bytes(), i.to_le_bytes()).unwrap();
+    }
+    cdb.add([0, 255, 10, 13], [0xab; 300]).unwrap();
+}
+
+#[test]
+fn test_matches_cdbmake() {
+    let mut cdb = CDBMake::in_memory().unwrap();
+    cdb.add_from_text(&fs::read("tests/test1.txt").unwrap()[..])
+        .unwrap();
+    assert!(cdb.into_vec().unwrap() == fs::read("tests/test1.cdb").unwrap());
+
+    // The records of tests/test2.sh.
+    let mut cdb = CDBMake::in_memory().unwrap();
+    for i in 1..=1000_u64 {
+        let key = (i * 3141592654 % 1000000).to_string();
+        let value = (i * 2718281828459045).to_string();
+        cdb.add(key.as_bytes(), value.as_bytes()).unwrap();
+        cdb.add(b"one", i.to_string().as_bytes()).unwrap();
+    }
+    cdb.add(b"two", b"Goodbye").unwrap();
+    assert!(cdb.into_vec().unwrap() == fs::read("tests/test2.cdb").unwrap());
+}
+
+#[test]
+fn test_reproducible_with_options() {
+    let created = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let dir = tempfile::tempdir().unwrap();
+    for spill in [false, true] {
+        let mut cdb = CDBMake::in_memory().unwrap();
+        cdb.set_checksums(true);
+        cdb.set_bloom_filter(10);
+        cdb.set_metadata("1.0", Some(created));
+        if spill {
+            cdb.spill_to_disk(dir.path(), 16).unwrap();
+        }
+        add_records(&mut cdb);
+        let bytes = cdb.into_vec().unwrap();
+        assert!(bytes == fs::read("tests/reproducible.cdb").unwrap());
+
+        let mut cdb = CDB64Make::in_memory().unwrap();
+        cdb.set_metadata("1.0", Some(created));
+        if spill {
+            cdb.spill_to_disk(dir.path(), 16).unwrap();
+        }
+        add_records(&mut cdb);
+        let bytes = cdb.into_vec().unwrap();
+        assert!(bytes == fs::read("tests/reproducible64.cdb").unwrap());
+    }
+}
+
+#[test]
+fn test_source_date_epoch() {
+    std::env::set_var("SOURCE_DATE_EPOCH", "1700000000");
+    let mut cdb = CDBMake::in_memory().unwrap();
+    cdb.set_metadata("1.0", None);
+    cdb.add(b"one", b"Hello").unwrap();
+    let cdb = cdb32::CDB::from_bytes(cdb.into_vec().unwrap()).unwrap();
+    let metadata = cdb.metadata().unwrap().unwrap();
+    assert_eq!(
+        metadata.created,
+        UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+    );
+}
@@ -0,0 +1,68 @@
+use std::fs;
+
+use cdb32::{CDBMake, CDBWriter, CDB};
+
+type Records = Vec<(Vec<u8>, Vec<u8>)>;
+
+fn records() -> Records {
+    (0..100)
+        .map(|i| (format!("key{}", i).into_bytes(), vec![b'v'; i]))
+        .collect()
+}
+
+fn salvage(path: &std::path::Path) -> (Records, Option<u64>) {
+    let cdb = CDB::open_lenient(path).unwrap();
+    let mut iter = cdb.salvage_iter();
+    let found = (&mut iter).map(Result::unwrap).collect();
+    (found, iter.damage())
+}
+
+#[test]
+fn test_salvage_truncated() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("full.cdb");
+    let mut cdb = CDBWriter::create(&path).unwrap();
+    for (key, value) in records() {
+        cdb.add(&key, &value).unwrap();
+    }
+    cdb.finish().unwrap();
+
+    let (found, damage) = salvage(&path);
+    assert_eq!(found, records());
+    assert_eq!(damage, None);
+
+    let data = fs::read(&path).unwrap();
+    let cut = dir.path().join("cut.cdb");
+    fs::write(&cut, &data[..4000]).unwrap();
+    assert!(CDB::open(&cut).unwrap().iter().any(|r| r.is_err()));
+    let (found, damage) = salvage(&cut);
+    assert!(!found.is_empty() && found.len() < 100);
+    assert_eq!(found, records()[..found.len()]);
+    let damage = damage.unwrap();
+    assert!(damage <= 4000);
+
+    let cdb = CDB::open_lenient(&cut).unwrap();
+    let mut iter = cdb.salvage_iter();
+    iter.by_ref().for_each(drop);
+    assert_eq!(iter.bytes_lost(), 4000 - damage);
+
+    fs::write(&cut, &data[..100]).unwrap();
+    assert!(CDB::open(&cut).is_err());
+    assert_eq!(salvage(&cut), (vec![], None));
+}
+
+#[test]
+fn test_salvage_unfinished() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("unfinished.cdb");
+    let mut cdb = CDBMake::new(fs::File::create(&path).unwrap()).unwrap();
+    for (key, value) in records() {
+        cdb.add(&key, &value).unwrap();
+    }
+    drop(cdb);
+
+    assert_eq!(CDB::open(&path).unwrap().iter().count(), 0);
+    let (found, damage) = salvage(&path);
+    assert_eq!(found, records());
+    assert_eq!(damage, None);
+}
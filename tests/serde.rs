@@ -0,0 +1,129 @@
+#![cfg(feature = "serde")]
+
+use std::collections::BTreeMap;
+
+use cdb32::{CDBWriter, Codec, Postcard, Result, CDB};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct User {
+    name: String,
+    uid: u32,
+    groups: Vec<String>,
+}
+
+/// Postcard with a version byte in front, to check that custom codecs
+/// are used on both sides.
+struct Versioned;
+
+impl Codec for Versioned {
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+        let mut data = vec![1];
+        data.extend(Postcard::encode(value)?);
+        Ok(data)
+    }
+
+    fn decode<T: DeserializeOwned>(data: &[u8]) -> Result<T> {
+        assert_eq!(data[0], 1);
+        Postcard::decode(&data[1..])
+    }
+}
+
+#[test]
+fn test_typed_roundtrip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("typed.cdb");
+    let user = User {
+        name: "root".to_string(),
+        uid: 0,
+        groups: vec!["wheel".to_string()],
+    };
+
+    let mut cdb = CDBWriter::create(&path).unwrap();
+    cdb.add_ser(b"root", &user).unwrap();
+    cdb.add_ser_with::<Versioned, _>(b"versioned", &user)
+        .unwrap();
+    cdb.add(b"raw", b"\xff").unwrap();
+    cdb.finish().unwrap();
+
+    let cdb = CDB::open(&path).unwrap();
+    assert_eq!(cdb.get_de::<User>(b"root").unwrap().unwrap(), user);
+    assert_eq!(
+        cdb.get_de_with::<Versioned, User>(b"versioned")
+            .unwrap()
+            .unwrap(),
+        user
+    );
+    assert!(cdb.get_de::<User>(b"missing").is_none());
+
+    let err = cdb.get_de::<String>(b"raw").unwrap().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Key {
+    User(u32),
+    Group(String),
+}
+
+#[test]
+fn test_typed_keys() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("keys.cdb");
+    let user = User {
+        name: "daemon".to_string(),
+        uid: 1,
+        groups: vec![],
+    };
+
+    let mut cdb = CDBWriter::create(&path).unwrap();
+    cdb.add_typed(&Key::User(1), &user).unwrap();
+    cdb.add_typed(&Key::Group("wheel".to_string()), &vec![0_u32])
+        .unwrap();
+    cdb.add_typed_with::<Versioned, _, _>(&Key::User(2), "versioned")
+        .unwrap();
+    cdb.finish().unwrap();
+
+    let cdb = CDB::open(&path).unwrap();
+    assert_eq!(
+        cdb.get_typed::<_, User>(&Key::User(1)).unwrap().unwrap(),
+        user
+    );
+    assert_eq!(
+        cdb.get_typed::<_, Vec<u32>>(&Key::Group("wheel".to_string()))
+            .unwrap()
+            .unwrap(),
+        [0]
+    );
+    assert_eq!(
+        cdb.get_typed_with::<Versioned, _, String>(&Key::User(2))
+            .unwrap()
+            .unwrap(),
+        "versioned"
+    );
+    assert!(cdb.get_typed::<_, User>(&Key::User(2)).is_none());
+}
+
+#[test]
+fn test_serialize_cdb() {
+    let cdb = CDB::open("tests/test1.cdb").unwrap();
+    let json = serde_json::to_value(&cdb).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "one": ["Hello", ", World!"],
+            "two": ["Goodbye"],
+            "this key will be split across two reads": ["Got it."],
+        })
+    );
+
+    let mut make = cdb32::CDBMake::in_memory().unwrap();
+    make.add(b"key", b"\xff").unwrap();
+    let cdb = CDB::from_bytes(make.into_vec().unwrap()).unwrap();
+    assert_eq!(serde_json::to_string(&cdb).unwrap(), r#"{"key":[[255]]}"#);
+
+    let encoded = Postcard::encode(&cdb).unwrap();
+    let decoded: BTreeMap<Vec<u8>, Vec<Vec<u8>>> = Postcard::decode(&encoded).unwrap();
+    assert_eq!(decoded[&b"key"[..]], [b"\xff"]);
+}
@@ -0,0 +1,104 @@
+use std::fs;
+use std::io;
+
+use cdb32::{CDBSet, CDBSetManifest, CDBWriter, SetLayout, ShardedCDBWriter};
+
+#[test]
+fn test_set_shards() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path().join("sharded.cdb");
+    let mut cdb = ShardedCDBWriter::create(&base, 3).unwrap();
+    for i in 0..100 {
+        cdb.add(
+            format!("key{}", i).as_bytes(),
+            format!("value{}", i).as_bytes(),
+        )
+        .unwrap();
+    }
+    cdb.add(b"key1", b"again").unwrap();
+    cdb.finish().unwrap();
+
+    let manifest = dir.path().join("set");
+    CDBSetManifest::shards("sharded.cdb", 3)
+        .write(&manifest)
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(&manifest).unwrap(),
+        "shards\nsharded.cdb.0\nsharded.cdb.1\nsharded.cdb.2\n"
+    );
+
+    let set = CDBSet::open(&manifest).unwrap();
+    assert_eq!(set.layout(), SetLayout::Shards);
+    assert_eq!(set.members().len(), 3);
+    for i in 0..100 {
+        let key = format!("key{}", i);
+        assert_eq!(
+            set.get(key.as_bytes()).unwrap().unwrap(),
+            format!("value{}", i).as_bytes()
+        );
+    }
+    assert_eq!(set.find(b"key1").count(), 2);
+    assert!(!set.exists(b"missing").unwrap());
+    assert!(set.get(b"missing").is_none());
+    assert_eq!(set.iter().count(), 101);
+}
+
+#[test]
+fn test_set_layers() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path();
+    CDBWriter::from_iter(
+        path.join("base.cdb"),
+        [("one", "1"), ("two", "2"), ("two", "2b"), ("three", "3")],
+    )
+    .unwrap();
+    CDBWriter::from_iter(path.join("top.cdb"), [("two", "deux"), ("four", "4")]).unwrap();
+    let mut manifest = CDBSetManifest::new(SetLayout::Layers);
+    manifest.members = vec!["top.cdb".into(), path.join("base.cdb")];
+    manifest.write(path.join("set")).unwrap();
+
+    let set = CDBSet::open(path.join("set")).unwrap();
+    assert_eq!(set.get(b"one").unwrap().unwrap(), b"1");
+    assert_eq!(
+        set.find(b"two").collect::<io::Result<Vec<_>>>().unwrap(),
+        [b"deux"]
+    );
+    assert!(set.exists(b"four").unwrap());
+    assert!(!set.exists(b"five").unwrap());
+    let records = set.iter().collect::<io::Result<Vec<_>>>().unwrap();
+    let records: Vec<(&[u8], &[u8])> = records.iter().map(|(k, v)| (&k[..], &v[..])).collect();
+    assert_eq!(
+        records,
+        [
+            (&b"two"[..], &b"deux"[..]),
+            (b"four", b"4"),
+            (b"one", b"1"),
+            (b"three", b"3"),
+        ]
+    );
+}
+
+#[test]
+fn test_set_manifest_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("set");
+    for (text, msg) in [
+        ("", "no layout"),
+        ("# comment\nstripes\na.cdb\n", "unknown layout in line 2"),
+        ("layers\n", "no members"),
+    ] {
+        fs::write(&path, text).unwrap();
+        let err = CDBSet::open(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().ends_with(msg), "{}", err);
+    }
+
+    fs::write(&path, "layers\nmissing.cdb\n").unwrap();
+    let err = CDBSet::open(&path).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+    let mut manifest = CDBSetManifest::new(SetLayout::Layers);
+    manifest.members = vec!["a\nb.cdb".into()];
+    let err = manifest.write(&path).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
@@ -0,0 +1,78 @@
+use std::fs;
+
+use cdb32::{ShardedCDB, ShardedCDB64, ShardedCDB64Writer, ShardedCDBWriter, CDB};
+
+#[test]
+fn test_sharded() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path().join("sharded.cdb");
+    let mut cdb = ShardedCDBWriter::create(&base, 3).unwrap();
+    for i in 0..1000 {
+        cdb.add(
+            format!("key{}", i).as_bytes(),
+            format!("value{}", i).as_bytes(),
+        )
+        .unwrap();
+    }
+    cdb.add(b"key1", b"again").unwrap();
+    cdb.finish().unwrap();
+
+    let cdb = ShardedCDB::open(&base, 3).unwrap();
+    assert_eq!(cdb.len(), 1001);
+    assert_eq!(cdb.iter().count(), 1001);
+    for i in 0..1000 {
+        let key = format!("key{}", i);
+        assert_eq!(
+            cdb.get(key.as_bytes()).unwrap().unwrap(),
+            format!("value{}", i).as_bytes()
+        );
+    }
+    assert_eq!(cdb.find(b"key1").count(), 2);
+    assert!(!cdb.exists(b"missing").unwrap());
+    for shard in cdb.shards() {
+        assert!(shard.len() > 200);
+    }
+    for i in 0..3 {
+        let shard = CDB::open(dir.path().join(format!("sharded.cdb.{}", i))).unwrap();
+        assert_eq!(shard.len(), cdb.shards()[i].len());
+    }
+}
+
+#[test]
+fn test_sharded_parallel() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path().join("parallel.cdb");
+    let mut cdb = ShardedCDB64Writer::create(&base, 4).unwrap();
+    let keys = (0..100).map(|i| format!("key{}", i)).collect::<Vec<_>>();
+    let shard_of = keys
+        .iter()
+        .map(|key| cdb.shard_for(key.as_bytes()))
+        .collect::<Vec<_>>();
+    std::thread::scope(|scope| {
+        for (i, shard) in cdb.shards_mut().iter_mut().enumerate() {
+            let (keys, shard_of) = (&keys, &shard_of);
+            scope.spawn(move || {
+                for (key, _) in keys.iter().zip(shard_of).filter(|(_, &s)| s == i) {
+                    shard.add(key.as_bytes(), b"x").unwrap();
+                }
+            });
+        }
+    });
+    cdb.finish().unwrap();
+
+    let cdb = ShardedCDB64::open(&base, 4).unwrap();
+    assert_eq!(cdb.len(), 100);
+    assert!(keys.iter().all(|key| cdb.exists(key.as_bytes()).unwrap()));
+}
+
+#[test]
+fn test_sharded_failed_finish() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path().join("failed.cdb");
+    let mut cdb = ShardedCDBWriter::create(&base, 2).unwrap();
+    cdb.add(b"one", b"Hello").unwrap();
+    fs::remove_dir_all(dir.path()).unwrap();
+    fs::create_dir(dir.path()).unwrap();
+    assert!(cdb.finish().is_err());
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+}
@@ -0,0 +1,135 @@
+#![cfg(feature = "signing")]
+
+use std::fs;
+use std::io;
+
+use cdb32::{CDB64Writer, CDBMake, CDBWriter, Error, OpenOptions, SigningKey, CDB, CDB64};
+
+fn key() -> SigningKey {
+    SigningKey::from_seed([0x42; 32])
+}
+
+fn signed(records: &[(&str, &str)]) -> Vec<u8> {
+    let mut cdb = CDBMake::in_memory().unwrap();
+    cdb.set_signing_key(&key());
+    cdb.set_bloom_filter(10);
+    for (key, value) in records {
+        cdb.add(key, value).unwrap();
+    }
+    cdb.into_vec().unwrap()
+}
+
+fn check(bytes: Vec<u8>) -> io::Result<()> {
+    CDB::from_bytes(bytes)?.verify_signature(&key().verifying_key())
+}
+
+#[test]
+fn test_signed_open() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("signed.cdb");
+    let mut writer = CDB64Writer::create(&path).unwrap();
+    writer.set_signing_key(&key());
+    writer.set_checksums(true);
+    writer.set_metadata("v1", None);
+    writer.add(b"one", b"Hello").unwrap();
+    writer.add(b"one", b"again").unwrap();
+    let stats = writer.finish().unwrap();
+    assert!(stats.digest.is_some());
+
+    let public = key().verifying_key();
+    let cdb = CDB64::open_verified(&path, &public).unwrap();
+    assert_eq!(cdb.get(b"one").unwrap().unwrap(), b"Hello");
+    assert_eq!(cdb.metadata().unwrap().unwrap().version, "v1");
+    CDB64::open(&path)
+        .unwrap()
+        .verify_signature(&public)
+        .unwrap();
+
+    let other = SigningKey::from_seed([0x43; 32]).verifying_key();
+    let err = Error::from(CDB64::open_verified(&path, &other).unwrap_err());
+    assert!(matches!(err, Error::BadSignature));
+    let mut options = OpenOptions::new();
+    options.mmap(false).verifying_key(&public);
+    assert!(CDB64::open_with_options(&path, &options).is_ok());
+}
+
+#[test]
+fn test_unsigned() {
+    let public = key().verifying_key();
+    let err = CDB::open_verified("tests/test1.cdb", &public).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(!matches!(Error::from(err), Error::BadSignature));
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("plain.cdb");
+    CDBWriter::from_iter(&path, [("one", "1")]).unwrap();
+    assert!(CDB::open_verified(&path, &public).is_err());
+}
+
+#[test]
+fn test_signed_tampering() {
+    let records = [("one", "first"), ("two", "other"), ("three", "3")];
+    let bytes = signed(&records);
+    check(bytes.clone()).unwrap();
+    assert_eq!(signed(&records), bytes);
+
+    // Change a value, a hash table slot, the header and the signature.
+    for pos in [2048 + 8 + 3, bytes.len() - 200, 0, bytes.len() - 20] {
+        let mut tampered = bytes.clone();
+        tampered[pos] ^= 1;
+        let err = Error::from(check(tampered).unwrap_err());
+        assert!(matches!(err, Error::BadSignature | Error::Corrupt { .. }));
+    }
+
+    // Rebuild with another value, keeping the old signature.
+    let other = signed(&[("one", "FIRST"), ("two", "other"), ("three", "3")]);
+    let mut forged = other[..other.len() - 113].to_vec();
+    forged.extend_from_slice(&bytes[bytes.len() - 113..]);
+    let err = Error::from(check(forged).unwrap_err());
+    assert!(matches!(err, Error::BadSignature));
+
+    // Anything appended after the signature is not covered by it.
+    let mut appended = bytes.clone();
+    appended.extend_from_slice(&[1]);
+    appended.extend_from_slice(&1_u64.to_le_bytes());
+    appended.extend_from_slice(b"CDBCKSUM");
+    let err = Error::from(check(appended).unwrap_err());
+    assert!(matches!(err, Error::BadSignature));
+
+    let mut garbage = bytes;
+    garbage.extend_from_slice(b"trailing garbage");
+    assert!(check(garbage).is_err());
+}
+
+#[test]
+fn test_signed_file_changed() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data.cdb");
+    let mut writer = CDBWriter::create(&path).unwrap();
+    writer.set_signing_key(&key());
+    writer.add(b"one", b"1").unwrap();
+    writer.finish().unwrap();
+    let mut bytes = fs::read(&path).unwrap();
+    assert_eq!(bytes[2048 + 8 + 3], b'1');
+    bytes[2048 + 8 + 3] = b'2';
+    fs::write(&path, bytes).unwrap();
+    assert!(CDB::open_verified(&path, &key().verifying_key()).is_err());
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn test_signed_and_encrypted() {
+    let secret = cdb32::EncryptionKey::new([7; 32]);
+    let mut cdb = CDBMake::in_memory().unwrap();
+    cdb.set_encryption(&secret);
+    cdb.set_signing_key(&key());
+    cdb.add(b"api", b"token").unwrap();
+    let bytes = cdb.into_vec().unwrap();
+
+    let mut options = OpenOptions::new();
+    options
+        .encryption_key(&secret)
+        .verifying_key(&key().verifying_key());
+    let cdb = CDB::from_bytes_with_options(bytes, &options).unwrap();
+    assert_eq!(cdb.get(b"api").unwrap().unwrap(), b"token");
+}
@@ -0,0 +1,187 @@
+use std::fs;
+use std::io;
+use std::ops::Bound;
+
+use cdb32::{
+    sorted_index_path, CDB64Writer, CDBMake, CDBWriter, Error, OpenOptions, SortedIndex, CDB, CDB64,
+};
+
+fn keys<I: Iterator<Item = io::Result<(Vec<u8>, Vec<u8>)>>>(records: I) -> Vec<Vec<u8>> {
+    records
+        .map(|record| record.map(|(key, _)| key))
+        .collect::<io::Result<_>>()
+        .unwrap()
+}
+
+#[test]
+fn test_sorted_writer() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("words.cdb");
+    let mut writer = CDBWriter::create(&path).unwrap();
+    writer.set_sorted_index(true);
+    for (key, value) in [("pear", "1"), ("apple", "2"), ("fig", "3"), ("apple", "4")] {
+        writer.add(key, value).unwrap();
+    }
+    writer.finish().unwrap();
+    assert!(sorted_index_path(&path).exists());
+
+    let cdb = CDB::open_with_options(&path, OpenOptions::new().sorted_index(true)).unwrap();
+    assert_eq!(cdb.sorted_index().unwrap().len(), 4);
+    let records = cdb.iter_sorted().collect::<io::Result<Vec<_>>>().unwrap();
+    assert_eq!(
+        records,
+        [
+            (b"apple".to_vec(), b"2".to_vec()),
+            (b"apple".to_vec(), b"4".to_vec()),
+            (b"fig".to_vec(), b"3".to_vec()),
+            (b"pear".to_vec(), b"1".to_vec()),
+        ]
+    );
+    assert_eq!(cdb.iter_sorted().len(), 4);
+    assert_eq!(cdb.iter_sorted().next_back().unwrap().unwrap().0, b"pear");
+}
+
+#[test]
+fn test_sorted_without_index() {
+    let cdb = CDB::open("tests/test1.cdb").unwrap();
+    assert!(cdb.sorted_index().is_none());
+    let mut expected = keys(cdb.iter());
+    expected.sort();
+    assert_eq!(keys(cdb.iter_sorted()), expected);
+
+    let cdb = CDB::from_bytes(CDBMake::in_memory().unwrap().into_vec().unwrap()).unwrap();
+    assert_eq!(cdb.iter_sorted().count(), 0);
+}
+
+#[test]
+fn test_sorted_build_and_write() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("numbers.cdb");
+    CDB64Writer::from_iter(&path, (0..100).rev().map(|i| (format!("{:03}", i), "x"))).unwrap();
+    let mut cdb = CDB64::open(&path).unwrap();
+    let index = SortedIndex::build(&cdb).unwrap();
+    assert_eq!(index.db_size(), fs::metadata(&path).unwrap().len());
+    index.write(sorted_index_path(&path)).unwrap();
+    cdb.set_sorted_index(SortedIndex::open(sorted_index_path(&path)).unwrap())
+        .unwrap();
+    let keys = keys(cdb.iter_sorted());
+    assert_eq!(keys.len(), 100);
+    assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn test_sorted_out_of_date() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data.cdb");
+    let mut writer = CDBWriter::create(&path).unwrap();
+    writer.set_sorted_index(true);
+    writer.add(b"one", b"1").unwrap();
+    writer.finish().unwrap();
+    let index = SortedIndex::open(sorted_index_path(&path)).unwrap();
+
+    CDBWriter::from_iter(&path, [("one", "1"), ("two", "2")]).unwrap();
+    let mut cdb = CDB::open(&path).unwrap();
+    let err = cdb.set_sorted_index(index).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(CDB::open_with_options(&path, OpenOptions::new().sorted_index(true)).is_err());
+}
+
+#[test]
+fn test_sorted_missing_or_corrupt() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data.cdb");
+    CDBWriter::from_iter(&path, [("one", "1")]).unwrap();
+    let err = CDB::open_with_options(&path, OpenOptions::new().sorted_index(true)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+    let index_path = sorted_index_path(&path);
+    let mut data = Vec::new();
+    data.extend_from_slice(b"CDBSORT1");
+    data.extend_from_slice(&[0; 8]);
+    data.extend_from_slice(&u64::MAX.to_le_bytes());
+    fs::write(&index_path, &data).unwrap();
+    let err = Error::from(SortedIndex::open(&index_path).unwrap_err());
+    assert!(matches!(err, Error::Corrupt { offset: 16, .. }));
+
+    fs::write(&index_path, b"not an index at all, no").unwrap();
+    let err = Error::from(SortedIndex::open(&index_path).unwrap_err());
+    assert!(matches!(err, Error::Corrupt { .. }));
+}
+
+#[test]
+fn test_find_prefix() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("hosts.cdb");
+    let mut writer = CDBWriter::create(&path).unwrap();
+    writer.set_sorted_index(true);
+    let records = [
+        ("example.org/www", "4"),
+        ("example.com/www", "1"),
+        ("example.com", "0"),
+        ("example.com/mail", "2"),
+        ("example.co", "5"),
+        ("example.com/www", "3"),
+    ];
+    for (key, value) in records {
+        writer.add(key, value).unwrap();
+    }
+    writer.finish().unwrap();
+
+    let expected = [
+        (b"example.com/mail".to_vec(), b"2".to_vec()),
+        (b"example.com/www".to_vec(), b"1".to_vec()),
+        (b"example.com/www".to_vec(), b"3".to_vec()),
+    ];
+    let cdb = CDB::open_with_options(&path, OpenOptions::new().sorted_index(true)).unwrap();
+    let found = cdb
+        .find_prefix(b"example.com/")
+        .collect::<io::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(found, expected);
+    assert_eq!(cdb.find_prefix(b"").count(), 6);
+    assert_eq!(cdb.find_prefix(b"example.net").count(), 0);
+    assert_eq!(cdb.find_prefix(b"zzz").count(), 0);
+
+    // Without the index the records come in the order they were added.
+    let cdb = CDB::open(&path).unwrap();
+    let mut found = cdb
+        .find_prefix(b"example.com/")
+        .collect::<io::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(found[0].1, b"1");
+    found.sort();
+    assert_eq!(found, expected);
+    assert_eq!(cdb.find_prefix(b"").count(), 6);
+}
+
+#[test]
+fn test_range() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("numbers.cdb");
+    let mut writer = CDB64Writer::create(&path).unwrap();
+    writer.set_sorted_index(true);
+    for i in (0..50).rev() {
+        writer.add(format!("{:02}", i * 2), i.to_string()).unwrap();
+    }
+    writer.finish().unwrap();
+
+    let with_index =
+        CDB64::open_with_options(&path, OpenOptions::new().sorted_index(true)).unwrap();
+    let without_index = CDB64::open(&path).unwrap();
+    for cdb in [&with_index, &without_index] {
+        assert_eq!(keys(cdb.range("10".."16")), [b"10", b"12", b"14"]);
+        assert_eq!(keys(cdb.range("10"..="16")), [b"10", b"12", b"14", b"16"]);
+        assert_eq!(keys(cdb.range("11".."15")), [b"12", b"14"]);
+        assert_eq!(keys(cdb.range(.."04")), [b"00", b"02"]);
+        assert_eq!(keys(cdb.range("95"..)), [b"96", b"98"]);
+        assert_eq!(
+            keys(cdb.range::<str, _>((Bound::Excluded("96"), Bound::Unbounded))),
+            [b"98"]
+        );
+        assert_eq!(cdb.range("50".."10").count(), 0);
+        assert_eq!(cdb.range("a".."z").count(), 0);
+        assert_eq!(cdb.range::<[u8], _>(..).count(), 50);
+        let (key, value) = cdb.range("20".."30").next_back().unwrap().unwrap();
+        assert_eq!((key, value), (b"28".to_vec(), b"14".to_vec()));
+    }
+}
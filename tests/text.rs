@@ -0,0 +1,39 @@
+use cdb32::{CDBMake, CDBWriter, CDB};
+
+#[test]
+fn test_add_from_text() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("text.cdb");
+    let input = b"+3,5:one->Hello\n+3,7:one->Goodbye\n+4,3:t\nwo->2\n3\n\n";
+    let mut cdb = CDBWriter::create(&path).unwrap();
+    assert_eq!(cdb.add_from_text(&input[..]).unwrap(), 3);
+    cdb.finish().unwrap();
+
+    let cdb = CDB::open(&path).unwrap();
+    let values = cdb.find(b"one").collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(values, [&b"Hello"[..], &b"Goodbye"[..]]);
+    assert_eq!(cdb.get(b"t\nwo").unwrap().unwrap(), b"2\n3");
+
+    let mut cdb = CDBWriter::create(&path).unwrap();
+    assert!(cdb.add_from_text(&b"+3,5:one->Hello\n+3"[..]).is_err());
+}
+
+#[test]
+fn test_dump_to() {
+    let mut cdb = CDBMake::in_memory().unwrap();
+    cdb.add(b"one", b"Hello").unwrap();
+    cdb.add(b"", b"").unwrap();
+    cdb.add(b"one", b"a\n->b").unwrap();
+    let cdb = CDB::from_bytes(cdb.into_vec().unwrap()).unwrap();
+
+    let mut out = Vec::new();
+    assert_eq!(cdb.dump_to(&mut out).unwrap(), 3);
+    assert_eq!(out, b"+3,5:one->Hello\n+0,0:->\n+3,5:one->a\n->b\n\n");
+
+    let mut copy = CDBMake::in_memory().unwrap();
+    assert_eq!(copy.add_from_text(&out[..]).unwrap(), 3);
+    let copy = CDB::from_bytes(copy.into_vec().unwrap()).unwrap();
+    let mut again = Vec::new();
+    copy.dump_to(&mut again).unwrap();
+    assert_eq!(again, out);
+}
@@ -0,0 +1,117 @@
+use std::io;
+
+use cdb32::tinydns;
+use cdb32::{CDBMake, CDB};
+
+/// The value of a record with no timestamp, as tinydns-data writes it.
+fn value(rtype: u16, loc: &[u8], ttl: u32, data: &[u8]) -> Vec<u8> {
+    let mut value = rtype.to_be_bytes().to_vec();
+    value.extend_from_slice(loc);
+    value.extend_from_slice(&ttl.to_be_bytes());
+    value.extend_from_slice(&[0; 8]);
+    value.extend_from_slice(data);
+    value
+}
+
+#[test]
+fn test_records() {
+    let input = b"# comment\n\
+        %in:192.168\n\
+        .example.com:192.0.2.53:a:3600\n\
+        @example.com::mail.example.net:10\n\
+        'example.com:v=spf1 -all\n\
+        -example.com:192.0.2.1\n\
+        +*.Example.COM:192.0.2.9:::in\n\
+        :example.com:99:\\141b\n";
+    let records = tinydns::records(&input[..], 7)
+        .collect::<io::Result<Vec<_>>>()
+        .unwrap();
+
+    let owner = b"\x07example\x03com\x00";
+    let server = b"\x01a\x02ns\x07example\x03com\x00";
+    let soa = [
+        &server[..],
+        b"\x0ahostmaster",
+        owner,
+        &7_u32.to_be_bytes(),
+        &16384_u32.to_be_bytes(),
+        &2048_u32.to_be_bytes(),
+        &1048576_u32.to_be_bytes(),
+        &2560_u32.to_be_bytes(),
+    ]
+    .concat();
+    let expected = vec![
+        (b"\x00%\xc0\xa8".to_vec(), b"in".to_vec()),
+        (owner.to_vec(), value(6, b"=", 2560, &soa)),
+        (owner.to_vec(), value(2, b"=", 3600, server)),
+        (server.to_vec(), value(1, b"=", 3600, &[192, 0, 2, 53])),
+        (
+            owner.to_vec(),
+            value(15, b"=", 86400, b"\x00\x0a\x04mail\x07example\x03net\x00"),
+        ),
+        (owner.to_vec(), value(16, b"=", 86400, b"\x0bv=spf1 -all")),
+        (owner.to_vec(), value(1, b"+in", 86400, &[192, 0, 2, 9])),
+        (owner.to_vec(), value(99, b"=", 86400, b"ab")),
+    ];
+    assert_eq!(records, expected);
+}
+
+#[test]
+fn test_long_text() {
+    let text = vec![b'x'; 200];
+    let input = [&b"'t:"[..], &text, b"\n"].concat();
+    let mut records = tinydns::records(&input[..], 1);
+    let (key, value) = records.next().unwrap().unwrap();
+    assert_eq!(key, b"\x01t\x00");
+    let data = &value[15..];
+    assert_eq!(data[0], 127);
+    assert_eq!(data[128], 73);
+    assert_eq!(data.len(), 2 + 200);
+    assert!(records.next().is_none());
+}
+
+#[test]
+fn test_malformed() {
+    for (input, msg) in [
+        (&b"+a:1.2.3.4\n:b:2:x\n"[..], "line 2: type NS prohibited"),
+        (b"!a\n", "line 1: unrecognized leading character"),
+        (b"+a:1.2.3.4:60:xyz\n", "line 1: malformed time"),
+    ] {
+        let mut cdb = CDBMake::in_memory().unwrap();
+        let err = cdb.add_from_tinydns(input, 1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().ends_with(msg), "{}", err);
+    }
+
+    let label = "x".repeat(64);
+    let input = format!("+{}.example.com:1.2.3.4\n", label);
+    let mut records = tinydns::records(input.as_bytes(), 1);
+    assert!(records.next().unwrap().is_err());
+    assert!(records.next().is_none());
+}
+
+#[test]
+fn test_build() {
+    let dir = tempfile::tempdir().unwrap();
+    let data = dir.path().join("data");
+    std::fs::write(
+        &data,
+        "=www.example.com:192.0.2.1\nZexample.com:ns.example.com.:hostmaster.example.com.:42\n",
+    )
+    .unwrap();
+    let path = dir.path().join("data.cdb");
+    assert_eq!(tinydns::build(&data, &path).unwrap(), 3);
+
+    let cdb = CDB::open(&path).unwrap();
+    let ptr = cdb
+        .get(b"\x011\x012\x010\x03192\x07in-addr\x04arpa\x00")
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        ptr,
+        value(12, b"=", 86400, b"\x03www\x07example\x03com\x00")
+    );
+    let soa = cdb.get(b"\x07example\x03com\x00").unwrap().unwrap();
+    assert_eq!(soa[..2], [0, 6]);
+    assert_eq!(soa[soa.len() - 20..soa.len() - 16], 42_u32.to_be_bytes());
+}
@@ -0,0 +1,53 @@
+use cdb32::{CDB64Updater, CDB64Writer, CDBUpdater, CDBWriter, CDB, CDB64};
+
+fn values(cdb: &CDB, key: &[u8]) -> Vec<Vec<u8>> {
+    cdb.find(key).map(|value| value.unwrap()).collect()
+}
+
+#[test]
+fn test_update() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("update.cdb");
+    let mut cdb = CDBWriter::create(&path).unwrap();
+    cdb.add(b"one", b"1").unwrap();
+    cdb.add(b"two", b"2").unwrap();
+    cdb.add(b"one", b"1 again").unwrap();
+    cdb.add(b"three", b"3").unwrap();
+    cdb.finish().unwrap();
+
+    let mut update = CDBUpdater::open(&path).unwrap();
+    update.insert(b"two", b"2 again");
+    update.remove(b"three");
+    update.insert(b"three", b"3 again");
+    update.insert(b"four", b"dropped");
+    update.remove(b"four");
+    update.replace(b"one", b"uno");
+    assert_eq!(update.source().len(), 4);
+    update.commit().unwrap();
+
+    let cdb = CDB::open(&path).unwrap();
+    assert_eq!(values(&cdb, b"one"), [b"uno"]);
+    assert_eq!(values(&cdb, b"two"), [&b"2"[..], &b"2 again"[..]]);
+    assert_eq!(values(&cdb, b"three"), [b"3 again"]);
+    assert!(cdb.get(b"four").is_none());
+    assert_eq!(cdb.len(), 4);
+}
+
+#[test]
+fn test_update64_commit_to() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("old.cdb");
+    let mut cdb = CDB64Writer::create(&path).unwrap();
+    cdb.add(b"one", b"1").unwrap();
+    cdb.finish().unwrap();
+
+    let mut update = CDB64Updater::open(&path).unwrap();
+    update.insert(b"two", b"2");
+    let new_path = dir.path().join("new.cdb");
+    update.commit_to(&new_path).unwrap();
+
+    assert_eq!(CDB64::open(&path).unwrap().len(), 1);
+    let cdb = CDB64::open(&new_path).unwrap();
+    assert_eq!(cdb.get(b"one").unwrap().unwrap(), b"1");
+    assert_eq!(cdb.get(b"two").unwrap().unwrap(), b"2");
+}
@@ -0,0 +1,312 @@
+use std::fs;
+
+use cdb32::{CDBWriter, VerifyProblem, CDB};
+
+fn djb_hash(key: &[u8]) -> u32 {
+    key.iter().fold(5381_u32, |h, &c| {
+        h.wrapping_shl(5).wrapping_add(h) ^ c as u32
+    })
+}
+
+fn make(path: &std::path::Path) -> Vec<u8> {
+    let mut cdb = CDBWriter::create(path).unwrap();
+    cdb.add(b"one", b"Hello").unwrap();
+    cdb.add(b"two", b"Goodbye").unwrap();
+    cdb.add(b"one", b", World!").unwrap();
+    cdb.finish().unwrap();
+    fs::read(path).unwrap()
+}
+
+#[test]
+fn test_verify_ok() {
+    for filename in ["tests/test1.cdb", "tests/test2.cdb"] {
+        let report = CDB::open(filename).unwrap().verify().unwrap();
+        assert!(report.is_ok(), "{:?}", report.problems);
+    }
+    let report = CDB::open("tests/test1.cdb").unwrap().verify().unwrap();
+    assert_eq!(report.records, 4);
+}
+
+#[test]
+fn test_verify_hash_mismatch() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("bad.cdb");
+    let mut raw = make(&path);
+    // The key of the second record, "two", starts 8 bytes into it.
+    let pos = 2048 + 8 + 3 + 5;
+    assert_eq!(&raw[pos + 8..pos + 11], b"two");
+    raw[pos + 8] = b'x';
+    fs::write(&path, &raw).unwrap();
+
+    let report = CDB::open(&path).unwrap().verify().unwrap();
+    assert_eq!(
+        report.problems,
+        [VerifyProblem::HashMismatch {
+            pos: pos as u64,
+            stored: djb_hash(b"two") as u64,
+            actual: djb_hash(b"xwo"),
+        }]
+    );
+}
+
+#[test]
+fn test_verify_unreferenced() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("bad.cdb");
+    let mut raw = make(&path);
+    // Empty the hash table holding "two".
+    let table = (djb_hash(b"two") & 0xff) as usize;
+    raw[table * 8 + 4..table * 8 + 8].copy_from_slice(&[0; 4]);
+    fs::write(&path, &raw).unwrap();
+
+    let report = CDB::open(&path).unwrap().verify().unwrap();
+    assert_eq!(report.records, 3);
+    assert_eq!(
+        report.problems,
+        [VerifyProblem::Unreferenced {
+            pos: 2048 + 8 + 3 + 5
+        }]
+    );
+}
+
+/// Return the position of the hash table holding `key`, and the slots
+/// which are used.
+fn used_slots(raw: &[u8], key: &[u8]) -> (usize, Vec<usize>) {
+    let table = (djb_hash(key) & 0xff) as usize;
+    let hpos = u32::from_le_bytes(raw[table * 8..table * 8 + 4].try_into().unwrap()) as usize;
+    let slots = u32::from_le_bytes(raw[table * 8 + 4..table * 8 + 8].try_into().unwrap());
+    let used = (0..slots as usize)
+        .filter(|slot| raw[hpos + slot * 8 + 4..hpos + slot * 8 + 8] != [0; 4])
+        .collect();
+    (hpos, used)
+}
+
+#[test]
+fn test_verify_duplicate_reference() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("bad.cdb");
+    let mut raw = make(&path);
+    // Copy a slot for "one" into an empty slot of the same table.
+    let (hpos, used) = used_slots(&raw, b"one");
+    let empty = (0..4).find(|slot| !used.contains(slot)).unwrap();
+    raw.copy_within(hpos + used[0] * 8..hpos + used[0] * 8 + 8, hpos + empty * 8);
+    fs::write(&path, &raw).unwrap();
+
+    let cdb = CDB::open(&path).unwrap();
+    let report = cdb.verify().unwrap();
+    let table = (djb_hash(b"one") & 0xff) as usize;
+    let pos = u32::from_le_bytes(
+        raw[hpos + empty * 8 + 4..hpos + empty * 8 + 8]
+            .try_into()
+            .unwrap(),
+    );
+    assert_eq!(
+        report.problems,
+        [VerifyProblem::DuplicateReference {
+            table,
+            slot: empty.max(used[0]) as u64,
+            pos: pos as u64,
+        }]
+    );
+}
+
+#[test]
+fn test_verify_not_record_start() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("bad.cdb");
+    let mut raw = make(&path);
+    // Point the slot for the first record one byte into it.
+    let (hpos, used) = used_slots(&raw, b"one");
+    let slot = used
+        .into_iter()
+        .find(|slot| raw[hpos + slot * 8 + 4..hpos + slot * 8 + 8] == 2048_u32.to_le_bytes())
+        .unwrap();
+    raw[hpos + slot * 8 + 4..hpos + slot * 8 + 8].copy_from_slice(&2049_u32.to_le_bytes());
+    fs::write(&path, &raw).unwrap();
+
+    let report = CDB::open(&path).unwrap().verify().unwrap();
+    let table = (djb_hash(b"one") & 0xff) as usize;
+    assert!(report.problems.contains(&VerifyProblem::NotRecordStart {
+        table,
+        slot: slot as u64,
+        pos: 2049,
+    }));
+    assert!(report
+        .problems
+        .contains(&VerifyProblem::Unreferenced { pos: 2048 }));
+}
+
+#[test]
+fn test_verify_table_out_of_bounds() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("bad.cdb");
+    let mut raw = make(&path);
+    let table = (djb_hash(b"one") & 0xff) as usize;
+    raw[table * 8..table * 8 + 4].copy_from_slice(&0xfffffff0_u32.to_le_bytes());
+    fs::write(&path, &raw).unwrap();
+
+    let report = CDB::open(&path).unwrap().verify().unwrap();
+    assert_eq!(
+        report.problems[0],
+        VerifyProblem::TableOutOfBounds {
+            table,
+            pos: 0xfffffff0,
+            slots: 4
+        }
+    );
+}
+
+#[test]
+fn test_verify_checksums() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("checksums.cdb");
+    let mut cdb = CDBWriter::create(&path).unwrap();
+    cdb.set_checksums(true);
+    cdb.add(b"one", b"Hello").unwrap();
+    cdb.add_stream(b"two", &mut &b"Goodbye"[..], 7).unwrap();
+    cdb.add_stream_unsized(b"three", &mut &b"Again"[..])
+        .unwrap();
+    cdb.finish().unwrap();
+
+    let cdb = CDB::open(&path).unwrap();
+    assert!(cdb.has_checksums());
+    assert!(cdb.verify().unwrap().is_ok());
+    assert_eq!(cdb.get(b"two").unwrap().unwrap(), b"Goodbye");
+    assert_eq!(cdb.get(b"three").unwrap().unwrap(), b"Again");
+    assert_eq!(cdb.get_ref(b"one").unwrap().unwrap(), b"Hello");
+    let mut value = String::new();
+    let mut reader = cdb.get_reader(b"two").unwrap().unwrap();
+    std::io::Read::read_to_string(&mut reader, &mut value).unwrap();
+    assert_eq!(value, "Goodbye");
+    drop(cdb);
+
+    // Flip a bit in the value of the second record.
+    let mut raw = fs::read(&path).unwrap();
+    let pos = 2048 + 8 + 3 + 5 + 4;
+    assert_eq!(&raw[pos + 8 + 3..pos + 8 + 10], b"Goodbye");
+    raw[pos + 8 + 3] ^= 1;
+    fs::write(&path, &raw).unwrap();
+
+    let cdb = CDB::open(&path).unwrap();
+    assert_eq!(
+        cdb.verify().unwrap().problems,
+        [VerifyProblem::ChecksumMismatch { pos: pos as u64 }]
+    );
+    let err = cdb.get(b"two").unwrap().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(cdb.get(b"one").unwrap().unwrap(), b"Hello");
+
+    let cdb =
+        CDB::open_with_options(&path, cdb32::OpenOptions::new().verify_checksums(false)).unwrap();
+    assert_eq!(cdb.get(b"two").unwrap().unwrap(), b"Foodbye");
+}
+
+#[test]
+fn test_open_strict() {
+    for filename in ["tests/test1.cdb", "tests/test2.cdb"] {
+        CDB::open_strict(filename).unwrap();
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("bad.cdb");
+    let good = make(&path);
+    let mut writer = CDBWriter::create(&path).unwrap();
+    writer.set_bloom_filter(10);
+    writer.set_checksums(true);
+    writer.add(b"one", b"Hello").unwrap();
+    writer.finish().unwrap();
+    CDB::open_strict(&path).unwrap();
+
+    // A table past the end of the file.
+    let mut raw = good.clone();
+    let len = raw.len() as u32;
+    raw[8..12].copy_from_slice(&len.to_le_bytes());
+    raw[12..16].copy_from_slice(&1_u32.to_le_bytes());
+    fs::write(&path, &raw).unwrap();
+    CDB::open(&path).unwrap();
+    let err = CDB::open_strict(&path).unwrap_err();
+    match cdb32::Error::from(err) {
+        cdb32::Error::Corrupt { offset, .. } => assert_eq!(offset, 8),
+        err => panic!("unexpected error {:?}", err),
+    }
+
+    // A table inside the data section.
+    let mut raw = good.clone();
+    raw[16..20].copy_from_slice(&2048_u32.to_le_bytes());
+    raw[20..24].copy_from_slice(&1_u32.to_le_bytes());
+    fs::write(&path, &raw).unwrap();
+    assert!(CDB::open_strict(&path).is_err());
+
+    // Two overlapping tables.
+    let mut raw = good;
+    let table = (0..256)
+        .find(|i| raw[i * 8 + 4..i * 8 + 8] != [0; 4])
+        .unwrap();
+    let other = (table + 1) % 256;
+    raw.copy_within(table * 8..table * 8 + 4, other * 8);
+    raw[other * 8 + 4..other * 8 + 8].copy_from_slice(&1_u32.to_le_bytes());
+    fs::write(&path, &raw).unwrap();
+    assert!(CDB::open_strict(&path).is_err());
+}
+
+struct Collide;
+
+impl cdb32::CdbHash for Collide {
+    fn hash(_key: &[u8]) -> u32 {
+        0
+    }
+}
+
+#[test]
+fn test_open_untrusted() {
+    let mut untrusted = cdb32::OpenOptions::new();
+    untrusted.untrusted(true);
+    let cdb = CDB::open_with_options("tests/test1.cdb", &untrusted).unwrap();
+    assert_eq!(cdb.find(b"one").count(), 2);
+    assert_eq!(cdb.iter().count(), 4);
+
+    // Every key collides, so later keys need long probes.
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("collide.cdb");
+    let mut writer = CDBWriter::create(&path).unwrap().with_hasher::<Collide>();
+    for i in 0..100 {
+        writer
+            .add(format!("key{}", i).as_bytes(), b"value")
+            .unwrap();
+    }
+    writer.finish().unwrap();
+    let open = |options: &cdb32::OpenOptions| -> CDB<Collide> {
+        CDB::open_with_options(&path, options)
+            .unwrap()
+            .with_hasher()
+    };
+    assert!(open(&untrusted).get(b"key99").unwrap().is_ok());
+    let cdb = open(untrusted.clone().max_probes(10));
+    assert!(cdb.get(b"key1").unwrap().is_ok());
+    let err = cdb.get(b"key99").unwrap().unwrap_err();
+    assert!(matches!(
+        cdb32::Error::from(err),
+        cdb32::Error::Corrupt { .. }
+    ));
+
+    let mut limited = untrusted.clone();
+    limited.max_value_len(5);
+    let cdb = CDB::open_with_options("tests/test1.cdb", &limited).unwrap();
+    assert_eq!(cdb.get(b"one").unwrap().unwrap(), b"Hello");
+    assert!(cdb.get(b"two").unwrap().is_err());
+    assert!(cdb.iter().any(|r| r.is_err()));
+
+    // Point the slot for "two" into the hash tables.
+    let mut raw = fs::read("tests/test1.cdb").unwrap();
+    let hash = djb_hash(b"two");
+    let table = (hash & 0xff) as usize * 8;
+    let hpos = u32::from_le_bytes(raw[table..table + 4].try_into().unwrap()) as usize;
+    let slot = (hpos..)
+        .step_by(8)
+        .find(|&s| raw[s..s + 4] == hash.to_le_bytes())
+        .unwrap();
+    raw[slot + 4..slot + 8].copy_from_slice(&(hpos as u32).to_le_bytes());
+    fs::write(&path, &raw).unwrap();
+    assert!(CDB::open(&path).unwrap().get(b"two").is_none());
+    let cdb = CDB::open_with_options(&path, &untrusted).unwrap();
+    assert!(cdb.get(b"two").unwrap().is_err());
+}